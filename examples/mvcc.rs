@@ -28,7 +28,8 @@ async fn main() -> Result<(), KernelError> {
     tx.set(
         Bytes::copy_from_slice(b"key_2"),
         Bytes::copy_from_slice(b"value_2"),
-    );
+    )
+    .await?;
 
     println!("Read key_2 on the transaction: {:?}", tx.get(b"key_2")?);
 
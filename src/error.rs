@@ -1,4 +1,5 @@
 use crate::kernel::lsm::compactor::CompactTask;
+use crate::kernel::lsm::storage::CorruptionReason;
 use crate::kernel::lsm::version::cleaner::CleanTag;
 use std::io;
 use thiserror::Error;
@@ -42,6 +43,13 @@ pub enum KernelError {
     #[error("CRC code does not match")]
     CrcMisMatch,
 
+    #[error(transparent)]
+    Snappy(#[from] snap::Error),
+
+    /// Block末尾记录了未知的压缩方式，通常由数据损坏导致
+    #[error("Unknown compress type of block: {0}")]
+    UnknownCompressType(u8),
+
     #[cfg(feature = "sled")]
     #[error(transparent)]
     SledErr(#[from] sled::Error),
@@ -83,6 +91,41 @@ pub enum KernelError {
 
     #[error("Same write in different transactions")]
     RepeatedWrite,
+
+    #[error("Column family not found")]
+    ColumnFamilyNotFound,
+
+    #[error("Merge operator not found")]
+    MergeOperatorNotFound,
+
+    /// 打开数据库时使用的Comparator与创建时不同
+    #[error("Comparator mismatch, the database was created with: {0}")]
+    ComparatorMismatch(String),
+
+    /// 悲观事务等待锁时形成了死锁，当前事务被中止
+    #[error("Deadlock detected, the transaction is aborted")]
+    Deadlock,
+
+    #[error("Timed out waiting for the lock")]
+    LockTimeout,
+
+    #[error("Data read by the transaction has been modified by another transaction")]
+    SerializationFailure,
+
+    #[error("The savepoint does not belong to the transaction or has been rolled back")]
+    InvalidSavepoint,
+
+    /// 组提交中由其他写入者负责的WAL写入失败，具体原因由该写入者返回
+    #[error("Failed to write the WAL of the commit group")]
+    GroupCommitFailed,
+
+    /// WAL恢复时发现当前WalRecoveryMode无法容忍的损坏
+    #[error("WAL {gen} is corrupted at offset {offset}: {reason:?}")]
+    WalCorrupted {
+        gen: i64,
+        offset: u64,
+        reason: CorruptionReason,
+    },
 }
 
 #[derive(Error, Debug)]
@@ -28,6 +28,7 @@ impl BufIoReader {
         let reader = BufReaderWithPos::new(
             OpenOptions::new()
                 .create(true)
+                .truncate(false)
                 .write(true)
                 .read(true)
                 .open(path)?,
@@ -56,6 +57,7 @@ impl BufIoWriter {
         // 通过路径构造写入器
         let file = OpenOptions::new()
             .create(true)
+            .truncate(false)
             .write(true)
             .read(true)
             .open(extension.path_with_gen(&dir_path, gen))?;
@@ -112,6 +114,13 @@ impl IoWriter for BufIoWriter {
     fn current_pos(&mut self) -> KernelResult<u64> {
         Ok(self.writer.pos)
     }
+
+    fn sync_data(&mut self) -> KernelResult<()> {
+        self.writer.flush()?;
+        self.writer.writer.get_ref().sync_data()?;
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
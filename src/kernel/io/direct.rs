@@ -27,6 +27,7 @@ impl DirectIoReader {
         let path = extension.path_with_gen(&dir_path, gen);
         let fs = OpenOptions::new()
             .create(true)
+            .truncate(false)
             .write(true)
             .read(true)
             .open(path)?;
@@ -49,6 +50,7 @@ impl DirectIoWriter {
         let path = extension.path_with_gen(&dir_path, gen);
         let fs = OpenOptions::new()
             .create(true)
+            .truncate(false)
             .write(true)
             .read(true)
             .open(path)?;
@@ -103,4 +105,8 @@ impl IoWriter for DirectIoWriter {
     fn current_pos(&mut self) -> KernelResult<u64> {
         Ok(self.fs.stream_position()?)
     }
+
+    fn sync_data(&mut self) -> KernelResult<()> {
+        Ok(self.fs.sync_data()?)
+    }
 }
@@ -14,6 +14,7 @@ pub enum FileExtension {
     Log,
     SSTable,
     Manifest,
+    Blob,
 }
 
 impl FileExtension {
@@ -22,6 +23,7 @@ impl FileExtension {
             FileExtension::Log => "log",
             FileExtension::SSTable => "sst",
             FileExtension::Manifest => "manifest",
+            FileExtension::Blob => "blob",
         }
     }
 
@@ -113,4 +115,7 @@ pub trait IoReader: Send + Sync + 'static + Read + Seek {
 
 pub trait IoWriter: Send + Sync + 'static + Write + Seek {
     fn current_pos(&mut self) -> KernelResult<u64>;
+
+    /// 将已写入的数据持久化至磁盘，缓冲区中的数据会先被写出
+    fn sync_data(&mut self) -> KernelResult<()>;
 }
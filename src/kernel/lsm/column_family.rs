@@ -0,0 +1,118 @@
+use crate::kernel::lsm::commit_history::ActiveTransactions;
+use crate::kernel::lsm::mem_table::{MemTable, Wal, WalRecords};
+use crate::kernel::lsm::storage::{Config, Sequence};
+use crate::kernel::lsm::table::ss_table::block::BlockCache;
+use crate::kernel::lsm::version::status::VersionStatus;
+use crate::kernel::lsm::version::Version;
+use crate::kernel::KernelResult;
+use crate::KernelError;
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::Arc;
+
+/// 默认ColumnFamily的名称
+pub const DEFAULT_COLUMN_FAMILY: &str = "default";
+
+pub(crate) const DEFAULT_COLUMN_FAMILY_PATH: &str = "column_family";
+
+/// ColumnFamily
+///
+/// 独立的键空间，各自持有MemTable、Version与Config
+/// 所有ColumnFamily共享WAL、Sequence、BlockCache与活跃事务
+pub(crate) struct ColumnFamily {
+    pub(crate) mem_table: Arc<MemTable>,
+    pub(crate) ver_status: VersionStatus,
+    pub(crate) config: Config,
+}
+
+impl ColumnFamily {
+    pub(crate) async fn new(
+        config: Config,
+        wal: &Arc<Wal>,
+        active_txs: &Arc<ActiveTransactions>,
+        wal_records: &mut WalRecords,
+        block_cache: &Arc<BlockCache>,
+    ) -> KernelResult<Self> {
+        let ver_status = VersionStatus::load_with_path(
+            config.clone(),
+            wal.log_loader_clone(),
+            Arc::clone(block_cache),
+        )?;
+        let last_sequence = ver_status.current().await.last_sequence;
+        Sequence::advance_to(last_sequence);
+
+        // Immutable MemTable依照WAL的顺序Flush，因此seq_id不大于last_sequence的数据均已持久化
+        let records = wal_records
+            .remove(&config.family_name)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(gen, mut data)| {
+                data.retain(|(_, value)| value.seq_id > last_sequence);
+                (!data.is_empty()).then_some((gen, data))
+            })
+            .collect();
+        let mem_table =
+            MemTable::with_wal(&config, Arc::clone(wal), Arc::clone(active_txs), records);
+
+        Ok(ColumnFamily {
+            mem_table: Arc::new(mem_table),
+            ver_status,
+            config,
+        })
+    }
+
+    pub(crate) fn id(&self) -> usize {
+        self.config.family_id
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.config.family_name
+    }
+
+    pub(crate) async fn current_version(&self) -> Arc<Version> {
+        self.ver_status.current().await
+    }
+
+    /// 通过Config获取所有ColumnFamily的Config，并以id作为索引
+    ///
+    /// 除Config中指定的ColumnFamily外，已存在于数据目录中的ColumnFamily会使用默认ColumnFamily的Config打开
+    pub(crate) fn configs(config: &Config) -> KernelResult<Vec<Config>> {
+        let family_path = config.path().join(DEFAULT_COLUMN_FAMILY_PATH);
+        let mut default_config = config.clone();
+        let mut family_configs = BTreeMap::new();
+
+        for (name, family_config) in std::mem::take(&mut default_config.column_families) {
+            if name == DEFAULT_COLUMN_FAMILY {
+                return Err(KernelError::NotSupport(
+                    "The default column family uses the root config",
+                ));
+            }
+            let _ = family_configs.insert(name, family_config);
+        }
+        if family_path.exists() {
+            for entry in fs::read_dir(&family_path)? {
+                let entry = entry?;
+
+                if entry.file_type()?.is_dir() {
+                    if let Some(name) = entry.file_name().to_str() {
+                        let _ = family_configs
+                            .entry(name.to_string())
+                            .or_insert_with(|| default_config.clone());
+                    }
+                }
+            }
+        }
+
+        let mut configs = vec![default_config];
+        for (family_id, (name, mut family_config)) in family_configs.into_iter().enumerate() {
+            family_config.dir_path = family_path.join(&name);
+            family_config.family_id = family_id + 1;
+            family_config.family_name = name;
+            family_config.column_families.clear();
+
+            configs.push(family_config);
+        }
+
+        Ok(configs)
+    }
+}
@@ -0,0 +1,202 @@
+use crate::kernel::lsm::comparator::{ComparableKey, Comparator};
+use crate::kernel::lsm::mem_table::ReadRange;
+use crate::kernel::lsm::range_tombstone::RangeTombstone;
+use crate::kernel::lsm::storage::Sequence;
+use bytes::Bytes;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, BTreeSet, Bound};
+use std::sync::Arc;
+
+/// 活跃事务的seq_id，所有ColumnFamily共享
+///
+/// 用于决定CommitHistory是否需要记录写入以及可裁剪的范围
+#[derive(Default)]
+pub(crate) struct ActiveTransactions {
+    seqs: Mutex<BTreeSet<i64>>,
+}
+
+impl ActiveTransactions {
+    /// 生成事务的seq_id并登记为活跃事务
+    ///
+    /// seq_id须在锁内生成，以保证此后生成seq_id的写入均能观测到该事务并记录至CommitHistory
+    pub(crate) fn begin(&self) -> i64 {
+        let mut seqs = self.seqs.lock();
+        let seq_id = Sequence::create();
+        let _ = seqs.insert(seq_id);
+
+        seq_id
+    }
+
+    /// 结束事务，结束的为最旧的事务时返回CommitHistory新的裁剪阈值
+    ///
+    /// 不存在活跃事务时以新生成的seq_id作为阈值，此后开始的事务的seq_id均大于该阈值
+    pub(crate) fn end(&self, seq_id: i64) -> Option<i64> {
+        let mut seqs = self.seqs.lock();
+        let is_oldest = seqs.first() == Some(&seq_id);
+        let _ = seqs.remove(&seq_id);
+
+        is_oldest.then(|| seqs.first().copied().unwrap_or_else(Sequence::create))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.seqs.lock().is_empty()
+    }
+}
+
+/// 最近提交的写入记录
+///
+/// 仅在存在活跃事务时记录各Key最新写入的seq_id以及范围删除标记，
+/// 最旧的活跃事务结束时裁剪其之前的记录；
+/// 记录不随MemTable的交换而转移，因此写入被Flush至Table后依旧能够检测冲突
+pub(crate) struct CommitHistory {
+    keys: BTreeMap<ComparableKey, i64>,
+    range_tombstones: Vec<RangeTombstone>,
+    comparator: Arc<dyn Comparator>,
+}
+
+impl CommitHistory {
+    pub(crate) fn new(comparator: &Arc<dyn Comparator>) -> Self {
+        CommitHistory {
+            keys: BTreeMap::new(),
+            range_tombstones: Vec::new(),
+            comparator: Arc::clone(comparator),
+        }
+    }
+
+    pub(crate) fn record_key(&mut self, key: Bytes, seq_id: i64) {
+        let seq = self
+            .keys
+            .entry(ComparableKey::new(key, &self.comparator))
+            .or_insert(seq_id);
+        *seq = (*seq).max(seq_id);
+    }
+
+    pub(crate) fn record_range_tombstone(&mut self, tombstone: RangeTombstone) {
+        self.range_tombstones.push(tombstone);
+    }
+
+    /// 移除seq_id不大于threshold的记录
+    pub(crate) fn prune(&mut self, threshold: i64) {
+        self.keys.retain(|_, seq_id| *seq_id > threshold);
+        self.range_tombstones
+            .retain(|tombstone| tombstone.seq_id > threshold);
+    }
+
+    /// 判断Key在seq_id之后是否被写入
+    pub(crate) fn is_key_written_after(&self, key: &Bytes, seq_id: i64) -> bool {
+        let key = ComparableKey::new(key.clone(), &self.comparator);
+
+        self.keys
+            .get(&key)
+            .map_or(false, |written_seq| *written_seq > seq_id)
+            || self.range_tombstones.iter().any(|tombstone| {
+                tombstone.seq_id > seq_id && tombstone.contains(&key.key, self.comparator.as_ref())
+            })
+    }
+
+    /// 判断范围内在seq_id之后是否存在写入或范围删除
+    pub(crate) fn is_range_written_after(&self, (min, max): &ReadRange, seq_id: i64) -> bool {
+        let comparator = self.comparator.as_ref();
+        let to_comparable_key = |bound: &Bound<Bytes>| {
+            bound
+                .clone()
+                .map(|key| ComparableKey::new(key, &self.comparator))
+        };
+        let (min_key, max_key) = (to_comparable_key(min), to_comparable_key(max));
+        // BTreeMap::range在范围颠倒时会panic
+        if let (
+            Bound::Included(min_key) | Bound::Excluded(min_key),
+            Bound::Included(max_key) | Bound::Excluded(max_key),
+        ) = (&min_key, &max_key)
+        {
+            if min_key > max_key {
+                return false;
+            }
+        }
+        let is_empty_range = matches!(
+            (&min_key, &max_key),
+            (Bound::Excluded(min_key), Bound::Excluded(max_key)) if min_key == max_key
+        );
+
+        (!is_empty_range
+            && self
+                .keys
+                .range((min_key, max_key))
+                .any(|(_, written_seq)| *written_seq > seq_id))
+            || self
+                .range_tombstones
+                .iter()
+                .filter(|tombstone| tombstone.seq_id > seq_id)
+                .any(|tombstone| {
+                    let is_after_min = match min {
+                        Bound::Included(key) | Bound::Excluded(key) => {
+                            comparator.compare(&tombstone.end, key).is_gt()
+                        }
+                        Bound::Unbounded => true,
+                    };
+                    let is_before_max = match max {
+                        Bound::Included(key) => comparator.compare(&tombstone.start, key).is_le(),
+                        Bound::Excluded(key) => comparator.compare(&tombstone.start, key).is_lt(),
+                        Bound::Unbounded => true,
+                    };
+                    is_after_min && is_before_max
+                })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::lsm::commit_history::{ActiveTransactions, CommitHistory};
+    use crate::kernel::lsm::comparator::{BytewiseComparator, Comparator};
+    use crate::kernel::lsm::range_tombstone::RangeTombstone;
+    use bytes::Bytes;
+    use std::collections::Bound;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_commit_history() {
+        let active_txs = ActiveTransactions::default();
+        let comparator: Arc<dyn Comparator> = Arc::new(BytewiseComparator);
+        let mut history = CommitHistory::new(&comparator);
+
+        let tx_1 = active_txs.begin();
+        let tx_2 = active_txs.begin();
+        assert!(!active_txs.is_empty());
+
+        history.record_key(Bytes::from_static(b"b"), tx_2 + 1);
+        history.record_range_tombstone(RangeTombstone::new(
+            Bytes::from_static(b"x"),
+            Bytes::from_static(b"z"),
+            tx_2 + 2,
+        ));
+
+        assert!(history.is_key_written_after(&Bytes::from_static(b"b"), tx_1));
+        assert!(!history.is_key_written_after(&Bytes::from_static(b"b"), tx_2 + 1));
+        assert!(history.is_key_written_after(&Bytes::from_static(b"y"), tx_2));
+        assert!(!history.is_key_written_after(&Bytes::from_static(b"z"), tx_2));
+
+        let range = |min: &'static [u8], max: &'static [u8]| {
+            (
+                Bound::Included(Bytes::from_static(min)),
+                Bound::Excluded(Bytes::from_static(max)),
+            )
+        };
+        assert!(history.is_range_written_after(&range(b"a", b"c"), tx_1));
+        assert!(!history.is_range_written_after(&range(b"c", b"x"), tx_1));
+        assert!(history.is_range_written_after(&range(b"c", b"y"), tx_1));
+        assert!(!history.is_range_written_after(&range(b"c", b"a"), tx_1));
+        assert!(history.is_range_written_after(&(Bound::Unbounded, Bound::Unbounded), tx_1));
+
+        // 结束的并非最旧的事务时无需裁剪
+        assert_eq!(active_txs.end(tx_2), None);
+        // 不存在活跃事务时以新的seq_id作为阈值
+        assert!(active_txs.end(tx_1).unwrap() > tx_2);
+        assert!(active_txs.is_empty());
+
+        history.prune(tx_2 + 1);
+        assert!(!history.is_key_written_after(&Bytes::from_static(b"b"), tx_1));
+        assert!(history.is_key_written_after(&Bytes::from_static(b"y"), tx_1));
+        history.prune(tx_2 + 2);
+        assert!(!history.is_range_written_after(&(Bound::Unbounded, Bound::Unbounded), i64::MIN));
+    }
+}
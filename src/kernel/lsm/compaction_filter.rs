@@ -0,0 +1,34 @@
+use bytes::Bytes;
+use std::fmt;
+
+/// CompactionFilter对单个键值对的处理结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterDecision {
+    /// 保留原有数据
+    Keep,
+    /// 移除该数据
+    Remove,
+    /// 使用新的Value替换原有数据
+    ChangeValue(Bytes),
+}
+
+/// 用户自定义的Compaction过滤器
+///
+/// 在Major压缩归并数据时对每个存活的键值对调用，删除标记、已过期的数据与未能完成合并的Merge操作数不会传入
+///
+/// Tips: 被移除的数据在压缩至最底层前会以删除标记的形式保留，以此避免更下层中的旧数据重新可见
+pub trait CompactionFilter: Send + Sync {
+    /// 过滤器名称
+    fn name(&self) -> &str;
+
+    /// level为数据压缩后所处的Level
+    fn filter(&self, level: usize, key: &[u8], value: &[u8]) -> FilterDecision;
+}
+
+impl fmt::Debug for dyn CompactionFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompactionFilter")
+            .field("name", &self.name())
+            .finish()
+    }
+}
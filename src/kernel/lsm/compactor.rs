@@ -1,8 +1,13 @@
-use crate::kernel::lsm::mem_table::{KeyValue, MemTable};
-use crate::kernel::lsm::storage::{Config, StoreInner};
+use crate::kernel::lsm::column_family::ColumnFamily;
+use crate::kernel::lsm::compaction_filter::{CompactionFilter, FilterDecision};
+use crate::kernel::lsm::mem_table::{now_millis, MemTable, SeqKeyValue, ValueMeta};
+use crate::kernel::lsm::merge_operator::merge_versions;
+use crate::kernel::lsm::range_tombstone::{cover_seq, truncate_covered, RangeTombstone};
+use crate::kernel::lsm::storage::{Config, Gen};
 use crate::kernel::lsm::table::meta::TableMeta;
 use crate::kernel::lsm::table::scope::Scope;
 use crate::kernel::lsm::table::{collect_gen, Table};
+use crate::kernel::lsm::value_log::{BlobPointer, ValueLog};
 use crate::kernel::lsm::version::edit::VersionEdit;
 use crate::kernel::lsm::version::status::VersionStatus;
 use crate::kernel::lsm::{data_sharding, MAX_LEVEL};
@@ -22,29 +27,40 @@ pub(crate) const LEVEL_0: usize = 0;
 
 /// 数据分片集
 /// 包含对应分片的Gen与数据
-pub(crate) type MergeShardingVec = Vec<(i64, Vec<KeyValue>)>;
+pub(crate) type MergeShardingVec = Vec<(i64, Vec<SeqKeyValue>)>;
 pub(crate) type DelNode = (Vec<i64>, TableMeta);
 /// Major压缩时的待删除Gen封装(N为此次Major所压缩的Level)，第一个为Level N级，第二个为Level N+1级
 pub(crate) type DelNodeTuple = (DelNode, DelNode);
 pub type SeekScope = (Scope, usize);
+/// Major压缩时加载的数据，依次为新Table的插入位置、待删除的Gen、数据分片、需保留的范围删除标记、压缩的范围
+/// 以及Blob文件的变更
+pub(crate) type LoadedData = (
+    usize,
+    DelNodeTuple,
+    MergeShardingVec,
+    Vec<RangeTombstone>,
+    Scope,
+    Vec<VersionEdit>,
+);
 
 /// Store与Compactor的交互信息
 #[derive(Debug)]
 pub enum CompactTask {
-    Seek(SeekScope),
+    /// 对指定id的ColumnFamily进行Seek Compaction
+    Seek(usize, SeekScope),
     Flush(Option<oneshot::Sender<()>>),
 }
 
 /// 压缩器
 ///
-/// 负责Minor和Major压缩
+/// 负责单个ColumnFamily的Minor和Major压缩
 pub(crate) struct Compactor {
-    store_inner: Arc<StoreInner>,
+    family: Arc<ColumnFamily>,
 }
 
 impl Compactor {
-    pub(crate) fn new(store_inner: Arc<StoreInner>) -> Self {
-        Compactor { store_inner }
+    pub(crate) fn new(family: Arc<ColumnFamily>) -> Self {
+        Compactor { family }
     }
 
     /// 检查并进行压缩 （默认为 异步、被动 的Lazy压缩）
@@ -53,19 +69,36 @@ impl Compactor {
     /// 多事务的commit脱离Compactor的耦合，
     /// 同时减少高并发事务或写入时的频繁Compaction，优先写入后统一压缩，
     /// 减少Level 0热数据的SSTable的冗余数据
+    ///
+    /// 由于所有ColumnFamily共享WAL，因此各ColumnFamily的MemTable会同时交换，
+    /// 随后各ColumnFamily由旧至新依次将等待中的Immutable MemTable进行Minor压缩
     pub(crate) async fn check_then_compaction(
-        &mut self,
+        compactors: &[Compactor],
         option_tx: Option<oneshot::Sender<()>>,
     ) -> KernelResult<()> {
-        if let Some((gen, values)) = self.mem_table().swap()? {
-            if !values.is_empty() {
-                let start = Instant::now();
-                // 目前minor触发major时是同步进行的，所以此处对live_tag是在此方法体保持存活
-                self.minor_compaction(gen, values).await?;
-                info!("[Compactor][Compaction Drop][Time: {:?}]", start.elapsed());
+        let mem_tables = compactors.iter().map(Compactor::mem_table).collect_vec();
+        let _ = MemTable::swap_with_families(&mem_tables)?;
+
+        for compactor in compactors {
+            while let Some((gen, values, range_tombstones)) =
+                compactor.mem_table().oldest_immut().await?
+            {
+                if !values.is_empty() || !range_tombstones.is_empty() {
+                    let start = Instant::now();
+                    // 目前minor触发major时是同步进行的，所以此处对live_tag是在此方法体保持存活
+                    compactor
+                        .minor_compaction(gen, values, range_tombstones)
+                        .await?;
+                    info!("[Compactor][Compaction Drop][Time: {:?}]", start.elapsed());
+                }
+                compactor.mem_table().pop_immut(gen)?;
             }
         }
 
+        for compactor in compactors {
+            compactor.blob_gc().await?;
+        }
+
         // 压缩请求响应
         if let Some(tx) = option_tx {
             tx.send(()).map_err(|_| KernelError::ChannelClose)?
@@ -76,19 +109,27 @@ impl Compactor {
 
     /// 持久化immutable_table为SSTable
     ///
-    /// 请注意：vec_values必须是依照key值有序的
+    /// 请注意：vec_values必须是依照key值有序的，range_tombstones会一同持久化至该SSTable中
     pub(crate) async fn minor_compaction(
         &self,
         gen: i64,
-        values: Vec<KeyValue>,
+        values: Vec<SeqKeyValue>,
+        range_tombstones: Vec<RangeTombstone>,
     ) -> KernelResult<()> {
-        if !values.is_empty() {
+        if !values.is_empty() || !range_tombstones.is_empty() {
+            let last_seq = values
+                .iter()
+                .map(|(_, seq_id, _)| *seq_id)
+                .chain(range_tombstones.iter().map(|tombstone| tombstone.seq_id))
+                .max()
+                .unwrap_or(0);
             let (scope, meta) = self
                 .ver_status()
                 .loader()
-                .create(
+                .create_with_range_tombstones(
                     gen,
                     values,
+                    range_tombstones,
                     LEVEL_0,
                     self.config().level_table_type[LEVEL_0],
                 )
@@ -98,7 +139,10 @@ impl Compactor {
             self.major_compaction(
                 LEVEL_0,
                 scope.clone(),
-                vec![VersionEdit::NewFile((vec![scope], 0), 0, meta)],
+                vec![
+                    VersionEdit::NewFile((vec![scope], 0), 0, meta),
+                    VersionEdit::LastSequence(last_seq),
+                ],
                 false,
             )
             .await?;
@@ -141,25 +185,48 @@ impl Compactor {
             if let Some((
                 index,
                 ((del_gens_l, del_meta_l), (del_gens_ll, del_meta_ll)),
-                vec_sharding,
+                mut vec_sharding,
+                range_tombstones,
+                fusion_scope,
+                mut blob_edits,
             )) = self
                 .data_loading_with_level(level, &scope, mem::replace(&mut is_skip_sized, false))
                 .await?
             {
                 let start = Instant::now();
+                // 保留的范围删除标记随首个分片持久化，不存在数据时则单独创建仅持有范围删除标记的SSTable
+                let is_tombstones_only = vec_sharding.is_empty() && !range_tombstones.is_empty();
+                if is_tombstones_only {
+                    vec_sharding.push((Gen::create(), Vec::new()));
+                }
+                let mut option_tombstones = Some(range_tombstones);
                 // 并行创建SSTable
                 let table_futures = vec_sharding.into_iter().map(|(gen, sharding)| {
-                    self.ver_status().loader().create(
+                    self.ver_status().loader().create_with_range_tombstones(
                         gen,
                         sharding,
+                        option_tombstones.take().unwrap_or_default(),
                         next_level,
                         config.level_table_type[next_level],
                     )
                 });
                 let vec_table_and_scope: Vec<(Scope, TableMeta)> =
                     future::try_join_all(table_futures).await?;
-                let (new_scopes, new_metas): (Vec<Scope>, Vec<TableMeta>) =
+                let (mut new_scopes, new_metas): (Vec<Scope>, Vec<TableMeta>) =
                     vec_table_and_scope.into_iter().unzip();
+                // 范围删除标记的范围可能与该Level中的其他Table相交，因此使用此次压缩的范围作为Scope
+                if is_tombstones_only {
+                    new_scopes = new_scopes
+                        .into_iter()
+                        .map(|scope| {
+                            Scope::from_range(
+                                scope.gen(),
+                                fusion_scope.start.clone(),
+                                fusion_scope.end.clone(),
+                            )
+                        })
+                        .collect_vec();
+                }
                 let fusion_meta = TableMeta::fusion(&new_metas);
 
                 vec_ver_edit.append(&mut vec![
@@ -167,6 +234,7 @@ impl Compactor {
                     VersionEdit::DeleteFile((del_gens_l, level), del_meta_l),
                     VersionEdit::DeleteFile((del_gens_ll, next_level), del_meta_ll),
                 ]);
+                vec_ver_edit.append(&mut blob_edits);
                 info!(
                     "[LsmStore][Major Compaction][recreate_sst][Level: {}][Time: {:?}]",
                     level,
@@ -194,7 +262,7 @@ impl Compactor {
         level: usize,
         target: &Scope,
         is_skip_sized: bool,
-    ) -> KernelResult<Option<(usize, DelNodeTuple, MergeShardingVec)>> {
+    ) -> KernelResult<Option<LoadedData>> {
         let version = self.ver_status().current().await;
         let config = self.config();
         let next_level = level + 1;
@@ -217,110 +285,402 @@ impl Compactor {
         }
 
         // 因此使用tables_l向下检测冲突时获取的集合应当含有tables_ll的元素
-        let fusion_scope_l = Scope::fusion(&scopes_l).unwrap_or(target.clone());
+        let comparator = config.comparator.as_ref();
+        let fusion_scope_l = Scope::fusion(&scopes_l, comparator).unwrap_or(target.clone());
         // 通过tables_l的scope获取下一级的父集
-        let (tables_ll, _, index) = version.tables_by_scopes(next_level, &fusion_scope_l);
+        let (tables_ll, scopes_ll, index) = version.tables_by_scopes(next_level, &fusion_scope_l);
+        let fusion_scope = Scope::fusion(
+            &[scopes_l.as_slice(), scopes_ll.as_slice()].concat(),
+            comparator,
+        )
+        .unwrap_or(fusion_scope_l);
 
         // 收集需要清除的SSTable
         let del_gen_l = collect_gen(&tables_l)?;
         let del_gen_ll = collect_gen(&tables_ll)?;
+        let del_gens = [del_gen_l.0.as_slice(), del_gen_ll.0.as_slice()].concat();
+
+        // 范围删除标记仅在压缩至最底层且不存在其他可能被其覆盖的Table时才可移除
+        let range_tombstones = tables_l
+            .iter()
+            .chain(tables_ll.iter())
+            .flat_map(|table| table.range_tombstones())
+            .filter(|tombstone| {
+                next_level < MAX_LEVEL - 1 || version.is_range_overlapped(tombstone, &del_gens)
+            })
+            .cloned()
+            .collect_vec();
+        // 完全被更新的范围删除标记覆盖的Table无需读取，直接删除即可
+        let is_uncovered = |(table, scope): &(&dyn Table, Scope)| {
+            !version.range_tombstones().any(|tombstone| {
+                tombstone.seq_id > table.max_seq()
+                    && tombstone.contains_range(&scope.start, &scope.end, comparator)
+            })
+        };
+        let [(tables_l, covered_l), (tables_ll, covered_ll)] =
+            [(tables_l, scopes_l), (tables_ll, scopes_ll)].map(|(tables, scopes)| {
+                let (uncovered, covered): (Vec<_>, Vec<_>) =
+                    tables.into_iter().zip(scopes).partition(is_uncovered);
+
+                (
+                    uncovered.into_iter().map(|(table, _)| table).collect_vec(),
+                    covered.into_iter().map(|(table, _)| table).collect_vec(),
+                )
+            });
+        // 被直接删除的Table所引用的Value均已失效
+        let mut discard_pointers = Vec::new();
+        for table in covered_l.iter().chain(covered_ll.iter()) {
+            if !table.blob_gens().is_empty() {
+                discard_pointers.append(&mut Self::table_load_data(table, |_| false)?.1);
+            }
+        }
 
         // 数据合并并切片
-        let vec_merge_sharding =
-            Self::data_merge_and_sharding(tables_l, tables_ll, config.sst_file_size).await?;
+        let (vec_merge_sharding, mut blob_edits) = Self::data_merge_and_sharding(
+            tables_l,
+            tables_ll,
+            config,
+            version.value_log(),
+            next_level,
+        )
+        .await?;
+        blob_edits.extend(Self::blob_discard_edits(discard_pointers));
         info!(
             "[LsmStore][Major Compaction][data_loading_with_level][Time: {:?}]",
             start.elapsed()
         );
 
-        Ok(Some((index, (del_gen_l, del_gen_ll), vec_merge_sharding)))
+        Ok(Some((
+            index,
+            (del_gen_l, del_gen_ll),
+            vec_merge_sharding,
+            range_tombstones,
+            fusion_scope,
+            blob_edits,
+        )))
     }
 
     /// 以SSTables的数据归并再排序后切片，获取以KeyValue的Key值由小到大的切片排序
     /// 1. 并行获取Level l(当前等级)的待合并SSTables_l的全量数据
     /// 2. 基于SSTables_l获取唯一KeySet用于迭代过滤
     /// 3. 并行对Level ll的SSTables_ll通过KeySet进行迭代同时过滤数据
-    /// 4. 组合SSTables_l和SSTables_ll的数据合并并进行唯一，排序处理
+    /// 4. 组合SSTables_l和SSTables_ll的数据合并并进行唯一，排序处理，Merge操作数会与更旧的数据合并，详见`merge_versions`
+    /// 5. 清除已过期以及被CompactionFilter移除的数据，详见`Compactor::merge_filter`
+    /// 6. 丢弃被tables中的范围删除标记覆盖的数据
+    /// 7. 将不小于阈值的Value分离至新的Blob文件中，并统计不再被引用的BlobPointer，详见`ValueLog::separate`
     #[allow(clippy::mutable_key_type)]
     async fn data_merge_and_sharding(
         tables_l: Vec<&dyn Table>,
         tables_ll: Vec<&dyn Table>,
-        file_size: usize,
-    ) -> KernelResult<MergeShardingVec> {
+        config: &Config,
+        value_log: &ValueLog,
+        next_level: usize,
+    ) -> KernelResult<(MergeShardingVec, Vec<VersionEdit>)> {
         // SSTables的Gen会基于时间有序生成,所有以此作为SSTables的排序依据
         let map_futures_l = tables_l
             .iter()
             .sorted_unstable_by_key(|table| table.gen())
             .map(|table| async { Self::table_load_data(table, |_| true) });
 
-        let sharding_l = future::try_join_all(map_futures_l).await?;
+        let (sharding_l, _): (Vec<_>, Vec<_>) = future::try_join_all(map_futures_l)
+            .await?
+            .into_iter()
+            .unzip();
 
         // 获取Level l的唯一KeySet用于Level ll的迭代过滤数据
+        // 仅存在Merge操作数的Key仍需要与Level ll中更旧的数据合并，因此不进行过滤
         let filter_set_l: HashSet<&Bytes> = sharding_l
             .iter()
             .flatten()
-            .map(|key_value| &key_value.0)
+            .filter(|(.., meta)| !meta.is_merge)
+            .map(|((key, _), ..)| key)
             .collect();
 
         // 通过KeySet过滤出Level l中需要补充的数据
         // 并行: 因为即使l为0时，此时的ll(Level 1)仍然保证SSTable数据之间排列有序且不冲突，因此并行迭代不会导致数据冲突
         // 过滤: 基于l进行数据过滤避免冗余的数据迭代导致占用大量内存占用
-        let sharding_ll = future::try_join_all(tables_ll.iter().map(|table| async {
-            Self::table_load_data(table, |key| !filter_set_l.contains(key))
-        }))
-        .await?;
+        let (sharding_ll, skipped_pointers): (Vec<_>, Vec<_>) =
+            future::try_join_all(tables_ll.iter().map(|table| async {
+                Self::table_load_data(table, |key| !filter_set_l.contains(key))
+            }))
+            .await?
+            .into_iter()
+            .unzip();
+        let mut input_pointers = skipped_pointers.into_iter().flatten().collect_vec();
+        for item in sharding_l.iter().chain(sharding_ll.iter()).flatten() {
+            input_pointers.extend(BlobPointer::from_item(item)?);
+        }
 
+        let now = now_millis();
+        let is_bottom = next_level == MAX_LEVEL - 1;
+        let range_tombstones = tables_l
+            .iter()
+            .chain(tables_ll.iter())
+            .flat_map(|table| table.range_tombstones())
+            .collect_vec();
+        let mut vec_cmd_data = Vec::new();
         // 使用sharding_ll来链接sharding_l以保持数据倒序的顺序是由新->旧
-        let vec_cmd_data = sharding_ll
+        // 稳定排序后同一Key的数据依旧由新至旧相邻排列
+        for (key, versions) in &sharding_ll
             .into_iter()
             .chain(sharding_l)
             .flatten()
             .rev()
-            .unique_by(|(key, _)| key.clone())
-            .sorted_unstable_by_key(|(key, _)| key.clone())
-            .collect();
-        Ok(data_sharding(vec_cmd_data, file_size))
+            .sorted_by(|((key_a, _), ..), ((key_b, _), ..)| config.comparator.compare(key_a, key_b))
+            .group_by(|((key, _), ..)| key.clone())
+        {
+            let cover = cover_seq(
+                range_tombstones.iter().copied(),
+                &key,
+                None,
+                config.comparator.as_ref(),
+            );
+            let Some(mut versions) = truncate_covered(versions.collect_vec(), cover) else {
+                continue;
+            };
+            // Merge操作数需要与实际的Value合并，因此获取被分离的合并基准
+            if versions.first().map_or(false, |(.., meta)| meta.is_merge) {
+                if let Some(position) = versions.iter().position(|(.., meta)| !meta.is_merge) {
+                    let base = versions.remove(position);
+                    versions.insert(position, value_log.resolve_item(base)?);
+                }
+            }
+
+            if let Some(item) =
+                merge_versions(versions, config.merge_operator.as_deref(), now, is_bottom)?
+            {
+                if let Some(item) = Self::merge_filter(
+                    item,
+                    now,
+                    next_level,
+                    config.compaction_filter.as_deref(),
+                    value_log,
+                )? {
+                    vec_cmd_data.push(item);
+                }
+            }
+        }
+        let (vec_cmd_data, new_blobs) = value_log.separate(vec_cmd_data)?;
+
+        let output_pointers: HashSet<BlobPointer> = vec_cmd_data
+            .iter()
+            .map(BlobPointer::from_item)
+            .flatten_ok()
+            .try_collect()?;
+        input_pointers.retain(|pointer| !output_pointers.contains(pointer));
+        let blob_edits = new_blobs
+            .into_iter()
+            .map(|(gen, size)| VersionEdit::NewBlob(gen, size))
+            .chain(Self::blob_discard_edits(input_pointers))
+            .collect_vec();
+
+        Ok((
+            data_sharding(vec_cmd_data, config.sst_file_size),
+            blob_edits,
+        ))
     }
 
-    fn table_load_data<F>(table: &&dyn Table, fn_is_filter: F) -> KernelResult<Vec<KeyValue>>
+    /// 将失效的BlobPointer按所在的Blob文件汇总为VersionEdit
+    fn blob_discard_edits(pointers: Vec<BlobPointer>) -> Vec<VersionEdit> {
+        pointers
+            .into_iter()
+            .unique()
+            .into_group_map_by(|pointer| pointer.gen)
+            .into_iter()
+            .sorted_by_key(|(gen, _)| *gen)
+            .map(|(gen, pointers)| {
+                VersionEdit::BlobDiscard(
+                    gen,
+                    pointers.iter().map(|pointer| pointer.len as u64).sum(),
+                )
+            })
+            .collect_vec()
+    }
+
+    /// 对失效数据占比超出阈值的Blob文件进行GC
+    ///
+    /// 将引用该Blob文件的Table中仍存活的Value重新分离至新的Blob文件中，
+    /// 并以同一位置的新Table替换原Table后删除该Blob文件
+    pub(crate) async fn blob_gc(&self) -> KernelResult<()> {
+        let config = self.config();
+        let version = self.ver_status().current().await;
+        let Some(blob_gen) = version.blob_gc_candidate(config.value_log_gc_ratio) else {
+            return Ok(());
+        };
+        let start = Instant::now();
+        let loader = self.ver_status().loader();
+        let value_log = version.value_log();
+        let mut vec_ver_edit = Vec::new();
+
+        for level in 1..MAX_LEVEL {
+            for (index, scope) in version.level_slice[level].iter().enumerate() {
+                let Some(table) = version.table(level, index) else {
+                    continue;
+                };
+                if !table.blob_gens().contains(&blob_gen) {
+                    continue;
+                }
+                let mut vec_data = Self::table_load_data(&table, |_| true)?.0;
+                for item in vec_data.iter_mut() {
+                    if BlobPointer::from_item(item)?
+                        .map_or(false, |pointer| pointer.gen == blob_gen)
+                    {
+                        *item = value_log.resolve_item(mem::replace(
+                            item,
+                            ((Bytes::new(), None), 0, ValueMeta::default()),
+                        ))?;
+                    }
+                }
+                let (vec_data, new_blobs) = value_log.separate(vec_data)?;
+                let new_gen = Gen::create();
+                let (_, meta) = loader
+                    .create_with_range_tombstones(
+                        new_gen,
+                        vec_data,
+                        table.range_tombstones().to_vec(),
+                        level,
+                        config.level_table_type[level],
+                    )
+                    .await?;
+
+                // 沿用原Table的范围以保持该Level中Table的位置与范围不变
+                vec_ver_edit.append(&mut vec![
+                    VersionEdit::DeleteFile((vec![scope.gen()], level), TableMeta::from(table)),
+                    VersionEdit::NewFile(
+                        (
+                            vec![Scope::from_range(
+                                new_gen,
+                                scope.start.clone(),
+                                scope.end.clone(),
+                            )],
+                            level,
+                        ),
+                        index,
+                        meta,
+                    ),
+                ]);
+                vec_ver_edit.extend(
+                    new_blobs
+                        .into_iter()
+                        .map(|(gen, size)| VersionEdit::NewBlob(gen, size)),
+                );
+            }
+        }
+        vec_ver_edit.push(VersionEdit::DeleteBlob(vec![blob_gen]));
+
+        self.ver_status()
+            .log_and_apply(vec_ver_edit, config.ver_log_snapshot_threshold)
+            .await?;
+        info!(
+            "[Compactor][Blob GC][Blob: {}][Time: {:?}]",
+            blob_gen,
+            start.elapsed()
+        );
+
+        Ok(())
+    }
+
+    /// 对归并后的键值对进行过期判断以及CompactionFilter的处理
+    ///
+    /// 非最底层时被移除的数据需要以删除标记的形式保留，避免更下层中的旧数据重新可见，
+    /// 压缩至最底层时则直接丢弃
+    ///
+    /// 被分离的Value仅在交由CompactionFilter处理时读取
+    fn merge_filter(
+        item: SeqKeyValue,
+        now: i64,
+        next_level: usize,
+        filter: Option<&dyn CompactionFilter>,
+        value_log: &ValueLog,
+    ) -> KernelResult<Option<SeqKeyValue>> {
+        let ((key, value), seq_id, meta) = item;
+        let Some(value) = value else {
+            return Ok(Some(((key, None), seq_id, ValueMeta::default())));
+        };
+        // Merge操作数在与更旧的数据合并前无法确定其Value，因此不交由CompactionFilter处理
+        if meta.is_merge {
+            return Ok(Some(((key, Some(value)), seq_id, meta)));
+        }
+        let decision = if meta.expire_at.map_or(false, |expire_at| expire_at <= now) {
+            FilterDecision::Remove
+        } else if let Some(filter) = filter {
+            if meta.is_blob {
+                filter.filter(next_level, &key, &value_log.resolve(&value)?)
+            } else {
+                filter.filter(next_level, &key, &value)
+            }
+        } else {
+            FilterDecision::Keep
+        };
+
+        Ok(match decision {
+            FilterDecision::Keep => Some(((key, Some(value)), seq_id, meta)),
+            FilterDecision::ChangeValue(value) => Some((
+                (key, Some(value)),
+                seq_id,
+                ValueMeta {
+                    is_blob: false,
+                    ..meta
+                },
+            )),
+            FilterDecision::Remove => {
+                (next_level < MAX_LEVEL - 1).then_some(((key, None), seq_id, ValueMeta::default()))
+            }
+        })
+    }
+
+    /// 加载Table中满足fn_is_filter的数据，同时返回被过滤的数据中的BlobPointer
+    fn table_load_data<F>(
+        table: &&dyn Table,
+        fn_is_filter: F,
+    ) -> KernelResult<(Vec<SeqKeyValue>, Vec<BlobPointer>)>
     where
         F: Fn(&Bytes) -> bool,
     {
         let mut iter = table.iter()?;
         let mut vec_cmd = Vec::with_capacity(table.len());
+        let mut skipped_pointers = Vec::new();
         while let Some(item) = iter.try_next()? {
-            if fn_is_filter(&item.0) {
+            if fn_is_filter(&item.0 .0) {
                 vec_cmd.push(item)
+            } else if let Some(pointer) = BlobPointer::from_item(&item)? {
+                skipped_pointers.push(pointer);
             }
         }
-        Ok(vec_cmd)
+        Ok((vec_cmd, skipped_pointers))
     }
 
     pub(crate) fn config(&self) -> &Config {
-        &self.store_inner.config
+        &self.family.config
     }
 
     pub(crate) fn mem_table(&self) -> &MemTable {
-        &self.store_inner.mem_table
+        &self.family.mem_table
     }
 
     pub(crate) fn ver_status(&self) -> &VersionStatus {
-        &self.store_inner.ver_status
+        &self.family.ver_status
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::kernel::io::{FileExtension, IoFactory, IoType};
+    use crate::kernel::lsm::compaction_filter::{CompactionFilter, FilterDecision};
     use crate::kernel::lsm::compactor::{Compactor, LEVEL_0};
+    use crate::kernel::lsm::mem_table::ValueMeta;
+    use crate::kernel::lsm::merge_operator::MergeOperands;
     use crate::kernel::lsm::storage::{Config, KipStorage, StoreInner};
+    use crate::kernel::lsm::table::btree_table::BTreeTable;
     use crate::kernel::lsm::table::meta::TableMeta;
     use crate::kernel::lsm::table::scope::Scope;
     use crate::kernel::lsm::table::ss_table::SSTable;
     use crate::kernel::lsm::table::TableType;
     use crate::kernel::lsm::trigger::TriggerType;
+    use crate::kernel::lsm::value_log::ValueLog;
     use crate::kernel::lsm::version::edit::VersionEdit;
     use crate::kernel::lsm::version::DEFAULT_SS_TABLE_PATH;
+    use crate::kernel::lsm::MAX_LEVEL;
     use crate::kernel::utils::lru_cache::ShardingLruCache;
     use crate::kernel::{KernelResult, Storage};
     use bytes::Bytes;
@@ -430,9 +790,21 @@ mod tests {
             Arc::clone(&cache),
             1,
             vec![
-                (Bytes::from_static(b"1"), Some(Bytes::from_static(b"1"))),
-                (Bytes::from_static(b"2"), Some(Bytes::from_static(b"2"))),
-                (Bytes::from_static(b"3"), Some(Bytes::from_static(b"31"))),
+                (
+                    (Bytes::from_static(b"1"), Some(Bytes::from_static(b"1"))),
+                    3,
+                    ValueMeta::default(),
+                ),
+                (
+                    (Bytes::from_static(b"2"), Some(Bytes::from_static(b"2"))),
+                    3,
+                    ValueMeta::default(),
+                ),
+                (
+                    (Bytes::from_static(b"3"), Some(Bytes::from_static(b"31"))),
+                    3,
+                    ValueMeta::default(),
+                ),
             ],
             0,
             IoType::Direct,
@@ -444,8 +816,16 @@ mod tests {
             Arc::clone(&cache),
             2,
             vec![
-                (Bytes::from_static(b"3"), Some(Bytes::from_static(b"3"))),
-                (Bytes::from_static(b"4"), Some(Bytes::from_static(b"4"))),
+                (
+                    (Bytes::from_static(b"3"), Some(Bytes::from_static(b"3"))),
+                    4,
+                    ValueMeta::default(),
+                ),
+                (
+                    (Bytes::from_static(b"4"), Some(Bytes::from_static(b"4"))),
+                    4,
+                    ValueMeta::default(),
+                ),
             ],
             0,
             IoType::Direct,
@@ -457,8 +837,16 @@ mod tests {
             Arc::clone(&cache),
             3,
             vec![
-                (Bytes::from_static(b"1"), Some(Bytes::from_static(b"11"))),
-                (Bytes::from_static(b"2"), Some(Bytes::from_static(b"21"))),
+                (
+                    (Bytes::from_static(b"1"), Some(Bytes::from_static(b"11"))),
+                    1,
+                    ValueMeta::default(),
+                ),
+                (
+                    (Bytes::from_static(b"2"), Some(Bytes::from_static(b"21"))),
+                    1,
+                    ValueMeta::default(),
+                ),
             ],
             1,
             IoType::Direct,
@@ -470,9 +858,21 @@ mod tests {
             Arc::clone(&cache),
             4,
             vec![
-                (Bytes::from_static(b"3"), Some(Bytes::from_static(b"32"))),
-                (Bytes::from_static(b"4"), Some(Bytes::from_static(b"41"))),
-                (Bytes::from_static(b"5"), Some(Bytes::from_static(b"5"))),
+                (
+                    (Bytes::from_static(b"3"), Some(Bytes::from_static(b"32"))),
+                    2,
+                    ValueMeta::default(),
+                ),
+                (
+                    (Bytes::from_static(b"4"), Some(Bytes::from_static(b"41"))),
+                    2,
+                    ValueMeta::default(),
+                ),
+                (
+                    (Bytes::from_static(b"5"), Some(Bytes::from_static(b"5"))),
+                    2,
+                    ValueMeta::default(),
+                ),
             ],
             1,
             IoType::Direct,
@@ -482,20 +882,254 @@ mod tests {
         let (_, vec_data) = &Compactor::data_merge_and_sharding(
             vec![&ss_table_1, &ss_table_2],
             vec![&ss_table_3, &ss_table_4],
-            config.sst_file_size,
+            &config,
+            &ValueLog::new(&config)?,
+            1,
+        )
+        .await?
+        .0[0];
+
+        assert_eq!(
+            vec_data,
+            &vec![
+                (
+                    (Bytes::from_static(b"1"), Some(Bytes::from_static(b"1"))),
+                    3,
+                    ValueMeta::default()
+                ),
+                (
+                    (Bytes::from_static(b"2"), Some(Bytes::from_static(b"2"))),
+                    3,
+                    ValueMeta::default()
+                ),
+                (
+                    (Bytes::from_static(b"3"), Some(Bytes::from_static(b"3"))),
+                    4,
+                    ValueMeta::default()
+                ),
+                (
+                    (Bytes::from_static(b"4"), Some(Bytes::from_static(b"4"))),
+                    4,
+                    ValueMeta::default()
+                ),
+                (
+                    (Bytes::from_static(b"5"), Some(Bytes::from_static(b"5"))),
+                    2,
+                    ValueMeta::default()
+                )
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_data_merge_with_expire() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let value = Some(Bytes::from_static(b"v"));
+        let table_l = BTreeTable::new(
+            1,
+            1,
+            vec![
+                (
+                    (Bytes::from_static(b"1"), value.clone()),
+                    3,
+                    ValueMeta::with_expire(Some(0)),
+                ),
+                (
+                    (Bytes::from_static(b"2"), value.clone()),
+                    3,
+                    ValueMeta::with_expire(Some(i64::MAX)),
+                ),
+            ],
+            &config.comparator,
+        );
+        let table_ll = BTreeTable::new(
+            2,
+            2,
+            vec![
+                (
+                    (Bytes::from_static(b"1"), value.clone()),
+                    1,
+                    ValueMeta::default(),
+                ),
+                (
+                    (Bytes::from_static(b"3"), value.clone()),
+                    1,
+                    ValueMeta::with_expire(Some(0)),
+                ),
+            ],
+            &config.comparator,
+        );
+
+        // 非最底层时过期数据转换为删除标记，以覆盖更下层中的旧数据
+        let (_, vec_data) = &Compactor::data_merge_and_sharding(
+            vec![&table_l],
+            vec![&table_ll],
+            &config,
+            &ValueLog::new(&config)?,
+            1,
         )
-        .await?[0];
+        .await?
+        .0[0];
+        assert_eq!(
+            vec_data,
+            &vec![
+                ((Bytes::from_static(b"1"), None), 3, ValueMeta::default()),
+                (
+                    (Bytes::from_static(b"2"), value.clone()),
+                    3,
+                    ValueMeta::with_expire(Some(i64::MAX))
+                ),
+                ((Bytes::from_static(b"3"), None), 1, ValueMeta::default()),
+            ]
+        );
 
+        // 最底层时过期数据被直接丢弃
+        let (_, vec_data) = &Compactor::data_merge_and_sharding(
+            vec![&table_l],
+            vec![&table_ll],
+            &config,
+            &ValueLog::new(&config)?,
+            MAX_LEVEL - 1,
+        )
+        .await?
+        .0[0];
+        assert_eq!(
+            vec_data,
+            &vec![(
+                (Bytes::from_static(b"2"), value.clone()),
+                3,
+                ValueMeta::with_expire(Some(i64::MAX))
+            )]
+        );
+
+        Ok(())
+    }
+
+    struct TenantFilter;
+
+    impl CompactionFilter for TenantFilter {
+        fn name(&self) -> &str {
+            "TenantFilter"
+        }
+
+        fn filter(&self, _level: usize, key: &[u8], value: &[u8]) -> FilterDecision {
+            if key.starts_with(b"deleted_") {
+                FilterDecision::Remove
+            } else if value == b"old" {
+                FilterDecision::ChangeValue(Bytes::from_static(b"new"))
+            } else {
+                FilterDecision::Keep
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_data_merge_with_filter() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path()).compaction_filter(TenantFilter);
+        let (old, new) = (Bytes::from_static(b"old"), Bytes::from_static(b"new"));
+        let table_l = BTreeTable::new(
+            1,
+            1,
+            vec![
+                (
+                    (Bytes::from_static(b"deleted_1"), Some(old.clone())),
+                    3,
+                    ValueMeta::default(),
+                ),
+                (
+                    (Bytes::from_static(b"tenant_1"), Some(old.clone())),
+                    3,
+                    ValueMeta::with_expire(Some(i64::MAX)),
+                ),
+            ],
+            &config.comparator,
+        );
+        let table_ll = BTreeTable::new(
+            2,
+            2,
+            vec![
+                (
+                    (Bytes::from_static(b"deleted_2"), None),
+                    1,
+                    ValueMeta::default(),
+                ),
+                (
+                    (Bytes::from_static(b"tenant_2"), Some(new.clone())),
+                    1,
+                    ValueMeta::default(),
+                ),
+            ],
+            &config.comparator,
+        );
+
+        let (_, vec_data) = &Compactor::data_merge_and_sharding(
+            vec![&table_l],
+            vec![&table_ll],
+            &config,
+            &ValueLog::new(&config)?,
+            1,
+        )
+        .await?
+        .0[0];
         assert_eq!(
             vec_data,
             &vec![
-                (Bytes::from_static(b"1"), Some(Bytes::from_static(b"1"))),
-                (Bytes::from_static(b"2"), Some(Bytes::from_static(b"2"))),
-                (Bytes::from_static(b"3"), Some(Bytes::from_static(b"3"))),
-                (Bytes::from_static(b"4"), Some(Bytes::from_static(b"4"))),
-                (Bytes::from_static(b"5"), Some(Bytes::from_static(b"5")))
+                (
+                    (Bytes::from_static(b"deleted_1"), None),
+                    3,
+                    ValueMeta::default()
+                ),
+                (
+                    (Bytes::from_static(b"deleted_2"), None),
+                    1,
+                    ValueMeta::default()
+                ),
+                (
+                    (Bytes::from_static(b"tenant_1"), Some(new.clone())),
+                    3,
+                    ValueMeta::with_expire(Some(i64::MAX))
+                ),
+                (
+                    (Bytes::from_static(b"tenant_2"), Some(new.clone())),
+                    1,
+                    ValueMeta::default()
+                ),
             ]
         );
+
+        let (_, vec_data) = &Compactor::data_merge_and_sharding(
+            vec![&table_l],
+            vec![&table_ll],
+            &config,
+            &ValueLog::new(&config)?,
+            MAX_LEVEL - 1,
+        )
+        .await?
+        .0[0];
+        assert_eq!(
+            vec_data,
+            &vec![
+                (
+                    (Bytes::from_static(b"deleted_2"), None),
+                    1,
+                    ValueMeta::default()
+                ),
+                (
+                    (Bytes::from_static(b"tenant_1"), Some(new.clone())),
+                    3,
+                    ValueMeta::with_expire(Some(i64::MAX))
+                ),
+                (
+                    (Bytes::from_static(b"tenant_2"), Some(new)),
+                    1,
+                    ValueMeta::default()
+                ),
+            ]
+        );
+
         Ok(())
     }
 
@@ -513,7 +1147,7 @@ mod tests {
 
         tokio_test::block_on(async move {
             let inner = StoreInner::new(config).await?;
-            let compactor = Compactor::new(Arc::new(inner));
+            let compactor = Compactor::new(Arc::clone(inner.default_family()));
             let version_status = compactor.ver_status();
             let table_loader = version_status.loader();
 
@@ -521,8 +1155,8 @@ mod tests {
                 .create(
                     1,
                     vec![
-                        (Bytes::from_static(b"1"), None),
-                        (Bytes::from_static(b"2"), None),
+                        ((Bytes::from_static(b"1"), None), 0, ValueMeta::default()),
+                        ((Bytes::from_static(b"2"), None), 0, ValueMeta::default()),
                     ],
                     1,
                     TableType::BTree,
@@ -532,9 +1166,9 @@ mod tests {
                 .create(
                     2,
                     vec![
-                        (Bytes::from_static(b"3"), None),
-                        (Bytes::from_static(b"5"), None),
-                        (Bytes::from_static(b"6"), None),
+                        ((Bytes::from_static(b"3"), None), 0, ValueMeta::default()),
+                        ((Bytes::from_static(b"5"), None), 0, ValueMeta::default()),
+                        ((Bytes::from_static(b"6"), None), 0, ValueMeta::default()),
                     ],
                     1,
                     TableType::BTree,
@@ -544,8 +1178,8 @@ mod tests {
                 .create(
                     3,
                     vec![
-                        (Bytes::from_static(b"1"), None),
-                        (Bytes::from_static(b"2"), None),
+                        ((Bytes::from_static(b"1"), None), 0, ValueMeta::default()),
+                        ((Bytes::from_static(b"2"), None), 0, ValueMeta::default()),
                     ],
                     2,
                     TableType::BTree,
@@ -555,8 +1189,8 @@ mod tests {
                 .create(
                     4,
                     vec![
-                        (Bytes::from_static(b"3"), None),
-                        (Bytes::from_static(b"4"), None),
+                        ((Bytes::from_static(b"3"), None), 0, ValueMeta::default()),
+                        ((Bytes::from_static(b"4"), None), 0, ValueMeta::default()),
                     ],
                     2,
                     TableType::BTree,
@@ -566,8 +1200,8 @@ mod tests {
                 .create(
                     5,
                     vec![
-                        (Bytes::from_static(b"5"), None),
-                        (Bytes::from_static(b"6"), None),
+                        ((Bytes::from_static(b"5"), None), 0, ValueMeta::default()),
+                        ((Bytes::from_static(b"6"), None), 0, ValueMeta::default()),
                     ],
                     2,
                     TableType::BTree,
@@ -596,7 +1230,9 @@ mod tests {
             let mut failure_count = 0;
             loop {
                 failure_count += 1;
-                if let (_, Some((scope, level))) = version_1.query(b"4")? {
+                if let (_, Some((scope, level))) =
+                    version_1.query(b"4", None, &mut MergeOperands::default())?
+                {
                     compactor
                         .major_compaction(level, scope, vec![], true)
                         .await?;
@@ -632,4 +1268,66 @@ mod tests {
             Ok(())
         })
     }
+
+    #[tokio::test]
+    async fn test_immut_flush_and_recover() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let (key_1, key_2) = (Bytes::from_static(b"k1"), Bytes::from_static(b"k2"));
+        let (value_1, value_2) = (Bytes::from_static(b"1"), Bytes::from_static(b"2"));
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+
+        let inner = StoreInner::new(config.clone()).await?;
+        let compactors = vec![Compactor::new(Arc::clone(inner.default_family()))];
+        let mem_table = &inner.default_family().mem_table;
+
+        let _ = mem_table
+            .insert_data((key_1.clone(), Some(value_1.clone())))
+            .await?;
+        inner.flush_try(&tx)?;
+        let _ = mem_table
+            .insert_data((key_1.clone(), Some(value_2.clone())))
+            .await?;
+        inner.flush_try(&tx)?;
+        let _ = mem_table
+            .insert_data((key_2.clone(), Some(value_1.clone())))
+            .await?;
+        assert_eq!(mem_table.immut_count(), 2);
+
+        // 仅Flush最旧的Immutable MemTable
+        let (gen, values, range_tombstones) = mem_table.oldest_immut().await?.unwrap();
+        compactors[0]
+            .minor_compaction(gen, values, range_tombstones)
+            .await?;
+        mem_table.pop_immut(gen)?;
+        drop((compactors, inner));
+
+        // 已持久化的数据通过last_sequence过滤，其余数据恢复至Immutable MemTable与_mem中
+        let inner = StoreInner::new(config).await?;
+        let compactors = vec![Compactor::new(Arc::clone(inner.default_family()))];
+        let mem_table = &inner.default_family().mem_table;
+        assert_eq!(mem_table.immut_count(), 1);
+        assert_eq!(mem_table.len(), 1);
+        assert_eq!(
+            mem_table.find(&key_1),
+            Some((key_1.clone(), Some(value_2.clone())))
+        );
+
+        Compactor::check_then_compaction(&compactors, None).await?;
+        assert_eq!(mem_table.immut_count(), 0);
+        assert!(mem_table.is_empty());
+
+        let version = inner.default_family().current_version().await;
+        let mut operands = MergeOperands::default();
+        assert_eq!(
+            version.query(&key_1, None, &mut operands)?.0,
+            Some((key_1, Some(value_2)))
+        );
+        assert_eq!(
+            version.query(&key_2, None, &mut operands)?.0,
+            Some((key_2, Some(value_1)))
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,124 @@
+use bytes::Bytes;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+/// Key的排序规则
+///
+/// 名称会被持久化至Version中，数据库无法使用不同名称的Comparator重新打开
+///
+/// Tips: 仅当两个Key的字节完全相同时才可返回`Ordering::Equal`
+pub trait Comparator: Send + Sync {
+    /// 排序规则名称
+    fn name(&self) -> &str;
+
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering;
+}
+
+impl fmt::Debug for dyn Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Comparator")
+            .field("name", &self.name())
+            .finish()
+    }
+}
+
+/// 默认的按字节序排序的Comparator
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BytewiseComparator;
+
+impl Comparator for BytewiseComparator {
+    fn name(&self) -> &str {
+        "kipdb.BytewiseComparator"
+    }
+
+    fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// 通过Comparator进行排序的Key，用于BTreeMap等依赖Ord的容器
+#[derive(Clone)]
+pub(crate) struct ComparableKey {
+    pub(crate) key: Bytes,
+    comparator: Arc<dyn Comparator>,
+}
+
+impl ComparableKey {
+    pub(crate) fn new(key: Bytes, comparator: &Arc<dyn Comparator>) -> Self {
+        ComparableKey {
+            key,
+            comparator: Arc::clone(comparator),
+        }
+    }
+}
+
+impl fmt::Debug for ComparableKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.key.fmt(f)
+    }
+}
+
+impl PartialEq for ComparableKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for ComparableKey {}
+
+impl PartialOrd<Self> for ComparableKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ComparableKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.comparator.compare(&self.key, &other.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::lsm::comparator::{BytewiseComparator, ComparableKey, Comparator};
+    use bytes::Bytes;
+    use itertools::Itertools;
+    use std::cmp::Ordering;
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+
+    struct ReverseComparator;
+
+    impl Comparator for ReverseComparator {
+        fn name(&self) -> &str {
+            "reverse"
+        }
+
+        fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+            b.cmp(a)
+        }
+    }
+
+    #[test]
+    fn test_comparable_key() {
+        let keys = [b"1", b"3", b"2"].map(|key| Bytes::from_static(key));
+        let collect = |comparator: Arc<dyn Comparator>| {
+            keys.iter()
+                .map(|key| ComparableKey::new(key.clone(), &comparator))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|key| key.key)
+                .collect_vec()
+        };
+
+        assert_eq!(
+            collect(Arc::new(BytewiseComparator)),
+            vec![keys[0].clone(), keys[2].clone(), keys[1].clone()]
+        );
+        assert_eq!(
+            collect(Arc::new(ReverseComparator)),
+            vec![keys[1].clone(), keys[2].clone(), keys[0].clone()]
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::kernel::lsm::compactor::LEVEL_0;
-use crate::kernel::lsm::iterator::{Iter, Seek, SeekIter};
-use crate::kernel::lsm::mem_table::KeyValue;
+use crate::kernel::lsm::iterator::seq_iter::BoxSeqIter;
+use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
+use crate::kernel::lsm::mem_table::SeqKeyValue;
 use crate::kernel::lsm::version::Version;
 use crate::kernel::KernelResult;
 use crate::KernelError;
@@ -13,7 +14,7 @@ pub(crate) struct LevelIter<'a> {
     level_len: usize,
 
     offset: usize,
-    child_iter: Box<dyn SeekIter<'a, Item = KeyValue> + 'a + Sync + Send>,
+    child_iter: BoxSeqIter<'a>,
 }
 
 impl<'a> LevelIter<'a> {
@@ -55,15 +56,18 @@ impl<'a> LevelIter<'a> {
 }
 
 impl<'a> Iter<'a> for LevelIter<'a> {
-    type Item = KeyValue;
+    type Item = SeqKeyValue;
 
     fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
-        match self.child_iter.try_next()? {
-            None => {
-                self.child_iter_seek(Seek::First, self.offset + 1)?;
-                self.child_iter.try_next()
+        loop {
+            if let Some(item) = self.child_iter.try_next()? {
+                return Ok(Some(item));
             }
-            Some(item) => Ok(Some(item)),
+            // 停留在最后一个Table上，以保证后续try_prev的正确
+            if self.offset + 1 >= self.level_len {
+                return Ok(None);
+            }
+            self.child_iter_seek(Seek::First, self.offset + 1)?;
         }
     }
 
@@ -72,6 +76,20 @@ impl<'a> Iter<'a> for LevelIter<'a> {
     }
 }
 
+impl<'a> ForwardIter<'a> for LevelIter<'a> {
+    fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
+        loop {
+            if let Some(item) = self.child_iter.try_prev()? {
+                return Ok(Some(item));
+            }
+            if self.offset == 0 {
+                return Ok(None);
+            }
+            self.child_iter_seek(Seek::Last, self.offset - 1)?;
+        }
+    }
+}
+
 impl<'a> SeekIter<'a> for LevelIter<'a> {
     /// Tips: Level 0的LevelIter不支持Seek
     /// 因为Level 0中的SSTable并非有序排列，其中数据范围是可能交错的
@@ -79,7 +97,7 @@ impl<'a> SeekIter<'a> for LevelIter<'a> {
         match seek {
             Seek::First => self.child_iter_seek(Seek::First, 0),
             Seek::Last => self.child_iter_seek(Seek::Last, self.level_len - 1),
-            Seek::Backward(key) => self.seek_ward(key, seek),
+            Seek::Backward(key) | Seek::Forward(key) => self.seek_ward(key, seek),
         }
     }
 }
@@ -88,17 +106,20 @@ impl<'a> SeekIter<'a> for LevelIter<'a> {
 mod tests {
     use crate::kernel::io::IoType;
     use crate::kernel::lsm::iterator::level_iter::LevelIter;
-    use crate::kernel::lsm::iterator::{Iter, Seek, SeekIter};
+    use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
     use crate::kernel::lsm::log::LogLoader;
-    use crate::kernel::lsm::mem_table::DEFAULT_WAL_PATH;
+    use crate::kernel::lsm::mem_table::{ValueMeta, DEFAULT_WAL_PATH};
     use crate::kernel::lsm::storage::Config;
     use crate::kernel::lsm::table::meta::TableMeta;
     use crate::kernel::lsm::table::TableType;
     use crate::kernel::lsm::version::edit::VersionEdit;
     use crate::kernel::lsm::version::status::VersionStatus;
+    use crate::kernel::utils::lru_cache::ShardingLruCache;
     use crate::kernel::KernelResult;
     use bincode::Options;
     use bytes::Bytes;
+    use std::collections::hash_map::RandomState;
+    use std::sync::Arc;
     use tempfile::TempDir;
 
     #[test]
@@ -118,7 +139,12 @@ mod tests {
 
             // 注意：将ss_table的创建防止VersionStatus的创建前
             // 因为VersionStatus检测无Log时会扫描当前文件夹下的SSTable进行重组以进行容灾
-            let ver_status = VersionStatus::load_with_path(config.clone(), wal.clone())?;
+            let cache = Arc::new(ShardingLruCache::new(
+                config.block_cache_size,
+                16,
+                RandomState::default(),
+            )?);
+            let ver_status = VersionStatus::load_with_path(config.clone(), wal.clone(), cache)?;
 
             let value =
                 Bytes::from_static(b"What you are you do not see, what you see is your shadow.");
@@ -130,7 +156,11 @@ mod tests {
             for i in 0..times {
                 let mut key = b"KipDB-".to_vec();
                 key.append(&mut bincode::options().with_big_endian().serialize(&i)?);
-                vec_data.push((Bytes::from(key), Some(value.clone())));
+                vec_data.push((
+                    (Bytes::from(key), Some(value.clone())),
+                    i as i64,
+                    ValueMeta::default(),
+                ));
             }
             let (slice_1, slice_2) = vec_data.split_at(2000);
 
@@ -166,10 +196,10 @@ mod tests {
                 assert_eq!(iterator.try_next()?.unwrap(), kv.clone());
             }
 
-            iterator.seek(Seek::Backward(&vec_data[114].0))?;
+            iterator.seek(Seek::Backward(&vec_data[114].0 .0))?;
             assert_eq!(iterator.try_next()?.unwrap(), vec_data[114]);
 
-            iterator.seek(Seek::Backward(&vec_data[2048].0))?;
+            iterator.seek(Seek::Backward(&vec_data[2048].0 .0))?;
             assert_eq!(iterator.try_next()?.unwrap(), vec_data[2048]);
 
             iterator.seek(Seek::First)?;
@@ -178,10 +208,25 @@ mod tests {
             iterator.seek(Seek::Last)?;
             assert_eq!(iterator.try_next()?, None);
 
+            iterator.seek(Seek::Last)?;
+            for kv in vec_data.iter().rev() {
+                assert_eq!(iterator.try_prev()?.unwrap(), kv.clone());
+            }
+            assert_eq!(iterator.try_prev()?, None);
+            assert_eq!(iterator.try_next()?.unwrap(), vec_data[0]);
+
+            iterator.seek(Seek::Forward(&vec_data[1999].0 .0))?;
+            assert_eq!(iterator.try_prev()?.unwrap(), vec_data[1999]);
+            assert_eq!(iterator.try_next()?.unwrap(), vec_data[2000]);
+            assert_eq!(iterator.try_prev()?.unwrap(), vec_data[1999]);
+
+            iterator.seek(Seek::Forward(&vec_data[2048].0 .0))?;
+            assert_eq!(iterator.try_prev()?.unwrap(), vec_data[2048]);
+
             let mut iterator_level_0 = LevelIter::new(&version, 0)?;
 
             assert!(iterator_level_0
-                .seek(Seek::Backward(&vec_data[3333].0))
+                .seek(Seek::Backward(&vec_data[3333].0 .0))
                 .is_err());
 
             Ok(())
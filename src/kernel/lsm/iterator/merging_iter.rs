@@ -1,18 +1,29 @@
-use crate::kernel::lsm::iterator::{Iter, Seek, SeekIter};
+use crate::kernel::lsm::comparator::Comparator;
+use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
 use crate::kernel::lsm::mem_table::KeyValue;
 use crate::kernel::KernelResult;
 use bytes::Bytes;
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 /// 用于取值以及对应的Iter下标
 /// 通过序号进行同值优先获取
-#[derive(Eq, PartialEq, Debug)]
+#[derive(Debug)]
 struct IterKey {
     num: usize,
     key: Bytes,
+    comparator: Arc<dyn Comparator>,
+}
+
+impl PartialEq for IterKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.num == other.num && self.key == other.key
+    }
 }
 
+impl Eq for IterKey {}
+
 impl PartialOrd<Self> for IterKey {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -21,19 +32,52 @@ impl PartialOrd<Self> for IterKey {
 
 impl Ord for IterKey {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.key
-            .cmp(&other.key)
+        self.comparator
+            .compare(&self.key, &other.key)
             .then_with(|| self.num.cmp(&other.num))
     }
 }
 
+/// 反向迭代时使用的IterKey
+/// Key较大者优先，同值时依旧是序号较小者优先
+#[derive(Eq, PartialEq, Debug)]
+struct RevIterKey(IterKey);
+
+impl PartialOrd<Self> for RevIterKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RevIterKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .comparator
+            .compare(&other.0.key, &self.0.key)
+            .then_with(|| self.0.num.cmp(&other.0.num))
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Direction {
+    Forward,
+    Reverse,
+}
+
 struct InnerIter {
-    map_buf: BTreeMap<IterKey, Option<Bytes>>,
-    next_buf: Option<KeyValue>,
+    // 正向迭代时各Iter的下一个元素
+    forward_buf: BTreeMap<IterKey, Option<Bytes>>,
+    // 反向迭代时各Iter的上一个元素
+    reverse_buf: BTreeMap<RevIterKey, Option<Bytes>>,
+    direction: Direction,
+    // 上一次返回的Key，用于切换迭代方向时重新定位各Iter
+    // 为None时表示游标位于各Iter的缓存元素之间(如刚Seek完或已迭代至端点)
+    current: Option<Bytes>,
+    comparator: Arc<dyn Comparator>,
 }
 
 pub(crate) struct MergingIter<'a> {
-    vec_iter: Vec<Box<dyn Iter<'a, Item = KeyValue> + 'a + Send + Sync>>,
+    vec_iter: Vec<Box<dyn ForwardIter<'a, Item = KeyValue> + 'a + Send + Sync>>,
     inner: InnerIter,
 }
 
@@ -45,20 +89,19 @@ pub(crate) struct SeekMergingIter<'a> {
 macro_rules! impl_new {
     ($struct_name:ident, $vec_iter_type:ty) => {
         impl<'a> $struct_name<'a> {
-            #[allow(clippy::mutable_key_type)]
-            pub(crate) fn new(mut vec_iter: $vec_iter_type) -> KernelResult<Self> {
-                let mut map_buf = BTreeMap::new();
-
-                for (num, iter) in vec_iter.iter_mut().enumerate() {
-                    if let Some(item) = iter.try_next()? {
-                        InnerIter::buf_map_insert(&mut map_buf, num, item);
-                    }
-                }
+            #[allow(dead_code)]
+            pub(crate) fn new(
+                mut vec_iter: $vec_iter_type,
+                comparator: &Arc<dyn Comparator>,
+            ) -> KernelResult<Self> {
                 let mut inner = InnerIter {
-                    map_buf,
-                    next_buf: None,
+                    forward_buf: BTreeMap::new(),
+                    reverse_buf: BTreeMap::new(),
+                    direction: Direction::Forward,
+                    current: None,
+                    comparator: Arc::clone(comparator),
                 };
-                inner.init_next_buf();
+                inner.fill(&mut vec_iter, Direction::Forward)?;
 
                 Ok($struct_name { vec_iter, inner })
             }
@@ -68,7 +111,7 @@ macro_rules! impl_new {
 
 impl_new!(
     MergingIter,
-    Vec<Box<dyn Iter<'a, Item = KeyValue> + 'a + Send + Sync>>
+    Vec<Box<dyn ForwardIter<'a, Item = KeyValue> + 'a + Send + Sync>>
 );
 impl_new!(
     SeekMergingIter,
@@ -88,7 +131,7 @@ impl<'a> Iter<'a> for MergingIter<'a> {
     type Item = KeyValue;
 
     fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
-        self.inner.try_next_1(&mut self.vec_iter)
+        self.inner.try_next(&mut self.vec_iter)
     }
 
     fn is_valid(&self) -> bool {
@@ -96,11 +139,17 @@ impl<'a> Iter<'a> for MergingIter<'a> {
     }
 }
 
+impl<'a> ForwardIter<'a> for MergingIter<'a> {
+    fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
+        self.inner.try_prev(&mut self.vec_iter)
+    }
+}
+
 impl<'a> Iter<'a> for SeekMergingIter<'a> {
     type Item = KeyValue;
 
     fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
-        self.inner.try_next_2(&mut self.vec_iter)
+        self.inner.try_next(&mut self.vec_iter)
     }
 
     fn is_valid(&self) -> bool {
@@ -108,91 +157,172 @@ impl<'a> Iter<'a> for SeekMergingIter<'a> {
     }
 }
 
-impl InnerIter {
-    fn init_next_buf(&mut self) {
-        self.next_buf = self
-            .map_buf
-            .first_key_value()
-            .map(|(key, value)| (key.key.clone(), value.clone()));
+impl<'a> ForwardIter<'a> for SeekMergingIter<'a> {
+    fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
+        self.inner.try_prev(&mut self.vec_iter)
     }
 }
 
-macro_rules! impl_try_next {
-    ($func:ident, $vec_iter:ty) => {
-        impl InnerIter {
-            fn $func(&mut self, vec_iter: &mut [$vec_iter]) -> KernelResult<Option<KeyValue>> {
-                if let Some(item_buf) = self.next_buf.take() {
-                    while let Some((IterKey { num, key }, value)) = self.map_buf.pop_first() {
-                        if let Some(item) = vec_iter[num].try_next()? {
-                            Self::buf_map_insert(&mut self.map_buf, num, item);
-                        }
-                        if item_buf.0 == key {
-                            continue;
-                        }
-                        self.next_buf = Some((key, value));
-                        break;
+impl InnerIter {
+    /// 各Iter按方向取出一个元素作为缓存
+    fn fill<'a, I>(&mut self, vec_iter: &mut [Box<I>], direction: Direction) -> KernelResult<()>
+    where
+        I: ForwardIter<'a, Item = KeyValue> + ?Sized,
+    {
+        self.forward_buf.clear();
+        self.reverse_buf.clear();
+        self.direction = direction;
+        self.current = None;
+
+        for (num, iter) in vec_iter.iter_mut().enumerate() {
+            let item = match direction {
+                Direction::Forward => iter.try_next()?,
+                Direction::Reverse => iter.try_prev()?,
+            };
+            if let Some(item) = item {
+                self.buf_insert(num, item);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 切换迭代方向
+    ///
+    /// 各Iter的游标停留在当前方向的缓存元素上，因此需要反向移动越过当前Key，
+    /// 若当前无Key则各Iter只需反向移动一次即可
+    fn switch<'a, I>(&mut self, vec_iter: &mut [Box<I>], direction: Direction) -> KernelResult<()>
+    where
+        I: ForwardIter<'a, Item = KeyValue> + ?Sized,
+    {
+        self.forward_buf.clear();
+        self.reverse_buf.clear();
+        self.direction = direction;
+
+        for (num, iter) in vec_iter.iter_mut().enumerate() {
+            loop {
+                let item = match direction {
+                    Direction::Forward => iter.try_next()?,
+                    Direction::Reverse => iter.try_prev()?,
+                };
+                let Some(item) = item else {
+                    break;
+                };
+                let is_passed = self.current.as_ref().map_or(true, |current| {
+                    let ordering = self.comparator.compare(&item.0, current);
+
+                    match direction {
+                        Direction::Forward => ordering.is_gt(),
+                        Direction::Reverse => ordering.is_lt(),
                     }
-                    return Ok(Some(item_buf));
+                });
+                if is_passed {
+                    self.buf_insert(num, item);
+                    break;
                 }
-                Ok(None)
             }
         }
-    };
-}
 
-impl_try_next!(
-    try_next_1,
-    Box<dyn Iter<'_, Item = KeyValue> + '_ + Send + Sync>
-);
-impl_try_next!(
-    try_next_2,
-    Box<dyn SeekIter<'_, Item = KeyValue> + '_ + Send + Sync>
-);
-
-impl InnerIter {
-    #[allow(clippy::mutable_key_type)]
-    fn buf_map_insert(
-        seek_map: &mut BTreeMap<IterKey, Option<Bytes>>,
-        num: usize,
-        (key, value): KeyValue,
-    ) {
-        let _ = seek_map.insert(IterKey { num, key }, value);
+        Ok(())
     }
-}
 
-impl<'a> SeekIter<'a> for SeekMergingIter<'a> {
-    #[allow(clippy::mutable_key_type)]
-    fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()> {
-        if let Seek::Last = seek {
-            self.inner.map_buf.clear();
-        } else {
-            let mut seek_map = BTreeMap::new();
+    fn try_next<'a, I>(&mut self, vec_iter: &mut [Box<I>]) -> KernelResult<Option<KeyValue>>
+    where
+        I: ForwardIter<'a, Item = KeyValue> + ?Sized,
+    {
+        if self.direction == Direction::Reverse {
+            self.switch(vec_iter, Direction::Forward)?;
+        }
+        let Some((IterKey { num, key, .. }, value)) = self.forward_buf.pop_first() else {
+            self.current = None;
+            return Ok(None);
+        };
+        if let Some(item) = vec_iter[num].try_next()? {
+            self.buf_insert(num, item);
+        }
+        // 跳过其他Iter中的同Key旧值
+        while let Some(entry) = self.forward_buf.first_entry() {
+            if entry.key().key != key {
+                break;
+            }
+            let num = entry.remove_entry().0.num;
 
-            for (num, iter) in self.vec_iter.iter_mut().enumerate() {
-                iter.seek(seek)?;
+            if let Some(item) = vec_iter[num].try_next()? {
+                self.buf_insert(num, item);
+            }
+        }
+        self.current = Some(key.clone());
 
-                if let Some(item) = iter.try_next()? {
-                    InnerIter::buf_map_insert(&mut seek_map, num, item);
-                }
+        Ok(Some((key, value)))
+    }
+
+    fn try_prev<'a, I>(&mut self, vec_iter: &mut [Box<I>]) -> KernelResult<Option<KeyValue>>
+    where
+        I: ForwardIter<'a, Item = KeyValue> + ?Sized,
+    {
+        if self.direction == Direction::Forward {
+            self.switch(vec_iter, Direction::Reverse)?;
+        }
+        let Some((RevIterKey(IterKey { num, key, .. }), value)) = self.reverse_buf.pop_first()
+        else {
+            self.current = None;
+            return Ok(None);
+        };
+        if let Some(item) = vec_iter[num].try_prev()? {
+            self.buf_insert(num, item);
+        }
+        while let Some(entry) = self.reverse_buf.first_entry() {
+            if entry.key().0.key != key {
+                break;
             }
+            let num = entry.remove_entry().0 .0.num;
 
-            self.inner.map_buf = seek_map;
+            if let Some(item) = vec_iter[num].try_prev()? {
+                self.buf_insert(num, item);
+            }
         }
-        self.inner.init_next_buf();
+        self.current = Some(key.clone());
 
-        Ok(())
+        Ok(Some((key, value)))
+    }
+
+    #[allow(clippy::mutable_key_type)]
+    fn buf_insert(&mut self, num: usize, (key, value): KeyValue) {
+        let iter_key = IterKey {
+            num,
+            key,
+            comparator: Arc::clone(&self.comparator),
+        };
+
+        let _ = match self.direction {
+            Direction::Forward => self.forward_buf.insert(iter_key, value),
+            Direction::Reverse => self.reverse_buf.insert(RevIterKey(iter_key), value),
+        };
     }
 }
 
-#[allow(clippy::mutable_key_type)]
-impl MergingIter<'_> {}
+impl<'a> SeekIter<'a> for SeekMergingIter<'a> {
+    fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()> {
+        for iter in self.vec_iter.iter_mut() {
+            iter.seek(seek)?;
+        }
+        let direction = match seek {
+            Seek::First | Seek::Backward(_) => Direction::Forward,
+            Seek::Last | Seek::Forward(_) => Direction::Reverse,
+        };
+
+        self.inner.fill(&mut self.vec_iter, direction)
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use crate::kernel::io::{FileExtension, IoFactory, IoType};
+    use crate::kernel::lsm::comparator::{BytewiseComparator, Comparator};
     use crate::kernel::lsm::iterator::merging_iter::SeekMergingIter;
-    use crate::kernel::lsm::iterator::{Iter, Seek, SeekIter};
-    use crate::kernel::lsm::mem_table::KeyValue;
+    use crate::kernel::lsm::iterator::seq_iter::SeqFilterIter;
+    use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
+    use crate::kernel::lsm::mem_table::{KeyValue, ValueMeta};
     use crate::kernel::lsm::storage::Config;
     use crate::kernel::lsm::table::btree_table::iter::BTreeTableIter;
     use crate::kernel::lsm::table::btree_table::BTreeTable;
@@ -287,12 +417,91 @@ mod tests {
         test_with_data(data_1, data_2, test_sequence).await
     }
 
-    async fn test_with_data(
+    #[tokio::test]
+    async fn test_reverse_iterator() -> KernelResult<()> {
+        let data_1 = vec![
+            (Bytes::from(vec![b'4']), Some(Bytes::from(vec![b'0']))),
+            (Bytes::from(vec![b'5']), None),
+            (Bytes::from(vec![b'6']), Some(Bytes::from(vec![b'0']))),
+        ];
+        let data_2 = vec![
+            (Bytes::from(vec![b'3']), None),
+            (Bytes::from(vec![b'4']), None),
+            (Bytes::from(vec![b'5']), Some(Bytes::from(vec![b'1']))),
+            (Bytes::from(vec![b'7']), None),
+        ];
+        let (btree_table, ss_table) = create_tables(data_1, data_2).await?;
+        let comparator: Arc<dyn Comparator> = Arc::new(BytewiseComparator);
+        let mut merging_iter = SeekMergingIter::new(
+            vec![
+                Box::new(SeqFilterIter::new(
+                    Box::new(BTreeTableIter::new(&btree_table)),
+                    None,
+                )),
+                Box::new(SeqFilterIter::new(
+                    Box::new(SSTableIter::new(&ss_table)?),
+                    None,
+                )),
+            ],
+            &comparator,
+        )?;
+        let kv = |key: u8, value: Option<u8>| {
+            Some((Bytes::from(vec![key]), value.map(|v| Bytes::from(vec![v]))))
+        };
+
+        merging_iter.seek(Seek::Last)?;
+        assert_eq!(merging_iter.try_prev()?, kv(b'7', None));
+        assert_eq!(merging_iter.try_prev()?, kv(b'6', Some(b'0')));
+        assert_eq!(merging_iter.try_prev()?, kv(b'5', None));
+        assert_eq!(merging_iter.try_prev()?, kv(b'4', Some(b'0')));
+        assert_eq!(merging_iter.try_prev()?, kv(b'3', None));
+        assert_eq!(merging_iter.try_prev()?, None);
+
+        // 切换迭代方向
+        assert_eq!(merging_iter.try_next()?, kv(b'3', None));
+        assert_eq!(merging_iter.try_next()?, kv(b'4', Some(b'0')));
+        assert_eq!(merging_iter.try_next()?, kv(b'5', None));
+        assert_eq!(merging_iter.try_prev()?, kv(b'4', Some(b'0')));
+        assert_eq!(merging_iter.try_next()?, kv(b'5', None));
+        assert_eq!(merging_iter.try_next()?, kv(b'6', Some(b'0')));
+        assert_eq!(merging_iter.try_next()?, kv(b'7', None));
+        assert_eq!(merging_iter.try_next()?, None);
+        assert_eq!(merging_iter.try_prev()?, kv(b'7', None));
+
+        merging_iter.seek(Seek::Forward(&[b'5']))?;
+        assert_eq!(merging_iter.try_prev()?, kv(b'5', None));
+        assert_eq!(merging_iter.try_prev()?, kv(b'4', Some(b'0')));
+
+        merging_iter.seek(Seek::Forward(&[b'6', b'5']))?;
+        assert_eq!(merging_iter.try_prev()?, kv(b'6', Some(b'0')));
+
+        merging_iter.seek(Seek::Forward(&[b'5']))?;
+        assert_eq!(merging_iter.try_next()?, kv(b'6', Some(b'0')));
+
+        merging_iter.seek(Seek::Backward(&[b'5']))?;
+        assert_eq!(merging_iter.try_prev()?, kv(b'4', Some(b'0')));
+
+        merging_iter.seek(Seek::Forward(&[b'2']))?;
+        assert_eq!(merging_iter.try_prev()?, None);
+        assert_eq!(merging_iter.try_next()?, kv(b'3', None));
+
+        Ok(())
+    }
+
+    async fn create_tables(
         data_1: Vec<KeyValue>,
         data_2: Vec<KeyValue>,
-        sequence: Vec<Option<KeyValue>>,
-    ) -> KernelResult<()> {
-        let btree_table = BTreeTable::new(0, 0, data_1);
+    ) -> KernelResult<(BTreeTable, SSTable)> {
+        let comparator: Arc<dyn Comparator> = Arc::new(BytewiseComparator);
+        let btree_table = BTreeTable::new(
+            0,
+            0,
+            data_1
+                .into_iter()
+                .map(|kv| (kv, 0, ValueMeta::default()))
+                .collect(),
+            &comparator,
+        );
 
         let temp_dir = TempDir::new().expect("unable to create temporary working directory");
         let config = Config::new(temp_dir.into_path());
@@ -311,19 +520,34 @@ mod tests {
             &config,
             Arc::clone(&cache),
             1,
-            data_2,
+            data_2
+                .into_iter()
+                .map(|kv| (kv, 0, ValueMeta::default()))
+                .collect(),
             0,
             IoType::Direct,
         )
         .await?;
 
-        let bt_iter = BTreeTableIter::new(&btree_table);
+        Ok((btree_table, ss_table))
+    }
+
+    async fn test_with_data(
+        data_1: Vec<KeyValue>,
+        data_2: Vec<KeyValue>,
+        sequence: Vec<Option<KeyValue>>,
+    ) -> KernelResult<()> {
+        let (btree_table, ss_table) = create_tables(data_1, data_2).await?;
+        let comparator: Arc<dyn Comparator> = Arc::new(BytewiseComparator);
+
+        let bt_iter = SeqFilterIter::new(Box::new(BTreeTableIter::new(&btree_table)), None);
 
-        let sst_iter = SSTableIter::new(&ss_table)?;
+        let sst_iter = SeqFilterIter::new(Box::new(SSTableIter::new(&ss_table)?), None);
 
         let mut sequence_iter = sequence.into_iter();
 
-        let mut merging_iter = SeekMergingIter::new(vec![Box::new(bt_iter), Box::new(sst_iter)])?;
+        let mut merging_iter =
+            SeekMergingIter::new(vec![Box::new(bt_iter), Box::new(sst_iter)], &comparator)?;
 
         assert_eq!(merging_iter.try_next()?, sequence_iter.next().flatten());
 
@@ -1,7 +1,9 @@
 pub(crate) mod level_iter;
 pub(crate) mod merging_iter;
+pub(crate) mod seq_iter;
 
 use crate::kernel::KernelResult;
+use std::marker::PhantomData;
 
 #[derive(Clone, Copy)]
 #[allow(dead_code)]
@@ -12,6 +14,8 @@ pub enum Seek<'s> {
     Last,
     // 与key相等或稍大的元素
     Backward(&'s [u8]),
+    // 与key相等或稍小的元素(用于反向迭代)
+    Forward(&'s [u8]),
 }
 
 /// 硬盘迭代器
@@ -23,12 +27,58 @@ pub trait Iter<'a> {
     fn is_valid(&self) -> bool;
 }
 
-pub trait SeekIter<'a>: Iter<'a> {
+/// 可定位迭代器
+///
+/// seek之后:
+/// - `Seek::First`与`Seek::Backward`以try_next开始迭代
+/// - `Seek::Last`与`Seek::Forward`以try_prev开始迭代
+pub trait SeekIter<'a>: ForwardIter<'a> {
     fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()>;
 }
 
+/// 不包含任何元素的迭代器
+///
+/// 用于仅持有范围删除标记而没有数据的Table
+pub(crate) struct EmptyIter<T> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T> Default for EmptyIter<T> {
+    fn default() -> Self {
+        EmptyIter {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iter<'a> for EmptyIter<T> {
+    type Item = T;
+
+    fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
+        Ok(None)
+    }
+
+    fn is_valid(&self) -> bool {
+        false
+    }
+}
+
+impl<'a, T> ForwardIter<'a> for EmptyIter<T> {
+    fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
+        Ok(None)
+    }
+}
+
+impl<'a, T> SeekIter<'a> for EmptyIter<T> {
+    fn seek(&mut self, _: Seek<'_>) -> KernelResult<()> {
+        Ok(())
+    }
+}
+
 /// 向前迭代器
-#[allow(dead_code)]
-pub(crate) trait ForwardIter<'a>: Iter<'a> {
+///
+/// 迭代器的游标停留在上一次返回的元素上，try_prev返回其前一个元素，
+/// 当try_next迭代至末尾返回None后，try_prev返回最后一个元素，反之亦然
+pub trait ForwardIter<'a>: Iter<'a> {
     fn try_prev(&mut self) -> KernelResult<Option<Self::Item>>;
 }
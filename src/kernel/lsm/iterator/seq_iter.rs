@@ -0,0 +1,187 @@
+use crate::kernel::lsm::comparator::Comparator;
+use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
+use crate::kernel::lsm::mem_table::{expire_filter, now_millis, KeyValue, SeqKeyValue};
+use crate::kernel::lsm::range_tombstone::{cover_seq, RangeTombstone};
+use crate::kernel::lsm::value_log::ValueLog;
+use crate::kernel::lsm::version::Version;
+use crate::kernel::KernelResult;
+use std::sync::Arc;
+
+pub(crate) type BoxSeqIter<'a> = Box<dyn SeekIter<'a, Item = SeqKeyValue> + 'a + Send + Sync>;
+
+/// 附带seq_id的迭代器适配器
+///
+/// 将Table中的SeqKeyValue转换为KeyValue，read_seq为Some时会跳过seq_id大于read_seq的数据
+///
+/// 以创建时的时间判断数据是否过期，过期的数据会以删除标记的形式返回
+pub(crate) struct SeqFilterIter<'a> {
+    inner: BoxSeqIter<'a>,
+    read_seq: Option<i64>,
+    now: i64,
+    /// inner所在的Version及其位置，用于将Merge操作数与更旧的数据合并
+    ///
+    /// 未设置时Merge操作数会以原始的操作数序列返回
+    merge_base: Option<(&'a Version, (usize, usize))>,
+    /// 被其中的范围删除标记覆盖的数据会以删除标记的形式返回
+    range_tombstones: Option<(Arc<[RangeTombstone]>, &'a dyn Comparator)>,
+    /// 用于获取被分离至Blob文件中的Value
+    value_log: Option<&'a ValueLog>,
+}
+
+impl<'a> SeqFilterIter<'a> {
+    pub(crate) fn new(inner: BoxSeqIter<'a>, read_seq: Option<i64>) -> Self {
+        SeqFilterIter {
+            inner,
+            read_seq,
+            now: now_millis(),
+            merge_base: None,
+            range_tombstones: None,
+            value_log: None,
+        }
+    }
+
+    pub(crate) fn resolve_with(mut self, value_log: &'a ValueLog) -> Self {
+        self.value_log = Some(value_log);
+        self
+    }
+
+    /// start为inner之后(更旧)的数据在Version中的起始位置，详见`Version::merge_with_older`
+    pub(crate) fn merge_with(mut self, version: &'a Version, start: (usize, usize)) -> Self {
+        self.merge_base = Some((version, start));
+        self
+    }
+
+    pub(crate) fn covered_by(
+        mut self,
+        range_tombstones: Arc<[RangeTombstone]>,
+        comparator: &'a dyn Comparator,
+    ) -> Self {
+        if !range_tombstones.is_empty() {
+            self.range_tombstones = Some((range_tombstones, comparator));
+        }
+        self
+    }
+
+    fn is_covered(&self, key: &[u8], seq_id: i64) -> bool {
+        self.range_tombstones
+            .as_ref()
+            .and_then(|(range_tombstones, comparator)| {
+                cover_seq(range_tombstones.iter(), key, self.read_seq, *comparator)
+            })
+            .map_or(false, |cover| seq_id < cover)
+    }
+
+    fn is_visible(&self, seq_id: i64) -> bool {
+        self.read_seq.map_or(true, |read_seq| seq_id <= read_seq)
+    }
+
+    fn to_key_value(&self, item: SeqKeyValue) -> KernelResult<KeyValue> {
+        let ((key, value), seq_id, meta) = item;
+
+        if self.is_covered(&key, seq_id) {
+            return Ok((key, None));
+        }
+        if let (true, Some((version, start)), Some(operands)) =
+            (meta.is_merge, self.merge_base, &value)
+        {
+            let value = version.merge_with_older(&key, operands.clone(), self.read_seq, start)?;
+
+            return Ok((key, value));
+        }
+        let (key, value) = expire_filter((key, value), meta.expire_at, self.now);
+        if let (true, Some(value_log), Some(pointer)) = (meta.is_blob, self.value_log, &value) {
+            return Ok((key, Some(value_log.resolve(pointer)?)));
+        }
+        Ok((key, value))
+    }
+}
+
+impl<'a> Iter<'a> for SeqFilterIter<'a> {
+    type Item = KeyValue;
+
+    fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
+        while let Some(item) = self.inner.try_next()? {
+            if self.is_visible(item.1) {
+                return self.to_key_value(item).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn is_valid(&self) -> bool {
+        self.inner.is_valid()
+    }
+}
+
+impl<'a> ForwardIter<'a> for SeqFilterIter<'a> {
+    fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
+        while let Some(item) = self.inner.try_prev()? {
+            if self.is_visible(item.1) {
+                return self.to_key_value(item).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<'a> SeekIter<'a> for SeqFilterIter<'a> {
+    fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()> {
+        self.inner.seek(seek)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::lsm::comparator::{BytewiseComparator, Comparator};
+    use crate::kernel::lsm::iterator::seq_iter::SeqFilterIter;
+    use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
+    use crate::kernel::lsm::mem_table::ValueMeta;
+    use crate::kernel::lsm::table::btree_table::BTreeTable;
+    use crate::kernel::lsm::table::Table;
+    use crate::kernel::KernelResult;
+    use bytes::Bytes;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_seq_filter() -> KernelResult<()> {
+        let comparator: Arc<dyn Comparator> = Arc::new(BytewiseComparator);
+        let vec = vec![
+            ((Bytes::from(vec![b'1']), None), 1, ValueMeta::default()),
+            (
+                (Bytes::from(vec![b'2']), Some(Bytes::from(vec![b'2']))),
+                5,
+                ValueMeta::default(),
+            ),
+            (
+                (Bytes::from(vec![b'3']), Some(Bytes::from(vec![b'3']))),
+                3,
+                ValueMeta::default(),
+            ),
+            ((Bytes::from(vec![b'4']), None), 7, ValueMeta::default()),
+        ];
+        let table = BTreeTable::new(0, 0, vec.clone(), &comparator);
+
+        let mut iter = SeqFilterIter::new(table.iter()?, None);
+        for (key_value, ..) in vec.iter() {
+            assert_eq!(iter.try_next()?, Some(key_value.clone()));
+        }
+        assert_eq!(iter.try_next()?, None);
+
+        let mut iter = SeqFilterIter::new(table.iter()?, Some(4));
+        assert_eq!(iter.try_next()?, Some(vec[0].0.clone()));
+        assert_eq!(iter.try_next()?, Some(vec[2].0.clone()));
+        assert_eq!(iter.try_next()?, None);
+        assert_eq!(iter.try_prev()?, Some(vec[2].0.clone()));
+        assert_eq!(iter.try_prev()?, Some(vec[0].0.clone()));
+        assert_eq!(iter.try_prev()?, None);
+
+        iter.seek(Seek::Forward(&[b'2']))?;
+        assert_eq!(iter.try_prev()?, Some(vec[0].0.clone()));
+        iter.seek(Seek::Backward(&[b'2']))?;
+        assert_eq!(iter.try_next()?, Some(vec[2].0.clone()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+use crate::kernel::lsm::storage::Sequence;
+use crate::kernel::KernelResult;
+use crate::KernelError;
+use bytes::Bytes;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::iter;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::{timeout_at, Instant};
+
+pub(crate) const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 被锁定的Key: (ColumnFamily的id, Key)
+pub(crate) type LockKey = (usize, Bytes);
+
+#[derive(Default)]
+struct LockTable {
+    /// Key与持有该锁的事务id
+    holders: HashMap<LockKey, i64>,
+    /// 等待图: 等待中的事务id与其所等待的锁的持有者
+    ///
+    /// 每个事务同一时间仅会等待一把锁，因此以单条边表示
+    waits_for: HashMap<i64, i64>,
+}
+
+impl LockTable {
+    /// 判断tx_id等待holder后是否会形成环
+    fn is_deadlock(&self, tx_id: i64, holder: i64) -> bool {
+        let mut current = holder;
+
+        // 等待图中每个节点至多一条出边，因此沿边行走至多经过所有节点一次
+        for _ in 0..=self.waits_for.len() {
+            if current == tx_id {
+                return true;
+            }
+            match self.waits_for.get(&current) {
+                Some(next) => current = *next,
+                None => return false,
+            }
+        }
+        false
+    }
+}
+
+/// 悲观事务使用的Key锁管理器
+///
+/// 锁为事务级的排他锁，可重入且在事务结束时统一释放；
+/// 等待锁时会依据等待图进行死锁检测，形成环时由发起等待的事务返回`KernelError::Deadlock`
+#[derive(Default)]
+pub(crate) struct LockManager {
+    table: Mutex<LockTable>,
+    /// 任意锁释放时唤醒所有等待者重新竞争
+    notify: Notify,
+}
+
+impl LockManager {
+    /// 为事务获取Key的锁，锁被其他事务持有时等待其释放
+    ///
+    /// 超出timeout时返回`KernelError::LockTimeout`
+    pub(crate) async fn lock(
+        &self,
+        tx_id: i64,
+        key: LockKey,
+        timeout: Duration,
+    ) -> KernelResult<()> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            // 须在检查锁之前创建，避免错过检查后至等待前的释放通知
+            let notified = self.notify.notified();
+            {
+                let mut table = self.table.lock();
+                let holder = *table.holders.entry(key.clone()).or_insert(tx_id);
+
+                if holder == tx_id {
+                    let _ = table.waits_for.remove(&tx_id);
+                    return Ok(());
+                }
+                if table.is_deadlock(tx_id, holder) {
+                    let _ = table.waits_for.remove(&tx_id);
+                    return Err(KernelError::Deadlock);
+                }
+                let _ = table.waits_for.insert(tx_id, holder);
+            }
+            // 等待超时或被取消时均移除等待图中的边，避免此后的死锁检测误判
+            let _wait_edge = WaitEdge {
+                table: &self.table,
+                tx_id,
+            };
+            if timeout_at(deadline, notified).await.is_err() {
+                return Err(KernelError::LockTimeout);
+            }
+        }
+    }
+
+    /// 以新生成的seq_id作为持有者获取单个Key的锁，用于事务之外的原子操作，锁在KeyLock析构时释放
+    pub(crate) async fn lock_single(
+        &self,
+        key: LockKey,
+        timeout: Duration,
+    ) -> KernelResult<KeyLock<'_>> {
+        let owner = Sequence::create();
+        self.lock(owner, key.clone(), timeout).await?;
+
+        Ok(KeyLock {
+            manager: self,
+            owner,
+            key,
+        })
+    }
+
+    /// 释放事务所持有的锁并唤醒等待者
+    pub(crate) fn unlock_all(&self, tx_id: i64, keys: impl IntoIterator<Item = LockKey>) {
+        {
+            let mut table = self.table.lock();
+
+            for key in keys {
+                if table.holders.get(&key) == Some(&tx_id) {
+                    let _ = table.holders.remove(&key);
+                }
+            }
+            let _ = table.waits_for.remove(&tx_id);
+        }
+        self.notify.notify_waiters();
+    }
+}
+
+/// 等待锁期间于等待图中登记的边，析构时移除
+struct WaitEdge<'a> {
+    table: &'a Mutex<LockTable>,
+    tx_id: i64,
+}
+
+impl Drop for WaitEdge<'_> {
+    fn drop(&mut self) {
+        let _ = self.table.lock().waits_for.remove(&self.tx_id);
+    }
+}
+
+/// 通过`LockManager::lock_single`获取的单个Key的锁
+pub(crate) struct KeyLock<'a> {
+    manager: &'a LockManager,
+    owner: i64,
+    key: LockKey,
+}
+
+impl Drop for KeyLock<'_> {
+    fn drop(&mut self) {
+        self.manager
+            .unlock_all(self.owner, iter::once(self.key.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::lsm::lock_manager::LockManager;
+    use crate::kernel::KernelResult;
+    use crate::KernelError;
+    use bytes::Bytes;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_lock_manager() -> KernelResult<()> {
+        let manager = Arc::new(LockManager::default());
+        let (key_a, key_b) = ((0, Bytes::from_static(b"a")), (0, Bytes::from_static(b"b")));
+        let timeout = Duration::from_secs(1);
+
+        manager.lock(1, key_a.clone(), timeout).await?;
+        // 可重入
+        manager.lock(1, key_a.clone(), timeout).await?;
+        manager.lock(2, key_b.clone(), timeout).await?;
+
+        assert!(matches!(
+            manager
+                .lock(3, key_a.clone(), Duration::from_millis(10))
+                .await,
+            Err(KernelError::LockTimeout)
+        ));
+
+        // 1 -> 2 -> 1
+        let waiter = {
+            let manager = Arc::clone(&manager);
+            let key_b = key_b.clone();
+            tokio::spawn(async move { manager.lock(1, key_b, timeout).await })
+        };
+        while manager.table.lock().waits_for.get(&1) != Some(&2) {
+            tokio::task::yield_now().await;
+        }
+        assert!(matches!(
+            manager.lock(2, key_a.clone(), timeout).await,
+            Err(KernelError::Deadlock)
+        ));
+
+        // 被中止的事务释放锁后等待者获得锁
+        manager.unlock_all(2, [key_b.clone()]);
+        waiter.await.expect("waiter panicked")?;
+        manager.unlock_all(1, [key_a, key_b.clone()]);
+        manager.lock(3, key_b, timeout).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lock_manager_cancel_wait() -> KernelResult<()> {
+        let manager = Arc::new(LockManager::default());
+        let (key_a, key_b) = ((0, Bytes::from_static(b"a")), (0, Bytes::from_static(b"b")));
+        let timeout = Duration::from_secs(1);
+
+        manager.lock(1, key_a.clone(), timeout).await?;
+        manager.lock(2, key_b.clone(), timeout).await?;
+
+        // 1等待2持有的b，等待期间被取消
+        let waiter = {
+            let manager = Arc::clone(&manager);
+            let key_b = key_b.clone();
+            tokio::spawn(async move { manager.lock(1, key_b, timeout).await })
+        };
+        while manager.table.lock().waits_for.get(&1) != Some(&2) {
+            tokio::task::yield_now().await;
+        }
+        waiter.abort();
+        assert!(waiter.await.is_err());
+        assert!(manager.table.lock().waits_for.is_empty());
+
+        // 以相反的顺序加锁时仅等待超时，不会因残留的边而误判为死锁
+        assert!(matches!(
+            manager.lock(2, key_a, Duration::from_millis(10)).await,
+            Err(KernelError::LockTimeout)
+        ));
+
+        Ok(())
+    }
+}
@@ -1,23 +1,34 @@
 use crate::kernel::io::{FileExtension, IoFactory, IoType, IoWriter};
-use crate::kernel::lsm::storage::Gen;
+use crate::kernel::lsm::storage::{CorruptionReason, Gen, RecoveryReport, WalRecoveryMode};
 use crate::kernel::{sorted_gen_list, KernelResult};
 use crate::KernelError;
 use integer_encoding::FixedInt;
+use parking_lot::Mutex;
 use std::cmp::min;
+use std::collections::HashMap;
+use std::fs;
 /// dermesser/leveldb-rs crates.io: v1.0.6
 /// https://github.com/dermesser/leveldb-rs/blob/master/src/log.rs
 /// The MIT License (MIT)
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::mem;
 use std::path::Path;
 use std::sync::Arc;
 
 const BLOCK_SIZE: usize = 32 * 1024;
 const HEADER_SIZE: usize = 4 + 4 + 1;
 
+/// 隔离目录，存放恢复时被丢弃了完好数据的日志
+pub(crate) const QUARANTINE_PATH: &str = "quarantine";
+
 #[derive(Clone)]
 pub(crate) struct LogLoader {
     factory: Arc<IoFactory>,
     io_type: IoType,
+    /// 日志文件的引用计数
+    ///
+    /// WAL被多个ColumnFamily中同gen的Level 0 Table所依赖时，仅当引用全部释放后才删除
+    refs: Arc<Mutex<HashMap<i64, usize>>>,
 }
 
 impl LogLoader {
@@ -31,13 +42,14 @@ impl LogLoader {
     where
         F: Fn(&mut Vec<u8>, &mut Vec<R>) -> KernelResult<()>,
     {
-        let (loader, log_gen) = Self::_reload(wal_dir_path, path_name, io_type)?;
+        let (loader, log_gen) = Self::open(wal_dir_path, path_name, io_type)?;
         loader.load(log_gen, records, fn_decode)?;
 
         Ok((loader, log_gen))
     }
 
-    fn _reload(
+    /// 打开日志目录并获取当前的gen，不载入数据
+    pub(crate) fn open(
         wal_dir_path: &Path,
         path_name: (&str, Option<i64>),
         io_type: IoType,
@@ -55,35 +67,176 @@ impl LogLoader {
             })
             .unwrap_or(Gen::create());
 
-        Ok((LogLoader { factory, io_type }, current_gen))
+        Ok((
+            LogLoader {
+                factory,
+                io_type,
+                refs: Arc::new(Mutex::new(HashMap::new())),
+            },
+            current_gen,
+        ))
     }
 
     /// 通过Gen载入数据进行读取
+    ///
+    /// 以AbsoluteConsistency模式恢复，存在任何损坏时返回错误；
+    /// WAL需要依照Config中的WalRecoveryMode恢复，此时应使用`load_with_mode`
     pub(crate) fn load<F, R>(
         &self,
         gen: i64,
         records: &mut Vec<R>,
         fn_decode: F,
     ) -> KernelResult<()>
+    where
+        F: Fn(&mut Vec<u8>, &mut Vec<R>) -> KernelResult<()>,
+    {
+        let _ = self.load_with_mode(
+            gen,
+            WalRecoveryMode::AbsoluteConsistency,
+            &mut RecoveryReport::default(),
+            records,
+            fn_decode,
+        )?;
+
+        Ok(())
+    }
+
+    /// 通过Gen载入数据，并依照WalRecoveryMode处理其中损坏的数据，被丢弃的数据记录于report中
+    ///
+    /// 返回是否因PointInTime模式而停止恢复，此时更新的日志也应当被丢弃
+    pub(crate) fn load_with_mode<F, R>(
+        &self,
+        gen: i64,
+        mode: WalRecoveryMode,
+        report: &mut RecoveryReport,
+        records: &mut Vec<R>,
+        fn_decode: F,
+    ) -> KernelResult<bool>
     where
         F: Fn(&mut Vec<u8>, &mut Vec<R>) -> KernelResult<()>,
     {
         let mut reader = LogReader::new(self.factory.reader(gen, self.io_type)?);
         let mut buf = vec![0; 128];
+        // TolerateCorruptedTail下尚未确认是否位于末尾的损坏
+        let mut tail = Vec::new();
+
+        loop {
+            let len = reader.read(&mut buf)?;
 
-        // 当数据排列有误时仅恢复已正常读取的数据
-        while reader.read(&mut buf).unwrap_or(0) > 0 {
-            fn_decode(&mut buf, records)?;
+            for (offset, reason) in reader.take_corruptions() {
+                if Self::on_corruption(gen, mode, (offset, reason), report, &mut tail)? {
+                    return Ok(true);
+                }
+            }
+            if len == 0 {
+                break;
+            }
+            // 损坏之后仍存在完好的记录，说明损坏并非位于末尾
+            if let Some((offset, reason)) = tail.first().cloned() {
+                return Err(KernelError::WalCorrupted {
+                    gen,
+                    offset,
+                    reason,
+                });
+            }
+            if fn_decode(&mut buf, records).is_err() {
+                let corruption = (reader.record_offset(), CorruptionReason::Undecodable);
+
+                if Self::on_corruption(gen, mode, corruption, report, &mut tail)? {
+                    return Ok(true);
+                }
+            }
+        }
+        for (offset, reason) in tail {
+            report.push(gen, offset, reason);
+        }
+
+        Ok(false)
+    }
+
+    /// 依照WalRecoveryMode处理单个损坏，返回是否停止恢复
+    fn on_corruption(
+        gen: i64,
+        mode: WalRecoveryMode,
+        (offset, reason): (u64, CorruptionReason),
+        report: &mut RecoveryReport,
+        tail: &mut Vec<(u64, CorruptionReason)>,
+    ) -> KernelResult<bool> {
+        match mode {
+            WalRecoveryMode::AbsoluteConsistency => {
+                return Err(KernelError::WalCorrupted {
+                    gen,
+                    offset,
+                    reason,
+                })
+            }
+            WalRecoveryMode::TolerateCorruptedTail => tail.push((offset, reason)),
+            WalRecoveryMode::SkipCorrupted => report.push(gen, offset, reason),
+            WalRecoveryMode::PointInTime => {
+                report.push(gen, offset, reason);
+                return Ok(true);
+            }
         }
 
+        Ok(false)
+    }
+
+    /// 将对应gen的日志文件复制至隔离目录，以保留恢复时被丢弃的数据
+    pub(crate) fn quarantine(&self, gen: i64) -> KernelResult<()> {
+        let dir_path = self.factory.get_path();
+        let quarantine_path = dir_path.join(QUARANTINE_PATH);
+        fs::create_dir_all(&quarantine_path)?;
+
+        let _ = fs::copy(
+            FileExtension::Log.path_with_gen(dir_path, gen),
+            FileExtension::Log.path_with_gen(&quarantine_path, gen),
+        )?;
+
         Ok(())
     }
 
+    /// 对应gen的日志文件大小
+    pub(crate) fn file_size(&self, gen: i64) -> KernelResult<u64> {
+        self.factory.reader(gen, self.io_type)?.file_size()
+    }
+
     #[allow(dead_code)]
     pub(crate) fn clean(&self, gen: i64) -> KernelResult<()> {
         self.factory.clean(gen)
     }
 
+    /// 获取所有日志文件的gen，由旧至新排列
+    pub(crate) fn gens(&self) -> KernelResult<Vec<i64>> {
+        sorted_gen_list(self.factory.get_path(), FileExtension::Log)
+    }
+
+    /// 判断对应gen的日志文件是否存在引用
+    pub(crate) fn is_retained(&self, gen: i64) -> bool {
+        self.refs.lock().contains_key(&gen)
+    }
+
+    /// 持有对应gen日志文件的引用
+    pub(crate) fn retain(&self, gen: i64) {
+        *self.refs.lock().entry(gen).or_insert(0) += 1;
+    }
+
+    /// 释放对应gen日志文件的引用，当引用归零或不存在引用时删除该文件
+    pub(crate) fn release(&self, gen: i64) -> KernelResult<()> {
+        {
+            let mut refs = self.refs.lock();
+
+            if let Some(count) = refs.get_mut(&gen) {
+                *count -= 1;
+                if *count > 0 {
+                    return Ok(());
+                }
+                let _ = refs.remove(&gen);
+            }
+        }
+
+        self.clean(gen)
+    }
+
     pub(crate) fn writer(&self, gen: i64) -> KernelResult<LogWriter<Box<dyn IoWriter>>> {
         let new_fs = self.factory.writer(gen, self.io_type)?;
         Ok(LogWriter::new(new_fs))
@@ -98,15 +251,17 @@ pub(crate) enum RecordType {
     Last = 4,
 }
 
-impl From<u8> for RecordType {
-    fn from(value: u8) -> Self {
-        match value {
+impl TryFrom<u8> for RecordType {
+    type Error = CorruptionReason;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
             1 => RecordType::Full,
             2 => RecordType::First,
             3 => RecordType::Middle,
             4 => RecordType::Last,
-            _ => panic!("Unknown value: {value}"),
-        }
+            _ => return Err(CorruptionReason::UnknownRecordType(value)),
+        })
     }
 }
 
@@ -198,11 +353,24 @@ impl<W: Write + Seek> LogWriter<W> {
     }
 }
 
+impl LogWriter<Box<dyn IoWriter>> {
+    pub(crate) fn sync_data(&mut self) -> KernelResult<()> {
+        self.dst.sync_data()
+    }
+}
+
 pub(crate) struct LogReader<R: Read + Seek> {
     src: R,
+    /// 当前Block内的偏移量
     offset: usize,
+    /// 文件内的偏移量
+    file_offset: u64,
     block_size: usize,
     head_scratch: [u8; HEADER_SIZE],
+    /// 最近一次读取的记录在文件内的偏移量
+    record_offset: u64,
+    /// 读取时跳过的损坏数据的偏移量及原因
+    corruptions: Vec<(u64, CorruptionReason)>,
 }
 
 impl<R: Read + Seek> LogReader<R> {
@@ -210,15 +378,20 @@ impl<R: Read + Seek> LogReader<R> {
         LogReader {
             src,
             offset: 0,
+            file_offset: 0,
             block_size: BLOCK_SIZE,
             head_scratch: [0u8; HEADER_SIZE],
+            record_offset: 0,
+            corruptions: Vec::new(),
         }
     }
 
     /// EOF is signalled by Ok(0)
+    ///
+    /// 遇到损坏的数据时跳过其所在Block的剩余部分并继续读取，损坏通过`take_corruptions`获取
     pub(crate) fn read(&mut self, dst: &mut Vec<u8>) -> KernelResult<usize> {
-        let mut dst_offset = 0;
-        let mut head_pos = 0;
+        // 分段记录的首段偏移量，为None时表示尚未读取到首段
+        let mut fragment_offset = None;
 
         dst.clear();
 
@@ -226,55 +399,149 @@ impl<R: Read + Seek> LogReader<R> {
             let leftover = self.block_size - self.offset;
             if leftover < HEADER_SIZE {
                 // skip to next block
-                if leftover != 0 {
-                    let _ = self.src.seek(SeekFrom::Current((leftover) as i64))?;
-                }
-                self.offset = 0;
+                self.skip_block()?;
             }
+            let physical_offset = self.file_offset;
 
-            head_pos += self.src.read(&mut self.head_scratch[head_pos..])?;
+            let head_len = Self::read_full(&mut self.src, &mut self.head_scratch)?;
+            self.offset += head_len;
+            self.file_offset += head_len as u64;
             // EOF
-            if head_pos == 0 {
-                return Ok(dst_offset);
-            } else if head_pos != HEADER_SIZE {
-                continue;
-            } else {
-                head_pos = 0;
+            if head_len == 0 {
+                if let Some(offset) = fragment_offset {
+                    self.corruptions.push((offset, CorruptionReason::Truncated));
+                }
+                return Ok(0);
+            } else if head_len != HEADER_SIZE {
+                let offset = fragment_offset.unwrap_or(physical_offset);
+                self.corruptions.push((offset, CorruptionReason::Truncated));
+                return Ok(0);
             }
 
-            self.offset += HEADER_SIZE;
-
             let crc = u32::decode_fixed(&self.head_scratch[0..4]);
             let length = u32::decode_fixed(&self.head_scratch[4..8]) as usize;
+            let corrupted_offset = fragment_offset.unwrap_or(physical_offset);
 
-            let mut buf = vec![0; length];
-
-            self.src.read_exact(&mut buf)?;
-            self.offset += length;
-            dst_offset += length;
+            let record_type = match RecordType::try_from(self.head_scratch[8]) {
+                Ok(_) if length > self.block_size - self.offset => Err(CorruptionReason::BadLength),
+                result => result,
+            };
+            let record_type = match record_type {
+                Ok(record_type) => record_type,
+                Err(reason) => {
+                    self.corruptions.push((corrupted_offset, reason));
+                    self.skip_block()?;
+                    fragment_offset = None;
+                    dst.clear();
+                    continue;
+                }
+            };
 
+            let mut buf = vec![0; length];
+            let read_len = Self::read_full(&mut self.src, &mut buf)?;
+            self.offset += read_len;
+            self.file_offset += read_len as u64;
+
+            if read_len != length {
+                self.corruptions
+                    .push((corrupted_offset, CorruptionReason::Truncated));
+                return Ok(0);
+            }
             if crc32fast::hash(&buf) != crc {
-                return Err(KernelError::CrcMisMatch);
+                self.corruptions
+                    .push((corrupted_offset, CorruptionReason::CrcMismatch));
+                self.skip_block()?;
+                fragment_offset = None;
+                dst.clear();
+                continue;
             }
 
-            dst.append(&mut buf);
+            match record_type {
+                RecordType::Full | RecordType::First => {
+                    // 上一条分段记录缺失尾段
+                    if let Some(offset) = fragment_offset.take() {
+                        self.corruptions
+                            .push((offset, CorruptionReason::BrokenFragment));
+                        dst.clear();
+                    }
+                    dst.append(&mut buf);
+
+                    if let RecordType::Full = record_type {
+                        self.record_offset = physical_offset;
+                        return Ok(dst.len());
+                    }
+                    fragment_offset = Some(physical_offset);
+                }
+                RecordType::Middle | RecordType::Last => {
+                    // 缺失首段的分段直接丢弃
+                    let Some(offset) = fragment_offset else {
+                        self.corruptions
+                            .push((physical_offset, CorruptionReason::BrokenFragment));
+                        continue;
+                    };
+                    dst.append(&mut buf);
+
+                    if let RecordType::Last = record_type {
+                        self.record_offset = offset;
+                        return Ok(dst.len());
+                    }
+                }
+            }
+        }
+    }
+
+    /// 最近一次读取的记录在文件内的偏移量
+    pub(crate) fn record_offset(&self) -> u64 {
+        self.record_offset
+    }
+
+    /// 取出读取时跳过的损坏数据
+    pub(crate) fn take_corruptions(&mut self) -> Vec<(u64, CorruptionReason)> {
+        mem::take(&mut self.corruptions)
+    }
+
+    /// 跳过当前Block的剩余部分
+    fn skip_block(&mut self) -> KernelResult<()> {
+        let leftover = self.block_size - self.offset;
 
-            if let RecordType::Full | RecordType::Last = RecordType::from(self.head_scratch[8]) {
-                return Ok(dst_offset);
+        if leftover != 0 {
+            let _ = self.src.seek(SeekFrom::Current(leftover as i64))?;
+            self.file_offset += leftover as u64;
+        }
+        self.offset = 0;
+
+        Ok(())
+    }
+
+    /// 尽可能填满buf，仅在EOF时返回小于buf的长度
+    fn read_full(src: &mut R, buf: &mut [u8]) -> KernelResult<usize> {
+        let mut pos = 0;
+
+        while pos < buf.len() {
+            match src.read(&mut buf[pos..]) {
+                Ok(0) => break,
+                Ok(n) => pos += n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
             }
         }
+
+        Ok(pos)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::kernel::io::IoType;
-    use crate::kernel::lsm::log::{LogLoader, LogReader, LogWriter, HEADER_SIZE};
+    use crate::kernel::io::{FileExtension, IoType};
+    use crate::kernel::lsm::log::{LogLoader, LogReader, LogWriter, BLOCK_SIZE, HEADER_SIZE};
     use crate::kernel::lsm::mem_table::DEFAULT_WAL_PATH;
-    use crate::kernel::lsm::storage::Config;
+    use crate::kernel::lsm::storage::{
+        Config, CorruptionReason, DroppedRecord, RecoveryReport, WalRecoveryMode,
+    };
     use crate::kernel::KernelResult;
+    use crate::KernelError;
     use std::fs::{File, OpenOptions};
-    use std::io::Cursor;
+    use std::io::{Cursor, Seek, SeekFrom, Write};
     use std::mem;
     use tempfile::TempDir;
 
@@ -335,6 +602,7 @@ mod tests {
 
         let fs = OpenOptions::new()
             .create(true)
+            .truncate(true)
             .write(true)
             .read(true)
             .open(file_path.clone())?;
@@ -421,4 +689,214 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_log_loader_release() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.into_path());
+
+        let (loader, _) = LogLoader::reload(
+            config.path(),
+            (DEFAULT_WAL_PATH, Some(1)),
+            IoType::Buf,
+            &mut vec![0],
+            |_, _| Ok(()),
+        )?;
+        loader.writer(1)?.flush()?;
+
+        // 两个引用时仅在全部释放后删除
+        loader.retain(1);
+        loader.retain(1);
+        loader.release(1)?;
+        assert!(loader.factory.exists(1)?);
+        loader.release(1)?;
+        assert!(!loader.factory.exists(1)?);
+
+        // 不存在引用时直接删除
+        loader.writer(2)?.flush()?;
+        loader.release(2)?;
+        assert!(!loader.factory.exists(2)?);
+
+        Ok(())
+    }
+
+    /// 写入数据后通过fn_corrupt破坏日志文件，并以mode重新载入
+    fn load_corrupted(
+        temp_dir: &TempDir,
+        data: &[Vec<u8>],
+        mode: WalRecoveryMode,
+        fn_corrupt: impl Fn(&mut File) -> KernelResult<()>,
+    ) -> KernelResult<(Vec<Vec<u8>>, RecoveryReport, bool)> {
+        let (loader, _) =
+            LogLoader::open(temp_dir.path(), (DEFAULT_WAL_PATH, Some(1)), IoType::Buf)?;
+        let mut writer = loader.writer(1)?;
+
+        for bytes in data {
+            let _ = writer.add_record(bytes)?;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(FileExtension::Log.path_with_gen(&temp_dir.path().join(DEFAULT_WAL_PATH), 1))?;
+        fn_corrupt(&mut file)?;
+
+        let mut records = Vec::new();
+        let mut report = RecoveryReport::default();
+        let is_stopped =
+            loader.load_with_mode(1, mode, &mut report, &mut records, |bytes, records| {
+                records.push(mem::take(bytes));
+
+                Ok(())
+            })?;
+        loader.clean(1)?;
+
+        Ok((records, report, is_stopped))
+    }
+
+    #[test]
+    fn test_log_loader_recovery_mode() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        // 每条记录恰好占满一个Block
+        let data = (0..3u8)
+            .map(|i| vec![i; BLOCK_SIZE - HEADER_SIZE])
+            .collect::<Vec<_>>();
+        // 破坏第二条记录的内容
+        let fn_corrupt = |file: &mut File| {
+            let _ = file.seek(SeekFrom::Start((BLOCK_SIZE + HEADER_SIZE + 10) as u64))?;
+            file.write_all(&[u8::MAX])?;
+            Ok(())
+        };
+        let dropped = DroppedRecord {
+            gen: 1,
+            offset: BLOCK_SIZE as u64,
+            reason: CorruptionReason::CrcMismatch,
+        };
+
+        let (records, report, is_stopped) =
+            load_corrupted(&temp_dir, &data, WalRecoveryMode::SkipCorrupted, fn_corrupt)?;
+        assert_eq!(records, vec![data[0].clone(), data[2].clone()]);
+        assert_eq!(report.dropped(), &[dropped]);
+        assert!(!is_stopped);
+
+        let (records, report, is_stopped) =
+            load_corrupted(&temp_dir, &data, WalRecoveryMode::PointInTime, fn_corrupt)?;
+        assert_eq!(records, vec![data[0].clone()]);
+        assert_eq!(report.dropped(), &[dropped]);
+        assert!(is_stopped);
+
+        // 损坏之后仍存在完好的记录，因此并非末尾的损坏
+        for mode in [
+            WalRecoveryMode::AbsoluteConsistency,
+            WalRecoveryMode::TolerateCorruptedTail,
+        ] {
+            assert!(matches!(
+                load_corrupted(&temp_dir, &data, mode, fn_corrupt),
+                Err(KernelError::WalCorrupted {
+                    gen: 1,
+                    offset,
+                    reason: CorruptionReason::CrcMismatch,
+                }) if offset == BLOCK_SIZE as u64
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_loader_corrupted_tail() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let data = vec![b"kip_key_1".to_vec(), b"kip_key_2".to_vec()];
+        // 模拟写入期间停机导致的不完整记录
+        let fn_truncate = |file: &mut File| {
+            let len = file.metadata()?.len();
+            file.set_len(len - 3)?;
+            Ok(())
+        };
+
+        let (records, report, is_stopped) = load_corrupted(
+            &temp_dir,
+            &data,
+            WalRecoveryMode::TolerateCorruptedTail,
+            fn_truncate,
+        )?;
+        assert_eq!(records, vec![data[0].clone()]);
+        assert_eq!(
+            report.dropped(),
+            &[DroppedRecord {
+                gen: 1,
+                offset: (HEADER_SIZE + data[0].len()) as u64,
+                reason: CorruptionReason::Truncated,
+            }]
+        );
+        assert!(!is_stopped);
+
+        assert!(matches!(
+            load_corrupted(
+                &temp_dir,
+                &data,
+                WalRecoveryMode::AbsoluteConsistency,
+                fn_truncate
+            ),
+            Err(KernelError::WalCorrupted {
+                reason: CorruptionReason::Truncated,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_unknown_record_type() -> KernelResult<()> {
+        let mut lw = LogWriter::new(Cursor::new(Vec::new()));
+        let _ = lw.add_record(b"kip_key_1")?;
+        let mut bytes = lw.dst.into_inner();
+        bytes[HEADER_SIZE - 1] = 9;
+
+        let mut lr = LogReader::new(Cursor::new(bytes));
+        let mut dst = Vec::new();
+
+        assert_eq!(lr.read(&mut dst)?, 0);
+        assert_eq!(
+            lr.take_corruptions(),
+            vec![(0, CorruptionReason::UnknownRecordType(9))]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_loader_load_is_strict() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let (loader, _) =
+            LogLoader::open(temp_dir.path(), (DEFAULT_WAL_PATH, Some(1)), IoType::Buf)?;
+        let mut writer = loader.writer(1)?;
+        let _ = writer.add_record(b"kip_key_1")?;
+        let _ = writer.add_record(b"kip_key_2")?;
+        writer.flush()?;
+        drop(writer);
+
+        let file = OpenOptions::new()
+            .write(true)
+            .open(FileExtension::Log.path_with_gen(&temp_dir.path().join(DEFAULT_WAL_PATH), 1))?;
+        file.set_len(file.metadata()?.len() - 1)?;
+
+        // 用于VersionLog等非WAL的日志，损坏时不会被静默截断
+        let result = loader.load(1, &mut Vec::new(), |bytes, records| {
+            records.push(mem::take(bytes));
+
+            Ok(())
+        });
+        assert!(matches!(
+            result,
+            Err(KernelError::WalCorrupted {
+                reason: CorruptionReason::Truncated,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
 }
@@ -1,29 +1,92 @@
 use crate::kernel::io::IoWriter;
-use crate::kernel::lsm::iterator::{Iter, Seek, SeekIter};
+use crate::kernel::lsm::commit_history::{ActiveTransactions, CommitHistory};
+use crate::kernel::lsm::comparator::Comparator;
+use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
 use crate::kernel::lsm::log::{LogLoader, LogWriter};
-use crate::kernel::lsm::storage::{Config, Gen, Sequence};
+use crate::kernel::lsm::merge_operator::{merge_versions, MergeOperator};
+use crate::kernel::lsm::range_tombstone::{cover_seq, truncate_covered, RangeTombstone};
+use crate::kernel::lsm::storage::{
+    Config, CorruptionReason, Gen, RecoveryReport, Sequence, WalSyncPolicy, WriteOptions,
+};
 use crate::kernel::lsm::table::ss_table::block::{Entry, Value};
-use crate::kernel::lsm::trigger::{Trigger, TriggerFactory};
+use crate::kernel::lsm::trigger::{Trigger, TriggerFactory, TriggerType};
 use crate::kernel::KernelResult;
+use crate::KernelError;
+use arc_swap::ArcSwap;
 use bytes::Bytes;
-use itertools::Itertools;
+use chrono::Local;
+use crossbeam_skiplist::SkipMap;
+use integer_encoding::{VarIntReader, VarIntWriter};
+use itertools::{EitherOrBoth, Itertools};
 use parking_lot::Mutex;
-use skiplist::{skipmap, SkipMap};
 use std::cmp::Ordering;
-use std::collections::Bound;
-use std::io::Cursor;
-use std::mem;
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering::Acquire;
+use std::collections::{BTreeSet, Bound, HashMap, VecDeque};
+use std::io::{Cursor, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::Arc;
+use std::{iter, mem};
+use tokio::sync::{oneshot, Notify};
 
 pub(crate) const DEFAULT_WAL_PATH: &str = "wal";
 
+/// 单次组提交合并的数据大小上限，避免Leader的写入延迟过高
+const MAX_GROUP_BYTES: usize = 1 << 20;
+
 /// Value为此Key的Records(Key与seq_id)
 pub(crate) type MemMap = SkipMap<InternalKey, Option<Bytes>>;
 
 pub(crate) type KeyValue = (Bytes, Option<Bytes>);
 
+/// 事务读取过的Key范围: (min, max)
+pub(crate) type ReadRange = (Bound<Bytes>, Bound<Bytes>);
+
+/// 附带seq_id与ValueMeta的键值对，用于持久化至Table中以保证MVCC、TTL与Merge在Flush后依旧有效
+pub(crate) type SeqKeyValue = (KeyValue, i64, ValueMeta);
+
+/// Value的附加信息，与seq_id一同写入WAL与Table中
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct ValueMeta {
+    /// 过期时间的毫秒时间戳，None时表示永不过期
+    pub(crate) expire_at: Option<i64>,
+    /// Value是否为Merge操作数序列
+    pub(crate) is_merge: bool,
+    /// 是否为范围删除标记，此时Key与Value分别为范围的start与end
+    pub(crate) is_range_del: bool,
+    /// Value是否已被分离至Blob文件中，此时Value为BlobPointer
+    pub(crate) is_blob: bool,
+}
+
+impl ValueMeta {
+    pub(crate) fn with_expire(expire_at: Option<i64>) -> Self {
+        ValueMeta {
+            expire_at,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn merge() -> Self {
+        ValueMeta {
+            is_merge: true,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn range_delete() -> Self {
+        ValueMeta {
+            is_range_del: true,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn blob(expire_at: Option<i64>) -> Self {
+        ValueMeta {
+            expire_at,
+            is_blob: true,
+            ..Default::default()
+        }
+    }
+}
+
 /// seq_id的上限值
 ///
 /// 用于默认的key的填充(补充使UserKey为高位，因此默认获取最新的seq_id数据)
@@ -33,12 +96,36 @@ pub(crate) fn key_value_bytes_len(key_value: &KeyValue) -> usize {
     key_value.0.len() + key_value.1.as_ref().map(Bytes::len).unwrap_or(0)
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+/// 当前的毫秒时间戳，用于TTL的过期判断
+pub(crate) fn now_millis() -> i64 {
+    Local::now().timestamp_millis()
+}
+
+/// 过期的数据视为删除标记，以此覆盖其更旧的数据
+pub(crate) fn expire_filter(key_value: KeyValue, expire_at: Option<i64>, now: i64) -> KeyValue {
+    match expire_at {
+        Some(expire_at) if expire_at <= now => (key_value.0, None),
+        _ => key_value,
+    }
+}
+
+#[derive(Debug, Clone)]
 pub(crate) struct InternalKey {
     key: Bytes,
     seq_id: i64,
+    /// 不参与排序，seq_id唯一时ValueMeta也随之唯一
+    meta: ValueMeta,
+    comparator: Arc<dyn Comparator>,
+}
+
+impl PartialEq for InternalKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.seq_id == other.seq_id
+    }
 }
 
+impl Eq for InternalKey {}
+
 impl PartialOrd<Self> for InternalKey {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -47,44 +134,99 @@ impl PartialOrd<Self> for InternalKey {
 
 impl Ord for InternalKey {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.key
-            .cmp(&other.key)
+        self.comparator
+            .compare(&self.key, &other.key)
             .then_with(|| self.seq_id.cmp(&other.seq_id))
     }
 }
 
 impl InternalKey {
-    pub(crate) fn new(key: Bytes) -> Self {
+    #[allow(dead_code)]
+    pub(crate) fn new(key: Bytes, comparator: &Arc<dyn Comparator>) -> Self {
+        Self::new_with_seq(key, Sequence::create(), comparator)
+    }
+
+    pub(crate) fn new_with_seq(key: Bytes, seq_id: i64, comparator: &Arc<dyn Comparator>) -> Self {
         InternalKey {
             key,
-            seq_id: Sequence::create(),
+            seq_id,
+            meta: ValueMeta::default(),
+            comparator: Arc::clone(comparator),
         }
     }
 
-    pub(crate) fn new_with_seq(key: Bytes, seq_id: i64) -> Self {
-        InternalKey { key, seq_id }
+    pub(crate) fn meta(mut self, meta: ValueMeta) -> Self {
+        self.meta = meta;
+        self
     }
 
     pub(crate) fn get_key(&self) -> &Bytes {
         &self.key
     }
+
+    /// 转换为KeyValue，已过期时转换为删除标记
+    fn to_key_value(&self, value: &Option<Bytes>, now: i64) -> KeyValue {
+        expire_filter((self.key.clone(), value.clone()), self.meta.expire_at, now)
+    }
 }
 
+/// MemMap迭代器
+///
+/// 同一Key仅返回其最新的seq_id数据，游标位置的表示方式与`BTreeTableIter`一致
 pub(crate) struct MemMapIter<'a> {
     mem_map: &'a MemMap,
+    comparator: &'a Arc<dyn Comparator>,
 
-    prev_item: Option<(Bytes, Option<Bytes>)>,
-    iter: Option<skipmap::Iter<'a, InternalKey, Option<Bytes>>>,
+    next_bound: Option<Bound<Bytes>>,
+    prev_bound: Option<Bound<Bytes>>,
 }
 
 impl<'a> MemMapIter<'a> {
     #[allow(dead_code)]
-    pub(crate) fn new(mem_map: &'a MemMap) -> Self {
+    pub(crate) fn new(mem_map: &'a MemMap, comparator: &'a Arc<dyn Comparator>) -> Self {
         Self {
             mem_map,
-            prev_item: None,
-            iter: Some(mem_map.iter()),
+            comparator,
+            next_bound: Some(Bound::Unbounded),
+            prev_bound: None,
+        }
+    }
+
+    /// 获取该Key的最新数据
+    fn latest(&self, key: &Bytes) -> Option<KeyValue> {
+        self.mem_map
+            .range((
+                Bound::Included(&InternalKey::new_with_seq(
+                    key.clone(),
+                    i64::MIN,
+                    self.comparator,
+                )),
+                Bound::Included(&InternalKey::new_with_seq(
+                    key.clone(),
+                    SEQ_MAX,
+                    self.comparator,
+                )),
+            ))
+            .next_back()
+            .map(|entry| entry.key().to_key_value(entry.value(), now_millis()))
+    }
+
+    fn item_move(&mut self, item: Option<KeyValue>, is_next: bool) -> Option<KeyValue> {
+        match &item {
+            Some((key, _)) => {
+                self.next_bound = Some(Bound::Excluded(key.clone()));
+                self.prev_bound = Some(Bound::Excluded(key.clone()));
+            }
+            None if is_next => {
+                self.next_bound = None;
+                self.prev_bound = Some(Bound::Unbounded);
+            }
+            None => {
+                self.next_bound = Some(Bound::Unbounded);
+                self.prev_bound = None;
+            }
         }
+        item
     }
 }
 
@@ -92,23 +234,29 @@ impl<'a> Iter<'a> for MemMapIter<'a> {
     type Item = KeyValue;
 
     fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
-        if let Some(iter) = &mut self.iter {
-            for (InternalKey { key, .. }, value) in iter.by_ref() {
-                if let Some(prev_item) = &self.prev_item {
-                    if key != &prev_item.0 {
-                        return Ok(mem::replace(
-                            &mut self.prev_item,
-                            Some((key.clone(), value.clone())),
-                        ));
-                    }
-                }
-                self.prev_item = Some((key.clone(), value.clone()));
-            }
-
-            return Ok(self.prev_item.take());
-        }
+        let Some(bound) = &self.next_bound else {
+            return Ok(None);
+        };
+        let min = match bound {
+            Bound::Included(key) => Bound::Included(InternalKey::new_with_seq(
+                key.clone(),
+                i64::MIN,
+                self.comparator,
+            )),
+            Bound::Excluded(key) => Bound::Excluded(InternalKey::new_with_seq(
+                key.clone(),
+                SEQ_MAX,
+                self.comparator,
+            )),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let item = self
+            .mem_map
+            .range((min.as_ref(), Bound::Unbounded))
+            .next()
+            .and_then(|entry| self.latest(&entry.key().key));
 
-        Ok(None)
+        Ok(self.item_move(item, true))
     }
 
     fn is_valid(&self) -> bool {
@@ -116,265 +264,1176 @@ impl<'a> Iter<'a> for MemMapIter<'a> {
     }
 }
 
+impl<'a> ForwardIter<'a> for MemMapIter<'a> {
+    fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
+        let Some(bound) = &self.prev_bound else {
+            return Ok(None);
+        };
+        let max = match bound {
+            Bound::Included(key) => Bound::Included(InternalKey::new_with_seq(
+                key.clone(),
+                SEQ_MAX,
+                self.comparator,
+            )),
+            Bound::Excluded(key) => Bound::Excluded(InternalKey::new_with_seq(
+                key.clone(),
+                i64::MIN,
+                self.comparator,
+            )),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let item = self
+            .mem_map
+            .range((Bound::Unbounded, max.as_ref()))
+            .next_back()
+            .map(|entry| entry.key().to_key_value(entry.value(), now_millis()));
+
+        Ok(self.item_move(item, false))
+    }
+}
+
 impl<'a> SeekIter<'a> for MemMapIter<'a> {
     fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()> {
-        self.prev_item = None;
+        let (next_bound, prev_bound) = match seek {
+            Seek::First => (Some(Bound::Unbounded), None),
+            Seek::Last => (None, Some(Bound::Unbounded)),
+            Seek::Backward(key) => {
+                let key = Bytes::copy_from_slice(key);
+                (
+                    Some(Bound::Included(key.clone())),
+                    Some(Bound::Excluded(key)),
+                )
+            }
+            Seek::Forward(key) => {
+                let key = Bytes::copy_from_slice(key);
+                (
+                    Some(Bound::Excluded(key.clone())),
+                    Some(Bound::Included(key)),
+                )
+            }
+        };
+        self.next_bound = next_bound;
+        self.prev_bound = prev_bound;
+
+        Ok(())
+    }
+}
+
+/// 预写日志
+///
+/// 由所有ColumnFamily的MemTable共享，单条记录中可包含多个ColumnFamily的数据，以此保证跨ColumnFamily写入的原子性
+pub(crate) struct Wal {
+    /// WAL载入器
+    ///
+    /// 用于异常停机时MemTable的恢复
+    /// 同时当Level 0的SSTable异常时，可以尝试恢复
+    log_loader: LogLoader,
+    log_writer: Mutex<(LogWriter<Box<dyn IoWriter>>, i64)>,
+    /// 最后分配的写入序号，仅在WAL写入锁内递增
+    assigned_ticket: AtomicU64,
+    /// 该序号及之前的写入均已插入至MemTable
+    published_ticket: AtomicU64,
+    /// 已插入完成但仍存在更早的写入尚未完成的写入序号
+    finished_tickets: Mutex<BTreeSet<u64>>,
+    /// published_ticket推进时唤醒等待者
+    published_notify: Notify,
+    /// 等待组提交的写入者
+    writers: Mutex<WriterQueue>,
+    sync_policy: WalSyncPolicy,
+    /// 当前WAL中是否存在尚未持久化的记录，仅在`WalSyncPolicy::Interval`下使用
+    is_unsynced: AtomicBool,
+}
+
+/// WAL中恢复的数据，以ColumnFamily的名称进行分组，各ColumnFamily的数据以WAL gen由旧至新排列
+pub(crate) type WalRecords = HashMap<String, Vec<(i64, Vec<(Bytes, Value)>)>>;
+
+/// 单条WAL记录解码后的数据，以ColumnFamily的名称进行分组
+pub(crate) type FamilyRecord = (String, Vec<(Bytes, Value)>);
+
+/// 写入某一ColumnFamily的MemTable的数据
+pub(crate) type FamilyBatch = (Arc<MemTable>, Vec<KeyValue>);
+
+/// 组提交中单个写入者的数据
+type GroupRecord = (
+    Vec<FamilyBatch>,
+    ValueMeta,
+    WriteOptions,
+    Option<WriteCondition>,
+);
+
+/// 写入的前置条件，由组提交的Leader在WAL写入锁内、生成seq_id之前检查
+///
+/// 其他写入的seq_id与CommitHistory同样在WAL写入锁内生成与记录，因此检查与写入之间不会穿插其他写入；
+/// 不满足时该写入不会写入WAL，仅返回对应的错误
+pub(crate) struct WriteCondition {
+    /// 须为通过`ActiveTransactions::begin`生成且尚未结束的seq_id
+    seq_id: i64,
+    /// 各MemTable中读取过的范围，用于可串行化事务避免写偏斜
+    read_ranges: Vec<(Arc<MemTable>, Vec<ReadRange>)>,
+}
+
+impl WriteCondition {
+    pub(crate) fn new(seq_id: i64) -> Self {
+        WriteCondition {
+            seq_id,
+            read_ranges: Vec::new(),
+        }
+    }
+
+    pub(crate) fn read_ranges(mut self, read_ranges: Vec<(Arc<MemTable>, Vec<ReadRange>)>) -> Self {
+        self.read_ranges = read_ranges;
+        self
+    }
+
+    /// 读取过的范围内存在seq_id之后的写入时返回`KernelError::SerializationFailure`，
+    /// 写入的Key在seq_id之后已被写入时返回`KernelError::RepeatedWrite`
+    pub(crate) fn check(&self, batches: &[FamilyBatch]) -> KernelResult<()> {
+        if self
+            .read_ranges
+            .iter()
+            .any(|(mem_table, ranges)| mem_table.check_read_conflict(ranges, self.seq_id))
+        {
+            return Err(KernelError::SerializationFailure);
+        }
+        if batches
+            .iter()
+            .any(|(mem_table, vec_data)| mem_table.check_key_conflict(vec_data, self.seq_id))
+        {
+            return Err(KernelError::RepeatedWrite);
+        }
+
+        Ok(())
+    }
+}
 
-        if let Seek::Last = seek {
-            self.iter = None;
+/// 交换后的数据、范围删除标记及其对应的WAL gen
+pub(crate) type SwappedData = (i64, Vec<SeqKeyValue>, Vec<RangeTombstone>);
+
+impl Wal {
+    /// 载入所有的WAL并恢复其中的数据
+    ///
+    /// 最新的WAL之外，更旧的WAL中可能存在尚未Flush的Immutable MemTable的数据，因此一同恢复，
+    /// 其中已持久化的数据由ColumnFamily通过Version的last_sequence过滤；
+    /// 损坏的数据依照Config中的WalRecoveryMode处理，被丢弃的数据记录于RecoveryReport中
+    pub(crate) fn reload(config: &Config) -> KernelResult<(Arc<Self>, WalRecords, RecoveryReport)> {
+        let fn_decode = |bytes: &mut Vec<u8>, records: &mut Vec<FamilyRecord>| {
+            records.append(&mut record_from_bytes(&mem::take(bytes))?);
+
+            Ok(())
+        };
+        let (log_loader, log_gen) =
+            LogLoader::open(config.path(), (DEFAULT_WAL_PATH, None), config.wal_io_type)?;
+        let mut report = RecoveryReport::default();
+        let mut gen_records = Vec::new();
+        let mut is_stopped = false;
+
+        for gen in log_loader
+            .gens()?
+            .into_iter()
+            .filter(|gen| *gen < log_gen)
+            .chain(iter::once(log_gen))
+        {
+            // PointInTime模式下首个损坏之后的WAL均不再恢复，将其隔离以免被清理时丢失数据
+            if is_stopped {
+                if log_loader.file_size(gen)? > 0 {
+                    report.push(gen, 0, CorruptionReason::AfterCorruption);
+                    log_loader.quarantine(gen)?;
+                }
+                continue;
+            }
+            let mut records = Vec::new();
+            is_stopped = log_loader.load_with_mode(
+                gen,
+                config.wal_recovery_mode,
+                &mut report,
+                &mut records,
+                fn_decode,
+            )?;
+            // 首个损坏之后同一WAL中的记录同样不再恢复
+            if is_stopped {
+                log_loader.quarantine(gen)?;
+            }
+            gen_records.push((gen, records));
+        }
+        // 存在损坏时写入新的WAL，避免新的记录追加于损坏的数据之后而在下次恢复时被一同丢弃
+        let writer_gen = if report.is_clean() {
+            log_gen
         } else {
-            self.iter = match seek {
-                Seek::First => Some(self.mem_map.iter()),
-                Seek::Last => None,
-                Seek::Backward(seek_key) => Some(self.mem_map.range(
-                    Bound::Included(&InternalKey::new_with_seq(
-                        Bytes::copy_from_slice(seek_key),
-                        0,
-                    )),
-                    Bound::Unbounded,
-                )),
+            Gen::create()
+        };
+        let log_writer = (log_loader.writer(writer_gen)?, writer_gen);
+        let mut wal_records = WalRecords::new();
+
+        for (gen, records) in gen_records {
+            for (family, data) in records {
+                let family_records = wal_records.entry(family).or_default();
+
+                match family_records.last_mut() {
+                    Some((last_gen, last_data)) if *last_gen == gen => last_data.extend(data),
+                    _ => family_records.push((gen, data)),
+                }
+            }
+        }
+        // WAL中记录了写入时的seq_id，恢复后需要使Sequence越过其中的最大值，避免新写入的seq_id重复
+        if let Some(max_seq) = wal_records
+            .values()
+            .flatten()
+            .flat_map(|(_, data)| data)
+            .map(|(_, value)| value.seq_id)
+            .max()
+        {
+            Sequence::advance_to(max_seq);
+        }
+
+        Ok((
+            Arc::new(Wal {
+                log_loader,
+                log_writer: Mutex::new(log_writer),
+                assigned_ticket: AtomicU64::new(0),
+                published_ticket: AtomicU64::new(0),
+                finished_tickets: Mutex::new(BTreeSet::new()),
+                published_notify: Notify::new(),
+                writers: Mutex::new(WriterQueue::default()),
+                sync_policy: config.wal_sync_policy,
+                is_unsynced: AtomicBool::new(false),
+            }),
+            wal_records,
+            report,
+        ))
+    }
+
+    pub(crate) fn log_loader_clone(&self) -> LogLoader {
+        self.log_loader.clone()
+    }
+
+    /// 删除当前WAL之外不再被引用的WAL
+    ///
+    /// 须在所有ColumnFamily恢复后调用，此时未被Level 0 Table或Immutable MemTable引用的WAL中的数据均已持久化
+    pub(crate) fn clean_unretained(&self) -> KernelResult<()> {
+        let current_gen = self.log_writer.lock().1;
+
+        for gen in self.log_loader.gens()? {
+            if gen != current_gen && !self.log_loader.is_retained(gen) {
+                self.log_loader.clean(gen)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 生成读取所使用的seq_id，并等待在此之前写入WAL的数据均已插入至MemTable
+    ///
+    /// 写入者在WAL写入锁外插入数据，以此保证seq_id更小的数据不会在读取后才出现
+    pub(crate) async fn read_seq(&self, fn_seq: impl FnOnce() -> i64) -> i64 {
+        let (seq_id, ticket) = {
+            let _guard = self.log_writer.lock();
+            (fn_seq(), self.assigned_ticket.load(AtomicOrdering::Relaxed))
+        };
+        self.wait_published(ticket).await;
+
+        seq_id
+    }
+
+    /// 将并发的写入合并为单条WAL记录写入
+    ///
+    /// 写入者依次排队，首个写入者作为Leader在获取WAL写入锁后将队列中的写入一同写入，再通知其余写入者各自的结果，
+    /// 随后将Leader移交给队列中的下一写入者；插入MemTable则由各写入者自行完成
+    async fn group_commit(
+        self: &Arc<Self>,
+        batches: Vec<FamilyBatch>,
+        meta: ValueMeta,
+        options: WriteOptions,
+        condition: Option<WriteCondition>,
+    ) -> KernelResult<PendingWrite> {
+        let (tx, rx) = oneshot::channel();
+        let is_leader = {
+            let mut queue = self.writers.lock();
+            queue
+                .writers
+                .push_back(GroupWriter::new((batches, meta, options, condition), tx));
+            !mem::replace(&mut queue.has_leader, true)
+        };
+        if !is_leader {
+            match (WriterWaiter {
+                wal: self,
+                rx: Some(rx),
+            })
+            .wait()
+            .await
+            {
+                Some(WriterState::Leader) => (),
+                Some(WriterState::Done(result)) => return result,
+                // Leader在写入前异常退出
+                None => return Err(KernelError::GroupCommitFailed),
+            }
+        }
+        let mut log_writer = self.log_writer.lock();
+        // 等待WAL写入锁期间到达的写入者也一同写入，队首即为Leader自身
+        let group = {
+            let mut queue = self.writers.lock();
+            let mut bytes_len = 0;
+            let len = queue
+                .writers
+                .iter()
+                .enumerate()
+                .take_while(|(i, writer)| {
+                    bytes_len += writer.bytes_len;
+                    *i == 0 || bytes_len <= MAX_GROUP_BYTES
+                })
+                .count();
+
+            queue.writers.drain(..len).collect_vec()
+        };
+        // 写入结束或异常时均移交Leader，避免队列中的写入者无限等待
+        let leader_guard = LeaderGuard(&self.writers);
+        let (records, notifiers): (Vec<_>, Vec<_>) = group
+            .into_iter()
+            .map(|writer| (writer.record, writer.notifier))
+            .unzip();
+        let len = records.len();
+        let result = self.append_group(&mut log_writer, records);
+        drop(log_writer);
+        drop(leader_guard);
+
+        let mut results = match result {
+            Ok(results) => results,
+            Err(err) => iter::once(Err(err))
+                .chain((1..len).map(|_| Err(KernelError::GroupCommitFailed)))
+                .collect_vec(),
+        };
+        let own = results.remove(0);
+
+        for (notifier, result) in notifiers.into_iter().skip(1).zip(results) {
+            let Some(notifier) = notifier else {
+                continue;
             };
+            // 写入者已被取消时由Leader代为插入，使已写入WAL的数据同样可被读取
+            if let Err(WriterState::Done(Ok(pending))) = notifier.send(WriterState::Done(result)) {
+                let _ = pending.insert();
+            }
+        }
+        own
+    }
+
+    /// 须在WAL写入锁内调用，将一组写入合并为单条WAL记录写入，并登记各MemTable中尚未插入的写入
+    ///
+    /// 各写入依次检查其WriteCondition并生成seq_id，不满足条件的写入仅返回其错误；
+    /// disable_wal的写入仅生成seq_id而不写入WAL；
+    /// 存在活跃事务时同时记录CommitHistory，使此后提交的事务以及组内之后的写入能够检测到该写入
+    fn append_group(
+        self: &Arc<Self>,
+        log_writer: &mut (LogWriter<Box<dyn IoWriter>>, i64),
+        records: Vec<GroupRecord>,
+    ) -> KernelResult<Vec<KernelResult<PendingWrite>>> {
+        let mut bytes = Vec::new();
+        let mut is_sync = false;
+        let mut results = Vec::with_capacity(records.len());
+
+        for (batches, meta, options, condition) in records {
+            if let Some(Err(err)) = condition.map(|condition| condition.check(&batches)) {
+                results.push(Err(err));
+                continue;
+            }
+            let seq_id = Sequence::create();
+
+            if !options.disable_wal {
+                let groups = batches
+                    .iter()
+                    .map(|(mem_table, vec_data)| (mem_table.family.as_str(), vec_data.as_slice()))
+                    .collect_vec();
+                bytes.append(&mut record_to_bytes(&groups, seq_id, meta)?);
+                is_sync |= options.sync;
+            }
+            for (mem_table, vec_data) in &batches {
+                mem_table.record_history(vec_data, seq_id, meta);
+            }
+            results.push(Ok((batches, meta, seq_id)));
+        }
+        if !bytes.is_empty() {
+            let _ = log_writer.0.add_record(&bytes)?;
+            // 组内任一写入要求持久化时，整组仅持久化一次
+            self.sync_locked(&mut log_writer.0, is_sync)?;
+        }
+
+        Ok(results
+            .into_iter()
+            .map(|result| {
+                result.map(|(batches, meta, seq_id)| {
+                    let mems = batches
+                        .iter()
+                        .map(|(mem_table, _)| mem_table.prepare_insert())
+                        .collect_vec();
+
+                    PendingWrite {
+                        batches,
+                        meta,
+                        seq_id,
+                        mems,
+                        wal: Arc::clone(self),
+                        ticket: self.assign(),
+                    }
+                })
+            })
+            .collect_vec())
+    }
+
+    /// 须在WAL写入锁内调用，依照写入参数与WalSyncPolicy持久化已写入的记录
+    fn sync_locked(
+        &self,
+        log_writer: &mut LogWriter<Box<dyn IoWriter>>,
+        is_sync: bool,
+    ) -> KernelResult<()> {
+        if is_sync || self.sync_policy == WalSyncPolicy::EveryWrite {
+            log_writer.sync_data()?;
+            self.is_unsynced.store(false, AtomicOrdering::Relaxed);
+        } else if matches!(self.sync_policy, WalSyncPolicy::Interval(_)) {
+            self.is_unsynced.store(true, AtomicOrdering::Relaxed);
         }
 
         Ok(())
     }
+
+    /// 持久化当前WAL中尚未持久化的记录，由`WalSyncPolicy::Interval`的后台任务定时调用
+    pub(crate) fn sync(&self) -> KernelResult<()> {
+        let mut log_writer = self.log_writer.lock();
+
+        if self.is_unsynced.load(AtomicOrdering::Relaxed) {
+            log_writer.0.sync_data()?;
+            self.is_unsynced.store(false, AtomicOrdering::Relaxed);
+        }
+
+        Ok(())
+    }
+
+    /// 须在WAL写入锁内调用，为已写入WAL的记录分配写入序号
+    fn assign(&self) -> u64 {
+        self.assigned_ticket.fetch_add(1, AtomicOrdering::Relaxed) + 1
+    }
+
+    /// 发布插入完成的写入，published_ticket仅在更早的写入均完成后才推进至该写入
+    fn publish(&self, ticket: u64) {
+        {
+            let mut finished = self.finished_tickets.lock();
+            let mut published = self.published_ticket.load(AtomicOrdering::Relaxed);
+
+            if ticket != published + 1 {
+                let _ = finished.insert(ticket);
+                return;
+            }
+            published = ticket;
+            while finished.remove(&(published + 1)) {
+                published += 1;
+            }
+            self.published_ticket
+                .store(published, AtomicOrdering::Release);
+        }
+        self.published_notify.notify_waiters();
+    }
+
+    async fn wait_published(&self, ticket: u64) {
+        loop {
+            // 须在检查之前创建，避免错过检查后至等待前的通知
+            let notified = self.published_notify.notified();
+
+            if self.published_ticket.load(AtomicOrdering::Acquire) >= ticket {
+                return;
+            }
+            notified.await;
+        }
+    }
 }
 
 pub(crate) struct MemTable {
-    inner: Mutex<TableInner>,
-    pub(crate) tx_count: AtomicUsize,
+    state: ArcSwap<MemState>,
+    wal: Arc<Wal>,
+    /// 所属ColumnFamily的名称，用于标识WAL记录中数据的归属
+    family: String,
+    /// 交换时用于合并同一Key的Merge操作数
+    merge_operator: Option<Arc<dyn MergeOperator>>,
+    comparator: Arc<dyn Comparator>,
+    /// 所有ColumnFamily共享的活跃事务，存在时才会记录CommitHistory
+    active_txs: Arc<ActiveTransactions>,
+    /// 用于事务的冲突检测，不随交换而转移
+    history: Mutex<CommitHistory>,
+    /// 交换时为新的_mem创建溢出触发器
+    trigger: (TriggerType, usize),
+    /// 等待Flush的Immutable MemTable的数量上限
+    max_immut_count: usize,
 }
 
-pub(crate) struct TableInner {
-    pub(crate) _mem: MemMap,
-    pub(crate) _immut: Option<Arc<MemMap>>,
-    /// WAL载入器
+/// 可被并发写入与读取的MemMap及其范围删除标记
+pub(crate) struct MemData {
+    /// 数据所在的WAL gen，Flush后的Level 0 Table使用相同的gen
+    gen: i64,
+    mem_map: MemMap,
+    /// 以(seq_id, start)排列的范围删除标记
+    range_tombstones: SkipMap<(i64, Bytes), RangeTombstone>,
+    trigger: Box<dyn Trigger + Send + Sync>,
+    /// 已写入WAL但尚未插入完成的写入数量
     ///
-    /// 用于异常停机时MemTable的恢复
-    /// 同时当Level 0的SSTable异常时，可以尝试恢复
-    log_loader: LogLoader,
-    log_writer: (LogWriter<Box<dyn IoWriter>>, i64),
-    trigger: Box<dyn Trigger + Send>,
+    /// 写入者在WAL写入锁外插入数据，因此交换后仍可能存在尚未插入的数据，Flush前须等待其归零
+    pending: AtomicUsize,
+    /// pending归零时唤醒等待者
+    pending_notify: Notify,
 }
 
-macro_rules! check_count {
-    ($count:ident) => {
-        if 0 != $count.load(Acquire) {
-            std::hint::spin_loop();
-            continue;
+impl MemData {
+    fn new(gen: i64, (trigger_type, threshold): (TriggerType, usize)) -> Self {
+        MemData {
+            gen,
+            mem_map: MemMap::new(),
+            range_tombstones: SkipMap::new(),
+            trigger: TriggerFactory::create(trigger_type, threshold),
+            pending: AtomicUsize::new(0),
+            pending_notify: Notify::new(),
         }
-    };
+    }
+
+    fn from_records(
+        gen: i64,
+        records: Vec<(Bytes, Value)>,
+        trigger: (TriggerType, usize),
+        comparator: &Arc<dyn Comparator>,
+    ) -> Self {
+        let data = MemData::new(gen, trigger);
+
+        for (key, value) in records {
+            if value.meta.is_range_del {
+                let tombstone =
+                    RangeTombstone::new(key.clone(), value.bytes.unwrap_or_default(), value.seq_id);
+                let _ = data.range_tombstones.insert((value.seq_id, key), tombstone);
+            } else {
+                let _ = data.mem_map.insert(
+                    InternalKey::new_with_seq(key, value.seq_id, comparator).meta(value.meta),
+                    value.bytes,
+                );
+            }
+        }
+
+        data
+    }
+
+    /// 尚未插入完成的写入视为存在数据
+    fn is_empty(&self) -> bool {
+        self.mem_map.is_empty()
+            && self.range_tombstones.is_empty()
+            && self.pending.load(AtomicOrdering::Acquire) == 0
+    }
+
+    fn range_tombstones(&self) -> impl Iterator<Item = RangeTombstone> + '_ {
+        self.range_tombstones
+            .iter()
+            .map(|entry| entry.value().clone())
+    }
+
+    /// 结束一次尚未插入的写入，无论其是否插入成功
+    fn finish_pending(&self) {
+        if self.pending.fetch_sub(1, AtomicOrdering::AcqRel) == 1 {
+            self.pending_notify.notify_waiters();
+        }
+    }
+
+    /// 等待交换前已写入WAL的数据插入完成
+    async fn wait_pending(&self) {
+        loop {
+            let notified = self.pending_notify.notified();
+
+            if self.pending.load(AtomicOrdering::Acquire) == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// MemTable当前的_mem与等待Flush的Immutable MemTable
+///
+/// 交换与Flush时整体替换，因此读取时无需加锁
+pub(crate) struct MemState {
+    mem: Arc<MemData>,
+    /// 由旧至新排列
+    immuts: Vec<Arc<MemData>>,
+}
+
+impl MemState {
+    /// 由新至旧依次为_mem与各个Immutable MemTable
+    fn datas(&self) -> impl Iterator<Item = &MemData> + '_ {
+        iter::once(self.mem.as_ref()).chain(self.immuts.iter().rev().map(Arc::as_ref))
+    }
+
+    fn maps(&self) -> impl Iterator<Item = &MemMap> + '_ {
+        self.datas().map(|data| &data.mem_map)
+    }
+
+    fn range_tombstones(&self) -> Vec<RangeTombstone> {
+        self.datas()
+            .flat_map(MemData::range_tombstones)
+            .collect_vec()
+    }
 }
 
+/// 事务与快照所固定的MemTable数据
+///
+/// 被固定的MemState在交换与Flush后依旧可被读取，由此交换与Flush无需等待事务或快照结束，
+/// 固定后写入_mem的数据则通过seq_id过滤
+pub(crate) type MemPin = Arc<MemState>;
+
 macro_rules! range_iter {
     ($map:expr, $min_key:expr, $max_key:expr, $option_seq:expr) => {
-        $map.range($min_key.as_ref(), $max_key.as_ref())
+        $map.range(($min_key.as_ref(), $max_key.as_ref()))
             .rev()
-            .filter(|(InternalKey { seq_id, .. }, _)| {
-                $option_seq.map_or(true, |current_seq| &current_seq >= seq_id)
+            .filter(|entry| {
+                $option_seq.map_or(true, |current_seq| current_seq >= entry.key().seq_id)
             })
-            .map(|(internal_key, value)| (&internal_key.key, value))
     };
 }
 
 impl MemTable {
+    /// 使用独占的WAL构建MemTable
+    #[allow(dead_code)]
     pub(crate) fn new(config: &Config) -> KernelResult<Self> {
-        let mut log_records = Vec::new();
-        let (log_loader, log_gen) = LogLoader::reload(
-            config.path(),
-            (DEFAULT_WAL_PATH, None),
-            config.wal_io_type,
-            &mut log_records,
-            |bytes, records| {
-                for (_, Entry { key, item, .. }) in
-                    Entry::<Value>::batch_decode(&mut Cursor::new(mem::take(bytes)))?
-                {
-                    records.push((InternalKey::new_with_seq(key, 0), item.bytes));
-                }
+        let (wal, mut wal_records, _) = Wal::reload(config)?;
+        let records = wal_records.remove(&config.family_name).unwrap_or_default();
 
-                Ok(())
-            },
-        )?;
-        let log_writer = (log_loader.writer(log_gen)?, log_gen);
-        // Q: 为什么INIT_SEQ作为Seq id?
-        // A: 因为此处是当存在有停机异常时使用wal恢复数据,此处也不存在有Version(VersionStatus的初始化在此代码之后)
-        // 因此不会影响Version的读取顺序
-        let mem_map = MemMap::from_iter(log_records);
-        let (trigger_type, threshold) = config.minor_trigger_with_threshold;
-
-        Ok(MemTable {
-            inner: Mutex::new(TableInner {
-                _mem: mem_map,
-                _immut: None,
-                log_loader,
-                log_writer,
-                trigger: TriggerFactory::create(trigger_type, threshold),
+        Ok(Self::with_wal(config, wal, Arc::default(), records))
+    }
+
+    /// 使用共享的WAL、活跃事务以及WAL中属于该ColumnFamily的数据构建MemTable
+    ///
+    /// 当前WAL中的数据恢复至_mem，更旧的WAL中的数据则作为Immutable MemTable等待Flush
+    pub(crate) fn with_wal(
+        config: &Config,
+        wal: Arc<Wal>,
+        active_txs: Arc<ActiveTransactions>,
+        records: Vec<(i64, Vec<(Bytes, Value)>)>,
+    ) -> Self {
+        let trigger = config.minor_trigger_with_threshold;
+        let comparator = &config.comparator;
+        let current_gen = wal.log_writer.lock().1;
+        let mut mem = None;
+        let mut immuts = Vec::new();
+
+        for (gen, data) in records {
+            let mem_data = MemData::from_records(gen, data, trigger, comparator);
+
+            if gen == current_gen {
+                mem = Some(mem_data);
+            } else {
+                wal.log_loader.retain(gen);
+                immuts.push(Arc::new(mem_data));
+            }
+        }
+        let mem = mem.unwrap_or_else(|| MemData::new(current_gen, trigger));
+
+        MemTable {
+            state: ArcSwap::from_pointee(MemState {
+                mem: Arc::new(mem),
+                immuts,
             }),
-            tx_count: AtomicUsize::new(0),
-        })
+            wal,
+            family: config.family_name.clone(),
+            merge_operator: config.merge_operator.clone(),
+            comparator: Arc::clone(comparator),
+            active_txs,
+            history: Mutex::new(CommitHistory::new(comparator)),
+            trigger,
+            max_immut_count: config.max_immut_count,
+        }
     }
 
+    /// 判断Key在seq_id之后是否被写入，数据已被交换或Flush时依旧有效
+    ///
+    /// seq_id须为通过`ActiveTransactions::begin`生成且尚未结束的事务的seq_id
     pub(crate) fn check_key_conflict(&self, kvs: &[KeyValue], seq_id: i64) -> bool {
-        let inner = self.inner.lock();
+        let history = self.history.lock();
 
-        for (key, _) in kvs {
-            let internal_key = InternalKey::new_with_seq(key.clone(), seq_id);
+        kvs.iter()
+            .any(|(key, _)| history.is_key_written_after(key, seq_id))
+    }
 
-            if let Some(true) = inner
-                ._mem
-                .lower_bound(Bound::Excluded(&internal_key))
-                .map(|(lower_key, _)| lower_key.key == key)
-            {
-                return true;
-            }
-        }
+    /// 判断读取过的范围内是否存在seq_id之后写入的数据或范围删除标记
+    ///
+    /// 单个Key的读取以两端均为Included的范围表示，seq_id的要求同`check_key_conflict`
+    pub(crate) fn check_read_conflict(&self, ranges: &[ReadRange], seq_id: i64) -> bool {
+        let history = self.history.lock();
+
+        ranges
+            .iter()
+            .any(|range| history.is_range_written_after(range, seq_id))
+    }
 
-        false
+    /// 将最旧的活跃事务之前的CommitHistory裁剪，详见`ActiveTransactions::end`
+    pub(crate) fn prune_history(&self, threshold: i64) {
+        self.history.lock().prune(threshold);
     }
 
     /// 插入并判断是否溢出
     ///
     /// 插入时不会去除重复键值，而是进行追加
-    pub(crate) fn insert_data(&self, data: KeyValue) -> KernelResult<bool> {
-        let mut inner = self.inner.lock();
+    #[allow(dead_code)]
+    pub(crate) async fn insert_data(self: &Arc<Self>, data: KeyValue) -> KernelResult<bool> {
+        self.insert_data_with_meta(data, ValueMeta::default(), WriteOptions::default())
+            .await
+    }
 
-        let _ = inner
-            .log_writer
-            .0
-            .add_record(&data_to_bytes(data.clone())?)?;
+    /// 插入附带过期时间或Merge标记的数据
+    pub(crate) async fn insert_data_with_meta(
+        self: &Arc<Self>,
+        data: KeyValue,
+        meta: ValueMeta,
+        options: WriteOptions,
+    ) -> KernelResult<bool> {
+        Self::write_with_families(vec![(Arc::clone(self), vec![data])], meta, options, None).await
+    }
+
+    /// 插入覆盖[start, end)的范围删除标记并判断是否溢出
+    pub(crate) async fn insert_range_tombstone(
+        self: &Arc<Self>,
+        start: Bytes,
+        end: Bytes,
+    ) -> KernelResult<bool> {
+        Self::write_with_families(
+            vec![(Arc::clone(self), vec![(start, Some(end))])],
+            ValueMeta::range_delete(),
+            WriteOptions::default(),
+            None,
+        )
+        .await
+    }
+
+    /// 将多个ColumnFamily的数据作为单条WAL记录写入，再插入至各自的MemTable中
+    ///
+    /// 所有MemTable需共享同一个WAL，返回是否存在溢出的MemTable；
+    /// seq_id在WAL写入锁内生成，以保证seq_id的顺序与WAL一致
+    pub(crate) async fn insert_batch_with_families(
+        batches: Vec<FamilyBatch>,
+        options: WriteOptions,
+    ) -> KernelResult<bool> {
+        Self::write_with_families(batches, ValueMeta::default(), options, None).await
+    }
+
+    /// 仅当满足WriteCondition时写入多个ColumnFamily的数据，否则返回其错误且不写入任何数据
+    pub(crate) async fn insert_batch_with_condition(
+        batches: Vec<FamilyBatch>,
+        options: WriteOptions,
+        condition: WriteCondition,
+    ) -> KernelResult<bool> {
+        Self::write_with_families(batches, ValueMeta::default(), options, Some(condition)).await
+    }
+
+    /// 通过组提交写入WAL，插入MemTable则在WAL写入锁外进行，以此使多个写入者能够并发插入
+    async fn write_with_families(
+        batches: Vec<FamilyBatch>,
+        meta: ValueMeta,
+        options: WriteOptions,
+        condition: Option<WriteCondition>,
+    ) -> KernelResult<bool> {
+        let Some((first, _)) = batches.first() else {
+            return Ok(false);
+        };
+        let wal = Arc::clone(&first.wal);
+
+        Ok(wal
+            .group_commit(batches, meta, options, condition)
+            .await?
+            .insert())
+    }
+
+    /// 须在WAL写入锁内调用，存在活跃事务时将写入记录至CommitHistory
+    fn record_history(&self, vec_data: &[KeyValue], seq_id: i64, meta: ValueMeta) {
+        if !self.active_txs.is_empty() {
+            let mut history = self.history.lock();
+
+            for (key, value) in vec_data {
+                if meta.is_range_del {
+                    history.record_range_tombstone(RangeTombstone::new(
+                        key.clone(),
+                        value.clone().unwrap_or_default(),
+                        seq_id,
+                    ));
+                } else {
+                    history.record_key(key.clone(), seq_id);
+                }
+            }
+        }
+    }
 
-        inner.trigger.item_process(&data);
-        let (key, value) = data;
-        let _ = inner._mem.insert(InternalKey::new(key), value);
+    /// 须在WAL写入锁内调用，获取当前的_mem并将该写入登记为尚未插入
+    fn prepare_insert(&self) -> Arc<MemData> {
+        let mem = Arc::clone(&self.state.load().mem);
+        let _ = mem.pending.fetch_add(1, AtomicOrdering::AcqRel);
 
-        Ok(inner.trigger.is_exceeded())
+        mem
     }
 
-    /// Tips: 当数据在插入mem_table中停机，则不会存入日志中
-    pub(crate) fn insert_batch_data(
+    /// 将数据插入至写入WAL时的_mem，此时该_mem可能已被交换
+    fn insert_with_seq(
         &self,
+        mem: &MemData,
         vec_data: Vec<KeyValue>,
         seq_id: i64,
-    ) -> KernelResult<bool> {
-        let mut inner = self.inner.lock();
-
-        let mut buf = Vec::new();
+        meta: ValueMeta,
+    ) -> bool {
         for item in vec_data {
-            let (key, value) = item.clone();
-            inner.trigger.item_process(&item);
+            mem.trigger.item_process(&item);
 
-            let _ = inner
-                ._mem
-                .insert(InternalKey::new_with_seq(key, seq_id), value);
-            buf.append(&mut data_to_bytes(item)?);
+            let (key, value) = item;
+            if meta.is_range_del {
+                let tombstone = RangeTombstone::new(key.clone(), value.unwrap_or_default(), seq_id);
+                let _ = mem.range_tombstones.insert((seq_id, key), tombstone);
+            } else {
+                let _ = mem.mem_map.insert(
+                    InternalKey::new_with_seq(key, seq_id, &self.comparator).meta(meta),
+                    value,
+                );
+            }
         }
-        let _ = inner.log_writer.0.add_record(&buf)?;
 
-        Ok(inner.trigger.is_exceeded())
+        mem.trigger.is_exceeded()
     }
 
     pub(crate) fn is_empty(&self) -> bool {
-        self.inner.lock()._mem.is_empty()
+        let state = self.state.load();
+
+        state.mem.mem_map.is_empty() && state.mem.range_tombstones.is_empty()
     }
 
     pub(crate) fn len(&self) -> usize {
-        self.inner.lock()._mem.len()
+        self.state.load().mem.mem_map.len()
     }
 
+    #[allow(dead_code)]
     pub(crate) fn log_loader_clone(&self) -> LogLoader {
-        self.inner.lock().log_loader.clone()
+        self.wal.log_loader_clone()
+    }
+
+    /// 等待Flush的Immutable MemTable的数量
+    pub(crate) fn immut_count(&self) -> usize {
+        self.state.load().immuts.len()
     }
 
     /// MemTable将数据弹出并转移到immut table中  (弹出数据为转移至immut table中数据的迭代器)
-    pub(crate) fn swap(&self) -> KernelResult<Option<(i64, Vec<KeyValue>)>> {
-        let count = &self.tx_count;
+    #[allow(dead_code)]
+    pub(crate) async fn swap(&self) -> KernelResult<Option<SwappedData>> {
+        if !Self::swap_with_families(&[self])? {
+            return Ok(None);
+        }
+        let immut = self.state.load().immuts.last().cloned();
 
-        loop {
-            check_count!(count);
+        match immut {
+            Some(immut) => self.immut_data(&immut).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 将共享同一WAL的多个MemTable同时交换至各自的Immutable MemTable队列中，并切换至新的WAL
+    ///
+    /// 各ColumnFamily交换后的数据均对应交换前的WAL gen，以此使Level 0的Table能够通过同gen的WAL进行恢复；
+    /// 存在数据的MemTable中任一队列已满时放弃交换，返回是否进行了交换
+    pub(crate) fn swap_with_families(tables: &[&MemTable]) -> KernelResult<bool> {
+        let Some(first) = tables.first() else {
+            return Ok(false);
+        };
+
+        // 交换期间持有WAL写入锁，因此写入者无法在交换前后分别写入不同的_mem
+        // 事务与快照通过MemPin固定其所需的数据，因此无需等待其结束
+        let mut log_writer = first.wal.log_writer.lock();
+        let swappable = tables
+            .iter()
+            .map(|table| table.state.load())
+            .zip(tables)
+            .filter(|(state, _)| !state.mem.is_empty())
+            .map(|(state, table)| state.immuts.len() < table.max_immut_count)
+            .collect_vec();
+
+        if swappable.is_empty() || swappable.contains(&false) {
+            return Ok(false);
+        }
+        let new_gen = Gen::create();
+        let new_writer = (first.wal.log_loader.writer(new_gen)?, new_gen);
+        let (mut old_writer, old_gen) = mem::replace(&mut *log_writer, new_writer);
+
+        for table in tables {
+            // 持有旧WAL的引用直至该Immutable MemTable被Flush
+            if table.swap_(new_gen) {
+                first.wal.log_loader.retain(old_gen);
+            }
+        }
+        old_writer.flush()?;
+        // 未持久化的记录随旧WAL一同持久化，此后由新WAL重新计量
+        if first.wal.is_unsynced.swap(false, AtomicOrdering::Relaxed) {
+            old_writer.sync_data()?;
+        }
 
-            let mut inner = self.inner.lock();
-            // 二重检测防止lock时(前)突然出现事务
-            // 当lock后，即使出现事务，会因为lock已被Compactor获取而无法读写，
-            // 因此不会对读写进行干扰
-            // 并且事务即使在lock后出现，所持有的seq为该压缩之前，
-            // 也不会丢失该seq的_mem，因为转移到了_immut，可以从_immut得到对应seq的数据
-            check_count!(count);
+        Ok(true)
+    }
 
-            return if !inner._mem.is_empty() {
-                inner.trigger.reset();
+    /// 以新WAL gen的_mem替换当前的_mem，存在数据时将其加入Immutable MemTable队列，返回是否加入
+    fn swap_(&self, new_gen: i64) -> bool {
+        let mem = Arc::new(MemData::new(new_gen, self.trigger));
+        let mut is_queued = false;
 
-                let mut vec_data = inner
-                    ._mem
-                    .iter()
-                    .map(|(k, v)| (k.key.clone(), v.clone()))
-                    // rev以使用最后(最新)的key
-                    .rev()
-                    .unique_by(|(k, _)| k.clone())
-                    .collect_vec();
+        let _ = self.state.rcu(|state| {
+            let mut immuts = state.immuts.clone();
 
-                vec_data.reverse();
+            is_queued = !state.mem.is_empty();
+            if is_queued {
+                immuts.push(Arc::clone(&state.mem));
+            }
+            MemState {
+                mem: Arc::clone(&mem),
+                immuts,
+            }
+        });
 
-                inner._immut = Some(Arc::new(mem::replace(&mut inner._mem, SkipMap::new())));
+        is_queued
+    }
 
-                let new_gen = Gen::create();
-                let new_writer = (inner.log_loader.writer(new_gen)?, new_gen);
-                let (mut old_writer, old_gen) = mem::replace(&mut inner.log_writer, new_writer);
-                old_writer.flush()?;
+    /// 获取最旧的Immutable MemTable的数据，Flush完成后须通过`MemTable::pop_immut`将其移除
+    pub(crate) async fn oldest_immut(&self) -> KernelResult<Option<SwappedData>> {
+        let immut = self.state.load().immuts.first().cloned();
 
-                Ok(Some((old_gen, vec_data)))
-            } else {
-                Ok(None)
+        match immut {
+            Some(immut) => self.immut_data(&immut).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 移除已Flush的最旧的Immutable MemTable，并释放其对WAL的引用
+    ///
+    /// 须在Flush后的Version生效后调用，使读取期间数据总是存在于MemTable或Version其中之一
+    pub(crate) fn pop_immut(&self, gen: i64) -> KernelResult<()> {
+        let mut is_popped = false;
+
+        let _ = self.state.rcu(|state| {
+            is_popped = state.immuts.first().map(|immut| immut.gen) == Some(gen);
+
+            MemState {
+                mem: Arc::clone(&state.mem),
+                immuts: state
+                    .immuts
+                    .iter()
+                    .skip(usize::from(is_popped))
+                    .cloned()
+                    .collect_vec(),
+            }
+        });
+        if is_popped {
+            self.wal.log_loader.release(gen)?;
+        }
+
+        Ok(())
+    }
+
+    async fn immut_data(&self, immut: &MemData) -> KernelResult<SwappedData> {
+        immut.wait_pending().await;
+        let range_tombstones = immut.range_tombstones().collect_vec();
+
+        Ok((
+            immut.gen,
+            Self::swap_data(
+                &immut.mem_map,
+                &range_tombstones,
+                self.merge_operator.as_deref(),
+                self.comparator.as_ref(),
+            )?,
+            range_tombstones,
+        ))
+    }
+
+    /// 获取MemMap中各Key合并后的数据
+    ///
+    /// 同一Key仅保留最新的数据，最新数据为Merge操作数时与更旧的数据合并，详见`merge_versions`
+    ///
+    /// 被范围删除标记覆盖的数据会被丢弃，标记本身则随数据一同持久化
+    fn swap_data(
+        mem_map: &MemMap,
+        range_tombstones: &[RangeTombstone],
+        operator: Option<&dyn MergeOperator>,
+        comparator: &dyn Comparator,
+    ) -> KernelResult<Vec<SeqKeyValue>> {
+        let now = now_millis();
+        let mut vec_data = Vec::new();
+
+        // rev以使同一Key的数据由新至旧排列
+        for (key, versions) in &mem_map
+            .iter()
+            .rev()
+            .group_by(|entry| entry.key().key.clone())
+        {
+            let versions = versions
+                .map(|entry| {
+                    let (k, v) = (entry.key(), entry.value());
+                    ((k.key.clone(), v.clone()), k.seq_id, k.meta)
+                })
+                .collect_vec();
+            let cover = cover_seq(range_tombstones, &key, None, comparator);
+            let Some(versions) = truncate_covered(versions, cover) else {
+                continue;
             };
+
+            if let Some(item) = merge_versions(versions, operator, now, false)? {
+                vec_data.push(item);
+            }
         }
+        vec_data.reverse();
+
+        Ok(vec_data)
+    }
+
+    /// 固定当前的_mem与_immuts，须在获取Version之前固定，避免期间发生的Minor Compaction导致数据丢失
+    pub(crate) fn pin(&self) -> MemPin {
+        self.state.load_full()
     }
 
+    /// 获取Key由新至旧的可见数据，最新数据为Merge操作数时会继续获取更旧的数据，直至首个非Merge的数据
+    ///
+    /// option_pin为None时读取当前的数据，否则读取其所固定的数据
+    pub(crate) fn find_versions(
+        &self,
+        key: &[u8],
+        option_seq: Option<i64>,
+        option_pin: Option<&MemPin>,
+    ) -> Vec<SeqKeyValue> {
+        let state = self.state.load();
+        let mut versions = Vec::new();
+
+        Self::versions_(
+            &Bytes::copy_from_slice(key),
+            option_seq,
+            option_pin.map_or(&**state, Arc::as_ref),
+            &self.comparator,
+            &mut versions,
+        );
+        versions
+    }
+
+    /// 基于同一MemState获取一组Key各自的可见数据，详见`find_versions`
+    pub(crate) fn multi_find_versions(
+        &self,
+        keys: &[&[u8]],
+        option_seq: Option<i64>,
+    ) -> Vec<Vec<SeqKeyValue>> {
+        let state = self.state.load();
+
+        keys.iter()
+            .map(|key| {
+                let mut versions = Vec::new();
+                Self::versions_(
+                    &Bytes::copy_from_slice(key),
+                    option_seq,
+                    &state,
+                    &self.comparator,
+                    &mut versions,
+                );
+                versions
+            })
+            .collect_vec()
+    }
+
+    /// 被范围删除标记覆盖的数据会以单个seq_id为该标记的删除标记代替
+    fn versions_(
+        key: &Bytes,
+        option_seq: Option<i64>,
+        state: &MemState,
+        comparator: &Arc<dyn Comparator>,
+        versions: &mut Vec<SeqKeyValue>,
+    ) {
+        let min_key = InternalKey::new_with_seq(key.clone(), i64::MIN, comparator);
+        let max_key =
+            InternalKey::new_with_seq(key.clone(), option_seq.unwrap_or(SEQ_MAX), comparator);
+        let cover = cover_seq(
+            &state.range_tombstones(),
+            key,
+            option_seq,
+            comparator.as_ref(),
+        );
+
+        for mem_map in state.maps() {
+            for entry in mem_map
+                .range((Bound::Included(&min_key), Bound::Included(&max_key)))
+                .rev()
+            {
+                let (internal_key, value) = (entry.key(), entry.value());
+
+                if cover.map_or(false, |cover| internal_key.seq_id < cover) {
+                    break;
+                }
+                versions.push((
+                    (internal_key.key.clone(), value.clone()),
+                    internal_key.seq_id,
+                    internal_key.meta,
+                ));
+                if !internal_key.meta.is_merge {
+                    return;
+                }
+            }
+        }
+        if let Some(cover) = cover {
+            versions.push(((key.clone(), None), cover, ValueMeta::default()));
+        }
+    }
+
+    /// 获取对option_seq可见的范围删除标记
+    pub(crate) fn range_tombstones(
+        &self,
+        option_seq: Option<i64>,
+        option_pin: Option<&MemPin>,
+    ) -> Vec<RangeTombstone> {
+        let state = self.state.load();
+
+        option_pin
+            .map_or(&**state, Arc::as_ref)
+            .range_tombstones()
+            .into_iter()
+            .filter(|tombstone| option_seq.map_or(true, |seq_id| tombstone.seq_id <= seq_id))
+            .collect_vec()
+    }
+
+    #[allow(dead_code)]
     pub(crate) fn find(&self, key: &[u8]) -> Option<KeyValue> {
         // 填充SEQ_MAX使其变为最高位以尽可能获取最新数据
-        let internal_key = InternalKey::new_with_seq(Bytes::copy_from_slice(key), SEQ_MAX);
-        let inner = self.inner.lock();
+        let internal_key =
+            InternalKey::new_with_seq(Bytes::copy_from_slice(key), SEQ_MAX, &self.comparator);
 
-        Self::find_(&internal_key, &inner._mem).or_else(|| {
-            inner
-                ._immut
-                .as_ref()
-                .and_then(|mem_map| Self::find_(&internal_key, mem_map))
-        })
+        self.state
+            .load()
+            .maps()
+            .find_map(|mem_map| Self::find_(&internal_key, mem_map))
     }
 
     /// 查询时附带seq_id进行历史数据查询
+    #[allow(dead_code)]
     pub(crate) fn find_with_sequence_id(&self, key: &[u8], seq_id: i64) -> Option<KeyValue> {
-        let internal_key = InternalKey::new_with_seq(Bytes::copy_from_slice(key), seq_id);
-        let inner = self.inner.lock();
+        let internal_key =
+            InternalKey::new_with_seq(Bytes::copy_from_slice(key), seq_id, &self.comparator);
 
-        if let Some(key_value) = MemTable::find_(&internal_key, &inner._mem) {
-            Some(key_value)
-        } else if let Some(mem_map) = &inner._immut {
-            MemTable::find_(&internal_key, mem_map)
-        } else {
-            None
-        }
+        self.state
+            .load()
+            .maps()
+            .find_map(|mem_map| MemTable::find_(&internal_key, mem_map))
     }
 
     fn find_(internal_key: &InternalKey, mem_map: &MemMap) -> Option<KeyValue> {
         mem_map
             .upper_bound(Bound::Included(internal_key))
-            .and_then(|(upper_key, value)| {
-                let key = internal_key.get_key();
-                (key == &upper_key.key).then(|| (key.clone(), value.clone()))
+            .and_then(|entry| {
+                let upper_key = entry.key();
+
+                (internal_key.get_key() == &upper_key.key)
+                    .then(|| upper_key.to_key_value(entry.value(), now_millis()))
             })
     }
 
+    /// 获取范围内各Key最新的可见数据
     fn _range_scan(
-        inner: &TableInner,
+        state: &MemState,
         min: Bound<&[u8]>,
         max: Bound<&[u8]>,
         option_seq: Option<i64>,
-    ) -> Vec<KeyValue> {
-        fn to_internal_key(
-            bound: &Bound<&[u8]>,
-            included: i64,
-            excluded: i64,
-        ) -> Bound<InternalKey> {
+        comparator: &Arc<dyn Comparator>,
+    ) -> Vec<SeqKeyValue> {
+        let to_internal_key = |bound: &Bound<&[u8]>, included: i64, excluded: i64| {
             bound.map(|key| {
                 InternalKey::new_with_seq(
                     Bytes::copy_from_slice(key),
@@ -383,138 +1442,382 @@ impl MemTable {
                     } else {
                         excluded
                     },
+                    comparator,
                 )
             })
-        }
-        let inner = unsafe {
-            // Tips: make sure the `mem_iter` and `immut_mem_iter` destruct in this method
-            mem::transmute::<&TableInner, &'static TableInner>(inner)
         };
 
         let min_key = to_internal_key(&min, i64::MIN, i64::MAX);
         let max_key = to_internal_key(&max, i64::MAX, i64::MIN);
 
-        let mut merged = Vec::new();
-        let fn_push =
-            |results: &mut Vec<KeyValue>, item: &mut Option<(&Bytes, &Option<Bytes>)>, new_item| {
-                if let Some((internal_key, value)) = mem::replace(item, new_item) {
-                    Self::duplicates_push(results, internal_key, value);
-                }
-            };
-        let mut mem_iter = range_iter!(inner._mem, min_key, max_key, option_seq);
-
-        if let Some(immut) = &inner._immut {
-            let mut immut_mem_iter = range_iter!(immut, min_key, max_key, option_seq);
-            let (mut mem_current, mut immut_mem_current) = (mem_iter.next(), immut_mem_iter.next());
-
-            while mem_current.is_some() && immut_mem_current.is_some() {
-                match mem_current
-                    .as_ref()
-                    .unwrap()
-                    .0
-                    .cmp(immut_mem_current.as_ref().unwrap().0)
-                {
-                    Ordering::Greater => fn_push(&mut merged, &mut mem_current, mem_iter.next()),
-                    Ordering::Less => {
-                        fn_push(&mut merged, &mut immut_mem_current, immut_mem_iter.next())
-                    }
-                    Ordering::Equal => {
-                        fn_push(&mut merged, &mut mem_current, mem_iter.next());
-
-                        immut_mem_current = immut_mem_iter.next();
-                    }
-                }
-            }
-
-            if let Some((internal_key, value)) = mem_current {
-                Self::duplicates_push(&mut merged, internal_key, value);
-            }
-            if let Some((internal_key, value)) = immut_mem_current {
-                Self::duplicates_push(&mut merged, internal_key, value);
-            }
-            // one of the two is empty
-            mem_iter
-                .chain(immut_mem_iter)
-                .for_each(|(internal_key, value)| {
-                    Self::duplicates_push(&mut merged, internal_key, value)
+        // 各MemMap中各Key最新的数据由新至旧两两归并，同一Key以更新的MemMap中的数据为准
+        let merged = state
+            .maps()
+            .map(|mem_map| {
+                let mut results = Vec::new();
+                range_iter!(mem_map, min_key, max_key, option_seq).for_each(|entry| {
+                    Self::duplicates_push(&mut results, entry.key(), entry.value())
                 });
-        } else {
-            mem_iter.for_each(|(internal_key, value)| {
-                Self::duplicates_push(&mut merged, internal_key, value)
-            });
-        }
+                results.reverse();
+                results
+            })
+            .reduce(|newer, older| {
+                newer
+                    .into_iter()
+                    .merge_join_by(older, |((key_a, _), ..), ((key_b, _), ..)| {
+                        comparator.compare(key_a, key_b)
+                    })
+                    .map(|either| match either {
+                        EitherOrBoth::Both(item, _)
+                        | EitherOrBoth::Left(item)
+                        | EitherOrBoth::Right(item) => item,
+                    })
+                    .collect_vec()
+            })
+            .unwrap_or_default();
 
-        merged.reverse();
-        assert!(merged.is_sorted_by_key(|(k, _)| k));
-        assert!(merged.iter().all_unique());
+        assert!(merged
+            .iter()
+            .tuple_windows()
+            .all(|(((key_a, _), ..), ((key_b, _), ..))| comparator.compare(key_a, key_b).is_lt()));
         merged
     }
 
-    fn duplicates_push(results: &mut Vec<KeyValue>, key: &Bytes, value: &Option<Bytes>) {
+    fn duplicates_push(
+        results: &mut Vec<SeqKeyValue>,
+        internal_key: &InternalKey,
+        value: &Option<Bytes>,
+    ) {
         if !matches!(
-            results.last().map(|(last_key, _)| last_key == key),
+            results
+                .last()
+                .map(|((last_key, _), ..)| last_key == &internal_key.key),
             Some(true)
         ) {
-            results.push((key.clone(), value.clone()))
+            results.push((
+                (internal_key.key.clone(), value.clone()),
+                internal_key.seq_id,
+                internal_key.meta,
+            ))
         }
     }
 
+    #[allow(dead_code)]
     pub(crate) fn range_scan(
         &self,
         min: Bound<&[u8]>,
         max: Bound<&[u8]>,
         option_seq: Option<i64>,
     ) -> Vec<KeyValue> {
-        let inner = self.inner.lock();
+        let now = now_millis();
+
+        Self::_range_scan(&self.state.load(), min, max, option_seq, &self.comparator)
+            .into_iter()
+            .map(|(key_value, _, meta)| expire_filter(key_value, meta.expire_at, now))
+            .collect_vec()
+    }
+
+    /// 获取范围内各Key由新至旧的可见数据，同一Key的数据相邻排列
+    ///
+    /// 与`MemTable::find_versions`相同，最新数据为Merge操作数时会继续获取更旧的数据
+    pub(crate) fn range_versions(
+        &self,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+        option_seq: Option<i64>,
+        option_pin: Option<&MemPin>,
+    ) -> Vec<SeqKeyValue> {
+        let current = self.state.load();
+        let state = option_pin.map_or(&**current, Arc::as_ref);
+        let range_tombstones = state.range_tombstones();
+        let mut vec_data = Vec::new();
+
+        for item in Self::_range_scan(state, min, max, option_seq, &self.comparator) {
+            let cover = cover_seq(
+                &range_tombstones,
+                &item.0 .0,
+                option_seq,
+                self.comparator.as_ref(),
+            );
+
+            if let Some(cover) = cover.filter(|cover| item.1 < *cover) {
+                vec_data.push(((item.0 .0, None), cover, ValueMeta::default()));
+            } else if item.2.is_merge {
+                Self::versions_(
+                    &item.0 .0,
+                    option_seq,
+                    state,
+                    &self.comparator,
+                    &mut vec_data,
+                );
+            } else {
+                vec_data.push(item);
+            }
+        }
+
+        vec_data
+    }
+}
+
+/// 等待组提交的写入者队列
+#[derive(Default)]
+struct WriterQueue {
+    writers: VecDeque<GroupWriter>,
+    /// 是否存在Leader，不存在时首个到达的写入者成为Leader
+    has_leader: bool,
+}
+
+impl WriterQueue {
+    /// 将Leader移交给队首的写入者，已被取消的写入者尚未写入，因此直接移除
+    fn hand_off(&mut self) {
+        while let Some(writer) = self.writers.front_mut() {
+            if let Some(Ok(())) = writer
+                .notifier
+                .take()
+                .map(|notifier| notifier.send(WriterState::Leader))
+            {
+                return;
+            }
+            let _ = self.writers.pop_front();
+        }
+        self.has_leader = false;
+    }
+}
+
+/// Leader写入结束或异常退出时移交Leader
+struct LeaderGuard<'a>(&'a Mutex<WriterQueue>);
+
+impl Drop for LeaderGuard<'_> {
+    fn drop(&mut self) {
+        self.0.lock().hand_off();
+    }
+}
+
+/// 组提交中等待写入WAL的写入者
+struct GroupWriter {
+    record: GroupRecord,
+    bytes_len: usize,
+    /// 用于通知其成为Leader或其写入结果，通知时被取出
+    notifier: Option<oneshot::Sender<WriterState>>,
+}
+
+enum WriterState {
+    /// 位于队首，由其作为Leader写入队列中的写入
+    Leader,
+    /// 已由Leader写入WAL
+    Done(KernelResult<PendingWrite>),
+}
+
+impl GroupWriter {
+    fn new(record: GroupRecord, notifier: oneshot::Sender<WriterState>) -> Self {
+        let bytes_len = record
+            .0
+            .iter()
+            .flat_map(|(_, vec_data)| vec_data)
+            .map(key_value_bytes_len)
+            .sum();
+
+        GroupWriter {
+            record,
+            bytes_len,
+            notifier: Some(notifier),
+        }
+    }
+}
+
+/// 等待成为Leader或写入结果的写入者
+///
+/// 等待期间被取消时，已收到的Leader将移交给下一写入者，已写入WAL的数据则代为插入
+struct WriterWaiter<'a> {
+    wal: &'a Wal,
+    rx: Option<oneshot::Receiver<WriterState>>,
+}
+
+impl WriterWaiter<'_> {
+    /// Leader在通知前异常退出时返回None
+    async fn wait(mut self) -> Option<WriterState> {
+        let state = self.rx.as_mut()?.await.ok();
+        self.rx = None;
+
+        state
+    }
+}
+
+impl Drop for WriterWaiter<'_> {
+    fn drop(&mut self) {
+        let Some(mut rx) = self.rx.take() else {
+            return;
+        };
+        // 关闭后Leader无法再通知，因此此后仅需处理已收到的通知
+        rx.close();
+        match rx.try_recv() {
+            Ok(WriterState::Leader) => {
+                let mut queue = self.wal.writers.lock();
+                let _ = queue.writers.pop_front();
+                queue.hand_off();
+            }
+            Ok(WriterState::Done(Ok(pending))) => {
+                let _ = pending.insert();
+            }
+            _ => (),
+        }
+    }
+}
+
+/// 已写入WAL但尚未插入MemTable的写入
+///
+/// 析构时结束各_mem中的pending并发布写入序号，因此写入异常或被取消时也不会阻塞后续的读取与Flush
+struct PendingWrite {
+    batches: Vec<FamilyBatch>,
+    meta: ValueMeta,
+    seq_id: i64,
+    /// 写入WAL时各MemTable的_mem
+    mems: Vec<Arc<MemData>>,
+    wal: Arc<Wal>,
+    /// 插入完成后依此发布，详见`Wal::publish`
+    ticket: u64,
+}
+
+impl PendingWrite {
+    /// 将数据插入至写入WAL时的各_mem，并判断是否溢出
+    fn insert(mut self) -> bool {
+        let mut is_exceeded = false;
+
+        for ((mem_table, vec_data), mem) in mem::take(&mut self.batches).into_iter().zip(&self.mems)
+        {
+            is_exceeded |= mem_table.insert_with_seq(mem, vec_data, self.seq_id, self.meta);
+        }
+
+        is_exceeded
+    }
+}
 
-        Self::_range_scan(&inner, min, max, option_seq)
+impl Drop for PendingWrite {
+    fn drop(&mut self) {
+        for mem in &self.mems {
+            mem.finish_pending();
+        }
+        self.wal.publish(self.ticket);
     }
 }
 
-pub(crate) fn data_to_bytes(data: KeyValue) -> KernelResult<Vec<u8>> {
-    let (key, value) = data.clone();
+/// 将各ColumnFamily的数据编码为单条WAL记录
+///
+/// 格式为多组[family_len, family, data_len, data]，其中data为同一seq_id与ValueMeta下的Entry序列
+pub(crate) fn record_to_bytes(
+    groups: &[(&str, &[KeyValue])],
+    seq_id: i64,
+    meta: ValueMeta,
+) -> KernelResult<Vec<u8>> {
     let mut bytes = Vec::new();
 
-    Entry::new(0, key.len(), key, Value::from(value)).encode(&mut bytes)?;
+    for (family, vec_data) in groups {
+        let mut data_bytes = Vec::new();
+
+        for (key, value) in vec_data.iter() {
+            let value = Value::new(value.clone(), seq_id).meta(meta);
+
+            Entry::new(0, key.len(), key.clone(), value).encode(&mut data_bytes)?;
+        }
+        bytes.write_varint(family.len() as u32)?;
+        bytes.write_all(family.as_bytes())?;
+        bytes.write_varint(data_bytes.len() as u32)?;
+        bytes.append(&mut data_bytes);
+    }
+
     Ok(bytes)
 }
 
+/// 解码`record_to_bytes`所编码的WAL记录
+pub(crate) fn record_from_bytes(bytes: &[u8]) -> KernelResult<Vec<FamilyRecord>> {
+    let mut cursor = Cursor::new(bytes);
+    let mut groups = Vec::new();
+
+    while !cursor.is_empty() {
+        let mut family = vec![0; cursor.read_varint::<u32>()? as usize];
+        cursor.read_exact(&mut family)?;
+        let mut data_bytes = vec![0; cursor.read_varint::<u32>()? as usize];
+        cursor.read_exact(&mut data_bytes)?;
+
+        let vec_data = Entry::<Value>::batch_decode(&mut Cursor::new(data_bytes))?
+            .into_iter()
+            .map(|(_, Entry { key, item, .. })| (key, item))
+            .collect_vec();
+        let family = String::from_utf8(family).map_err(|_| KernelError::UnexpectedCommandType)?;
+
+        groups.push((family, vec_data));
+    }
+
+    Ok(groups)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::kernel::lsm::iterator::{Iter, Seek, SeekIter};
+    use crate::kernel::lsm::comparator::{BytewiseComparator, Comparator};
+    use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
     use crate::kernel::lsm::mem_table::{
-        data_to_bytes, InternalKey, KeyValue, MemMap, MemMapIter, MemTable,
+        record_from_bytes, record_to_bytes, InternalKey, KeyValue, MemMap, MemMapIter, MemTable,
+        ValueMeta, Wal,
     };
-    use crate::kernel::lsm::storage::{Config, Sequence};
+    use crate::kernel::lsm::storage::{Config, Sequence, WalSyncPolicy, WriteOptions};
     use crate::kernel::KernelResult;
+    use crate::KernelError;
     use bytes::Bytes;
+    use itertools::Itertools;
     use std::collections::Bound;
+    use std::mem;
+    use std::sync::atomic::Ordering as AtomicOrdering;
+    use std::sync::Arc;
+    use std::time::Duration;
     use tempfile::TempDir;
+    use tokio::time::timeout;
 
     impl MemTable {
         pub(crate) fn insert_data_with_seq(&self, data: KeyValue, seq: i64) -> KernelResult<usize> {
-            let (key, value) = data.clone();
-            let mut inner = self.inner.lock();
+            let data = vec![data];
+            let mem = {
+                let mut log_writer = self.wal.log_writer.lock();
+                let _ = log_writer.0.add_record(&record_to_bytes(
+                    &[(&self.family, &data)],
+                    seq,
+                    ValueMeta::default(),
+                )?)?;
+                self.record_history(&data, seq, ValueMeta::default());
+                self.prepare_insert()
+            };
+            let _ = self.insert_with_seq(&mem, data, seq, ValueMeta::default());
+            mem.finish_pending();
+
+            Ok(self.len())
+        }
+    }
+
+    impl Wal {
+        /// 占用Leader，使此后的写入均在队列中等待
+        pub(crate) fn hold_leader(&self) {
+            self.writers.lock().has_leader = true;
+        }
 
-            let _ = inner.log_writer.0.add_record(&data_to_bytes(data)?)?;
-            let _ = inner
-                ._mem
-                .insert(InternalKey::new_with_seq(key, seq), value);
+        /// 将Leader移交给队首的写入者
+        pub(crate) fn release_leader(&self) {
+            self.writers.lock().hand_off();
+        }
 
-            Ok(inner._mem.len())
+        pub(crate) fn queued_len(&self) -> usize {
+            self.writers.lock().writers.len()
         }
     }
 
-    #[test]
-    fn test_mem_table_find() -> KernelResult<()> {
+    #[tokio::test]
+    async fn test_mem_table_find() -> KernelResult<()> {
         let temp_dir = TempDir::new().expect("unable to create temporary working directory");
 
-        let mem_table = MemTable::new(&Config::new(temp_dir.path()))?;
+        let mem_table = Arc::new(MemTable::new(&Config::new(temp_dir.path()))?);
 
         let data_1 = (Bytes::from(vec![b'k']), Some(Bytes::from(vec![b'1'])));
         let data_2 = (Bytes::from(vec![b'k']), Some(Bytes::from(vec![b'2'])));
 
-        let _ = mem_table.insert_data(data_1)?;
+        let _ = mem_table.insert_data(data_1).await?;
 
         let old_seq_id = Sequence::create();
 
@@ -523,7 +1826,7 @@ mod tests {
             Some((Bytes::from(vec![b'k']), Some(Bytes::from(vec![b'1']))))
         );
 
-        let _ = mem_table.insert_data(data_2)?;
+        let _ = mem_table.insert_data(data_2).await?;
 
         assert_eq!(
             mem_table.find(&[b'k']),
@@ -545,22 +1848,90 @@ mod tests {
         Ok(())
     }
 
-    #[test]
-    fn test_mem_table_swap() -> KernelResult<()> {
+    #[tokio::test]
+    async fn test_mem_table_expire() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+
+        let mem_table = Arc::new(MemTable::new(&Config::new(temp_dir.path()))?);
+        let (key_1, key_2) = (Bytes::from(vec![b'1']), Bytes::from(vec![b'2']));
+        let value = Some(Bytes::from(vec![b'v']));
+
+        let _ = mem_table
+            .insert_data((key_1.clone(), value.clone()))
+            .await?;
+        let old_seq_id = Sequence::create();
+        // 已过期的数据会覆盖其更旧的数据
+        let _ = mem_table
+            .insert_data_with_meta(
+                (key_1.clone(), value.clone()),
+                ValueMeta::with_expire(Some(0)),
+                WriteOptions::default(),
+            )
+            .await?;
+        let _ = mem_table
+            .insert_data_with_meta(
+                (key_2.clone(), value.clone()),
+                ValueMeta::with_expire(Some(i64::MAX)),
+                WriteOptions::default(),
+            )
+            .await?;
+
+        assert_eq!(mem_table.find(&key_1), Some((key_1.clone(), None)));
+        assert_eq!(
+            mem_table.find_with_sequence_id(&key_1, old_seq_id),
+            Some((key_1.clone(), value.clone()))
+        );
+        assert_eq!(mem_table.find(&key_2), Some((key_2.clone(), value.clone())));
+        assert_eq!(
+            mem_table.range_scan(Bound::Unbounded, Bound::Unbounded, None),
+            vec![(key_1.clone(), None), (key_2.clone(), value.clone())]
+        );
+
+        let state = mem_table.state.load();
+        let mut iter = MemMapIter::new(&state.mem.mem_map, &mem_table.comparator);
+        assert_eq!(iter.try_next()?, Some((key_1.clone(), None)));
+        assert_eq!(iter.try_next()?, Some((key_2.clone(), value.clone())));
+        drop(state);
+
+        // 过期时间随交换一同转移
+        let (_, vec_data, _) = mem_table.swap().await?.unwrap();
+        assert_eq!(
+            vec_data
+                .into_iter()
+                .map(|(_, _, meta)| meta.expire_at)
+                .collect::<Vec<_>>(),
+            vec![Some(0), Some(i64::MAX)]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mem_table_swap() -> KernelResult<()> {
         let temp_dir = TempDir::new().expect("unable to create temporary working directory");
 
-        let mem_table = MemTable::new(&Config::new(temp_dir.path()))?;
+        let mem_table = Arc::new(MemTable::new(&Config::new(temp_dir.path()))?);
 
         let _ = mem_table
-            .insert_data((Bytes::from(vec![b'k', b'1']), Some(Bytes::from(vec![b'1']))))?;
+            .insert_data((Bytes::from(vec![b'k', b'1']), Some(Bytes::from(vec![b'1']))))
+            .await?;
         let _ = mem_table
-            .insert_data((Bytes::from(vec![b'k', b'1']), Some(Bytes::from(vec![b'2']))))?;
+            .insert_data((Bytes::from(vec![b'k', b'1']), Some(Bytes::from(vec![b'2']))))
+            .await?;
         let _ = mem_table
-            .insert_data((Bytes::from(vec![b'k', b'2']), Some(Bytes::from(vec![b'1']))))?;
+            .insert_data((Bytes::from(vec![b'k', b'2']), Some(Bytes::from(vec![b'1']))))
+            .await?;
         let _ = mem_table
-            .insert_data((Bytes::from(vec![b'k', b'2']), Some(Bytes::from(vec![b'2']))))?;
+            .insert_data((Bytes::from(vec![b'k', b'2']), Some(Bytes::from(vec![b'2']))))
+            .await?;
 
-        let (_, mut vec) = mem_table.swap()?.unwrap();
+        let (_, vec, _) = mem_table.swap().await?.unwrap();
+        let (mut vec, vec_seq): (Vec<_>, Vec<_>) = vec
+            .into_iter()
+            .map(|(key_value, seq_id, _)| (key_value, seq_id))
+            .unzip();
+        // 同一Key保留最新的数据以及其对应的seq_id
+        assert!(vec_seq[0] < vec_seq[1]);
 
         assert_eq!(
             vec.pop(),
@@ -574,11 +1945,581 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_mem_table_pin() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+
+        let mem_table = Arc::new(MemTable::new(&Config::new(temp_dir.path()))?);
+        let (key_1, key_2, key_3, key_4) = (
+            Bytes::from_static(b"k1"),
+            Bytes::from_static(b"k2"),
+            Bytes::from_static(b"k3"),
+            Bytes::from_static(b"k4"),
+        );
+
+        let _ = mem_table
+            .insert_data((key_1.clone(), Some(key_1.clone())))
+            .await?;
+        let _ = mem_table.swap().await?;
+        let _ = mem_table
+            .insert_data((key_2.clone(), Some(key_2.clone())))
+            .await?;
+        let _ = mem_table
+            .insert_range_tombstone(key_1.clone(), key_2.clone())
+            .await?;
+
+        let pin = mem_table.pin();
+        let seq_id = Sequence::create();
+        let _ = mem_table
+            .insert_data((key_3.clone(), Some(key_3.clone())))
+            .await?;
+
+        // 交换并Flush后固定的_mem与_immuts均已不在MemTable中
+        let _ = mem_table.swap().await?;
+        let _ = mem_table
+            .insert_data((key_4.clone(), Some(key_4.clone())))
+            .await?;
+        let _ = mem_table.swap().await?;
+        for _ in 0..2 {
+            let (gen, ..) = mem_table.oldest_immut().await?.unwrap();
+            mem_table.pop_immut(gen)?;
+        }
+        assert!(mem_table.find_versions(&key_2, None, None).is_empty());
+        // 固定后新的_mem中的数据不可见
+        assert!(mem_table.find_versions(&key_4, None, Some(&pin)).is_empty());
+
+        assert_eq!(
+            mem_table.find_versions(&key_2, Some(seq_id), Some(&pin))[0].0,
+            (key_2.clone(), Some(key_2.clone()))
+        );
+        assert_eq!(
+            mem_table.find_versions(&key_1, Some(seq_id), Some(&pin))[0].0,
+            (key_1.clone(), None)
+        );
+        assert_eq!(
+            mem_table.range_tombstones(Some(seq_id), Some(&pin)).len(),
+            1
+        );
+        assert_eq!(
+            mem_table
+                .range_versions(Bound::Unbounded, Bound::Unbounded, None, Some(&pin))
+                .into_iter()
+                .map(|(key_value, ..)| key_value)
+                .collect::<Vec<_>>(),
+            vec![
+                (key_1, None),
+                (key_2.clone(), Some(key_2)),
+                (key_3.clone(), Some(key_3)),
+            ]
+        );
+
+        Ok(())
+    }
+
     #[test]
-    fn test_mem_table_check_key_conflict() -> KernelResult<()> {
+    fn test_wal_record() -> KernelResult<()> {
+        let data_1 = vec![
+            (Bytes::from_static(b"k1"), Some(Bytes::from_static(b"v1"))),
+            (Bytes::from_static(b"k2"), None),
+        ];
+        let data_2 = vec![(Bytes::from_static(b"k1"), Some(Bytes::new()))];
+
+        let groups = record_from_bytes(&record_to_bytes(
+            &[("default", &data_1), ("meta", &data_2)],
+            7,
+            ValueMeta::default(),
+        )?)?;
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "default");
+        assert_eq!(groups[1].0, "meta");
+        for ((_, vec_data), data) in groups.into_iter().zip([data_1, data_2]) {
+            assert_eq!(
+                vec_data
+                    .into_iter()
+                    .map(|(key, value)| ((key, value.bytes), value.seq_id))
+                    .collect::<Vec<_>>(),
+                data.into_iter().map(|item| (item, 7)).collect::<Vec<_>>()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mem_table_range_tombstone() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let (key_1, key_2, key_3) = (
+            Bytes::from_static(b"1"),
+            Bytes::from_static(b"2"),
+            Bytes::from_static(b"3"),
+        );
+
+        let mem_table = Arc::new(MemTable::new(&config)?);
+        for key in [&key_1, &key_2, &key_3] {
+            let _ = mem_table
+                .insert_data((key.clone(), Some(key.clone())))
+                .await?;
+        }
+        let old_seq_id = Sequence::create();
+        let _ = mem_table
+            .insert_range_tombstone(key_1.clone(), key_3.clone())
+            .await?;
+        let _ = mem_table
+            .insert_data((key_2.clone(), Some(key_3.clone())))
+            .await?;
+
+        let tombstone_seq = mem_table.range_tombstones(None, None)[0].seq_id;
+        assert!(mem_table
+            .range_tombstones(Some(old_seq_id), None)
+            .is_empty());
+        // 被覆盖的数据以seq_id为该标记的删除标记代替
+        assert_eq!(
+            mem_table.find_versions(&key_1, None, None),
+            vec![((key_1.clone(), None), tombstone_seq, ValueMeta::default())]
+        );
+        assert_eq!(
+            mem_table.find_versions(&key_1, Some(old_seq_id), None)[0].0,
+            (key_1.clone(), Some(key_1.clone()))
+        );
+        assert_eq!(
+            mem_table
+                .range_versions(Bound::Unbounded, Bound::Unbounded, None, None)
+                .into_iter()
+                .map(|(key_value, ..)| key_value)
+                .collect::<Vec<_>>(),
+            vec![
+                (key_1.clone(), None),
+                (key_2.clone(), Some(key_3.clone())),
+                (key_3.clone(), Some(key_3.clone())),
+            ]
+        );
+        drop(mem_table);
+
+        // 范围删除标记可通过WAL恢复
+        let mem_table = Arc::new(MemTable::new(&config)?);
+        assert_eq!(mem_table.range_tombstones(None, None).len(), 1);
+
+        let (_, vec_data, range_tombstones) = mem_table.swap().await?.unwrap();
+        assert_eq!(
+            vec_data
+                .into_iter()
+                .map(|(key_value, ..)| key_value)
+                .collect::<Vec<_>>(),
+            vec![
+                (key_2.clone(), Some(key_3.clone())),
+                (key_3.clone(), Some(key_3.clone())),
+            ]
+        );
+        assert_eq!(range_tombstones[0].seq_id, tombstone_seq);
+        // 交换后的范围删除标记仍对读取生效
+        assert_eq!(
+            mem_table.find_versions(&key_1, None, None)[0].0,
+            (key_1, None)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mem_table_swap_with_families() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let mut config_meta = config.clone();
+        config_meta.family_name = "meta".to_string();
+        let mut config_empty = config.clone();
+        config_empty.family_name = "empty".to_string();
+
+        let (wal, _, _) = Wal::reload(&config)?;
+        let mem_table = Arc::new(MemTable::with_wal(
+            &config,
+            Arc::clone(&wal),
+            Arc::default(),
+            vec![],
+        ));
+        let mem_table_meta = Arc::new(MemTable::with_wal(
+            &config_meta,
+            Arc::clone(&wal),
+            Arc::default(),
+            vec![],
+        ));
+        let mem_table_empty = Arc::new(MemTable::with_wal(
+            &config_empty,
+            Arc::clone(&wal),
+            Arc::default(),
+            vec![],
+        ));
+
+        let key = Bytes::from_static(b"k");
+        let _ = MemTable::insert_batch_with_families(
+            vec![
+                (
+                    Arc::clone(&mem_table),
+                    vec![(key.clone(), Some(key.clone()))],
+                ),
+                (Arc::clone(&mem_table_meta), vec![(key.clone(), None)]),
+            ],
+            WriteOptions::default(),
+        )
+        .await?;
+        let _ = mem_table_meta
+            .insert_data((key.clone(), Some(Bytes::new())))
+            .await?;
+
+        assert!(MemTable::swap_with_families(&[
+            &mem_table,
+            &mem_table_meta,
+            &mem_table_empty
+        ])?);
+        assert!(mem_table_empty.oldest_immut().await?.is_none());
+        let (gen_meta, data_meta, _) = mem_table_meta.oldest_immut().await?.unwrap();
+        let (gen, data, _) = mem_table.oldest_immut().await?.unwrap();
+        // 共享WAL的MemTable交换后使用相同的gen
+        assert_eq!(gen, gen_meta);
+        assert_eq!(data[0].0, (key.clone(), Some(key.clone())));
+        assert_eq!(data_meta[0].0, (key.clone(), Some(Bytes::new())));
+        assert!(mem_table.is_empty() && mem_table_meta.is_empty());
+        assert_eq!(
+            mem_table_meta.find(&key),
+            Some((key.clone(), Some(Bytes::new())))
+        );
+        drop((mem_table, mem_table_meta, mem_table_empty, wal));
+
+        // 交换后的数据存在于旧gen的WAL中，并以ColumnFamily区分
+        let (wal, wal_records, _) = Wal::reload(&config)?;
+        assert_eq!(wal_records["default"][0].0, gen);
+        assert_eq!(wal_records["meta"][0].1.len(), 2);
+        let mut records = Vec::new();
+        wal.log_loader.load(gen, &mut records, |bytes, records| {
+            records.append(&mut record_from_bytes(&mem::take(bytes))?);
+
+            Ok(())
+        })?;
+        assert_eq!(
+            records
+                .iter()
+                .map(|(family, vec_data)| (family.as_str(), vec_data.len()))
+                .collect::<Vec<_>>(),
+            vec![("default", 1), ("meta", 1), ("meta", 1)]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_mem_table_concurrent_write() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path()).max_immut_count(usize::MAX);
+        let mem_table = Arc::new(MemTable::new(&config)?);
+        let (tasks, times) = (8, 200);
+
+        let writers = (0..tasks)
+            .map(|i| {
+                let mem_table = Arc::clone(&mem_table);
+                tokio::spawn(async move {
+                    for j in 0..times {
+                        let key = Bytes::from(format!("{i}_{j}"));
+                        let _ = mem_table.insert_data((key.clone(), Some(key))).await?;
+                    }
+                    Ok::<_, KernelError>(())
+                })
+            })
+            .collect_vec();
+        let swapper = {
+            let mem_table = Arc::clone(&mem_table);
+            tokio::spawn(async move {
+                for _ in 0..50 {
+                    let _ = MemTable::swap_with_families(&[&mem_table])?;
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+                Ok::<_, KernelError>(())
+            })
+        };
+
+        // 通过read_seq生成的seq_id所读取到的数据不会因此后完成的插入而改变
+        for _ in 0..50 {
+            let seq_id = mem_table.wal.read_seq(Sequence::create).await;
+            let scan = || mem_table.range_scan(Bound::Unbounded, Bound::Unbounded, Some(seq_id));
+            let before = scan();
+
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            assert_eq!(before, scan());
+        }
+        for writer in writers {
+            writer.await.expect("writer panicked")?;
+        }
+        swapper.await.expect("swapper panicked")?;
+
+        for i in 0..tasks {
+            for j in 0..times {
+                let key = Bytes::from(format!("{i}_{j}"));
+                assert_eq!(mem_table.find(&key), Some((key.clone(), Some(key))));
+            }
+        }
+        let mut count = mem_table.len();
+        while let Some((gen, vec_data, _)) = mem_table.oldest_immut().await? {
+            count += vec_data.len();
+            mem_table.pop_immut(gen)?;
+        }
+        assert_eq!(count, tasks * times);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[allow(clippy::await_holding_lock)]
+    async fn test_mem_table_group_commit() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let mem_table = Arc::new(MemTable::new(&config)?);
+        let tasks = 8;
+
+        // 持有WAL写入锁期间排队的写入由同一Leader合并为单条WAL记录
+        let log_writer = mem_table.wal.log_writer.lock();
+        let writers = (0..tasks)
+            .map(|i| {
+                let mem_table = Arc::clone(&mem_table);
+                tokio::spawn(async move {
+                    let key = Bytes::from(format!("{i}"));
+                    mem_table.insert_data((key.clone(), Some(key))).await
+                })
+            })
+            .collect_vec();
+        while mem_table.wal.writers.lock().writers.len() < tasks {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        drop(log_writer);
+
+        for writer in writers {
+            let _ = writer.await.expect("writer panicked")?;
+        }
+        let gen = {
+            let mut log_writer = mem_table.wal.log_writer.lock();
+            log_writer.0.flush()?;
+            log_writer.1
+        };
+        let mut records = Vec::new();
+        mem_table
+            .wal
+            .log_loader
+            .load(gen, &mut records, |bytes, records| {
+                records.push(record_from_bytes(&mem::take(bytes))?);
+                Ok(())
+            })?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].len(), tasks);
+
+        // 各写入依旧拥有各自的seq_id
+        let seqs = records[0]
+            .iter()
+            .flat_map(|(_, data)| data)
+            .map(|(_, value)| value.seq_id)
+            .collect_vec();
+        assert!(seqs.iter().tuple_windows().all(|(a, b)| a < b));
+        for i in 0..tasks {
+            let key = Bytes::from(format!("{i}"));
+            assert_eq!(mem_table.find(&key), Some((key.clone(), Some(key))));
+        }
+
+        Ok(())
+    }
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    #[allow(clippy::await_holding_lock)]
+    async fn test_mem_table_group_commit_abort() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let mem_table = Arc::new(MemTable::new(&config)?);
+        let key = Bytes::from_static(b"k");
+
+        // 未插入即析构的写入依旧发布其写入序号，不会阻塞此后的读取
+        let pending = mem_table
+            .wal
+            .group_commit(
+                vec![(
+                    Arc::clone(&mem_table),
+                    vec![(key.clone(), Some(key.clone()))],
+                )],
+                ValueMeta::default(),
+                WriteOptions::default(),
+                None,
+            )
+            .await?;
+        drop(pending);
+        let _ = timeout(
+            Duration::from_secs(5),
+            mem_table.wal.read_seq(Sequence::create),
+        )
+        .await
+        .expect("read_seq blocked by an unpublished ticket");
+        assert_eq!(mem_table.find(&key), None);
+
+        // 等待期间被取消的写入者不会阻塞队列，已写入WAL的数据由Leader代为插入
+        let tasks = 4;
+        let log_writer = mem_table.wal.log_writer.lock();
+        let writers = (0..tasks)
+            .map(|i| {
+                let mem_table = Arc::clone(&mem_table);
+                tokio::spawn(async move {
+                    let key = Bytes::from(format!("{i}"));
+                    mem_table.insert_data((key.clone(), Some(key))).await
+                })
+            })
+            .collect_vec();
+        while mem_table.wal.writers.lock().writers.len() < tasks {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        writers[tasks - 1].abort();
+        drop(log_writer);
+
+        for writer in writers {
+            if let Ok(result) = writer.await {
+                let _ = result?;
+            }
+        }
+        let _ = timeout(
+            Duration::from_secs(5),
+            mem_table.insert_data((key.clone(), Some(key.clone()))),
+        )
+        .await
+        .expect("group commit blocked by a cancelled writer")?;
+        for i in 0..tasks {
+            let key = Bytes::from(format!("{i}"));
+            assert_eq!(mem_table.find(&key), Some((key.clone(), Some(key))));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mem_table_write_options() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path())
+            .wal_sync_policy(WalSyncPolicy::Interval(Duration::from_secs(3600)));
+        let (key_1, key_2) = (Bytes::from_static(b"k1"), Bytes::from_static(b"k2"));
+
+        let mem_table = Arc::new(MemTable::new(&config)?);
+        let _ = mem_table
+            .insert_data_with_meta(
+                (key_1.clone(), Some(key_1.clone())),
+                ValueMeta::default(),
+                WriteOptions::new().disable_wal(true),
+            )
+            .await?;
+        // 不写入WAL的数据依旧可被读取
+        assert_eq!(
+            mem_table.find(&key_1),
+            Some((key_1.clone(), Some(key_1.clone())))
+        );
+        assert!(!mem_table.wal.is_unsynced.load(AtomicOrdering::Relaxed));
+
+        let _ = mem_table
+            .insert_data((key_2.clone(), Some(key_2.clone())))
+            .await?;
+        assert!(mem_table.wal.is_unsynced.load(AtomicOrdering::Relaxed));
+        mem_table.wal.sync()?;
+        assert!(!mem_table.wal.is_unsynced.load(AtomicOrdering::Relaxed));
+
+        let _ = mem_table
+            .insert_data_with_meta(
+                (key_2.clone(), None),
+                ValueMeta::default(),
+                WriteOptions::new().sync(true),
+            )
+            .await?;
+        assert!(!mem_table.wal.is_unsynced.load(AtomicOrdering::Relaxed));
+        drop(mem_table);
+
+        // 仅写入WAL的数据可被恢复
+        let mem_table = Arc::new(MemTable::new(&config)?);
+        assert_eq!(mem_table.find(&key_1), None);
+        assert_eq!(mem_table.find(&key_2), Some((key_2.clone(), None)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mem_table_immut_queue() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path()).max_immut_count(2);
+
+        let mem_table = Arc::new(MemTable::new(&config)?);
+        let (key_1, key_2) = (Bytes::from_static(b"k1"), Bytes::from_static(b"k2"));
+        let (value_1, value_2, value_3) = (
+            Bytes::from_static(b"1"),
+            Bytes::from_static(b"2"),
+            Bytes::from_static(b"3"),
+        );
+
+        let _ = mem_table
+            .insert_data((key_1.clone(), Some(value_1.clone())))
+            .await?;
+        let _ = mem_table
+            .insert_data((key_2.clone(), Some(value_1.clone())))
+            .await?;
+        let (gen_1, ..) = mem_table.swap().await?.unwrap();
+        let _ = mem_table
+            .insert_data((key_1.clone(), Some(value_2.clone())))
+            .await?;
+        let (gen_2, ..) = mem_table.swap().await?.unwrap();
+        let _ = mem_table
+            .insert_data((key_1.clone(), Some(value_3.clone())))
+            .await?;
+
+        // 队列已满时不再交换，写入依旧进入_mem
+        assert!(mem_table.swap().await?.is_none());
+        assert_eq!(mem_table.immut_count(), 2);
+        assert_eq!(mem_table.len(), 1);
+
+        // 读取时由新至旧依次读取_mem与各个Immutable MemTable
+        let seq_id = Sequence::create();
+        assert_eq!(
+            mem_table.find(&key_1),
+            Some((key_1.clone(), Some(value_3.clone())))
+        );
+        assert_eq!(
+            mem_table.find(&key_2),
+            Some((key_2.clone(), Some(value_1.clone())))
+        );
+        assert_eq!(
+            mem_table.range_scan(Bound::Unbounded, Bound::Unbounded, Some(seq_id)),
+            vec![
+                (key_1.clone(), Some(value_3.clone())),
+                (key_2.clone(), Some(value_1.clone())),
+            ]
+        );
+
+        // 由旧至新依次Flush
+        let (gen, vec_data, _) = mem_table.oldest_immut().await?.unwrap();
+        assert_eq!(gen, gen_1);
+        assert_eq!(vec_data[0].0, (key_1.clone(), Some(value_1)));
+        mem_table.pop_immut(gen)?;
+        // 非最旧的gen不会被移除
+        mem_table.pop_immut(gen_1)?;
+        assert_eq!(mem_table.immut_count(), 1);
+
+        let (gen, vec_data, _) = mem_table.oldest_immut().await?.unwrap();
+        assert_eq!(gen, gen_2);
+        assert_eq!(vec_data[0].0, (key_1.clone(), Some(value_2)));
+        assert!(mem_table.swap().await?.is_some());
+        assert_eq!(mem_table.immut_count(), 2);
+        drop(mem_table);
+
+        // 未Flush的Immutable MemTable可通过WAL恢复，已Flush的WAL则在移除时被删除
+        let mem_table = Arc::new(MemTable::new(&config)?);
+        assert_eq!(mem_table.immut_count(), 2);
+        assert!(mem_table.is_empty());
+        assert_eq!(mem_table.oldest_immut().await?.unwrap().0, gen_2);
+        assert_eq!(mem_table.find(&key_1), Some((key_1, Some(value_3))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mem_table_check_key_conflict() -> KernelResult<()> {
         let temp_dir = TempDir::new().expect("unable to create temporary working directory");
 
-        let mem_table = MemTable::new(&Config::new(temp_dir.path()))?;
+        let mem_table = Arc::new(MemTable::new(&Config::new(temp_dir.path()))?);
 
         let key1 = vec![b'k', b'1'];
         let bytes_key1 = Bytes::copy_from_slice(&key1);
@@ -588,6 +2529,9 @@ mod tests {
         let bytes_key2 = Bytes::copy_from_slice(&key2);
         let kv_2 = (bytes_key2.clone(), Some(bytes_key2.clone()));
 
+        // 仅存在活跃事务时才会记录CommitHistory
+        let _ = mem_table.active_txs.begin();
+
         let _ = mem_table.insert_data_with_seq(kv_1.clone(), 0)?;
         let _ = mem_table.insert_data_with_seq(kv_1.clone(), 1)?;
         let _ = mem_table.insert_data_with_seq(kv_1.clone(), 2)?;
@@ -597,14 +2541,21 @@ mod tests {
 
         assert!(!mem_table.check_key_conflict(&[kv_1.clone()], 2));
 
+        // 数据被交换后依旧能够检测冲突
+        let _ = mem_table.swap().await?;
+        assert!(mem_table.check_key_conflict(&[kv_2.clone()], 2));
+
+        mem_table.prune_history(3);
+        assert!(!mem_table.check_key_conflict(&[kv_1, kv_2], 1));
+
         Ok(())
     }
 
-    #[test]
-    fn test_mem_table_range_scan() -> KernelResult<()> {
+    #[tokio::test]
+    async fn test_mem_table_range_scan() -> KernelResult<()> {
         let temp_dir = TempDir::new().expect("unable to create temporary working directory");
 
-        let mem_table = MemTable::new(&Config::new(temp_dir.path()))?;
+        let mem_table = Arc::new(MemTable::new(&Config::new(temp_dir.path()))?);
 
         let key1 = vec![b'k', b'1'];
         let bytes_key1 = Bytes::copy_from_slice(&key1);
@@ -640,7 +2591,7 @@ mod tests {
             6
         );
 
-        let _ = mem_table.swap().unwrap();
+        let _ = mem_table.swap().await.unwrap();
 
         assert_eq!(
             mem_table
@@ -722,14 +2673,15 @@ mod tests {
 
     #[test]
     fn test_mem_map_iter() -> KernelResult<()> {
-        let mut map = MemMap::new();
+        let comparator: Arc<dyn Comparator> = Arc::new(BytewiseComparator);
+        let map = MemMap::new();
 
-        let key_1_1 = InternalKey::new(Bytes::from(vec![b'1']));
-        let key_1_2 = InternalKey::new(Bytes::from(vec![b'1']));
-        let key_2_1 = InternalKey::new(Bytes::from(vec![b'2']));
-        let key_2_2 = InternalKey::new(Bytes::from(vec![b'2']));
-        let key_4_1 = InternalKey::new(Bytes::from(vec![b'4']));
-        let key_4_2 = InternalKey::new(Bytes::from(vec![b'4']));
+        let key_1_1 = InternalKey::new(Bytes::from(vec![b'1']), &comparator);
+        let key_1_2 = InternalKey::new(Bytes::from(vec![b'1']), &comparator);
+        let key_2_1 = InternalKey::new(Bytes::from(vec![b'2']), &comparator);
+        let key_2_2 = InternalKey::new(Bytes::from(vec![b'2']), &comparator);
+        let key_4_1 = InternalKey::new(Bytes::from(vec![b'4']), &comparator);
+        let key_4_2 = InternalKey::new(Bytes::from(vec![b'4']), &comparator);
 
         let _ = map.insert(key_1_1.clone(), Some(Bytes::new()));
         let _ = map.insert(key_1_2.clone(), None);
@@ -738,7 +2690,7 @@ mod tests {
         let _ = map.insert(key_4_1.clone(), Some(Bytes::new()));
         let _ = map.insert(key_4_2.clone(), None);
 
-        let mut iter = MemMapIter::new(&map);
+        let mut iter = MemMapIter::new(&map, &comparator);
 
         assert_eq!(iter.try_next()?, Some((key_1_2.key.clone(), None)));
 
@@ -755,6 +2707,17 @@ mod tests {
         iter.seek(Seek::Backward(&[b'3']))?;
         assert_eq!(iter.try_next()?, Some((key_4_2.key.clone(), None)));
 
+        iter.seek(Seek::Last)?;
+        assert_eq!(iter.try_prev()?, Some((key_4_2.key.clone(), None)));
+        assert_eq!(iter.try_prev()?, Some((key_2_2.key.clone(), None)));
+        assert_eq!(iter.try_prev()?, Some((key_1_2.key.clone(), None)));
+        assert_eq!(iter.try_prev()?, None);
+        assert_eq!(iter.try_next()?, Some((key_1_2.key.clone(), None)));
+
+        iter.seek(Seek::Forward(&[b'3']))?;
+        assert_eq!(iter.try_prev()?, Some((key_2_2.key.clone(), None)));
+        assert_eq!(iter.try_next()?, Some((key_4_2.key.clone(), None)));
+
         Ok(())
     }
 }
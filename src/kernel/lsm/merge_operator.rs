@@ -0,0 +1,298 @@
+use crate::kernel::lsm::mem_table::{expire_filter, SeqKeyValue, ValueMeta};
+use crate::kernel::KernelResult;
+use crate::KernelError;
+use bytes::Bytes;
+use integer_encoding::{VarIntReader, VarIntWriter};
+use std::fmt;
+use std::io::{Cursor, Read, Write};
+
+/// 用户自定义的Merge操作
+///
+/// 通过`KipStorage::merge`写入的操作数会被追加存储，在读取以及压缩时才与更旧的数据合并，
+/// 以此实现无需先读后写的计数器、列表追加等操作
+pub trait MergeOperator: Send + Sync {
+    /// 合并器名称
+    fn name(&self) -> &str;
+
+    /// existing为合并前的Value，不存在或已被删除时为None
+    ///
+    /// operands按写入的先后顺序排列
+    fn full_merge(&self, key: &[u8], existing: Option<&[u8]>, operands: &[Bytes]) -> Bytes;
+}
+
+impl fmt::Debug for dyn MergeOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MergeOperator")
+            .field("name", &self.name())
+            .finish()
+    }
+}
+
+/// 将单个操作数编码为操作数序列
+///
+/// 操作数序列的格式为多组[operand_len, operand]，因此多个序列可以直接拼接
+pub(crate) fn encode_operand(operand: &[u8]) -> KernelResult<Bytes> {
+    let mut bytes = Vec::with_capacity(operand.len() + 5);
+    bytes.write_varint(operand.len() as u32)?;
+    bytes.write_all(operand)?;
+
+    Ok(Bytes::from(bytes))
+}
+
+fn decode_operands(bytes: &[u8]) -> KernelResult<Vec<Bytes>> {
+    let mut cursor = Cursor::new(bytes);
+    let mut operands = Vec::new();
+
+    while !cursor.is_empty() {
+        let mut operand = vec![0; cursor.read_varint::<u32>()? as usize];
+        cursor.read_exact(&mut operand)?;
+        operands.push(Bytes::from(operand));
+    }
+
+    Ok(operands)
+}
+
+/// 由新至旧收集的Merge操作数序列
+#[derive(Debug, Default)]
+pub(crate) struct MergeOperands {
+    vec_operands: Vec<Bytes>,
+}
+
+impl MergeOperands {
+    /// 追加更旧的操作数序列
+    pub(crate) fn push(&mut self, operands: Bytes) {
+        self.vec_operands.push(operands);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.vec_operands.is_empty()
+    }
+
+    /// 以由旧至新的顺序拼接为单个操作数序列
+    pub(crate) fn concat(&self) -> Bytes {
+        match self.vec_operands.as_slice() {
+            [operands] => operands.clone(),
+            vec_operands => Bytes::from(vec_operands.iter().rev().fold(
+                Vec::new(),
+                |mut bytes, operands| {
+                    bytes.extend_from_slice(operands);
+                    bytes
+                },
+            )),
+        }
+    }
+
+    /// 与更旧的Value进行合并，不存在操作数时直接返回existing
+    pub(crate) fn fold(
+        &self,
+        operator: Option<&dyn MergeOperator>,
+        key: &[u8],
+        existing: Option<Bytes>,
+    ) -> KernelResult<Option<Bytes>> {
+        if self.is_empty() {
+            return Ok(existing);
+        }
+        let operator = operator.ok_or(KernelError::MergeOperatorNotFound)?;
+
+        Ok(Some(operator.full_merge(
+            key,
+            existing.as_deref(),
+            &decode_operands(&self.concat())?,
+        )))
+    }
+}
+
+/// 将同一Key由新至旧排列的数据合并为单条数据
+///
+/// - 最新的数据不为Merge操作数时直接使用该数据
+/// - 存在更旧的非Merge数据时与其完整合并，合并后的数据沿用其过期时间
+/// - 否则拼接为单条Merge操作数序列，is_bottom为true时说明不存在更旧的数据，此时直接与None合并
+///
+/// 合并后的数据使用最新的seq_id
+pub(crate) fn merge_versions(
+    versions: impl IntoIterator<Item = SeqKeyValue>,
+    operator: Option<&dyn MergeOperator>,
+    now: i64,
+    is_bottom: bool,
+) -> KernelResult<Option<SeqKeyValue>> {
+    let mut operands = MergeOperands::default();
+    let mut latest: Option<(Bytes, i64)> = None;
+
+    for ((key, value), seq_id, meta) in versions {
+        if !meta.is_merge {
+            let Some((key, latest_seq)) = latest else {
+                return Ok(Some(((key, value), seq_id, meta)));
+            };
+            let (_, existing) = expire_filter((Bytes::new(), value), meta.expire_at, now);
+            let expire_at = meta.expire_at.filter(|_| existing.is_some());
+            let value = operands.fold(operator, &key, existing)?;
+
+            return Ok(Some((
+                (key, value),
+                latest_seq,
+                ValueMeta::with_expire(expire_at),
+            )));
+        }
+        let _ = latest.get_or_insert((key, seq_id));
+        operands.push(value.unwrap_or_default());
+    }
+
+    let Some((key, latest_seq)) = latest else {
+        return Ok(None);
+    };
+    Ok(Some(if is_bottom {
+        let value = operands.fold(operator, &key, None)?;
+
+        ((key, value), latest_seq, ValueMeta::default())
+    } else {
+        (
+            (key, Some(operands.concat())),
+            latest_seq,
+            ValueMeta::merge(),
+        )
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::lsm::mem_table::ValueMeta;
+    use crate::kernel::lsm::merge_operator::{
+        encode_operand, merge_versions, MergeOperands, MergeOperator,
+    };
+    use crate::kernel::KernelResult;
+    use crate::KernelError;
+    use bytes::Bytes;
+
+    struct AppendOperator;
+
+    impl MergeOperator for AppendOperator {
+        fn name(&self) -> &str {
+            "append"
+        }
+
+        fn full_merge(&self, _key: &[u8], existing: Option<&[u8]>, operands: &[Bytes]) -> Bytes {
+            let mut value = existing.map(<[u8]>::to_vec).unwrap_or_default();
+
+            for operand in operands {
+                value.extend_from_slice(operand);
+            }
+            Bytes::from(value)
+        }
+    }
+
+    #[test]
+    fn test_merge_operands() -> KernelResult<()> {
+        let mut operands = MergeOperands::default();
+        assert_eq!(
+            operands.fold(None, b"k", Some(Bytes::from_static(b"v")))?,
+            Some(Bytes::from_static(b"v"))
+        );
+
+        // 由新至旧收集
+        operands.push(encode_operand(b"3")?);
+        operands.push(Bytes::from(
+            [
+                encode_operand(b"1")?,
+                encode_operand(b"")?,
+                encode_operand(b"2")?,
+            ]
+            .concat(),
+        ));
+
+        assert!(matches!(
+            operands.fold(None, b"k", None),
+            Err(KernelError::MergeOperatorNotFound)
+        ));
+        assert_eq!(
+            operands.fold(Some(&AppendOperator), b"k", Some(Bytes::from_static(b"0")))?,
+            Some(Bytes::from_static(b"0123"))
+        );
+        assert_eq!(
+            operands.fold(Some(&AppendOperator), b"k", None)?,
+            Some(Bytes::from_static(b"123"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_versions() -> KernelResult<()> {
+        let key = Bytes::from_static(b"k");
+        let merge = |operand: &[u8], seq_id| -> KernelResult<_> {
+            Ok((
+                (key.clone(), Some(encode_operand(operand)?)),
+                seq_id,
+                ValueMeta::merge(),
+            ))
+        };
+        let put = (
+            (key.clone(), Some(Bytes::from_static(b"0"))),
+            1,
+            ValueMeta::with_expire(Some(i64::MAX)),
+        );
+
+        // 最新的数据不为Merge操作数
+        assert_eq!(
+            merge_versions(vec![put.clone(), merge(b"1", 0)?], None, 0, false)?,
+            Some(put.clone())
+        );
+        // 与更旧的非Merge数据合并
+        assert_eq!(
+            merge_versions(
+                vec![merge(b"2", 3)?, merge(b"1", 2)?, put.clone()],
+                Some(&AppendOperator),
+                0,
+                false
+            )?,
+            Some((
+                (key.clone(), Some(Bytes::from_static(b"012"))),
+                3,
+                ValueMeta::with_expire(Some(i64::MAX))
+            ))
+        );
+        // 已过期的数据视为不存在
+        assert_eq!(
+            merge_versions(
+                vec![merge(b"2", 3)?, put.clone()],
+                Some(&AppendOperator),
+                i64::MAX,
+                false
+            )?,
+            Some((
+                (key.clone(), Some(Bytes::from_static(b"2"))),
+                3,
+                ValueMeta::default()
+            ))
+        );
+        // 不存在更旧的数据时拼接为单条Merge操作数序列
+        assert_eq!(
+            merge_versions(vec![merge(b"2", 3)?, merge(b"1", 2)?], None, 0, false)?,
+            Some((
+                (
+                    key.clone(),
+                    Some(Bytes::from(
+                        [encode_operand(b"1")?, encode_operand(b"2")?].concat()
+                    ))
+                ),
+                3,
+                ValueMeta::merge()
+            ))
+        );
+        assert_eq!(
+            merge_versions(
+                vec![merge(b"2", 3)?, merge(b"1", 2)?],
+                Some(&AppendOperator),
+                0,
+                true
+            )?,
+            Some((
+                (key.clone(), Some(Bytes::from_static(b"12"))),
+                3,
+                ValueMeta::default()
+            ))
+        );
+        assert_eq!(merge_versions(vec![], None, 0, true)?, None);
+
+        Ok(())
+    }
+}
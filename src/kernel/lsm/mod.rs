@@ -1,30 +1,49 @@
-use crate::kernel::lsm::compactor::{CompactTask, MergeShardingVec};
-use crate::kernel::lsm::mem_table::{key_value_bytes_len, KeyValue};
+use crate::kernel::lsm::compactor::{CompactTask, MergeShardingVec, SeekScope};
+use crate::kernel::lsm::mem_table::{
+    expire_filter, key_value_bytes_len, now_millis, KeyValue, SeqKeyValue,
+};
+use crate::kernel::lsm::merge_operator::MergeOperands;
 use crate::kernel::lsm::storage::Gen;
 use crate::kernel::lsm::version::Version;
 use crate::kernel::KernelResult;
 use crate::KernelError;
+use bytes::Bytes;
+use itertools::Itertools;
 use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::mpsc::Sender;
 
+pub mod column_family;
+mod commit_history;
+pub mod compaction_filter;
 pub mod compactor;
+pub mod comparator;
 pub mod iterator;
+mod lock_manager;
 mod log;
 mod mem_table;
+pub mod merge_operator;
 pub mod mvcc;
+mod range_tombstone;
+pub mod snapshot;
 pub mod storage;
 mod table;
 pub mod trigger;
+mod value_log;
 pub mod version;
 
 const MAX_LEVEL: usize = 4;
 
 /// KeyValue数据分片，尽可能将数据按给定的分片大小：file_size，填满一片（可能会溢出一些）
 /// 保持原有数据的顺序进行分片，所有第一片分片中最后的值肯定会比其他分片开始的值Key排序较前（如果vec_data是以Key从小到大排序的话）
-fn data_sharding(mut vec_data: Vec<KeyValue>, file_size: usize) -> MergeShardingVec {
+fn data_sharding(mut vec_data: Vec<SeqKeyValue>, file_size: usize) -> MergeShardingVec {
     // 向上取整计算SSTable数量
-    let part_size =
-        (vec_data.iter().map(key_value_bytes_len).sum::<usize>() + file_size - 1) / file_size;
+    let part_size = (vec_data
+        .iter()
+        .map(|(key_value, ..)| key_value_bytes_len(key_value))
+        .sum::<usize>()
+        + file_size
+        - 1)
+        / file_size;
 
     vec_data.reverse();
     let mut vec_sharding = vec![(0, Vec::new()); part_size];
@@ -36,7 +55,7 @@ fn data_sharding(mut vec_data: Vec<KeyValue>, file_size: usize) -> MergeSharding
         let mut data_len = 0;
         while !vec_data.is_empty() {
             if let Some(key_value) = vec_data.pop() {
-                data_len += key_value_bytes_len(&key_value);
+                data_len += key_value_bytes_len(&key_value.0);
                 if data_len >= file_size && i < part_size - 1 {
                     slice[i + 1].1.push(key_value);
                     break;
@@ -52,23 +71,156 @@ fn data_sharding(mut vec_data: Vec<KeyValue>, file_size: usize) -> MergeSharding
     vec_sharding
 }
 
-/// 使用Version进行Key查询，当触发Seek Miss的阈值时，
+/// 使用MemTable中Key的数据与Version进行Key查询，当触发Seek Miss的阈值时，
 /// 使用其第一次Miss的Level进行Seek Compaction
+///
+/// mem_versions为`MemTable::find_versions`所获取的数据
 fn query_and_compaction(
     key: &[u8],
+    mem_versions: Vec<SeqKeyValue>,
+    family_id: usize,
     version: &Version,
+    read_seq: Option<i64>,
     compactor_tx: &Sender<CompactTask>,
-) -> KernelResult<Option<KeyValue>> {
-    let (value_option, miss_option) = version.query(key)?;
+) -> KernelResult<Option<Bytes>> {
+    let (value, miss_option) = merge_with_version(key, mem_versions, version, read_seq)?;
 
     if let Some(miss_scope) = miss_option {
-        if let Err(TrySendError::Closed(_)) = compactor_tx.try_send(CompactTask::Seek(miss_scope)) {
+        if let Err(TrySendError::Closed(_)) =
+            compactor_tx.try_send(CompactTask::Seek(family_id, miss_scope))
+        {
             return Err(KernelError::ChannelClose);
         }
     }
 
-    if let Some(key_value) = value_option {
-        return Ok(Some(key_value));
+    Ok(value)
+}
+
+/// 使用MemTable中一组有序且不重复的Key的数据与Version进行批量查询，结果与keys一一对应
+///
+/// mem_versions为`MemTable::multi_find_versions`所获取的数据，
+/// 未能在MemTable中确定Value的Key会通过`Version::multi_query`一并查询
+fn multi_query_and_compaction(
+    keys: &[&[u8]],
+    mem_versions: Vec<Vec<SeqKeyValue>>,
+    family_id: usize,
+    version: &Version,
+    read_seq: Option<i64>,
+    compactor_tx: &Sender<CompactTask>,
+) -> KernelResult<Vec<Option<Bytes>>> {
+    let mut values = Vec::with_capacity(keys.len());
+    let mut pending = Vec::new();
+    let mut pending_operands = Vec::new();
+
+    for (i, (key, versions)) in keys.iter().zip(mem_versions).enumerate() {
+        let mut operands = MergeOperands::default();
+
+        match merge_with_mem(key, versions, version, &mut operands)? {
+            Some(value) => values.push(value),
+            None => {
+                values.push(None);
+                pending.push(i);
+                pending_operands.push(operands);
+            }
+        }
     }
+    let pending_keys = pending.iter().map(|i| keys[*i]).collect_vec();
+    let results = version.multi_query(&pending_keys, read_seq, &mut pending_operands)?;
+
+    for ((i, (key_value, miss_option)), operands) in
+        pending.into_iter().zip(results).zip(pending_operands)
+    {
+        values[i] = operands.fold(
+            version.merge_operator(),
+            keys[i],
+            key_value.and_then(|(_, value)| value),
+        )?;
+
+        if let Some(miss_scope) = miss_option {
+            if let Err(TrySendError::Closed(_)) =
+                compactor_tx.try_send(CompactTask::Seek(family_id, miss_scope))
+            {
+                return Err(KernelError::ChannelClose);
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+/// 将MemTable中同一Key由新至旧的数据与Version中的数据合并为该Key的Value
+fn merge_with_version(
+    key: &[u8],
+    mem_versions: impl IntoIterator<Item = SeqKeyValue>,
+    version: &Version,
+    read_seq: Option<i64>,
+) -> KernelResult<(Option<Bytes>, Option<SeekScope>)> {
+    let mut operands = MergeOperands::default();
+
+    if let Some(value) = merge_with_mem(key, mem_versions, version, &mut operands)? {
+        return Ok((value, None));
+    }
+    let (key_value, miss_option) = version.query(key, read_seq, &mut operands)?;
+    let value = operands.fold(
+        version.merge_operator(),
+        key,
+        key_value.and_then(|(_, value)| value),
+    )?;
+
+    Ok((value, miss_option))
+}
+
+/// 尝试仅通过MemTable中同一Key由新至旧的数据确定该Key的Value
+///
+/// MemTable中seq_id不大于`Version::last_sequence`的数据已持久化至Version中，
+/// 因此合并Merge操作数时会跳过这些数据，避免操作数被重复合并
+///
+/// 返回None时说明仍需从Version中查询，此时operands为已收集的Merge操作数
+fn merge_with_mem(
+    key: &[u8],
+    mem_versions: impl IntoIterator<Item = SeqKeyValue>,
+    version: &Version,
+    operands: &mut MergeOperands,
+) -> KernelResult<Option<Option<Bytes>>> {
+    for (key_value, seq_id, meta) in mem_versions {
+        if !meta.is_merge && operands.is_empty() {
+            return Ok(Some(
+                expire_filter(key_value, meta.expire_at, now_millis()).1,
+            ));
+        }
+        if seq_id <= version.last_sequence {
+            break;
+        }
+        if !meta.is_merge {
+            let (_, existing) = expire_filter(key_value, meta.expire_at, now_millis());
+
+            return Ok(Some(operands.fold(
+                version.merge_operator(),
+                key,
+                existing,
+            )?));
+        }
+        operands.push(key_value.1.unwrap_or_default());
+    }
+
     Ok(None)
 }
+
+/// 将`MemTable::range_versions`所获取的数据与Version合并为各Key的KeyValue
+fn mem_buf_with_version(
+    mem_versions: Vec<SeqKeyValue>,
+    version: &Version,
+    read_seq: Option<i64>,
+) -> KernelResult<Vec<KeyValue>> {
+    let mut mem_buf = Vec::new();
+
+    for (key, versions) in &mem_versions
+        .into_iter()
+        .group_by(|((key, _), ..)| key.clone())
+    {
+        let (value, _) = merge_with_version(&key, versions, version, read_seq)?;
+        mem_buf.push((key, value));
+    }
+
+    Ok(mem_buf)
+}
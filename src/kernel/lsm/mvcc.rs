@@ -1,209 +1,538 @@
+use crate::kernel::lsm::column_family::ColumnFamily;
 use crate::kernel::lsm::compactor::CompactTask;
-use crate::kernel::lsm::iterator::merging_iter::MergingIter;
-use crate::kernel::lsm::iterator::{Iter, Seek};
-use crate::kernel::lsm::mem_table::{KeyValue, MemTable};
-use crate::kernel::lsm::query_and_compaction;
-use crate::kernel::lsm::storage::{KipStorage, Sequence, StoreInner};
+use crate::kernel::lsm::comparator::{ComparableKey, Comparator};
+use crate::kernel::lsm::iterator::merging_iter::SeekMergingIter;
+use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
+use crate::kernel::lsm::lock_manager::LockKey;
+use crate::kernel::lsm::mem_table::{
+    KeyValue, MemPin, MemTable, ReadRange, SeqKeyValue, WriteCondition,
+};
+use crate::kernel::lsm::range_tombstone::RangeTombstone;
+use crate::kernel::lsm::storage::{KipStorage, StoreInner, WriteOptions};
 use crate::kernel::lsm::version::iter::VersionIter;
 use crate::kernel::lsm::version::Version;
+use crate::kernel::lsm::{mem_buf_with_version, query_and_compaction};
 use crate::kernel::KernelResult;
 use crate::KernelError;
 use bytes::Bytes;
 use core::slice::SlicePattern;
 use itertools::Itertools;
-use std::collections::btree_map::Range;
-use std::collections::{BTreeMap, Bound};
-use std::ptr::NonNull;
-use std::sync::atomic::Ordering;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, Bound, HashSet};
+use std::mem;
 use std::sync::Arc;
-use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::mpsc::Sender;
 
-unsafe impl Send for BufPtr {}
-unsafe impl Sync for BufPtr {}
-
-struct BufPtr(NonNull<Vec<KeyValue>>);
-
 pub enum CheckType {
+    /// 提交时检查写入的Key是否已被其他事务修改，冲突时返回`KernelError::RepeatedWrite`
     Optimistic,
+    /// 写入以及`Transaction::get_for_update`时获取Key的锁，持有至事务结束
+    ///
+    /// 等待锁超时返回`KernelError::LockTimeout`，形成死锁时返回`KernelError::Deadlock`
+    Pessimistic,
+    /// 在Optimistic的基础上记录事务读取过的Key与范围，
+    /// 提交时其中存在事务创建后写入的数据则返回`KernelError::SerializationFailure`，以此避免写偏斜
+    Serializable,
+}
+
+/// 事务的保存点，通过`Transaction::rollback_to`撤销其后的写入
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Savepoint {
+    seq_id: i64,
+    id: usize,
 }
 
+/// 写缓存的撤销记录: (ColumnFamily的id, Key, 写入前写缓存中的Value)
+type UndoRecord = (usize, ComparableKey, Option<Option<Bytes>>);
+
 pub struct Transaction {
     store_inner: Arc<StoreInner>,
     compactor_tx: Sender<CompactTask>,
 
-    version: Arc<Version>,
+    /// 各ColumnFamily在事务创建时的Version，以ColumnFamily的id作为索引
+    versions: Vec<Arc<Version>>,
+    /// 各ColumnFamily在事务创建时所固定的MemTable数据，以ColumnFamily的id作为索引
+    mem_pins: Vec<MemPin>,
     seq_id: i64,
     check_type: CheckType,
 
-    write_buf: Option<BTreeMap<Bytes, Option<Bytes>>>,
+    /// 各ColumnFamily的写缓存，以ColumnFamily的id作为Key
+    write_buf: BTreeMap<usize, BTreeMap<ComparableKey, Option<Bytes>>>,
+    /// 悲观事务已持有的锁
+    locked_keys: HashSet<LockKey>,
+    /// 可串行化事务中各ColumnFamily读取过的范围，以ColumnFamily的id作为Key
+    read_set: Mutex<BTreeMap<usize, Vec<ReadRange>>>,
+    /// 写缓存的撤销日志，用于回滚至保存点
+    undo_log: Vec<UndoRecord>,
+    /// 有效的保存点: (保存点id, 创建时撤销日志的长度)，保存点id单调递增
+    savepoints: Vec<(usize, usize)>,
+    next_savepoint_id: usize,
 }
 
 impl Transaction {
     pub(crate) async fn new(storage: &KipStorage, check_type: CheckType) -> Self {
-        let _ = storage.mem_table().tx_count.fetch_add(1, Ordering::Release);
+        // seq_id须先于MemPin生成，使seq_id更小的写入均位于被固定的数据中
+        let seq_id = storage
+            .inner
+            .wal
+            .read_seq(|| storage.inner.active_txs.begin())
+            .await;
+        // 先固定MemTable再获取Version，避免期间发生的Minor Compaction导致数据丢失
+        let mem_pins = storage
+            .inner
+            .families
+            .iter()
+            .map(|family| family.mem_table.pin())
+            .collect_vec();
+        let mut versions = Vec::with_capacity(storage.inner.families.len());
+
+        for family in &storage.inner.families {
+            versions.push(family.current_version().await);
+        }
 
         Transaction {
             store_inner: Arc::clone(&storage.inner),
-            version: storage.current_version().await,
+            versions,
             compactor_tx: storage.compactor_tx.clone(),
 
-            seq_id: Sequence::create(),
-            write_buf: None,
+            mem_pins,
+            seq_id,
+            write_buf: BTreeMap::new(),
+            locked_keys: HashSet::new(),
+            read_set: Mutex::new(BTreeMap::new()),
+            undo_log: Vec::new(),
+            savepoints: Vec::new(),
+            next_savepoint_id: 0,
             check_type,
         }
     }
 
-    fn write_buf_or_init(&mut self) -> &mut BTreeMap<Bytes, Option<Bytes>> {
-        self.write_buf.get_or_insert_with(BTreeMap::new)
+    /// 悲观事务获取Key的锁，乐观事务则直接跳过
+    async fn lock_key(&mut self, family: &ColumnFamily, key: &[u8]) -> KernelResult<()> {
+        if !matches!(self.check_type, CheckType::Pessimistic) {
+            return Ok(());
+        }
+        let lock_key = (family.id(), Bytes::copy_from_slice(key));
+        if self.locked_keys.contains(&lock_key) {
+            return Ok(());
+        }
+        self.store_inner
+            .lock_manager
+            .lock(self.seq_id, lock_key.clone(), self.store_inner.lock_timeout)
+            .await?;
+        let _ = self.locked_keys.insert(lock_key);
+
+        Ok(())
+    }
+
+    fn record_read(&self, family: &ColumnFamily, min: Bound<&[u8]>, max: Bound<&[u8]>) {
+        if !matches!(self.check_type, CheckType::Serializable) {
+            return;
+        }
+        self.read_set.lock().entry(family.id()).or_default().push((
+            min.map(Bytes::copy_from_slice),
+            max.map(Bytes::copy_from_slice),
+        ));
+    }
+
+    fn write_buf_insert(&mut self, family: &ColumnFamily, key: Bytes, value: Option<Bytes>) {
+        let key = ComparableKey::new(key, &family.config.comparator);
+        let previous = self
+            .write_buf
+            .entry(family.id())
+            .or_default()
+            .insert(key.clone(), value);
+
+        // 未创建保存点时不存在可回滚的位置，无需记录
+        if !self.savepoints.is_empty() {
+            self.undo_log.push((family.id(), key, previous));
+        }
+    }
+
+    /// 创建保存点，保存点可嵌套
+    #[inline]
+    pub fn savepoint(&mut self) -> Savepoint {
+        let id = self.next_savepoint_id;
+        self.next_savepoint_id += 1;
+        self.savepoints.push((id, self.undo_log.len()));
+
+        Savepoint {
+            seq_id: self.seq_id,
+            id,
+        }
+    }
+
+    /// 撤销保存点之后的写入，该保存点依旧有效，而其后创建的保存点均会失效
+    ///
+    /// 悲观事务已获取的锁不会因此释放；保存点不属于该事务或已失效时返回`KernelError::InvalidSavepoint`
+    #[inline]
+    #[allow(clippy::mutable_key_type)]
+    pub fn rollback_to(&mut self, savepoint: Savepoint) -> KernelResult<()> {
+        let position = self
+            .savepoints
+            .iter()
+            .position(|(id, _)| *id == savepoint.id)
+            .filter(|_| savepoint.seq_id == self.seq_id)
+            .ok_or(KernelError::InvalidSavepoint)?;
+        let undo_len = self.savepoints[position].1;
+        self.savepoints.truncate(position + 1);
+
+        for (family_id, key, previous) in self.undo_log.drain(undo_len..).rev() {
+            let buf = self.write_buf.entry(family_id).or_default();
+
+            match previous {
+                Some(value) => {
+                    let _ = buf.insert(key, value);
+                }
+                None => {
+                    let _ = buf.remove(&key);
+                }
+            }
+        }
+        self.write_buf.retain(|_, buf| !buf.is_empty());
+
+        Ok(())
+    }
+
+    /// 放弃事务的所有写入，并释放事务所持有的锁
+    #[inline]
+    pub fn rollback(mut self) {
+        self.write_buf.clear();
+        self.undo_log.clear();
+        self.savepoints.clear();
     }
 
     /// 通过Key获取对应的Value
     ///
-    /// 此处不需要等待压缩，因为在Transaction存活时不会触发Compaction
+    /// 读取事务创建时所固定的MemTable数据与Version，期间发生的压缩不会对其产生影响
     #[inline]
     pub fn get(&self, key: &[u8]) -> KernelResult<Option<Bytes>> {
-        if let Some(value) = self.write_buf.as_ref().and_then(|buf| buf.get(key)) {
+        self.get_with_family(self.store_inner.default_family(), key)
+    }
+
+    /// 通过Key获取指定的ColumnFamily中对应的Value
+    #[inline]
+    pub fn get_cf(&self, cf: &str, key: &[u8]) -> KernelResult<Option<Bytes>> {
+        self.get_with_family(self.store_inner.family(cf)?, key)
+    }
+
+    fn get_with_family(&self, family: &ColumnFamily, key: &[u8]) -> KernelResult<Option<Bytes>> {
+        let family_id = family.id();
+        self.record_read(family, Bound::Included(key), Bound::Included(key));
+
+        if let Some(value) = self.write_buf.get(&family_id).and_then(|buf| {
+            buf.get(&ComparableKey::new(
+                Bytes::copy_from_slice(key),
+                &family.config.comparator,
+            ))
+        }) {
             return Ok(value.clone());
         }
 
-        if let Some((_, value)) = self.mem_table().find_with_sequence_id(key, self.seq_id) {
-            return Ok(value);
-        }
+        query_and_compaction(
+            key,
+            family
+                .mem_table
+                .find_versions(key, Some(self.seq_id), Some(&self.mem_pins[family_id])),
+            family_id,
+            &self.versions[family_id],
+            Some(self.seq_id),
+            &self.compactor_tx,
+        )
+    }
 
-        if let Some((_, value)) = query_and_compaction(key, &self.version, &self.compactor_tx)? {
-            return Ok(value);
-        }
+    /// 获取Key的锁并读取其最新已提交的Value，用于后续基于该Value进行写入
+    ///
+    /// 悲观事务中读取的是获取锁后的最新数据，而非事务创建时的快照，
+    /// 因此可能与`Transaction::get`的结果不同；乐观事务中等同于`Transaction::get`
+    #[inline]
+    pub async fn get_for_update(&mut self, key: &[u8]) -> KernelResult<Option<Bytes>> {
+        self.get_for_update_with_family(Arc::clone(self.store_inner.default_family()), key)
+            .await
+    }
 
-        Ok(None)
+    /// 获取指定的ColumnFamily中Key的锁并读取其最新已提交的Value
+    #[inline]
+    pub async fn get_for_update_cf(&mut self, cf: &str, key: &[u8]) -> KernelResult<Option<Bytes>> {
+        self.get_for_update_with_family(Arc::clone(self.store_inner.family(cf)?), key)
+            .await
     }
 
+    async fn get_for_update_with_family(
+        &mut self,
+        family: Arc<ColumnFamily>,
+        key: &[u8],
+    ) -> KernelResult<Option<Bytes>> {
+        if !matches!(self.check_type, CheckType::Pessimistic) {
+            return self.get_with_family(&family, key);
+        }
+        self.lock_key(&family, key).await?;
+
+        if let Some(value) = self.write_buf.get(&family.id()).and_then(|buf| {
+            buf.get(&ComparableKey::new(
+                Bytes::copy_from_slice(key),
+                &family.config.comparator,
+            ))
+        }) {
+            return Ok(value.clone());
+        }
+        let version = family.current_version().await;
+
+        query_and_compaction(
+            key,
+            family.mem_table.find_versions(key, None, None),
+            family.id(),
+            &version,
+            None,
+            &self.compactor_tx,
+        )
+    }
+
+    /// 悲观事务中会等待并持有该Key的锁
     #[inline]
-    pub fn set(&mut self, key: Bytes, value: Bytes) {
-        let _ignore = self.write_buf_or_init().insert(key, Some(value));
+    pub async fn set(&mut self, key: Bytes, value: Bytes) -> KernelResult<()> {
+        let family = Arc::clone(self.store_inner.default_family());
+        self.lock_key(&family, &key).await?;
+        self.write_buf_insert(&family, key, Some(value));
+
+        Ok(())
     }
 
+    /// 在指定的ColumnFamily中设置键值对
     #[inline]
-    pub fn remove(&mut self, key: &[u8]) -> KernelResult<()> {
-        let _ = self.get(key)?.ok_or(KernelError::KeyNotFound)?;
-        let bytes = Bytes::copy_from_slice(key);
-        let _ignore = self.write_buf_or_init().insert(bytes, None);
+    pub async fn set_cf(&mut self, cf: &str, key: Bytes, value: Bytes) -> KernelResult<()> {
+        let family = Arc::clone(self.store_inner.family(cf)?);
+        self.lock_key(&family, &key).await?;
+        self.write_buf_insert(&family, key, Some(value));
 
         Ok(())
     }
 
     #[inline]
-    pub async fn commit(mut self) -> KernelResult<()> {
-        if let Some(buf) = self.write_buf.take() {
-            let batch_data = buf.into_iter().collect_vec();
-
-            match self.check_type {
-                CheckType::Optimistic => {
-                    if self
-                        .mem_table()
-                        .check_key_conflict(&batch_data, self.seq_id)
-                    {
-                        return Err(KernelError::RepeatedWrite);
-                    }
-                }
-            }
+    pub async fn remove(&mut self, key: &[u8]) -> KernelResult<()> {
+        self.remove_with_family(Arc::clone(self.store_inner.default_family()), key)
+            .await
+    }
 
-            let is_exceeds = self
-                .store_inner
-                .mem_table
-                .insert_batch_data(batch_data, Sequence::create())?;
+    /// 删除指定的ColumnFamily中的键值对
+    #[inline]
+    pub async fn remove_cf(&mut self, cf: &str, key: &[u8]) -> KernelResult<()> {
+        self.remove_with_family(Arc::clone(self.store_inner.family(cf)?), key)
+            .await
+    }
 
-            if is_exceeds {
-                if let Err(TrySendError::Closed(_)) =
-                    self.compactor_tx.try_send(CompactTask::Flush(None))
-                {
-                    return Err(KernelError::ChannelClose);
-                }
+    async fn remove_with_family(
+        &mut self,
+        family: Arc<ColumnFamily>,
+        key: &[u8],
+    ) -> KernelResult<()> {
+        self.lock_key(&family, key).await?;
+        let _ = self
+            .get_with_family(&family, key)?
+            .ok_or(KernelError::KeyNotFound)?;
+        self.write_buf_insert(&family, Bytes::copy_from_slice(key), None);
+
+        Ok(())
+    }
+
+    /// 提交事务
+    ///
+    /// 所有ColumnFamily的写缓存共享同一个Sequence id并作为单条WAL记录写入
+    #[inline]
+    pub async fn commit(self) -> KernelResult<()> {
+        self.commit_with_options(&WriteOptions::default()).await
+    }
+
+    /// 通过WriteOptions提交事务
+    #[inline]
+    pub async fn commit_with_options(mut self, options: &WriteOptions) -> KernelResult<()> {
+        let families = &self.store_inner.families;
+        let batches = mem::take(&mut self.write_buf)
+            .into_iter()
+            .map(|(family_id, buf)| {
+                let batch_data = buf
+                    .into_iter()
+                    .map(|(key, value)| (key.key, value))
+                    .collect_vec();
+
+                (Arc::clone(&families[family_id].mem_table), batch_data)
+            })
+            .collect_vec();
+        // 冲突检查由组提交的Leader在WAL写入锁内进行，与写入之间不会穿插其他事务的提交
+        let condition = match self.check_type {
+            CheckType::Optimistic => Some(WriteCondition::new(self.seq_id)),
+            CheckType::Serializable => Some(
+                WriteCondition::new(self.seq_id).read_ranges(
+                    mem::take(self.read_set.get_mut())
+                        .into_iter()
+                        .map(|(family_id, ranges)| {
+                            (Arc::clone(&families[family_id].mem_table), ranges)
+                        })
+                        .collect_vec(),
+                ),
+            ),
+            // 写入的Key均已持有锁，其他悲观事务无法并发写入
+            CheckType::Pessimistic => None,
+        };
+        let is_exceeded = match condition {
+            // 只读事务无需写入，直接检查即可
+            Some(condition) if batches.is_empty() => {
+                condition.check(&batches)?;
+                false
+            }
+            Some(condition) => {
+                MemTable::insert_batch_with_condition(batches, *options, condition).await?
             }
+            None => MemTable::insert_batch_with_families(batches, *options).await?,
+        };
+        if is_exceeded {
+            self.store_inner.flush_try(&self.compactor_tx)?;
         }
 
         Ok(())
     }
 
-    fn mem_table(&self) -> &MemTable {
-        &self.store_inner.mem_table
+    #[inline]
+    pub fn disk_iter(&self) -> KernelResult<VersionIter> {
+        self.record_read(
+            self.store_inner.default_family(),
+            Bound::Unbounded,
+            Bound::Unbounded,
+        );
+        VersionIter::new(&self.versions[0], Some(self.seq_id))
     }
 
     #[inline]
-    pub fn disk_iter(&self) -> KernelResult<VersionIter> {
-        VersionIter::new(&self.version)
+    pub fn iter(&self, min: Bound<&[u8]>, max: Bound<&[u8]>) -> KernelResult<TransactionIter> {
+        self.iter_with_family(self.store_inner.default_family(), min, max)
     }
 
+    /// 指定的ColumnFamily的范围迭代器
     #[inline]
-    pub fn iter<'a>(
-        &'a self,
+    pub fn iter_cf(
+        &self,
+        cf: &str,
         min: Bound<&[u8]>,
         max: Bound<&[u8]>,
     ) -> KernelResult<TransactionIter> {
-        let mut vec_iter: Vec<Box<dyn Iter<'a, Item = KeyValue> + 'a + Send + Sync>> =
-            Vec::with_capacity(3);
-
-        if let Some(write_buf) = &self.write_buf {
-            let range = write_buf.range::<Bytes, (Bound<&Bytes>, Bound<&Bytes>)>((
-                min.map(Bytes::copy_from_slice).as_ref(),
-                max.map(Bytes::copy_from_slice).as_ref(),
-            ));
-
-            vec_iter.push(Box::new(InnerIter { iter: range }));
-        }
-
-        let mem_buf = self.mem_table().range_scan(min, max, Some(self.seq_id));
-        let mem_buf_ptr = BufPtr(Box::leak(Box::new(mem_buf)).into());
-
-        vec_iter.push(Box::new(unsafe {
-            BufIter {
-                inner: mem_buf_ptr.0.as_ref(),
-                pos: 0,
-            }
-        }));
-        let mut vec_seek_iter = Vec::new();
-        VersionIter::merging_with_version(&self.version, &mut vec_seek_iter)?;
-
-        match &min {
-            Bound::Included(key) | Bound::Excluded(key) => {
-                for mut seek_iter in vec_seek_iter {
-                    seek_iter.seek(Seek::Backward(key.as_slice()))?;
-                    vec_iter.push(seek_iter as Box<dyn Iter<Item = KeyValue> + Send + Sync>)
-                }
-            }
-            Bound::Unbounded => (),
-        };
+        self.iter_with_family(self.store_inner.family(cf)?, min, max)
+    }
 
-        Ok(TransactionIter {
-            inner: MergingIter::new(vec_iter)?,
-            max: max.map(Bytes::copy_from_slice),
-            mem_buf_ptr,
-            is_overed: false,
-            min: min.map(Bytes::copy_from_slice),
-            is_inited: false,
-        })
+    fn iter_with_family(
+        &self,
+        family: &ColumnFamily,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+    ) -> KernelResult<TransactionIter> {
+        let family_id = family.id();
+        self.record_read(family, min, max);
+        let mem_pin = Some(&self.mem_pins[family_id]);
+        let mem_buf = family
+            .mem_table
+            .range_versions(min, max, Some(self.seq_id), mem_pin);
+        let mem_tombstones = family
+            .mem_table
+            .range_tombstones(Some(self.seq_id), mem_pin);
+
+        TransactionIter::new(
+            self.write_buf.get(&family_id),
+            mem_buf,
+            mem_tombstones,
+            &self.versions[family_id],
+            self.seq_id,
+            (min, max),
+        )
     }
 }
 
 impl Drop for Transaction {
     #[inline]
     fn drop(&mut self) {
-        let _ = self.mem_table().tx_count.fetch_sub(1, Ordering::Release);
+        self.store_inner.end_transaction(self.seq_id);
+        if !self.locked_keys.is_empty() {
+            self.store_inner
+                .lock_manager
+                .unlock_all(self.seq_id, mem::take(&mut self.locked_keys));
+        }
     }
 }
 
-unsafe impl Sync for TransactionIter<'_> {}
-
-unsafe impl Send for TransactionIter<'_> {}
-
 pub struct TransactionIter<'a> {
-    inner: MergingIter<'a>,
-    mem_buf_ptr: BufPtr,
+    inner: SeekMergingIter<'a>,
 
     min: Bound<Bytes>,
     max: Bound<Bytes>,
-    is_overed: bool,
-    is_inited: bool,
+    comparator: Arc<dyn Comparator>,
+}
+
+impl<'a> TransactionIter<'a> {
+    /// 归并写缓存、MemTable的范围数据与Version构建范围迭代器
+    ///
+    /// 迭代器优先级依次为: write_buf > mem_buf > Version
+    ///
+    /// Version中仅会迭代出seq_id不大于seq_id的数据
+    ///
+    /// mem_buf为`MemTable::range_versions`所获取的数据，其中的Merge操作数会在此与Version中的数据合并
+    ///
+    /// mem_tombstones为`MemTable::range_tombstones`所获取的范围删除标记，用于覆盖Version中的数据
+    pub(crate) fn new(
+        write_buf: Option<&'a BTreeMap<ComparableKey, Option<Bytes>>>,
+        mem_buf: Vec<SeqKeyValue>,
+        mem_tombstones: Vec<RangeTombstone>,
+        version: &'a Version,
+        seq_id: i64,
+        (min, max): (Bound<&[u8]>, Bound<&[u8]>),
+    ) -> KernelResult<Self> {
+        let mut vec_iter: Vec<Box<dyn SeekIter<'a, Item = KeyValue> + 'a + Send + Sync>> =
+            Vec::with_capacity(3);
+
+        let comparator = version.comparator();
+        let comparable_key =
+            |key: &[u8]| ComparableKey::new(Bytes::copy_from_slice(key), comparator);
+
+        if let Some(write_buf) = write_buf {
+            let buf = write_buf
+                .range((min.map(comparable_key), max.map(comparable_key)))
+                .map(|(key, value)| (key.key.clone(), value.clone()))
+                .collect_vec();
+
+            vec_iter.push(Box::new(BufIter::new(buf, comparator)));
+        }
+        vec_iter.push(Box::new(BufIter::new(
+            mem_buf_with_version(mem_buf, version, Some(seq_id))?,
+            comparator,
+        )));
+        VersionIter::merging_with_version(version, Some(seq_id), mem_tombstones, &mut vec_iter)?;
+
+        for seek_iter in vec_iter.iter_mut() {
+            if let Bound::Included(key) | Bound::Excluded(key) = &min {
+                seek_iter.seek(Seek::Backward(key))?;
+            }
+        }
+
+        Ok(TransactionIter {
+            inner: SeekMergingIter::new(vec_iter, comparator)?,
+            min: min.map(Bytes::copy_from_slice),
+            max: max.map(Bytes::copy_from_slice),
+            comparator: Arc::clone(comparator),
+        })
+    }
+
+    fn is_under_min(&self, key: &[u8]) -> bool {
+        match &self.min {
+            Bound::Included(min) => self.comparator.compare(key, min).is_lt(),
+            Bound::Excluded(min) => self.comparator.compare(key, min).is_le(),
+            Bound::Unbounded => false,
+        }
+    }
+
+    fn is_over_max(&self, key: &[u8]) -> bool {
+        match &self.max {
+            Bound::Included(max) => self.comparator.compare(key, max).is_gt(),
+            Bound::Excluded(max) => self.comparator.compare(key, max).is_ge(),
+            Bound::Unbounded => false,
+        }
+    }
 }
 
 impl<'a> Iter<'a> for TransactionIter<'a> {
@@ -211,36 +540,14 @@ impl<'a> Iter<'a> for TransactionIter<'a> {
 
     #[inline]
     fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
-        if self.is_overed {
-            return Ok(None);
-        }
-        let mut item = self.inner.try_next()?;
-
-        if !self.is_inited {
-            loop {
-                if match &self.min {
-                    Bound::Included(key) => {
-                        !matches!(item.as_ref().map(|data| data.0 < key), Some(true))
-                    }
-                    Bound::Excluded(key) => {
-                        !matches!(item.as_ref().map(|data| data.0 <= key), Some(true))
-                    }
-                    Bound::Unbounded => true,
-                } {
-                    break;
-                }
-                item = self.inner.try_next()?;
+        while let Some(item) = self.inner.try_next()? {
+            if self.is_under_min(&item.0) {
+                continue;
             }
-            self.is_inited = true
+            return Ok((!self.is_over_max(&item.0)).then_some(item));
         }
-        let option = match &self.max {
-            Bound::Included(key) => item.and_then(|data| (data.0 <= key).then_some(data)),
-            Bound::Excluded(key) => item.and_then(|data| (data.0 < key).then_some(data)),
-            Bound::Unbounded => item,
-        };
-        self.is_overed = option.is_none();
 
-        Ok(option)
+        Ok(None)
     }
 
     #[inline]
@@ -249,31 +556,80 @@ impl<'a> Iter<'a> for TransactionIter<'a> {
     }
 }
 
-impl Drop for TransactionIter<'_> {
+impl<'a> ForwardIter<'a> for TransactionIter<'a> {
     #[inline]
-    fn drop(&mut self) {
-        unsafe { drop(Box::from_raw(self.mem_buf_ptr.0.as_ptr())) }
+    fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
+        while let Some(item) = self.inner.try_prev()? {
+            if self.is_over_max(&item.0) {
+                continue;
+            }
+            return Ok((!self.is_under_min(&item.0)).then_some(item));
+        }
+
+        Ok(None)
+    }
+}
+
+impl<'a> SeekIter<'a> for TransactionIter<'a> {
+    /// 定位时会受限于迭代器的范围，超出范围的Key将定位至对应的边界
+    fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()> {
+        match seek {
+            Seek::First | Seek::Backward(_) => {
+                let key = match seek {
+                    Seek::Backward(key) if !self.is_under_min(key) => Some(key),
+                    _ => match &self.min {
+                        Bound::Included(key) | Bound::Excluded(key) => Some(key.as_slice()),
+                        Bound::Unbounded => None,
+                    },
+                };
+                self.inner
+                    .seek(key.map(Seek::Backward).unwrap_or(Seek::First))
+            }
+            Seek::Last | Seek::Forward(_) => {
+                let key = match seek {
+                    Seek::Forward(key) if !self.is_over_max(key) => Some(key),
+                    _ => match &self.max {
+                        Bound::Included(key) | Bound::Excluded(key) => Some(key.as_slice()),
+                        Bound::Unbounded => None,
+                    },
+                };
+                self.inner
+                    .seek(key.map(Seek::Forward).unwrap_or(Seek::Last))
+            }
+        }
     }
 }
 
-struct BufIter<'a> {
-    inner: &'a Vec<KeyValue>,
+/// 有序键值对缓存的迭代器
+///
+/// Tips: pos会额外向上偏移一位以使用0作为迭代的下界，与`BlockIter`一致
+struct BufIter {
+    inner: Vec<KeyValue>,
     pos: usize,
+    comparator: Arc<dyn Comparator>,
 }
 
-struct InnerIter<'a> {
-    iter: Range<'a, Bytes, Option<Bytes>>,
+impl BufIter {
+    fn new(inner: Vec<KeyValue>, comparator: &Arc<dyn Comparator>) -> Self {
+        BufIter {
+            inner,
+            pos: 0,
+            comparator: Arc::clone(comparator),
+        }
+    }
+
+    fn item(&self) -> Option<KeyValue> {
+        (self.pos > 0 && self.pos <= self.inner.len()).then(|| self.inner[self.pos - 1].clone())
+    }
 }
 
-impl<'a> Iter<'a> for BufIter<'a> {
+impl<'a> Iter<'a> for BufIter {
     type Item = KeyValue;
 
     fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
-        Ok(self.is_valid().then(|| {
-            let item = self.inner[self.pos].clone();
-            self.pos += 1;
-            item
-        }))
+        self.pos = (self.pos + 1).min(self.inner.len() + 1);
+
+        Ok(self.item())
     }
 
     fn is_valid(&self) -> bool {
@@ -281,26 +637,39 @@ impl<'a> Iter<'a> for BufIter<'a> {
     }
 }
 
-impl<'a> Iter<'a> for InnerIter<'a> {
-    type Item = KeyValue;
+impl<'a> ForwardIter<'a> for BufIter {
+    fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
+        self.pos = self.pos.saturating_sub(1);
 
-    fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
-        Ok(self
-            .iter
-            .next()
-            .map(|(key, value)| (key.clone(), value.clone())))
+        Ok(self.item())
     }
+}
 
-    fn is_valid(&self) -> bool {
-        true
+impl<'a> SeekIter<'a> for BufIter {
+    fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()> {
+        self.pos = match seek {
+            Seek::First => 0,
+            Seek::Last => self.inner.len() + 1,
+            Seek::Backward(key) => self
+                .inner
+                .partition_point(|(item_key, _)| self.comparator.compare(item_key, key).is_lt()),
+            Seek::Forward(key) => {
+                self.inner
+                    .partition_point(|(item_key, _)| self.comparator.compare(item_key, key).is_le())
+                    + 1
+            }
+        };
+
+        Ok(())
     }
 }
 
 /// TODO: 更多的Test Case
 #[cfg(test)]
 mod tests {
-    use crate::kernel::lsm::iterator::Iter;
-    use crate::kernel::lsm::mvcc::CheckType;
+    use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
+    use crate::kernel::lsm::mem_table::KeyValue;
+    use crate::kernel::lsm::mvcc::{CheckType, Transaction};
     use crate::kernel::lsm::storage::{Config, KipStorage};
     use crate::kernel::{KernelResult, Storage};
     use crate::KernelError;
@@ -308,6 +677,8 @@ mod tests {
     use bytes::Bytes;
     use itertools::Itertools;
     use std::collections::Bound;
+    use std::sync::Arc;
+    use std::time::Duration;
     use tempfile::TempDir;
 
     #[tokio::test]
@@ -345,10 +716,10 @@ mod tests {
         let mut tx_1 = kv_store.new_transaction(CheckType::Optimistic).await;
 
         for kv in vec_kv.iter().take(times).skip(100) {
-            tx_1.set(kv.0.clone(), kv.1.clone());
+            tx_1.set(kv.0.clone(), kv.1.clone()).await?;
         }
 
-        tx_1.remove(&vec_kv[times - 1].0)?;
+        tx_1.remove(&vec_kv[times - 1].0).await?;
 
         // 事务在提交前事务可以读取到自身以及Store已写入的数据
         for kv in vec_kv.iter().take(times - 1) {
@@ -387,6 +758,72 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_transaction_reverse_iter() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+
+        let times = 200;
+
+        let config = Config::new(temp_dir.into_path()).major_threshold_with_sst_size(4);
+        let kv_store = KipStorage::open_with_config(config).await?;
+
+        let vec_kv = (0..times)
+            .map(|i| {
+                let key = Bytes::from(bincode::options().with_big_endian().serialize(&i)?);
+                Ok((key.clone(), key))
+            })
+            .collect::<KernelResult<Vec<(Bytes, Bytes)>>>()?;
+
+        // 模拟数据分布在SSTable、MemTable以及事务写缓存中
+        for kv in vec_kv.iter().take(50) {
+            kv_store.set(kv.0.clone(), kv.1.clone()).await?;
+        }
+
+        kv_store.flush().await?;
+
+        for kv in vec_kv.iter().take(100).skip(50) {
+            kv_store.set(kv.0.clone(), kv.1.clone()).await?;
+        }
+
+        let mut tx = kv_store.new_transaction(CheckType::Optimistic).await;
+
+        for kv in vec_kv.iter().skip(100) {
+            tx.set(kv.0.clone(), kv.1.clone()).await?;
+        }
+        tx.remove(&vec_kv[150].0).await?;
+
+        let mut iter = tx.iter(
+            Bound::Included(&vec_kv[10].0),
+            Bound::Excluded(&vec_kv[180].0),
+        )?;
+
+        iter.seek(Seek::Last)?;
+        for i in (10..180).rev() {
+            let (key, value) = iter.try_prev()?.unwrap();
+
+            assert_eq!(key, vec_kv[i].0);
+            assert_eq!(value, (i != 150).then(|| vec_kv[i].1.clone()));
+        }
+        assert_eq!(iter.try_prev()?, None);
+        assert_eq!(iter.try_next()?.unwrap().0, vec_kv[10].0);
+
+        // 获取某个Key之前最新的N条数据
+        iter.seek(Seek::Forward(&vec_kv[101].0))?;
+        for i in (98..=101).rev() {
+            assert_eq!(iter.try_prev()?.unwrap().0, vec_kv[i].0);
+        }
+        assert_eq!(iter.try_next()?.unwrap().0, vec_kv[99].0);
+
+        // 超出范围的定位会被限制在边界上
+        iter.seek(Seek::Forward(&vec_kv[199].0))?;
+        assert_eq!(iter.try_prev()?.unwrap().0, vec_kv[179].0);
+
+        iter.seek(Seek::Backward(&vec_kv[0].0))?;
+        assert_eq!(iter.try_next()?.unwrap().0, vec_kv[10].0);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_transaction_check_optimistic() -> KernelResult<()> {
         let temp_dir = TempDir::new().expect("unable to create temporary working directory");
@@ -397,8 +834,8 @@ mod tests {
         let mut tx_1 = kv_store.new_transaction(CheckType::Optimistic).await;
         let mut tx_2 = kv_store.new_transaction(CheckType::Optimistic).await;
 
-        tx_1.set(Bytes::from("same_key"), Bytes::new());
-        tx_2.set(Bytes::from("same_key"), Bytes::new());
+        tx_1.set(Bytes::from("same_key"), Bytes::new()).await?;
+        tx_2.set(Bytes::from("same_key"), Bytes::new()).await?;
 
         tx_1.commit().await?;
 
@@ -409,4 +846,358 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_transaction_check_pessimistic() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+
+        let config = Config::new(temp_dir.into_path()).major_threshold_with_sst_size(4);
+        let kv_store = Arc::new(KipStorage::open_with_config(config).await?);
+        let counter = Bytes::from("counter");
+
+        kv_store
+            .set(counter.clone(), Bytes::from(0_u64.to_le_bytes().to_vec()))
+            .await?;
+
+        let tasks = (0..4)
+            .map(|_| {
+                let kv_store = Arc::clone(&kv_store);
+                let counter = counter.clone();
+
+                tokio::spawn(async move {
+                    for _ in 0..50 {
+                        let mut tx = kv_store.new_transaction(CheckType::Pessimistic).await;
+                        let value = tx.get_for_update(&counter).await?.unwrap();
+                        let count = u64::from_le_bytes(value[..].try_into().unwrap());
+
+                        tx.set(
+                            counter.clone(),
+                            Bytes::from((count + 1).to_le_bytes().to_vec()),
+                        )
+                        .await?;
+                        tx.commit().await?;
+                    }
+                    Ok::<(), KernelError>(())
+                })
+            })
+            .collect_vec();
+        for task in tasks {
+            task.await.expect("task panicked")?;
+        }
+
+        // 悲观事务间不会产生写冲突，所有递增均生效
+        let value = kv_store.get(&counter).await?.unwrap();
+        assert_eq!(u64::from_le_bytes(value[..].try_into().unwrap()), 200);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_transaction_pessimistic_lock_error() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+
+        let config = Config::new(temp_dir.into_path()).lock_timeout(Duration::from_millis(200));
+        let kv_store = KipStorage::open_with_config(config).await?;
+        let (key_a, key_b) = (Bytes::from("a"), Bytes::from("b"));
+
+        let mut tx_1 = kv_store.new_transaction(CheckType::Pessimistic).await;
+        let mut tx_2 = kv_store.new_transaction(CheckType::Pessimistic).await;
+
+        tx_1.set(key_a.clone(), Bytes::new()).await?;
+        assert!(matches!(
+            tx_2.set(key_a.clone(), Bytes::new()).await,
+            Err(KernelError::LockTimeout)
+        ));
+        tx_2.set(key_b.clone(), Bytes::new()).await?;
+
+        // tx_1等待tx_2持有的b，tx_2再等待tx_1持有的a时形成死锁
+        let waiter = tokio::spawn(async move {
+            let result = tx_1.set(Bytes::from("b"), Bytes::new()).await;
+            (tx_1, result)
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(matches!(
+            tx_2.set(key_a, Bytes::new()).await,
+            Err(KernelError::Deadlock)
+        ));
+        drop(tx_2);
+
+        let (tx_1, result) = waiter.await.expect("waiter panicked");
+        result?;
+        tx_1.commit().await?;
+        assert_eq!(kv_store.get(&key_b).await?, Some(Bytes::new()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_check_serializable() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+
+        let config = Config::new(temp_dir.into_path()).major_threshold_with_sst_size(4);
+        let kv_store = KipStorage::open_with_config(config).await?;
+        let (key_x, key_y) = (Bytes::from("x"), Bytes::from("y"));
+
+        kv_store.set(key_x.clone(), Bytes::from("1")).await?;
+        kv_store.set(key_y.clone(), Bytes::from("1")).await?;
+
+        // 写偏斜: 两事务读取相同的Key但写入不同的Key
+        for is_serializable in [false, true] {
+            let check_type = || {
+                if is_serializable {
+                    CheckType::Serializable
+                } else {
+                    CheckType::Optimistic
+                }
+            };
+            let mut tx_1 = kv_store.new_transaction(check_type()).await;
+            let mut tx_2 = kv_store.new_transaction(check_type()).await;
+
+            for tx in [&tx_1, &tx_2] {
+                assert!(tx.get(&key_x)?.is_some());
+                assert!(tx.get(&key_y)?.is_some());
+            }
+            tx_1.set(key_x.clone(), Bytes::from("0")).await?;
+            tx_2.set(key_y.clone(), Bytes::from("0")).await?;
+            tx_1.commit().await?;
+
+            if is_serializable {
+                assert!(matches!(
+                    tx_2.commit().await,
+                    Err(KernelError::SerializationFailure)
+                ));
+            } else {
+                tx_2.commit().await?;
+                kv_store.set(key_x.clone(), Bytes::from("1")).await?;
+                kv_store.set(key_y.clone(), Bytes::from("1")).await?;
+            }
+        }
+
+        // 扫描过的范围内写入新的Key
+        let mut tx = kv_store.new_transaction(CheckType::Serializable).await;
+        let mut iter = tx.iter(Bound::Included(b"a"), Bound::Excluded(b"m"))?;
+        while iter.try_next()?.is_some() {}
+        drop(iter);
+        kv_store.set(Bytes::from("z"), Bytes::new()).await?;
+        tx.set(Bytes::from("tx_key"), Bytes::new()).await?;
+        tx.commit().await?;
+
+        let mut tx = kv_store.new_transaction(CheckType::Serializable).await;
+        let mut iter = tx.iter(Bound::Included(b"a"), Bound::Excluded(b"m"))?;
+        while iter.try_next()?.is_some() {}
+        drop(iter);
+        kv_store.set(Bytes::from("b"), Bytes::new()).await?;
+        tx.set(Bytes::from("tx_key"), Bytes::new()).await?;
+        assert!(matches!(
+            tx.commit().await,
+            Err(KernelError::SerializationFailure)
+        ));
+
+        // 范围删除标记同样视为写入
+        let mut tx = kv_store.new_transaction(CheckType::Serializable).await;
+        assert!(tx.get(&key_y)?.is_some());
+        kv_store
+            .delete_range(Bytes::from("w"), Bytes::from("z"))
+            .await?;
+        tx.set(Bytes::from("tx_key"), Bytes::new()).await?;
+        assert!(matches!(
+            tx.commit().await,
+            Err(KernelError::SerializationFailure)
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_transaction_concurrent_commit() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let kv_store = KipStorage::open(temp_dir.path()).await?;
+        let wal = Arc::clone(&kv_store.inner.wal);
+        let (key_x, key_y) = (Bytes::from("x"), Bytes::from("y"));
+
+        kv_store.set(key_x.clone(), Bytes::from("1")).await?;
+        kv_store.set(key_y.clone(), Bytes::from("1")).await?;
+
+        // 占用Leader使两事务的提交在同一组中写入，冲突检查须在WAL写入锁内进行才能检测到彼此
+        let commit_concurrently = |tx_1: Transaction, tx_2: Transaction| {
+            let wal = Arc::clone(&wal);
+
+            async move {
+                wal.hold_leader();
+                let tasks = [tx_1, tx_2].map(|tx| tokio::spawn(tx.commit()));
+                while wal.queued_len() < 2 {
+                    tokio::task::yield_now().await;
+                }
+                wal.release_leader();
+
+                let mut results = Vec::new();
+                for task in tasks {
+                    results.push(task.await.expect("task panicked"));
+                }
+                results
+            }
+        };
+
+        // 丢失更新: 两乐观事务写入相同的Key
+        let mut tx_1 = kv_store.new_transaction(CheckType::Optimistic).await;
+        let mut tx_2 = kv_store.new_transaction(CheckType::Optimistic).await;
+        tx_1.set(key_x.clone(), Bytes::from("2")).await?;
+        tx_2.set(key_x.clone(), Bytes::from("3")).await?;
+
+        let results = commit_concurrently(tx_1, tx_2).await;
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        assert!(results
+            .iter()
+            .any(|result| matches!(result, Err(KernelError::RepeatedWrite))));
+
+        // 写偏斜: 两可串行化事务读取相同的Key但写入不同的Key
+        let mut tx_1 = kv_store.new_transaction(CheckType::Serializable).await;
+        let mut tx_2 = kv_store.new_transaction(CheckType::Serializable).await;
+        for tx in [&tx_1, &tx_2] {
+            assert!(tx.get(&key_x)?.is_some());
+            assert!(tx.get(&key_y)?.is_some());
+        }
+        tx_1.set(key_x.clone(), Bytes::from("0")).await?;
+        tx_2.set(key_y.clone(), Bytes::from("0")).await?;
+
+        let results = commit_concurrently(tx_1, tx_2).await;
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        assert!(results
+            .iter()
+            .any(|result| matches!(result, Err(KernelError::SerializationFailure))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_savepoint() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+
+        let config = Config::new(temp_dir.into_path()).lock_timeout(Duration::from_millis(50));
+        let kv_store = KipStorage::open_with_config(config).await?;
+        let keys = (0..4)
+            .map(|i| Bytes::from(format!("key_{i}")))
+            .collect_vec();
+        let scan = |tx: &Transaction| -> KernelResult<Vec<KeyValue>> {
+            let mut iter = tx.iter(Bound::Unbounded, Bound::Unbounded)?;
+            let mut vec_kv = Vec::new();
+            while let Some(item) = iter.try_next()? {
+                if item.1.is_some() {
+                    vec_kv.push(item);
+                }
+            }
+            Ok(vec_kv)
+        };
+
+        kv_store.set(keys[0].clone(), Bytes::from("0")).await?;
+
+        let mut tx = kv_store.new_transaction(CheckType::Optimistic).await;
+        tx.set(keys[1].clone(), Bytes::from("1")).await?;
+
+        let savepoint_1 = tx.savepoint();
+        tx.set(keys[1].clone(), Bytes::from("1_1")).await?;
+        tx.remove(&keys[0]).await?;
+
+        let savepoint_2 = tx.savepoint();
+        tx.set(keys[2].clone(), Bytes::from("2")).await?;
+        assert_eq!(scan(&tx)?.len(), 2);
+
+        tx.rollback_to(savepoint_2)?;
+        assert_eq!(tx.get(&keys[2])?, None);
+        assert_eq!(
+            scan(&tx)?,
+            vec![(keys[1].clone(), Some(Bytes::from("1_1")))]
+        );
+
+        tx.rollback_to(savepoint_1)?;
+        assert_eq!(tx.get(&keys[0])?, Some(Bytes::from("0")));
+        assert_eq!(
+            scan(&tx)?,
+            vec![
+                (keys[0].clone(), Some(Bytes::from("0"))),
+                (keys[1].clone(), Some(Bytes::from("1"))),
+            ]
+        );
+        // 回滚至更早的保存点后，其后创建的保存点失效
+        assert!(matches!(
+            tx.rollback_to(savepoint_2),
+            Err(KernelError::InvalidSavepoint)
+        ));
+        // 回滚后保存点依旧有效
+        tx.set(keys[3].clone(), Bytes::from("3")).await?;
+        tx.rollback_to(savepoint_1)?;
+        assert_eq!(tx.get(&keys[3])?, None);
+
+        let other_tx = kv_store.new_transaction(CheckType::Optimistic).await;
+        let other_savepoint = {
+            let mut other_tx = other_tx;
+            other_tx.savepoint()
+        };
+        assert!(matches!(
+            tx.rollback_to(other_savepoint),
+            Err(KernelError::InvalidSavepoint)
+        ));
+
+        tx.commit().await?;
+        assert_eq!(kv_store.get(&keys[0]).await?, Some(Bytes::from("0")));
+        assert_eq!(kv_store.get(&keys[1]).await?, Some(Bytes::from("1")));
+        assert_eq!(kv_store.get(&keys[3]).await?, None);
+
+        // 显式回滚时放弃写入并释放锁
+        let mut tx_1 = kv_store.new_transaction(CheckType::Pessimistic).await;
+        tx_1.set(keys[2].clone(), Bytes::from("2")).await?;
+        tx_1.rollback();
+
+        let mut tx_2 = kv_store.new_transaction(CheckType::Pessimistic).await;
+        assert_eq!(tx_2.get_for_update(&keys[2]).await?, None);
+        tx_2.set(keys[2].clone(), Bytes::from("2_2")).await?;
+        tx_2.commit().await?;
+        assert_eq!(kv_store.get(&keys[2]).await?, Some(Bytes::from("2_2")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_across_flush() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+
+        let config = Config::new(temp_dir.into_path());
+        let kv_store = KipStorage::open_with_config(config).await?;
+        let (key_a, key_b) = (Bytes::from("a"), Bytes::from("b"));
+
+        kv_store.set(key_a.clone(), Bytes::from("1")).await?;
+
+        let mut tx = kv_store.new_transaction(CheckType::Optimistic).await;
+        let snapshot = kv_store.snapshot().await;
+
+        // 事务与快照存活时交换与Flush不会被阻塞
+        kv_store.set(key_b.clone(), Bytes::from("1")).await?;
+        kv_store.flush().await?;
+        kv_store.set(key_a.clone(), Bytes::from("2")).await?;
+        kv_store.flush().await?;
+        kv_store.flush().await?;
+        assert!(kv_store.current_version().await.len() >= 2);
+
+        assert_eq!(tx.get(&key_a)?, Some(Bytes::from("1")));
+        assert_eq!(tx.get(&key_b)?, None);
+        assert_eq!(snapshot.get(&key_a)?, Some(Bytes::from("1")));
+        assert_eq!(
+            snapshot.scan(Bound::Unbounded, Bound::Unbounded, None)?,
+            vec![(key_a.clone(), Bytes::from("1"))]
+        );
+        let mut iter = tx.iter(Bound::Unbounded, Bound::Unbounded)?;
+        assert_eq!(
+            iter.try_next()?,
+            Some((key_a.clone(), Some(Bytes::from("1"))))
+        );
+        assert_eq!(iter.try_next()?, None);
+        drop(iter);
+
+        // 冲突的写入已被Flush至Table中依旧能够检测
+        tx.set(key_a.clone(), Bytes::from("3")).await?;
+        assert!(matches!(tx.commit().await, Err(KernelError::RepeatedWrite)));
+        assert_eq!(kv_store.get(&key_a).await?, Some(Bytes::from("2")));
+
+        Ok(())
+    }
 }
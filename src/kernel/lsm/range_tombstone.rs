@@ -0,0 +1,179 @@
+use crate::kernel::lsm::comparator::Comparator;
+use crate::kernel::lsm::mem_table::{SeqKeyValue, ValueMeta};
+use crate::kernel::KernelResult;
+use bytes::Bytes;
+use integer_encoding::{VarIntReader, VarIntWriter};
+use std::io::{Read, Write};
+
+/// 范围删除标记
+///
+/// 覆盖[start, end)范围内seq_id小于该标记的所有数据
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct RangeTombstone {
+    pub(crate) start: Bytes,
+    pub(crate) end: Bytes,
+    pub(crate) seq_id: i64,
+}
+
+impl RangeTombstone {
+    pub(crate) fn new(start: Bytes, end: Bytes, seq_id: i64) -> Self {
+        RangeTombstone { start, end, seq_id }
+    }
+
+    /// 判断key是否处于该标记的范围内
+    pub(crate) fn contains(&self, key: &[u8], comparator: &dyn Comparator) -> bool {
+        comparator.compare(&self.start, key).is_le() && comparator.compare(key, &self.end).is_lt()
+    }
+
+    /// 判断[start, end]是否完全处于该标记的范围内
+    pub(crate) fn contains_range(
+        &self,
+        start: &[u8],
+        end: &[u8],
+        comparator: &dyn Comparator,
+    ) -> bool {
+        comparator.compare(&self.start, start).is_le() && comparator.compare(end, &self.end).is_lt()
+    }
+
+    pub(crate) fn encode(&self, bytes: &mut Vec<u8>) -> KernelResult<()> {
+        bytes.write_varint(self.start.len() as u32)?;
+        bytes.write_all(&self.start)?;
+        bytes.write_varint(self.end.len() as u32)?;
+        bytes.write_all(&self.end)?;
+        bytes.write_varint(self.seq_id)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn decode<R: Read>(reader: &mut R) -> KernelResult<Self> {
+        let mut start = vec![0u8; reader.read_varint::<u32>()? as usize];
+        reader.read_exact(&mut start)?;
+        let mut end = vec![0u8; reader.read_varint::<u32>()? as usize];
+        reader.read_exact(&mut end)?;
+        let seq_id = reader.read_varint::<i64>()?;
+
+        Ok(RangeTombstone::new(
+            Bytes::from(start),
+            Bytes::from(end),
+            seq_id,
+        ))
+    }
+}
+
+/// 获取覆盖key且对read_seq可见的范围删除标记中最大的seq_id
+///
+/// 该Key中seq_id小于返回值的数据均已被删除
+pub(crate) fn cover_seq<'a>(
+    tombstones: impl IntoIterator<Item = &'a RangeTombstone>,
+    key: &[u8],
+    read_seq: Option<i64>,
+    comparator: &dyn Comparator,
+) -> Option<i64> {
+    tombstones
+        .into_iter()
+        .filter(|tombstone| read_seq.map_or(true, |read_seq| tombstone.seq_id <= read_seq))
+        .filter(|tombstone| tombstone.contains(key, comparator))
+        .map(|tombstone| tombstone.seq_id)
+        .max()
+}
+
+/// 将同一Key由新至旧的数据中被覆盖的部分替换为单个删除标记
+///
+/// 最新的数据已被覆盖时返回None，此时该Key可直接丢弃
+pub(crate) fn truncate_covered(
+    mut versions: Vec<SeqKeyValue>,
+    cover: Option<i64>,
+) -> Option<Vec<SeqKeyValue>> {
+    let Some((cover, position)) = cover.and_then(|cover| {
+        versions
+            .iter()
+            .position(|(_, seq_id, _)| *seq_id < cover)
+            .map(|position| (cover, position))
+    }) else {
+        return Some(versions);
+    };
+    if position == 0 {
+        return None;
+    }
+    let ((key, _), ..) = &versions[position];
+    let marker = ((key.clone(), None), cover, ValueMeta::default());
+
+    versions.truncate(position);
+    versions.push(marker);
+    Some(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::lsm::comparator::BytewiseComparator;
+    use crate::kernel::lsm::mem_table::ValueMeta;
+    use crate::kernel::lsm::range_tombstone::{cover_seq, truncate_covered, RangeTombstone};
+    use crate::kernel::KernelResult;
+    use bytes::Bytes;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_range_tombstone() -> KernelResult<()> {
+        let tombstones = vec![
+            RangeTombstone::new(Bytes::from_static(b"b"), Bytes::from_static(b"d"), 5),
+            RangeTombstone::new(Bytes::from_static(b"c"), Bytes::from_static(b"f"), 8),
+        ];
+
+        assert!(tombstones[0].contains(b"b", &BytewiseComparator));
+        assert!(!tombstones[0].contains(b"d", &BytewiseComparator));
+        assert!(tombstones[1].contains_range(b"c", b"e", &BytewiseComparator));
+        assert!(!tombstones[1].contains_range(b"c", b"f", &BytewiseComparator));
+
+        assert_eq!(
+            cover_seq(&tombstones, b"a", None, &BytewiseComparator),
+            None
+        );
+        assert_eq!(
+            cover_seq(&tombstones, b"c", None, &BytewiseComparator),
+            Some(8)
+        );
+        assert_eq!(
+            cover_seq(&tombstones, b"c", Some(6), &BytewiseComparator),
+            Some(5)
+        );
+        assert_eq!(
+            cover_seq(&tombstones, b"e", Some(6), &BytewiseComparator),
+            None
+        );
+
+        let mut bytes = Vec::new();
+        tombstones[0].encode(&mut bytes)?;
+        assert_eq!(
+            RangeTombstone::decode(&mut Cursor::new(bytes))?,
+            tombstones[0]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_covered() {
+        let key = Bytes::from_static(b"k");
+        let versions = vec![
+            ((key.clone(), Some(key.clone())), 9, ValueMeta::merge()),
+            ((key.clone(), Some(key.clone())), 3, ValueMeta::default()),
+        ];
+
+        assert_eq!(
+            truncate_covered(versions.clone(), None),
+            Some(versions.clone())
+        );
+        assert_eq!(truncate_covered(versions.clone(), Some(10)), None);
+        assert_eq!(
+            truncate_covered(versions.clone(), Some(2)),
+            Some(versions.clone())
+        );
+        assert_eq!(
+            truncate_covered(versions.clone(), Some(5)),
+            Some(vec![
+                versions[0].clone(),
+                ((key, None), 5, ValueMeta::default())
+            ])
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use crate::kernel::lsm::compactor::CompactTask;
+use crate::kernel::lsm::mem_table::{MemPin, MemTable};
+use crate::kernel::lsm::query_and_compaction;
+use crate::kernel::lsm::storage::{KipStorage, Sequence, StorageIter, StoreInner};
+use crate::kernel::lsm::version::Version;
+use crate::kernel::KernelResult;
+use bytes::Bytes;
+use std::collections::Bound;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+
+/// 只读快照
+///
+/// 固定于创建时的seq_id与Version，与`Transaction`不同的是没有写缓存与冲突检测，并且可以Clone
+///
+/// Tips: 快照会持有其创建时的MemTable数据与Version，因此请避免长时间持有
+#[derive(Clone)]
+pub struct Snapshot {
+    store_inner: Arc<StoreInner>,
+    compactor_tx: Sender<CompactTask>,
+
+    mem_pin: MemPin,
+    version: Arc<Version>,
+    seq_id: i64,
+}
+
+impl Snapshot {
+    pub(crate) async fn new(storage: &KipStorage) -> Self {
+        // seq_id须先于MemPin生成，且先固定MemTable再获取Version，详见`Transaction::new`
+        let seq_id = storage.inner.wal.read_seq(Sequence::create).await;
+        let mem_pin = storage.mem_table().pin();
+
+        Snapshot {
+            store_inner: Arc::clone(&storage.inner),
+            version: storage.current_version().await,
+            compactor_tx: storage.compactor_tx.clone(),
+
+            mem_pin,
+            seq_id,
+        }
+    }
+
+    fn mem_table(&self) -> &MemTable {
+        &self.store_inner.default_family().mem_table
+    }
+
+    /// 快照所固定的seq_id
+    #[inline]
+    pub fn seq_id(&self) -> i64 {
+        self.seq_id
+    }
+
+    /// 通过Key获取快照中对应的Value
+    #[inline]
+    pub fn get(&self, key: &[u8]) -> KernelResult<Option<Bytes>> {
+        query_and_compaction(
+            key,
+            self.mem_table()
+                .find_versions(key, Some(self.seq_id), Some(&self.mem_pin)),
+            self.store_inner.default_family().id(),
+            &self.version,
+            Some(self.seq_id),
+            &self.compactor_tx,
+        )
+    }
+
+    /// 范围扫描快照中的数据，limit为None时不限制数量
+    #[inline]
+    pub fn scan(
+        &self,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+        limit: Option<usize>,
+    ) -> KernelResult<Vec<(Bytes, Bytes)>> {
+        self.iter(min, max)?.collect_with_limit(limit)
+    }
+
+    /// 快照的范围迭代器
+    #[inline]
+    pub fn iter(&self, min: Bound<&[u8]>, max: Bound<&[u8]>) -> KernelResult<StorageIter> {
+        let mem_buf =
+            self.mem_table()
+                .range_versions(min, max, Some(self.seq_id), Some(&self.mem_pin));
+        let mem_tombstones = self
+            .mem_table()
+            .range_tombstones(Some(self.seq_id), Some(&self.mem_pin));
+
+        StorageIter::new(
+            Arc::clone(&self.version),
+            mem_buf,
+            mem_tombstones,
+            self.seq_id,
+            (min, max),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
+    use crate::kernel::lsm::storage::{Config, KipStorage, ReadOptions};
+    use crate::kernel::{KernelResult, Storage};
+    use bincode::Options;
+    use bytes::Bytes;
+    use std::collections::Bound;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_snapshot() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+
+        let times = 200;
+
+        let config = Config::new(temp_dir.into_path()).major_threshold_with_sst_size(4);
+        let kv_store = KipStorage::open_with_config(config).await?;
+
+        let vec_kv = (0..times)
+            .map(|i| {
+                let key = Bytes::from(bincode::options().with_big_endian().serialize(&i)?);
+                Ok((key.clone(), key))
+            })
+            .collect::<KernelResult<Vec<(Bytes, Bytes)>>>()?;
+
+        // 模拟数据分布在MemTable以及SSTable中
+        for kv in vec_kv.iter().take(100) {
+            kv_store.set(kv.0.clone(), kv.1.clone()).await?;
+        }
+
+        kv_store.flush().await?;
+
+        for kv in vec_kv.iter().skip(100) {
+            kv_store.set(kv.0.clone(), kv.1.clone()).await?;
+        }
+
+        let snapshot = kv_store.snapshot().await;
+
+        // 快照创建后的写入对快照不可见
+        kv_store.set(vec_kv[0].0.clone(), Bytes::new()).await?;
+        kv_store.remove(&vec_kv[150].0).await?;
+        kv_store
+            .set(Bytes::from_static(b"KipDB"), Bytes::new())
+            .await?;
+
+        let snapshot_clone = snapshot.clone();
+        drop(snapshot);
+
+        for kv in vec_kv.iter() {
+            assert_eq!(snapshot_clone.get(&kv.0)?, Some(kv.1.clone()));
+        }
+        assert_eq!(snapshot_clone.get(b"KipDB")?, None);
+
+        assert_eq!(
+            snapshot_clone.scan(Bound::Unbounded, Bound::Unbounded, None)?,
+            vec_kv
+        );
+        assert_eq!(
+            snapshot_clone.scan(Bound::Excluded(&vec_kv[10].0), Bound::Unbounded, Some(3))?,
+            vec_kv[11..14].to_vec()
+        );
+
+        let mut iter = snapshot_clone.iter(Bound::Unbounded, Bound::Unbounded)?;
+        iter.seek(Seek::Last)?;
+        for kv in vec_kv.iter().rev() {
+            assert_eq!(iter.try_prev()?, Some(kv.clone()));
+        }
+        drop(iter);
+
+        let options = ReadOptions::new().snapshot(&snapshot_clone);
+
+        assert_eq!(
+            kv_store.get_with_options(&vec_kv[0].0, &options).await?,
+            Some(vec_kv[0].1.clone())
+        );
+        assert_eq!(
+            kv_store
+                .get_with_options(&vec_kv[0].0, &ReadOptions::new())
+                .await?,
+            Some(Bytes::new())
+        );
+        assert_eq!(
+            kv_store
+                .scan_with_options(Bound::Unbounded, Bound::Unbounded, None, &options)
+                .await?,
+            vec_kv
+        );
+        let mut iter = kv_store
+            .iter_with_options(Bound::Included(&vec_kv[150].0), Bound::Unbounded, &options)
+            .await?;
+        assert_eq!(iter.try_next()?, Some(vec_kv[150].clone()));
+        drop(iter);
+
+        drop(snapshot_clone);
+        // 快照释放后才可正常的进行MemTable交换
+        kv_store.flush().await?;
+
+        assert_eq!(kv_store.get(&vec_kv[150].0).await?, None);
+
+        Ok(())
+    }
+}
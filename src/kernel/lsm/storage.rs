@@ -1,14 +1,29 @@
 use crate::kernel::io::IoType;
+use crate::kernel::lsm::column_family::{ColumnFamily, DEFAULT_COLUMN_FAMILY};
+use crate::kernel::lsm::commit_history::ActiveTransactions;
+use crate::kernel::lsm::compaction_filter::CompactionFilter;
 use crate::kernel::lsm::compactor::{CompactTask, Compactor};
-use crate::kernel::lsm::mem_table::{KeyValue, MemTable};
-use crate::kernel::lsm::mvcc::{CheckType, Transaction};
+use crate::kernel::lsm::comparator::{BytewiseComparator, Comparator};
+use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
+use crate::kernel::lsm::lock_manager::LockManager;
+use crate::kernel::lsm::mem_table::{
+    now_millis, KeyValue, MemTable, SeqKeyValue, ValueMeta, Wal, WriteCondition,
+};
+use crate::kernel::lsm::merge_operator::MergeOperator;
+use crate::kernel::lsm::mvcc::{CheckType, Transaction, TransactionIter};
+use crate::kernel::lsm::range_tombstone::RangeTombstone;
+use crate::kernel::lsm::snapshot::Snapshot;
 use crate::kernel::lsm::table::scope::Scope;
-use crate::kernel::lsm::table::ss_table::block;
+use crate::kernel::lsm::table::ss_table::block::{self, CompressType};
 use crate::kernel::lsm::table::TableType;
 use crate::kernel::lsm::trigger::TriggerType;
-use crate::kernel::lsm::version::status::VersionStatus;
 use crate::kernel::lsm::version::Version;
-use crate::kernel::lsm::{query_and_compaction, version, MAX_LEVEL};
+use crate::kernel::lsm::{
+    lock_manager, merge_operator, multi_query_and_compaction, query_and_compaction, value_log,
+    version, MAX_LEVEL,
+};
+use crate::kernel::utils::lru_cache::ShardingLruCache;
+use crate::kernel::write_batch::WriteBatch;
 use crate::kernel::KernelResult;
 use crate::kernel::{lock_or_time_out, Storage, DEFAULT_LOCK_FILE};
 use crate::KernelError;
@@ -16,14 +31,19 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::Local;
 use fslock::LockFile;
+use itertools::Itertools;
+use self_cell::self_cell;
+use std::collections::hash_map::RandomState;
+use std::collections::Bound;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::mpsc::{channel, Sender};
 use tokio::sync::oneshot;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 pub(crate) const BANNER: &str = "
 █████   ████  ███            ██████████   ███████████
@@ -56,6 +76,12 @@ pub(crate) const DEFAULT_WAL_THRESHOLD: usize = 20;
 
 pub(crate) const DEFAULT_WAL_IO_TYPE: IoType = IoType::Buf;
 
+pub(crate) const DEFAULT_MAX_IMMUT_COUNT: usize = 4;
+
+pub(crate) const DEFAULT_WAL_SYNC_POLICY: WalSyncPolicy = WalSyncPolicy::Os;
+
+pub(crate) const DEFAULT_WAL_RECOVERY_MODE: WalRecoveryMode = WalRecoveryMode::PointInTime;
+
 static SEQ_COUNT: AtomicI64 = AtomicI64::new(1);
 
 static GEN_BUF: AtomicI64 = AtomicI64::new(0);
@@ -72,28 +98,107 @@ pub struct KipStorage {
 }
 
 pub(crate) struct StoreInner {
-    /// MemTable
-    /// https://zhuanlan.zhihu.com/p/79064869
-    pub(crate) mem_table: MemTable,
-    /// VersionVec
-    /// 用于管理内部多版本状态
-    pub(crate) ver_status: VersionStatus,
-    /// LSM全局参数配置
-    pub(crate) config: Config,
+    /// 所有的ColumnFamily，以ColumnFamily的id作为索引
+    /// 索引0为默认ColumnFamily
+    pub(crate) families: Vec<Arc<ColumnFamily>>,
+    /// 悲观事务的Key锁
+    pub(crate) lock_manager: LockManager,
+    /// 悲观事务等待锁的超时时间
+    pub(crate) lock_timeout: Duration,
+    /// 活跃事务，用于CommitHistory的记录与裁剪
+    pub(crate) active_txs: Arc<ActiveTransactions>,
+    /// 所有ColumnFamily共享的WAL，读取时通过其生成seq_id
+    pub(crate) wal: Arc<Wal>,
+    /// 打开时WAL恢复的报告
+    pub(crate) recovery_report: RecoveryReport,
 }
 
 impl StoreInner {
     pub(crate) async fn new(config: Config) -> KernelResult<Self> {
-        let mem_table = MemTable::new(&config)?;
-        let ver_status =
-            VersionStatus::load_with_path(config.clone(), mem_table.log_loader_clone())?;
+        let (wal, mut wal_records, recovery_report) = Wal::reload(&config)?;
+        let block_cache = Arc::new(ShardingLruCache::new(
+            config.block_cache_size,
+            16,
+            RandomState::default(),
+        )?);
+        let active_txs = Arc::new(ActiveTransactions::default());
+        let mut families = Vec::new();
+
+        for family_config in ColumnFamily::configs(&config)? {
+            families.push(Arc::new(
+                ColumnFamily::new(
+                    family_config,
+                    &wal,
+                    &active_txs,
+                    &mut wal_records,
+                    &block_cache,
+                )
+                .await?,
+            ));
+        }
+        wal.clean_unretained()?;
 
         Ok(StoreInner {
-            mem_table,
-            ver_status,
-            config,
+            families,
+            lock_manager: LockManager::default(),
+            lock_timeout: config.lock_timeout,
+            active_txs,
+            wal,
+            recovery_report,
         })
     }
+
+    pub(crate) fn default_family(&self) -> &Arc<ColumnFamily> {
+        &self.families[0]
+    }
+
+    /// 交换所有ColumnFamily的MemTable并尝试通知Compactor进行Flush，Compactor繁忙时忽略
+    ///
+    /// 交换后的数据在Immutable MemTable队列中等待Compactor依次Flush，因此写入无需等待Flush完成；
+    /// 队列已满时不进行交换，由Compactor在Flush后再次交换
+    pub(crate) fn flush_try(&self, compactor_tx: &Sender<CompactTask>) -> KernelResult<()> {
+        let mem_tables = self
+            .families
+            .iter()
+            .map(|family| family.mem_table.as_ref())
+            .collect_vec();
+        let _ = MemTable::swap_with_families(&mem_tables)?;
+
+        if let Err(TrySendError::Closed(_)) = compactor_tx.try_send(CompactTask::Flush(None)) {
+            return Err(KernelError::ChannelClose);
+        }
+
+        Ok(())
+    }
+
+    /// 通过名称获取ColumnFamily
+    pub(crate) fn family(&self, name: &str) -> KernelResult<&Arc<ColumnFamily>> {
+        self.families
+            .iter()
+            .find(|family| family.name() == name)
+            .ok_or(KernelError::ColumnFamilyNotFound)
+    }
+
+    /// 结束活跃事务，结束的为最旧的事务时裁剪各ColumnFamily的CommitHistory
+    pub(crate) fn end_transaction(&self, seq_id: i64) {
+        if let Some(threshold) = self.active_txs.end(seq_id) {
+            for family in &self.families {
+                family.mem_table.prune_history(threshold);
+            }
+        }
+    }
+}
+
+/// CAS期间登记的活跃事务，析构时结束，因此CAS被取消时也不会阻止CommitHistory的裁剪
+struct ActiveSeq<'a> {
+    inner: &'a StoreInner,
+    seq_id: i64,
+}
+
+impl Drop for ActiveSeq<'_> {
+    fn drop(&mut self) {
+        self.inner.end_transaction(self.seq_id);
+    }
 }
 
 #[async_trait]
@@ -124,32 +229,35 @@ impl Storage for KipStorage {
 
     #[inline]
     async fn set(&self, key: Bytes, value: Bytes) -> KernelResult<()> {
-        self.append_cmd_data((key, Some(value))).await
+        self.set_with_options(key, value, &WriteOptions::default())
+            .await
     }
 
     #[inline]
     async fn get(&self, key: &[u8]) -> KernelResult<Option<Bytes>> {
-        if let Some((_, value)) = self.mem_table().find(key) {
-            return Ok(value);
-        }
+        self.get_with_family(self.inner.default_family(), key).await
+    }
 
-        let version = self.current_version().await;
-        if let Some((_, value)) = query_and_compaction(key, &version, &self.compactor_tx)? {
-            return Ok(value);
-        }
+    #[inline]
+    async fn remove(&self, key: &[u8]) -> KernelResult<()> {
+        self.remove_with_options(key, &WriteOptions::default())
+            .await
+    }
 
-        Ok(None)
+    #[inline]
+    async fn write(&self, batch: WriteBatch) -> KernelResult<()> {
+        self.write_with_options(batch, &WriteOptions::default())
+            .await
     }
 
     #[inline]
-    async fn remove(&self, key: &[u8]) -> KernelResult<()> {
-        match self.get(key).await? {
-            Some(_) => {
-                self.append_cmd_data((Bytes::copy_from_slice(key), None))
-                    .await
-            }
-            None => Err(KernelError::KeyNotFound),
-        }
+    async fn scan(
+        &self,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+        limit: Option<usize>,
+    ) -> KernelResult<Vec<(Bytes, Bytes)>> {
+        self.iter(min, max).await?.collect_with_limit(limit)
     }
 
     #[inline]
@@ -180,18 +288,211 @@ impl Drop for KipStorage {
 
 impl KipStorage {
     /// 追加数据
-    async fn append_cmd_data(&self, data: KeyValue) -> KernelResult<()> {
-        if self.mem_table().insert_data(data)? {
-            if let Err(TrySendError::Closed(_)) =
-                self.compactor_tx.try_send(CompactTask::Flush(None))
+    async fn append_cmd_data(
+        &self,
+        family: &ColumnFamily,
+        data: KeyValue,
+        meta: ValueMeta,
+        options: &WriteOptions,
+    ) -> KernelResult<()> {
+        if family
+            .mem_table
+            .insert_data_with_meta(data, meta, *options)
+            .await?
+        {
+            self.flush_background_try()?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_with_family(
+        &self,
+        family: &ColumnFamily,
+        key: &[u8],
+    ) -> KernelResult<Option<Bytes>> {
+        let mem_versions = family.mem_table.find_versions(key, None, None);
+        let version = family.current_version().await;
+
+        query_and_compaction(
+            key,
+            mem_versions,
+            family.id(),
+            &version,
+            None,
+            &self.compactor_tx,
+        )
+    }
+
+    /// 将keys排序去重后仅对MemTable加锁一次，并对Version进行批量查询
+    async fn multi_get_with_family(
+        &self,
+        family: &ColumnFamily,
+        keys: &[&[u8]],
+    ) -> KernelResult<Vec<Option<Bytes>>> {
+        let comparator = family.config.comparator.as_ref();
+        let sorted_keys = keys
+            .iter()
+            .copied()
+            .sorted_by(|a, b| comparator.compare(a, b))
+            .dedup()
+            .collect_vec();
+        let mem_versions = family.mem_table.multi_find_versions(&sorted_keys, None);
+        let version = family.current_version().await;
+        let values = multi_query_and_compaction(
+            &sorted_keys,
+            mem_versions,
+            family.id(),
+            &version,
+            None,
+            &self.compactor_tx,
+        )?;
+
+        Ok(keys
+            .iter()
+            .map(|key| {
+                sorted_keys
+                    .binary_search_by(|sorted_key| comparator.compare(sorted_key, key))
+                    .ok()
+                    .and_then(|i| values[i].clone())
+            })
+            .collect_vec())
+    }
+
+    async fn merge_with_family(
+        &self,
+        family: &ColumnFamily,
+        key: Bytes,
+        operand: Bytes,
+    ) -> KernelResult<()> {
+        if family.config.merge_operator.is_none() {
+            return Err(KernelError::MergeOperatorNotFound);
+        }
+
+        self.append_cmd_data(
+            family,
+            (key, Some(merge_operator::encode_operand(&operand)?)),
+            ValueMeta::merge(),
+            &WriteOptions::default(),
+        )
+        .await
+    }
+
+    async fn remove_with_family(
+        &self,
+        family: &ColumnFamily,
+        key: &[u8],
+        options: &WriteOptions,
+    ) -> KernelResult<()> {
+        match self.get_with_family(family, key).await? {
+            Some(_) => {
+                self.append_cmd_data(
+                    family,
+                    (Bytes::copy_from_slice(key), None),
+                    ValueMeta::default(),
+                    options,
+                )
+                .await
+            }
+            None => Err(KernelError::KeyNotFound),
+        }
+    }
+
+    /// 持有该Key的锁期间读取当前值并比较，一致时再通过组提交写入
+    ///
+    /// 读取前登记为活跃事务，写入时由组提交的Leader在WAL写入锁内检查读取后该Key是否被其他写入修改，
+    /// 被修改时不写入并重新读取比较，因此普通写入与乐观事务同样无法穿插于比较与写入之间
+    async fn compare_and_swap_with_family(
+        &self,
+        family: &ColumnFamily,
+        key: Bytes,
+        expected: Option<&[u8]>,
+        new: Option<Bytes>,
+    ) -> KernelResult<Result<(), Option<Bytes>>> {
+        let _key_lock = self
+            .inner
+            .lock_manager
+            .lock_single((family.id(), key.clone()), self.inner.lock_timeout)
+            .await?;
+
+        loop {
+            let active_seq = ActiveSeq {
+                seq_id: self
+                    .inner
+                    .wal
+                    .read_seq(|| self.inner.active_txs.begin())
+                    .await,
+                inner: &self.inner,
+            };
+            let current = self.get_with_family(family, &key).await?;
+
+            if current.as_deref() != expected {
+                return Ok(Err(current));
+            }
+            if current.is_none() && new.is_none() {
+                return Ok(Ok(()));
+            }
+            match MemTable::insert_batch_with_condition(
+                vec![(
+                    Arc::clone(&family.mem_table),
+                    vec![(key.clone(), new.clone())],
+                )],
+                WriteOptions::default(),
+                WriteCondition::new(active_seq.seq_id),
+            )
+            .await
             {
-                return Err(KernelError::ChannelClose);
+                Ok(is_exceeded) => {
+                    if is_exceeded {
+                        self.flush_background_try()?;
+                    }
+                    return Ok(Ok(()));
+                }
+                // 读取后该Key已被修改，重新读取并比较
+                Err(KernelError::RepeatedWrite) => continue,
+                Err(err) => return Err(err),
             }
         }
+    }
+
+    async fn delete_range_with_family(
+        &self,
+        family: &ColumnFamily,
+        start: Bytes,
+        end: Bytes,
+    ) -> KernelResult<()> {
+        if family.config.comparator.compare(&start, &end).is_ge() {
+            return Ok(());
+        }
+        if family.mem_table.insert_range_tombstone(start, end).await? {
+            self.flush_background_try()?;
+        }
 
         Ok(())
     }
 
+    async fn iter_with_family(
+        &self,
+        family: &ColumnFamily,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+    ) -> KernelResult<StorageIter> {
+        let seq_id = self.inner.wal.read_seq(Sequence::create).await;
+        // 先读取MemTable再获取Version，避免期间发生的Minor Compaction导致数据丢失
+        let mem_buf = family
+            .mem_table
+            .range_versions(min, max, Some(seq_id), None);
+        let mem_tombstones = family.mem_table.range_tombstones(Some(seq_id), None);
+        let version = family.current_version().await;
+
+        StorageIter::new(version, mem_buf, mem_tombstones, seq_id, (min, max))
+    }
+
+    /// 尝试通知Compactor进行Flush，详见`StoreInner::flush_try`
+    fn flush_background_try(&self) -> KernelResult<()> {
+        self.inner.flush_try(&self.compactor_tx)
+    }
+
     /// 使用Config进行LsmStore初始化
     #[inline]
     pub async fn open_with_config(config: Config) -> KernelResult<Self>
@@ -204,13 +505,52 @@ impl KipStorage {
         fs::create_dir_all(&config.dir_path)?;
         let lock_file = lock_or_time_out(&config.path().join(DEFAULT_LOCK_FILE)).await?;
         let inner = Arc::new(StoreInner::new(config.clone()).await?);
-        let mut compactor = Compactor::new(Arc::clone(&inner));
+        for dropped in inner.recovery_report.dropped() {
+            warn!(
+                "[WAL][recovery][dropped]: gen: {}, offset: {}, reason: {:?}",
+                dropped.gen, dropped.offset, dropped.reason
+            );
+        }
+        let compactors = inner
+            .families
+            .iter()
+            .map(|family| Compactor::new(Arc::clone(family)))
+            .collect_vec();
         let (task_tx, mut task_rx) = channel(1);
+        // 通过WAL恢复的Immutable MemTable需要Flush
+        if inner
+            .families
+            .iter()
+            .any(|family| family.mem_table.immut_count() > 0)
+        {
+            let _ = task_tx.try_send(CompactTask::Flush(None));
+        }
+
+        if let WalSyncPolicy::Interval(interval) = config.wal_sync_policy {
+            let wal = Arc::downgrade(&inner.wal);
+            let mut ticker = tokio::time::interval(interval.max(Duration::from_millis(1)));
+
+            let _ignore = tokio::spawn(async move {
+                loop {
+                    let _ = ticker.tick().await;
+                    // KipStorage关闭后结束
+                    let Some(wal) = wal.upgrade() else {
+                        break;
+                    };
+                    if let Err(err) = wal.sync() {
+                        error!("[WAL][sync][error happen]: {:?}", err);
+                    }
+                }
+            });
+        }
 
         let _ignore = tokio::spawn(async move {
             while let Some(task) = task_rx.recv().await {
                 match task {
-                    CompactTask::Seek((scope, level)) => {
+                    CompactTask::Seek(family_id, (scope, level)) => {
+                        let Some(compactor) = compactors.get(family_id) else {
+                            continue;
+                        };
                         if let Err(err) =
                             compactor.major_compaction(level, scope, vec![], true).await
                         {
@@ -218,7 +558,9 @@ impl KipStorage {
                         }
                     }
                     CompactTask::Flush(option_tx) => {
-                        if let Err(err) = compactor.check_then_compaction(option_tx).await {
+                        if let Err(err) =
+                            Compactor::check_then_compaction(&compactors, option_tx).await
+                        {
                             error!("[Compactor][compaction][error happen]: {:?}", err);
                         }
                     }
@@ -233,12 +575,288 @@ impl KipStorage {
         })
     }
 
+    /// 打开时WAL恢复的报告，列出了恢复期间因损坏而被丢弃的数据
+    #[inline]
+    pub fn recovery_report(&self) -> &RecoveryReport {
+        &self.inner.recovery_report
+    }
+
     pub(crate) fn mem_table(&self) -> &MemTable {
-        &self.inner.mem_table
+        &self.inner.default_family().mem_table
     }
 
     pub(crate) async fn current_version(&self) -> Arc<Version> {
-        self.inner.ver_status.current().await
+        self.inner.default_family().current_version().await
+    }
+
+    /// 范围迭代器
+    ///
+    /// 读取创建时最新的数据，与`Transaction::iter`不同的是不会读取事务的写缓存
+    #[inline]
+    pub async fn iter(&self, min: Bound<&[u8]>, max: Bound<&[u8]>) -> KernelResult<StorageIter> {
+        self.iter_with_family(self.inner.default_family(), min, max)
+            .await
+    }
+
+    /// 在指定的ColumnFamily中设置键值对
+    #[inline]
+    pub async fn set_cf(&self, cf: &str, key: Bytes, value: Bytes) -> KernelResult<()> {
+        self.append_cmd_data(
+            self.inner.family(cf)?,
+            (key, Some(value)),
+            ValueMeta::default(),
+            &WriteOptions::default(),
+        )
+        .await
+    }
+
+    /// 设置附带存活时间的键值对
+    ///
+    /// 过期后的数据对读取立即不可见，并在Major压缩时被清除
+    #[inline]
+    pub async fn set_with_ttl(&self, key: Bytes, value: Bytes, ttl: Duration) -> KernelResult<()> {
+        let expire_at = now_millis().saturating_add(ttl.as_millis() as i64);
+
+        self.append_cmd_data(
+            self.inner.default_family(),
+            (key, Some(value)),
+            ValueMeta::with_expire(Some(expire_at)),
+            &WriteOptions::default(),
+        )
+        .await
+    }
+
+    /// 写入Merge操作数
+    ///
+    /// 操作数会被追加存储，在读取以及压缩时才通过Config中的MergeOperator与更旧的数据合并，
+    /// 因此无需先读后写，也不会因并发写入而冲突
+    #[inline]
+    pub async fn merge(&self, key: Bytes, operand: Bytes) -> KernelResult<()> {
+        self.merge_with_family(self.inner.default_family(), key, operand)
+            .await
+    }
+
+    /// 在指定的ColumnFamily中写入Merge操作数
+    #[inline]
+    pub async fn merge_cf(&self, cf: &str, key: Bytes, operand: Bytes) -> KernelResult<()> {
+        self.merge_with_family(self.inner.family(cf)?, key, operand)
+            .await
+    }
+
+    /// 删除[start, end)范围内的所有键值对
+    ///
+    /// 仅写入单个范围删除标记，被覆盖的数据在Major压缩时被清除，start不小于end时不进行任何操作
+    #[inline]
+    pub async fn delete_range(&self, start: Bytes, end: Bytes) -> KernelResult<()> {
+        self.delete_range_with_family(self.inner.default_family(), start, end)
+            .await
+    }
+
+    /// 删除指定的ColumnFamily中[start, end)范围内的所有键值对
+    #[inline]
+    pub async fn delete_range_cf(&self, cf: &str, start: Bytes, end: Bytes) -> KernelResult<()> {
+        self.delete_range_with_family(self.inner.family(cf)?, start, end)
+            .await
+    }
+
+    /// 当Key的当前值与expected一致时将其替换为new，None分别表示Key不存在以及删除该Key
+    ///
+    /// 比较与写入期间持有该Key的锁，因此与其他CAS及悲观事务对该Key的写入互斥，且无需开启事务；
+    /// 普通写入与乐观事务的提交穿插于比较与写入之间时重新比较，不会被覆盖；
+    /// 不一致时通过Err返回当前值，等待锁超时时返回`KernelError::LockTimeout`
+    #[inline]
+    pub async fn compare_and_swap(
+        &self,
+        key: Bytes,
+        expected: Option<Bytes>,
+        new: Option<Bytes>,
+    ) -> KernelResult<Result<(), Option<Bytes>>> {
+        self.compare_and_swap_with_family(
+            self.inner.default_family(),
+            key,
+            expected.as_deref(),
+            new,
+        )
+        .await
+    }
+
+    /// 仅当Key不存在时设置键值对，已存在时通过Err返回当前值
+    #[inline]
+    pub async fn put_if_absent(
+        &self,
+        key: Bytes,
+        value: Bytes,
+    ) -> KernelResult<Result<(), Option<Bytes>>> {
+        self.compare_and_swap_with_family(self.inner.default_family(), key, None, Some(value))
+            .await
+    }
+
+    /// 批量获取一组Key对应的Value，结果与keys一一对应
+    ///
+    /// 相较于逐个调用`get`，MemTable仅加锁一次，且处于同一Table内的Key共享Table与Block的加载
+    #[inline]
+    pub async fn multi_get(&self, keys: &[&[u8]]) -> KernelResult<Vec<Option<Bytes>>> {
+        self.multi_get_with_family(self.inner.default_family(), keys)
+            .await
+    }
+
+    /// 批量获取指定的ColumnFamily中一组Key对应的Value
+    #[inline]
+    pub async fn multi_get_cf(&self, cf: &str, keys: &[&[u8]]) -> KernelResult<Vec<Option<Bytes>>> {
+        self.multi_get_with_family(self.inner.family(cf)?, keys)
+            .await
+    }
+
+    /// 获取指定的ColumnFamily中Key对应的Value
+    #[inline]
+    pub async fn get_cf(&self, cf: &str, key: &[u8]) -> KernelResult<Option<Bytes>> {
+        self.get_with_family(self.inner.family(cf)?, key).await
+    }
+
+    /// 删除指定的ColumnFamily中的键值对
+    #[inline]
+    pub async fn remove_cf(&self, cf: &str, key: &[u8]) -> KernelResult<()> {
+        self.remove_with_family(self.inner.family(cf)?, key, &WriteOptions::default())
+            .await
+    }
+
+    /// 范围扫描指定的ColumnFamily，limit为None时不限制数量
+    #[inline]
+    pub async fn scan_cf(
+        &self,
+        cf: &str,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+        limit: Option<usize>,
+    ) -> KernelResult<Vec<(Bytes, Bytes)>> {
+        self.iter_cf(cf, min, max).await?.collect_with_limit(limit)
+    }
+
+    /// 指定的ColumnFamily的范围迭代器
+    #[inline]
+    pub async fn iter_cf(
+        &self,
+        cf: &str,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+    ) -> KernelResult<StorageIter> {
+        self.iter_with_family(self.inner.family(cf)?, min, max)
+            .await
+    }
+
+    /// 所有ColumnFamily的名称
+    #[inline]
+    pub fn column_families(&self) -> Vec<&str> {
+        self.inner
+            .families
+            .iter()
+            .map(|family| family.name())
+            .collect_vec()
+    }
+
+    /// 创建只读快照
+    ///
+    /// 快照固定于创建时的seq_id与Version，可廉价地Clone并传递给其他读取者
+    #[inline]
+    pub async fn snapshot(&self) -> Snapshot {
+        Snapshot::new(self).await
+    }
+
+    /// 通过WriteOptions设置键值对
+    #[inline]
+    pub async fn set_with_options(
+        &self,
+        key: Bytes,
+        value: Bytes,
+        options: &WriteOptions,
+    ) -> KernelResult<()> {
+        self.append_cmd_data(
+            self.inner.default_family(),
+            (key, Some(value)),
+            ValueMeta::default(),
+            options,
+        )
+        .await
+    }
+
+    /// 通过WriteOptions删除键值对
+    #[inline]
+    pub async fn remove_with_options(
+        &self,
+        key: &[u8],
+        options: &WriteOptions,
+    ) -> KernelResult<()> {
+        self.remove_with_family(self.inner.default_family(), key, options)
+            .await
+    }
+
+    /// 通过WriteOptions写入WriteBatch
+    #[inline]
+    pub async fn write_with_options(
+        &self,
+        batch: WriteBatch,
+        options: &WriteOptions,
+    ) -> KernelResult<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let (data, cf_data) = batch.into_parts();
+        let mut batches = Vec::with_capacity(cf_data.len() + 1);
+
+        if !data.is_empty() {
+            batches.push((Arc::clone(&self.inner.default_family().mem_table), data));
+        }
+        for (name, data) in cf_data {
+            batches.push((Arc::clone(&self.inner.family(&name)?.mem_table), data));
+        }
+        // 整个Batch共享同一个Sequence id并作为单条WAL记录写入，以此保证跨ColumnFamily的原子性
+        if MemTable::insert_batch_with_families(batches, *options).await? {
+            self.flush_background_try()?;
+        }
+
+        Ok(())
+    }
+
+    /// 通过ReadOptions获取Key对应的Value
+    #[inline]
+    pub async fn get_with_options(
+        &self,
+        key: &[u8],
+        options: &ReadOptions<'_>,
+    ) -> KernelResult<Option<Bytes>> {
+        match options.snapshot {
+            Some(snapshot) => snapshot.get(key),
+            None => self.get(key).await,
+        }
+    }
+
+    /// 通过ReadOptions进行范围扫描
+    #[inline]
+    pub async fn scan_with_options(
+        &self,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+        limit: Option<usize>,
+        options: &ReadOptions<'_>,
+    ) -> KernelResult<Vec<(Bytes, Bytes)>> {
+        match options.snapshot {
+            Some(snapshot) => snapshot.scan(min, max, limit),
+            None => self.scan(min, max, limit).await,
+        }
+    }
+
+    /// 通过ReadOptions创建范围迭代器
+    #[inline]
+    pub async fn iter_with_options<'a>(
+        &'a self,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+        options: &ReadOptions<'a>,
+    ) -> KernelResult<StorageIter> {
+        match options.snapshot {
+            Some(snapshot) => snapshot.iter(min, max),
+            None => self.iter(min, max).await,
+        }
     }
 
     /// 创建事务
@@ -254,9 +872,14 @@ impl KipStorage {
         max: Bytes,
         level: usize,
     ) -> KernelResult<()> {
-        if min <= max {
+        let family = self.inner.default_family();
+
+        if family.config.comparator.compare(&min, &max).is_le() {
             self.compactor_tx
-                .send(CompactTask::Seek((Scope::from_range(0, min, max), level)))
+                .send(CompactTask::Seek(
+                    family.id(),
+                    (Scope::from_range(0, min, max), level),
+                ))
                 .await?;
         }
 
@@ -271,6 +894,232 @@ impl KipStorage {
     }
 }
 
+self_cell!(
+    /// 持有Version及借用其的TransactionIter
+    struct VersionIterCell {
+        owner: Arc<Version>,
+
+        #[not_covariant]
+        dependent: TransactionIter,
+    }
+);
+
+/// KipStorage的范围迭代器
+///
+/// 持有创建时的Version以保证迭代期间所需的SSTable不会被清除，并过滤已删除的数据
+pub struct StorageIter {
+    inner: VersionIterCell,
+}
+
+impl StorageIter {
+    /// 通过MemTable的范围数据与Version构建迭代器，详见`TransactionIter::new`
+    pub(crate) fn new(
+        version: Arc<Version>,
+        mem_buf: Vec<SeqKeyValue>,
+        mem_tombstones: Vec<RangeTombstone>,
+        seq_id: i64,
+        range: (Bound<&[u8]>, Bound<&[u8]>),
+    ) -> KernelResult<Self> {
+        let inner = VersionIterCell::try_new(version, |version| {
+            TransactionIter::new(None, mem_buf, mem_tombstones, version, seq_id, range)
+        })?;
+
+        Ok(StorageIter { inner })
+    }
+
+    /// 迭代并收集至多limit个键值对
+    pub(crate) fn collect_with_limit(
+        mut self,
+        limit: Option<usize>,
+    ) -> KernelResult<Vec<(Bytes, Bytes)>> {
+        let mut items = Vec::new();
+
+        while limit.map_or(true, |limit| items.len() < limit) {
+            match self.try_next()? {
+                Some(item) => items.push(item),
+                None => break,
+            }
+        }
+
+        Ok(items)
+    }
+}
+
+impl<'a> Iter<'a> for StorageIter {
+    type Item = (Bytes, Bytes);
+
+    #[inline]
+    fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
+        self.inner.with_dependent_mut(|_, iter| {
+            while let Some((key, value)) = iter.try_next()? {
+                if let Some(value) = value {
+                    return Ok(Some((key, value)));
+                }
+            }
+
+            Ok(None)
+        })
+    }
+
+    #[inline]
+    fn is_valid(&self) -> bool {
+        self.inner.with_dependent(|_, iter| iter.is_valid())
+    }
+}
+
+impl<'a> ForwardIter<'a> for StorageIter {
+    #[inline]
+    fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
+        self.inner.with_dependent_mut(|_, iter| {
+            while let Some((key, value)) = iter.try_prev()? {
+                if let Some(value) = value {
+                    return Ok(Some((key, value)));
+                }
+            }
+
+            Ok(None)
+        })
+    }
+}
+
+impl<'a> SeekIter<'a> for StorageIter {
+    #[inline]
+    fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()> {
+        self.inner.with_dependent_mut(|_, iter| iter.seek(seek))
+    }
+}
+
+/// 写入参数
+#[derive(Clone, Copy, Default, Debug)]
+pub struct WriteOptions {
+    /// 写入后立即持久化WAL，不受Config中WalSyncPolicy的影响
+    pub(crate) sync: bool,
+    /// 不写入WAL，异常停机时尚未Flush的数据会丢失，此时sync不生效
+    pub(crate) disable_wal: bool,
+}
+
+impl WriteOptions {
+    #[inline]
+    pub fn new() -> Self {
+        WriteOptions::default()
+    }
+
+    #[inline]
+    pub fn sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    #[inline]
+    pub fn disable_wal(mut self, disable_wal: bool) -> Self {
+        self.disable_wal = disable_wal;
+        self
+    }
+}
+
+/// WAL的持久化策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalSyncPolicy {
+    /// 每次写入后均持久化，并发的写入通过组提交共享同一次持久化
+    EveryWrite,
+    /// 后台每隔该间隔持久化一次，异常停机时至多丢失该间隔内的写入
+    Interval(Duration),
+    /// 不主动持久化，由IoType与操作系统决定写回时机
+    Os,
+}
+
+/// WAL恢复时对损坏数据的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalRecoveryMode {
+    /// 仅容忍WAL末尾的损坏，通常由写入期间停机导致；其后仍存在完好的记录时打开失败
+    TolerateCorruptedTail,
+    /// 存在任何损坏时打开失败
+    AbsoluteConsistency,
+    /// 跳过损坏的记录，继续恢复其后的记录
+    SkipCorrupted,
+    /// 恢复至首个损坏处为止，其后的记录以及更新的WAL均不再恢复
+    ///
+    /// 相关的WAL会被复制至WAL目录下的quarantine目录中，避免其中完好的数据被清理
+    PointInTime,
+}
+
+/// WAL中数据损坏的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorruptionReason {
+    /// 校验码不一致
+    CrcMismatch,
+    /// 未知的记录类型
+    UnknownRecordType(u8),
+    /// 记录长度超出所在的Block
+    BadLength,
+    /// 文件末尾的记录不完整
+    Truncated,
+    /// 分段记录缺失首段或尾段
+    BrokenFragment,
+    /// 记录内容无法解码
+    Undecodable,
+    /// 位于PointInTime模式下的首个损坏之后
+    AfterCorruption,
+}
+
+/// WAL恢复时被丢弃的数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DroppedRecord {
+    /// 所在WAL的gen
+    pub gen: i64,
+    /// 在WAL文件中的偏移量
+    pub offset: u64,
+    pub reason: CorruptionReason,
+}
+
+/// WAL恢复报告
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    pub(crate) dropped: Vec<DroppedRecord>,
+}
+
+impl RecoveryReport {
+    /// 恢复期间被丢弃的数据，以WAL gen与偏移量由旧至新排列
+    #[inline]
+    pub fn dropped(&self) -> &[DroppedRecord] {
+        &self.dropped
+    }
+
+    /// 是否未丢弃任何数据
+    #[inline]
+    pub fn is_clean(&self) -> bool {
+        self.dropped.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, gen: i64, offset: u64, reason: CorruptionReason) {
+        self.dropped.push(DroppedRecord {
+            gen,
+            offset,
+            reason,
+        });
+    }
+}
+
+/// 读取参数
+#[derive(Clone, Copy, Default)]
+pub struct ReadOptions<'a> {
+    /// 指定读取的快照，为None时读取最新的数据
+    pub(crate) snapshot: Option<&'a Snapshot>,
+}
+
+impl<'a> ReadOptions<'a> {
+    #[inline]
+    pub fn new() -> Self {
+        ReadOptions { snapshot: None }
+    }
+
+    #[inline]
+    pub fn snapshot(mut self, snapshot: &'a Snapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     /// 数据目录地址
@@ -278,6 +1127,10 @@ pub struct Config {
     /// 各层级对应Table类型
     /// Tips: SkipTable仅可使用于Level 0之中，否则会因为Level 0外不支持WAL恢复而导致停机后丢失数据
     pub(crate) level_table_type: [TableType; MAX_LEVEL],
+    /// 各层级SSTable中DataBlock的压缩方式，默认均为LZ4
+    pub(crate) level_compress_type: [CompressType; MAX_LEVEL],
+    /// 压缩后的大小超过原大小的该比例时放弃压缩，直接存储原数据
+    pub(crate) max_compression_ratio: f64,
     /// WAL数量阈值
     pub(crate) wal_threshold: usize,
     /// SSTable文件大小
@@ -299,6 +1152,10 @@ pub struct Config {
     /// 直写: Direct
     /// 异步: Buf、Mmap
     pub(crate) wal_io_type: IoType,
+    /// WAL的持久化策略，可通过WriteOptions对单次写入进行持久化
+    pub(crate) wal_sync_policy: WalSyncPolicy,
+    /// WAL恢复时对损坏数据的处理方式
+    pub(crate) wal_recovery_mode: WalRecoveryMode,
     /// 每个Block之间的大小, 单位为B
     pub(crate) block_size: usize,
     /// DataBloc的前缀压缩Restart间隔
@@ -307,6 +1164,31 @@ pub struct Config {
     pub(crate) index_restart_interval: usize,
     /// VersionLog触发快照化的运行时计量阈值
     pub(crate) ver_log_snapshot_threshold: usize,
+    /// 所属ColumnFamily的id
+    /// 由KipStorage打开时分配，默认ColumnFamily为0
+    pub(crate) family_id: usize,
+    /// 所属ColumnFamily的名称
+    pub(crate) family_name: String,
+    /// 额外的ColumnFamily及其配置
+    pub(crate) column_families: Vec<(String, Config)>,
+    /// Major压缩时对数据进行过滤的CompactionFilter
+    pub(crate) compaction_filter: Option<Arc<dyn CompactionFilter>>,
+    /// 读取与压缩时用于合并Merge操作数的MergeOperator
+    pub(crate) merge_operator: Option<Arc<dyn MergeOperator>>,
+    /// Key的排序规则
+    pub(crate) comparator: Arc<dyn Comparator>,
+    /// 键值分离的Value大小阈值，Major压缩时不小于该值的Value会被写入Blob文件
+    /// None时不启用键值分离
+    pub(crate) value_log_threshold: Option<usize>,
+    /// Blob文件大小
+    pub(crate) value_log_file_size: usize,
+    /// Blob文件中失效数据占比超过该值时触发GC
+    pub(crate) value_log_gc_ratio: f64,
+    /// 悲观事务等待锁的超时时间
+    pub(crate) lock_timeout: Duration,
+    /// 每个ColumnFamily中等待Flush的Immutable MemTable的数量上限
+    /// 达到上限后MemTable不再交换，直至Compactor将最旧的Immutable MemTable持久化
+    pub(crate) max_immut_count: usize,
 }
 
 impl Config {
@@ -315,6 +1197,8 @@ impl Config {
         Config {
             dir_path: path.into(),
             level_table_type: [TableType::SortedString; MAX_LEVEL],
+            level_compress_type: [CompressType::LZ4; MAX_LEVEL],
+            max_compression_ratio: block::DEFAULT_MAX_COMPRESSION_RATIO,
             wal_threshold: DEFAULT_WAL_THRESHOLD,
             sst_file_size: DEFAULT_SST_FILE_SIZE,
             minor_trigger_with_threshold: (
@@ -327,10 +1211,23 @@ impl Config {
             block_cache_size: DEFAULT_BLOCK_CACHE_SIZE,
             table_cache_size: DEFAULT_TABLE_CACHE_SIZE,
             wal_io_type: DEFAULT_WAL_IO_TYPE,
+            wal_sync_policy: DEFAULT_WAL_SYNC_POLICY,
+            wal_recovery_mode: DEFAULT_WAL_RECOVERY_MODE,
             block_size: block::DEFAULT_BLOCK_SIZE,
             data_restart_interval: block::DEFAULT_DATA_RESTART_INTERVAL,
             index_restart_interval: block::DEFAULT_INDEX_RESTART_INTERVAL,
             ver_log_snapshot_threshold: version::DEFAULT_VERSION_LOG_THRESHOLD,
+            family_id: 0,
+            family_name: DEFAULT_COLUMN_FAMILY.to_string(),
+            column_families: Vec::new(),
+            compaction_filter: None,
+            merge_operator: None,
+            comparator: Arc::new(BytewiseComparator),
+            value_log_threshold: None,
+            value_log_file_size: value_log::DEFAULT_VALUE_LOG_FILE_SIZE,
+            value_log_gc_ratio: value_log::DEFAULT_VALUE_LOG_GC_RATIO,
+            lock_timeout: lock_manager::DEFAULT_LOCK_TIMEOUT,
+            max_immut_count: DEFAULT_MAX_IMMUT_COUNT,
         }
     }
 
@@ -356,6 +1253,19 @@ impl Config {
         self
     }
 
+    /// 覆盖指定Level的DataBlock压缩方式，未设置的Level沿用默认的LZ4
+    #[inline]
+    pub fn level_compress_type(mut self, level: usize, compress_type: CompressType) -> Self {
+        self.level_compress_type[level] = compress_type;
+        self
+    }
+
+    #[inline]
+    pub fn max_compression_ratio(mut self, max_compression_ratio: f64) -> Self {
+        self.max_compression_ratio = max_compression_ratio;
+        self
+    }
+
     #[inline]
     pub fn minor_trigger_with_threshold(
         mut self,
@@ -426,23 +1336,95 @@ impl Config {
         self
     }
 
+    #[inline]
+    pub fn compaction_filter(mut self, filter: impl CompactionFilter + 'static) -> Self {
+        self.compaction_filter = Some(Arc::new(filter));
+        self
+    }
+
+    #[inline]
+    pub fn merge_operator(mut self, operator: impl MergeOperator + 'static) -> Self {
+        self.merge_operator = Some(Arc::new(operator));
+        self
+    }
+
+    /// 数据库创建后不可更换Comparator
+    #[inline]
+    pub fn comparator(mut self, comparator: impl Comparator + 'static) -> Self {
+        self.comparator = Arc::new(comparator);
+        self
+    }
+
     #[inline]
     pub fn wal_io_type(mut self, wal_io_type: IoType) -> Self {
         self.wal_io_type = wal_io_type;
         self
     }
 
+    #[inline]
+    pub fn wal_sync_policy(mut self, wal_sync_policy: WalSyncPolicy) -> Self {
+        self.wal_sync_policy = wal_sync_policy;
+        self
+    }
+
+    #[inline]
+    pub fn wal_recovery_mode(mut self, wal_recovery_mode: WalRecoveryMode) -> Self {
+        self.wal_recovery_mode = wal_recovery_mode;
+        self
+    }
+
     #[inline]
     pub fn ver_log_snapshot_threshold(mut self, ver_log_snapshot_threshold: usize) -> Self {
         self.ver_log_snapshot_threshold = ver_log_snapshot_threshold;
         self
     }
+
+    /// 启用键值分离，大小不小于threshold的Value会在Major压缩时被分离至Blob文件中
+    #[inline]
+    pub fn value_log_threshold(mut self, threshold: usize) -> Self {
+        self.value_log_threshold = Some(threshold);
+        self
+    }
+
+    #[inline]
+    pub fn value_log_file_size(mut self, value_log_file_size: usize) -> Self {
+        self.value_log_file_size = value_log_file_size;
+        self
+    }
+
+    #[inline]
+    pub fn value_log_gc_ratio(mut self, value_log_gc_ratio: f64) -> Self {
+        self.value_log_gc_ratio = value_log_gc_ratio;
+        self
+    }
+
+    #[inline]
+    pub fn lock_timeout(mut self, lock_timeout: Duration) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    #[inline]
+    pub fn max_immut_count(mut self, max_immut_count: usize) -> Self {
+        self.max_immut_count = max_immut_count.max(1);
+        self
+    }
+
+    /// 添加ColumnFamily及其配置
+    ///
+    /// Tips: ColumnFamily的数据目录固定位于`column_family/{name}`下，
+    /// 且共享当前Config的WAL与BlockCache，因此其中的dir_path、WAL与BlockCache相关的配置不会生效
+    #[inline]
+    pub fn column_family(mut self, name: impl Into<String>, config: Config) -> Self {
+        self.column_families.push((name.into(), config));
+        self
+    }
 }
 
 /// 插入时Sequence id生成器
 ///
 /// 与`Gen`比较大的不同在于
-/// - `Sequence`随着每次重启都会重置为0，而seq上限很高，可以在此次运行时生成有序且不相同的id
+/// - `Sequence`会在重启时通过WAL与Version中记录的last_sequence进行恢复，而seq上限很高，可以生成有序且不相同的id
 /// - `Gen`以时间戳为基础，每次保证每次重启都保证时间有序，但不足以作为Seq的生成，因为上限较低
 pub(crate) struct Sequence {}
 
@@ -452,6 +1434,11 @@ impl Sequence {
     pub(crate) fn create() -> i64 {
         SEQ_COUNT.fetch_add(1, Ordering::Relaxed)
     }
+
+    /// 使后续生成的seq_id大于已持久化的seq_id
+    pub(crate) fn advance_to(seq_id: i64) {
+        let _ = SEQ_COUNT.fetch_max(seq_id + 1, Ordering::Relaxed);
+    }
 }
 
 impl Gen {
@@ -469,9 +1456,30 @@ impl Gen {
 
 #[cfg(test)]
 mod tests {
-    use crate::kernel::lsm::storage::{Gen, Sequence};
+    use crate::kernel::io::FileExtension;
+    use crate::kernel::lsm::comparator::Comparator;
+    use crate::kernel::lsm::iterator::Iter;
+    use crate::kernel::lsm::log::QUARANTINE_PATH;
+    use crate::kernel::lsm::mem_table::{MemTable, ValueMeta, DEFAULT_WAL_PATH};
+    use crate::kernel::lsm::merge_operator::{MergeOperands, MergeOperator};
+    use crate::kernel::lsm::mvcc::CheckType;
+    use crate::kernel::lsm::storage::{
+        Config, CorruptionReason, Gen, KipStorage, Sequence, WalRecoveryMode, WalSyncPolicy,
+        WriteOptions,
+    };
+    use crate::kernel::lsm::value_log;
+    use crate::kernel::write_batch::WriteBatch;
+    use crate::kernel::{sorted_gen_list, KernelResult, Storage};
+    use crate::KernelError;
+    use bytes::Bytes;
+    use itertools::Itertools;
+    use std::cmp::Ordering as CmpOrdering;
+    use std::collections::Bound;
+    use std::fs;
+    use std::sync::Arc;
     use std::thread::sleep;
     use std::time::Duration;
+    use tempfile::TempDir;
 
     #[test]
     fn test_seq_create() {
@@ -482,6 +1490,978 @@ mod tests {
         assert!(i_1 < i_2);
     }
 
+    #[tokio::test]
+    async fn test_sequence_persisted() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let (key_1, key_2) = (Bytes::from_static(b"key_1"), Bytes::from_static(b"key_2"));
+
+        let kv_store = KipStorage::open_with_config(config.clone()).await?;
+        kv_store
+            .set(key_1.clone(), Bytes::from_static(b"1"))
+            .await?;
+        let read_seq = Sequence::create();
+        kv_store.set(key_2.clone(), Bytes::new()).await?;
+        kv_store.flush().await?;
+
+        let version = kv_store.current_version().await;
+        let last_sequence = version.last_sequence;
+        assert!(last_sequence > read_seq);
+        // Flush后的数据依旧遵循读取时的seq_id
+        let mut operands = MergeOperands::default();
+        assert!(version
+            .query(&key_1, Some(read_seq), &mut operands)?
+            .0
+            .is_some());
+        assert!(version
+            .query(&key_2, Some(read_seq), &mut operands)?
+            .0
+            .is_none());
+        // 空值不会被视为删除
+        assert_eq!(
+            version.query(&key_2, None, &mut operands)?.0,
+            Some((key_2.clone(), Some(Bytes::new())))
+        );
+        drop(version);
+        drop(kv_store);
+
+        let kv_store = KipStorage::open_with_config(config).await?;
+        assert_eq!(
+            kv_store.current_version().await.last_sequence,
+            last_sequence
+        );
+        assert!(Sequence::create() > last_sequence);
+        assert_eq!(kv_store.get(&key_2).await?, Some(Bytes::new()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_column_family() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let (key_1, key_2, key_3) = (
+            Bytes::from_static(b"key_1"),
+            Bytes::from_static(b"key_2"),
+            Bytes::from_static(b"key_3"),
+        );
+
+        let kv_store = KipStorage::open_with_config(
+            config
+                .clone()
+                .column_family("meta", Config::new(temp_dir.path())),
+        )
+        .await?;
+        assert_eq!(kv_store.column_families(), vec!["default", "meta"]);
+        assert!(matches!(
+            kv_store.get_cf("unknown", &key_1).await,
+            Err(KernelError::ColumnFamilyNotFound)
+        ));
+
+        kv_store
+            .set(key_1.clone(), Bytes::from_static(b"1"))
+            .await?;
+        kv_store
+            .set_cf("meta", key_1.clone(), Bytes::from_static(b"meta_1"))
+            .await?;
+        // 不同ColumnFamily的键空间相互隔离
+        assert_eq!(kv_store.get(&key_1).await?, Some(Bytes::from_static(b"1")));
+        assert_eq!(
+            kv_store.get_cf("meta", &key_1).await?,
+            Some(Bytes::from_static(b"meta_1"))
+        );
+        assert_eq!(
+            kv_store.get_cf("default", &key_1).await?,
+            Some(Bytes::from_static(b"1"))
+        );
+
+        let mut batch = WriteBatch::new();
+        batch.put(key_2.clone(), Bytes::from_static(b"2"));
+        batch.put_cf("meta", key_2.clone(), Bytes::from_static(b"meta_2"));
+        batch.remove_cf("meta", &key_1);
+        kv_store.write(batch).await?;
+        assert_eq!(kv_store.get(&key_1).await?, Some(Bytes::from_static(b"1")));
+        assert_eq!(kv_store.get_cf("meta", &key_1).await?, None);
+        assert_eq!(
+            kv_store
+                .scan_cf("meta", Bound::Unbounded, Bound::Unbounded, None)
+                .await?,
+            vec![(key_2.clone(), Bytes::from_static(b"meta_2"))]
+        );
+
+        let mut batch = WriteBatch::new();
+        batch.put_cf("unknown", key_3.clone(), Bytes::new());
+        batch.put(key_3.clone(), Bytes::new());
+        assert!(matches!(
+            kv_store.write(batch).await,
+            Err(KernelError::ColumnFamilyNotFound)
+        ));
+        assert_eq!(kv_store.get(&key_3).await?, None);
+
+        kv_store.flush().await?;
+
+        let mut tx = kv_store.new_transaction(CheckType::Optimistic).await;
+        tx.set(key_3.clone(), Bytes::from_static(b"3")).await?;
+        tx.set_cf("meta", key_3.clone(), Bytes::from_static(b"meta_3"))
+            .await?;
+        assert_eq!(
+            tx.get_cf("meta", &key_3)?,
+            Some(Bytes::from_static(b"meta_3"))
+        );
+        assert_eq!(
+            tx.get_cf("meta", &key_2)?,
+            Some(Bytes::from_static(b"meta_2"))
+        );
+        tx.commit().await?;
+        assert_eq!(kv_store.get(&key_3).await?, Some(Bytes::from_static(b"3")));
+        kv_store.flush().await?;
+        drop(kv_store);
+
+        // 未在Config中指定的ColumnFamily会从数据目录中恢复
+        let kv_store = KipStorage::open_with_config(config).await?;
+        assert_eq!(kv_store.column_families(), vec!["default", "meta"]);
+        assert_eq!(kv_store.get(&key_1).await?, Some(Bytes::from_static(b"1")));
+        assert_eq!(kv_store.get(&key_3).await?, Some(Bytes::from_static(b"3")));
+        assert_eq!(
+            kv_store
+                .scan_cf("meta", Bound::Unbounded, Bound::Unbounded, None)
+                .await?,
+            vec![
+                (key_2.clone(), Bytes::from_static(b"meta_2")),
+                (key_3.clone(), Bytes::from_static(b"meta_3")),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_with_ttl() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let (key_1, key_2) = (Bytes::from_static(b"key_1"), Bytes::from_static(b"key_2"));
+        let value = Bytes::from_static(b"value");
+
+        let kv_store = KipStorage::open_with_config(config.clone()).await?;
+        kv_store.set(key_1.clone(), Bytes::new()).await?;
+        kv_store
+            .set_with_ttl(key_1.clone(), value.clone(), Duration::from_millis(100))
+            .await?;
+        kv_store
+            .set_with_ttl(key_2.clone(), value.clone(), Duration::from_secs(3600))
+            .await?;
+        assert_eq!(kv_store.get(&key_1).await?, Some(value.clone()));
+
+        sleep(Duration::from_millis(200));
+        // 过期后不可见，且不会使被其覆盖的旧数据重新可见
+        assert_eq!(kv_store.get(&key_1).await?, None);
+        assert_eq!(kv_store.get(&key_2).await?, Some(value.clone()));
+        assert_eq!(
+            kv_store
+                .scan(Bound::Unbounded, Bound::Unbounded, None)
+                .await?,
+            vec![(key_2.clone(), value.clone())]
+        );
+
+        kv_store.flush().await?;
+        assert_eq!(kv_store.get(&key_1).await?, None);
+        assert_eq!(kv_store.get(&key_2).await?, Some(value.clone()));
+        drop(kv_store);
+
+        let kv_store = KipStorage::open_with_config(config).await?;
+        assert_eq!(kv_store.get(&key_1).await?, None);
+        assert_eq!(kv_store.get(&key_2).await?, Some(value));
+
+        Ok(())
+    }
+
+    struct AppendOperator;
+
+    impl MergeOperator for AppendOperator {
+        fn name(&self) -> &str {
+            "append"
+        }
+
+        fn full_merge(&self, _key: &[u8], existing: Option<&[u8]>, operands: &[Bytes]) -> Bytes {
+            let mut value = existing.map(<[u8]>::to_vec).unwrap_or_default();
+
+            for operand in operands {
+                value.extend_from_slice(operand);
+            }
+            Bytes::from(value)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_merge() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let (key_1, key_2) = (Bytes::from_static(b"key_1"), Bytes::from_static(b"key_2"));
+
+        // 未配置MergeOperator时无法写入操作数
+        let kv_store = KipStorage::open_with_config(Config::new(temp_dir.path())).await?;
+        assert!(matches!(
+            kv_store
+                .merge(key_1.clone(), Bytes::from_static(b"1"))
+                .await,
+            Err(KernelError::MergeOperatorNotFound)
+        ));
+        drop(kv_store);
+
+        let config = Config::new(temp_dir.path()).merge_operator(AppendOperator);
+        let kv_store = KipStorage::open_with_config(config.clone()).await?;
+        kv_store
+            .set(key_1.clone(), Bytes::from_static(b"0"))
+            .await?;
+        kv_store
+            .merge(key_1.clone(), Bytes::from_static(b"1"))
+            .await?;
+        kv_store
+            .merge(key_2.clone(), Bytes::from_static(b"a"))
+            .await?;
+        assert_eq!(kv_store.get(&key_1).await?, Some(Bytes::from_static(b"01")));
+        assert_eq!(kv_store.get(&key_2).await?, Some(Bytes::from_static(b"a")));
+
+        // 操作数跨越持久化的数据
+        kv_store.flush().await?;
+        kv_store
+            .merge(key_1.clone(), Bytes::from_static(b"2"))
+            .await?;
+        kv_store
+            .merge(key_2.clone(), Bytes::from_static(b"b"))
+            .await?;
+        assert_eq!(
+            kv_store.get(&key_1).await?,
+            Some(Bytes::from_static(b"012"))
+        );
+        assert_eq!(
+            kv_store
+                .scan(Bound::Unbounded, Bound::Unbounded, None)
+                .await?,
+            vec![
+                (key_1.clone(), Bytes::from_static(b"012")),
+                (key_2.clone(), Bytes::from_static(b"ab"))
+            ]
+        );
+
+        // 删除后的操作数不会与被删除的数据合并
+        kv_store.remove(&key_2).await?;
+        kv_store
+            .merge(key_2.clone(), Bytes::from_static(b"c"))
+            .await?;
+        assert_eq!(kv_store.get(&key_2).await?, Some(Bytes::from_static(b"c")));
+
+        kv_store.flush().await?;
+        assert_eq!(
+            kv_store.get(&key_1).await?,
+            Some(Bytes::from_static(b"012"))
+        );
+        assert_eq!(kv_store.get(&key_2).await?, Some(Bytes::from_static(b"c")));
+        drop(kv_store);
+
+        let kv_store = KipStorage::open_with_config(config).await?;
+        kv_store
+            .merge(key_1.clone(), Bytes::from_static(b"3"))
+            .await?;
+        assert_eq!(
+            kv_store.get(&key_1).await?,
+            Some(Bytes::from_static(b"0123"))
+        );
+        assert_eq!(kv_store.get(&key_2).await?, Some(Bytes::from_static(b"c")));
+
+        Ok(())
+    }
+
+    /// 校验`test_delete_range`中范围删除后的数据
+    async fn check_delete_range(kv_store: &KipStorage, keys: &[Bytes]) -> KernelResult<()> {
+        let merged = Bytes::from_static(b"a");
+
+        assert_eq!(kv_store.get(&keys[2]).await?, None);
+        assert_eq!(kv_store.get(&keys[5]).await?, None);
+        // 删除后的操作数不会与被删除的数据合并
+        assert_eq!(kv_store.get(&keys[3]).await?, Some(merged.clone()));
+        assert_eq!(kv_store.get(&keys[6]).await?, Some(keys[6].clone()));
+        assert_eq!(
+            kv_store
+                .scan(Bound::Unbounded, Bound::Unbounded, None)
+                .await?,
+            vec![
+                (keys[0].clone(), keys[0].clone()),
+                (keys[1].clone(), keys[1].clone()),
+                (keys[3].clone(), merged.clone()),
+                (keys[6].clone(), keys[6].clone()),
+                (keys[7].clone(), keys[7].clone()),
+            ]
+        );
+
+        let tx = kv_store.new_transaction(CheckType::Optimistic).await;
+        assert_eq!(tx.get(&keys[4])?, None);
+        assert_eq!(tx.get(&keys[1])?, Some(keys[1].clone()));
+        // 事务迭代器会返回删除标记，被覆盖的数据以删除标记的形式返回
+        let mut iter = tx.iter(Bound::Included(&keys[1]), Bound::Unbounded)?;
+        let mut live_keys = Vec::new();
+        while let Some((key, value)) = iter.try_next()? {
+            if value.is_some() {
+                live_keys.push(key);
+            }
+        }
+        assert_eq!(
+            live_keys,
+            vec![
+                keys[1].clone(),
+                keys[3].clone(),
+                keys[6].clone(),
+                keys[7].clone()
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multi_get() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path())
+            .sst_file_size(4 * 1024)
+            .major_threshold_with_sst_size(2)
+            .merge_operator(AppendOperator);
+        let kv_store = KipStorage::open_with_config(config).await?;
+        let key = |i: usize| Bytes::from(format!("key_{i:04}"));
+
+        // 数据分布于多个Level以及MemTable中
+        for round in 0..3 {
+            for i in (round..600).step_by(3) {
+                kv_store.set(key(i), key(i)).await?;
+            }
+            kv_store.flush().await?;
+        }
+        for i in (0..600).step_by(50) {
+            kv_store.merge(key(i), Bytes::from_static(b"+")).await?;
+        }
+        for i in (1..600).step_by(70) {
+            kv_store.remove(&key(i)).await?;
+        }
+        kv_store.delete_range(key(300), key(320)).await?;
+        kv_store.set(key(305), key(305)).await?;
+
+        let keys = (0..650).rev().chain([7, 7, 42]).map(key).collect_vec();
+        let key_refs = keys.iter().map(|key| &key[..]).collect_vec();
+        let mut expected = Vec::with_capacity(keys.len());
+        for key in &keys {
+            expected.push(kv_store.get(key).await?);
+        }
+        assert_eq!(kv_store.multi_get(&key_refs).await?, expected);
+        assert_eq!(expected[649], Some(Bytes::from_static(b"key_0000+")));
+        assert_eq!(expected[649 - 1], None);
+        assert_eq!(expected[649 - 310], None);
+        assert_eq!(expected[649 - 305], Some(key(305)));
+        assert_eq!(expected[0], None);
+        assert_eq!(kv_store.multi_get(&[]).await?, Vec::<Option<Bytes>>::new());
+
+        kv_store.flush().await?;
+        assert_eq!(kv_store.multi_get(&key_refs).await?, expected);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_range() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path()).merge_operator(AppendOperator);
+        let keys = (0..8)
+            .map(|i| Bytes::from(format!("key_{i}")))
+            .collect_vec();
+
+        let kv_store = KipStorage::open_with_config(config.clone()).await?;
+        for key in &keys[..4] {
+            kv_store.set(key.clone(), key.clone()).await?;
+        }
+        kv_store.flush().await?;
+        for key in &keys[4..] {
+            kv_store.set(key.clone(), key.clone()).await?;
+        }
+        let snapshot = kv_store.snapshot().await;
+
+        // 同时覆盖已持久化与MemTable中的数据
+        kv_store
+            .delete_range(keys[2].clone(), keys[6].clone())
+            .await?;
+        // start不小于end时不进行任何操作
+        kv_store
+            .delete_range(keys[7].clone(), keys[0].clone())
+            .await?;
+        kv_store
+            .merge(keys[3].clone(), Bytes::from_static(b"a"))
+            .await?;
+        check_delete_range(&kv_store, &keys).await?;
+        // 范围删除之前创建的快照不受影响
+        assert_eq!(snapshot.get(&keys[2])?, Some(keys[2].clone()));
+        assert_eq!(snapshot.get(&keys[5])?, Some(keys[5].clone()));
+        drop(snapshot);
+
+        kv_store.flush().await?;
+        check_delete_range(&kv_store, &keys).await?;
+        drop(kv_store);
+
+        let kv_store = KipStorage::open_with_config(config).await?;
+        check_delete_range(&kv_store, &keys).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_range_compaction() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let keys = (0..4)
+            .map(|i| Bytes::from(format!("key_{i}")))
+            .collect_vec();
+
+        let kv_store = KipStorage::open_with_config(config.clone()).await?;
+        kv_store.set(keys[3].clone(), keys[3].clone()).await?;
+        kv_store.flush().await?;
+        for key in &keys[1..3] {
+            kv_store.set(key.clone(), key.clone()).await?;
+        }
+        kv_store.flush().await?;
+        kv_store
+            .delete_range(keys[0].clone(), keys[3].clone())
+            .await?;
+        kv_store.flush().await?;
+        assert_eq!(kv_store.current_version().await.level_len(0), 3);
+
+        // 被完全覆盖的Table无需读取直接删除，范围删除标记则由不持有数据的Table保留至下一层
+        kv_store
+            .manual_compaction(keys[0].clone(), keys[2].clone(), 0)
+            .await?;
+        kv_store.flush().await?;
+        let version = kv_store.current_version().await;
+        assert_eq!(version.level_len(0), 1);
+        assert_eq!(version.level_len(1), 1);
+        assert_eq!(version.len(), 1);
+        assert_eq!(version.range_tombstones().count(), 1);
+        drop(version);
+
+        let expected = vec![(keys[3].clone(), keys[3].clone())];
+        assert_eq!(kv_store.get(&keys[1]).await?, None);
+        assert_eq!(kv_store.get(&keys[3]).await?, Some(keys[3].clone()));
+        assert_eq!(
+            kv_store
+                .scan(Bound::Unbounded, Bound::Unbounded, None)
+                .await?,
+            expected
+        );
+        drop(kv_store);
+
+        let kv_store = KipStorage::open_with_config(config).await?;
+        assert_eq!(kv_store.get(&keys[2]).await?, None);
+        assert_eq!(
+            kv_store
+                .scan(Bound::Unbounded, Bound::Unbounded, None)
+                .await?,
+            expected
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_value_log() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path()).value_log_threshold(16);
+        let blob_path = temp_dir.path().join(value_log::DEFAULT_VALUE_LOG_PATH);
+        let keys = (0..4)
+            .map(|i| Bytes::from(format!("key_{i}")))
+            .collect_vec();
+        let (small_key, small_value) = (Bytes::from_static(b"key_s"), Bytes::from_static(b"v"));
+        let large_value =
+            |i: usize, round: usize| Bytes::from(format!("large_value_{i}_{round:08}"));
+
+        let kv_store = KipStorage::open_with_config(config.clone()).await?;
+        for (i, key) in keys.iter().enumerate() {
+            kv_store.set(key.clone(), large_value(i, 0)).await?;
+        }
+        kv_store.set(small_key.clone(), small_value.clone()).await?;
+        kv_store.flush().await?;
+        kv_store
+            .manual_compaction(keys[0].clone(), small_key.clone(), 0)
+            .await?;
+        kv_store.flush().await?;
+
+        // Major压缩后超出阈值的Value被分离至Blob文件中
+        let blob_gens = sorted_gen_list(&blob_path, FileExtension::Blob)?;
+        assert_eq!(blob_gens.len(), 1);
+        assert_eq!(kv_store.current_version().await.level_len(1), 1);
+        assert_eq!(kv_store.get(&keys[0]).await?, Some(large_value(0, 0)));
+        assert_eq!(kv_store.get(&small_key).await?, Some(small_value.clone()));
+
+        // 覆盖大部分Key使旧Blob文件的失效数据超出阈值，并在下一次Flush时进行GC
+        for (i, key) in keys[..3].iter().enumerate() {
+            kv_store.set(key.clone(), large_value(i, 1)).await?;
+        }
+        kv_store.flush().await?;
+        kv_store
+            .manual_compaction(keys[0].clone(), small_key.clone(), 0)
+            .await?;
+        kv_store.flush().await?;
+
+        let version = kv_store.current_version().await;
+        assert!(version.blob_meta(blob_gens[0]).is_none());
+        assert_eq!(version.blob_gc_candidate(0.5), None);
+        drop(version);
+
+        let expected = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key.clone(), large_value(i, if i < 3 { 1 } else { 0 })))
+            .chain([(small_key.clone(), small_value.clone())])
+            .collect_vec();
+        let check = |kv_store: KipStorage, expected: Vec<(Bytes, Bytes)>| async move {
+            for (key, value) in &expected {
+                assert_eq!(kv_store.get(key).await?, Some(value.clone()));
+            }
+            assert_eq!(
+                kv_store
+                    .scan(Bound::Unbounded, Bound::Unbounded, None)
+                    .await?,
+                expected
+            );
+            let keys = expected.iter().map(|(key, _)| key.as_ref()).collect_vec();
+            assert_eq!(
+                kv_store.multi_get(&keys).await?,
+                expected
+                    .iter()
+                    .map(|(_, value)| Some(value.clone()))
+                    .collect_vec()
+            );
+
+            KernelResult::Ok(())
+        };
+        check(kv_store, expected.clone()).await?;
+
+        // 重启后通过VersionLog恢复Blob文件的统计信息
+        let kv_store = KipStorage::open_with_config(config).await?;
+        assert!(kv_store
+            .current_version()
+            .await
+            .blob_meta(blob_gens[0])
+            .is_none());
+        check(kv_store, expected).await?;
+
+        Ok(())
+    }
+
+    struct ReverseComparator;
+
+    impl Comparator for ReverseComparator {
+        fn name(&self) -> &str {
+            "reverse"
+        }
+
+        fn compare(&self, a: &[u8], b: &[u8]) -> CmpOrdering {
+            b.cmp(a)
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_compare_and_swap() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let kv_store = Arc::new(KipStorage::open(temp_dir.path()).await?);
+        let (key, value_1, value_2) = (
+            Bytes::from_static(b"k"),
+            Bytes::from_static(b"1"),
+            Bytes::from_static(b"2"),
+        );
+
+        assert_eq!(
+            kv_store.put_if_absent(key.clone(), value_1.clone()).await?,
+            Ok(())
+        );
+        assert_eq!(
+            kv_store.put_if_absent(key.clone(), value_2.clone()).await?,
+            Err(Some(value_1.clone()))
+        );
+        assert_eq!(
+            kv_store
+                .compare_and_swap(key.clone(), Some(value_2.clone()), None)
+                .await?,
+            Err(Some(value_1.clone()))
+        );
+        // 当前值位于SSTable中时同样能够进行比较
+        kv_store.flush().await?;
+        assert_eq!(
+            kv_store
+                .compare_and_swap(key.clone(), Some(value_1.clone()), Some(value_2.clone()))
+                .await?,
+            Ok(())
+        );
+        assert_eq!(kv_store.get(&key).await?, Some(value_2.clone()));
+        assert_eq!(
+            kv_store
+                .compare_and_swap(key.clone(), Some(value_2.clone()), None)
+                .await?,
+            Ok(())
+        );
+        assert_eq!(kv_store.get(&key).await?, None);
+        assert_eq!(
+            kv_store.compare_and_swap(key.clone(), None, None).await?,
+            Ok(())
+        );
+
+        // 并发进行自增时不会丢失更新
+        let counter = Bytes::from_static(b"counter");
+        let tasks = (0..8)
+            .map(|_| {
+                let kv_store = Arc::clone(&kv_store);
+                let counter = counter.clone();
+
+                tokio::spawn(async move {
+                    for _ in 0..50 {
+                        let mut current = kv_store.get(&counter).await?;
+                        loop {
+                            let next = current.as_ref().map_or(0, |bytes| {
+                                u32::from_be_bytes(bytes[..].try_into().unwrap())
+                            }) + 1;
+                            match kv_store
+                                .compare_and_swap(
+                                    counter.clone(),
+                                    current,
+                                    Some(Bytes::from(next.to_be_bytes().to_vec())),
+                                )
+                                .await?
+                            {
+                                Ok(()) => break,
+                                Err(actual) => current = actual,
+                            }
+                        }
+                    }
+                    Ok::<_, KernelError>(())
+                })
+            })
+            .collect_vec();
+        for task in tasks {
+            task.await.unwrap()?;
+        }
+        assert_eq!(
+            kv_store.get(&counter).await?,
+            Some(Bytes::from(400u32.to_be_bytes().to_vec()))
+        );
+        kv_store.flush().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compare_and_swap_key_lock() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path()).lock_timeout(Duration::from_millis(50));
+        let kv_store = KipStorage::open_with_config(config).await?;
+        let (key, value_1, value_2) = (
+            Bytes::from_static(b"k"),
+            Bytes::from_static(b"1"),
+            Bytes::from_static(b"2"),
+        );
+
+        // 悲观事务持有Key的锁期间CAS等待其释放
+        let mut tx = kv_store.new_transaction(CheckType::Pessimistic).await;
+        tx.set(key.clone(), value_1.clone()).await?;
+        assert!(matches!(
+            kv_store.put_if_absent(key.clone(), value_2.clone()).await,
+            Err(KernelError::LockTimeout)
+        ));
+        tx.commit().await?;
+
+        // CAS结束后锁即被释放
+        assert_eq!(
+            kv_store.put_if_absent(key.clone(), value_2.clone()).await?,
+            Err(Some(value_1.clone()))
+        );
+        assert_eq!(
+            kv_store
+                .compare_and_swap(key.clone(), Some(value_1), Some(value_2.clone()))
+                .await?,
+            Ok(())
+        );
+        let mut tx = kv_store.new_transaction(CheckType::Pessimistic).await;
+        tx.remove(&key).await?;
+        tx.commit().await?;
+        assert_eq!(kv_store.get(&key).await?, None);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_compare_and_swap_with_plain_set() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let kv_store = Arc::new(KipStorage::open(temp_dir.path()).await?);
+        let wal = Arc::clone(&kv_store.inner.wal);
+        let (key, value_1, value_2, value_3) = (
+            Bytes::from_static(b"k"),
+            Bytes::from_static(b"1"),
+            Bytes::from_static(b"2"),
+            Bytes::from_static(b"3"),
+        );
+        kv_store.set(key.clone(), value_1.clone()).await?;
+
+        // 占用Leader使写入在队列中等待，普通写入于CAS读取前到达，却在CAS读取后才写入
+        wal.hold_leader();
+        let set_task = {
+            let (kv_store, key, value_2) = (Arc::clone(&kv_store), key.clone(), value_2.clone());
+            tokio::spawn(async move { kv_store.set(key, value_2).await })
+        };
+        while wal.queued_len() < 1 {
+            tokio::task::yield_now().await;
+        }
+        let cas_task = {
+            let (kv_store, key, value_1, value_3) = (
+                Arc::clone(&kv_store),
+                key.clone(),
+                value_1.clone(),
+                value_3.clone(),
+            );
+            tokio::spawn(async move {
+                kv_store
+                    .compare_and_swap(key, Some(value_1), Some(value_3))
+                    .await
+            })
+        };
+        while wal.queued_len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        wal.release_leader();
+        set_task.await.unwrap()?;
+
+        // CAS在写入时发现Key已被修改，重新比较后失败而不会覆盖普通写入
+        assert_eq!(cas_task.await.unwrap()?, Err(Some(value_2.clone())));
+        assert_eq!(kv_store.get(&key).await?, Some(value_2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_options() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path())
+            .wal_sync_policy(WalSyncPolicy::Interval(Duration::from_millis(10)));
+        let keys = [b"1", b"2", b"3"].map(|key| Bytes::from_static(key));
+        let sync = WriteOptions::new().sync(true);
+
+        let kv_store = KipStorage::open_with_config(config.clone()).await?;
+        kv_store
+            .set_with_options(keys[0].clone(), keys[0].clone(), &sync)
+            .await?;
+        let mut batch = WriteBatch::new();
+        batch.put(keys[1].clone(), keys[1].clone());
+        kv_store
+            .write_with_options(batch, &WriteOptions::new().disable_wal(true))
+            .await?;
+        let mut tx = kv_store.new_transaction(CheckType::Optimistic).await;
+        tx.set(keys[2].clone(), keys[2].clone()).await?;
+        tx.commit_with_options(&sync).await?;
+
+        for key in &keys {
+            assert_eq!(kv_store.get(key).await?, Some(key.clone()));
+        }
+        kv_store.remove_with_options(&keys[0], &sync).await?;
+        assert_eq!(kv_store.get(&keys[0]).await?, None);
+
+        // 后台定时持久化WAL
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        kv_store.inner.wal.sync()?;
+        drop(kv_store);
+
+        let kv_store = KipStorage::open_with_config(config).await?;
+        assert_eq!(kv_store.get(&keys[0]).await?, None);
+        assert_eq!(kv_store.get(&keys[2]).await?, Some(keys[2].clone()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wal_recovery_mode() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let keys = [b"1", b"2"].map(|key| Bytes::from_static(key));
+
+        let mem_table = Arc::new(MemTable::new(&config)?);
+        for key in &keys {
+            let _ = mem_table
+                .insert_data_with_meta(
+                    (key.clone(), Some(key.clone())),
+                    ValueMeta::default(),
+                    WriteOptions::new().sync(true),
+                )
+                .await?;
+        }
+        drop(mem_table);
+
+        // 截断最后一条WAL记录，模拟写入期间停机
+        let wal_dir = config.path().join(DEFAULT_WAL_PATH);
+        let gen = *sorted_gen_list(&wal_dir, FileExtension::Log)?
+            .last()
+            .unwrap();
+        let wal_file = fs::OpenOptions::new()
+            .write(true)
+            .open(FileExtension::Log.path_with_gen(&wal_dir, gen))?;
+        wal_file.set_len(wal_file.metadata()?.len() - 1)?;
+
+        assert!(matches!(
+            KipStorage::open_with_config(
+                config
+                    .clone()
+                    .wal_recovery_mode(WalRecoveryMode::AbsoluteConsistency)
+            )
+            .await,
+            Err(KernelError::WalCorrupted {
+                reason: CorruptionReason::Truncated,
+                ..
+            })
+        ));
+
+        let kv_store = KipStorage::open_with_config(
+            config.wal_recovery_mode(WalRecoveryMode::TolerateCorruptedTail),
+        )
+        .await?;
+        let dropped = kv_store.recovery_report().dropped();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(
+            (dropped[0].gen, dropped[0].reason),
+            (gen, CorruptionReason::Truncated)
+        );
+        assert_eq!(kv_store.get(&keys[0]).await?, Some(keys[0].clone()));
+        assert_eq!(kv_store.get(&keys[1]).await?, None);
+
+        // 新的写入不会追加于损坏的WAL之后
+        assert!(
+            *sorted_gen_list(&wal_dir, FileExtension::Log)?
+                .last()
+                .unwrap()
+                > gen
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wal_recovery_quarantine() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path());
+        let keys = [b"1", b"2"].map(|key| Bytes::from_static(key));
+        let sync = WriteOptions::new().sync(true);
+
+        // 两个Key分别位于新旧两个WAL中
+        let mem_table = Arc::new(MemTable::new(&config)?);
+        let _ = mem_table
+            .insert_data_with_meta(
+                (keys[0].clone(), Some(keys[0].clone())),
+                ValueMeta::default(),
+                sync,
+            )
+            .await?;
+        assert!(MemTable::swap_with_families(&[&mem_table])?);
+        let _ = mem_table
+            .insert_data_with_meta(
+                (keys[1].clone(), Some(keys[1].clone())),
+                ValueMeta::default(),
+                sync,
+            )
+            .await?;
+        drop(mem_table);
+
+        let wal_dir = config.path().join(DEFAULT_WAL_PATH);
+        let gens = sorted_gen_list(&wal_dir, FileExtension::Log)?;
+        assert_eq!(gens.len(), 2);
+        let new_wal = fs::read(FileExtension::Log.path_with_gen(&wal_dir, gens[1]))?;
+        let old_wal = fs::OpenOptions::new()
+            .write(true)
+            .open(FileExtension::Log.path_with_gen(&wal_dir, gens[0]))?;
+        old_wal.set_len(old_wal.metadata()?.len() - 1)?;
+        drop(old_wal);
+
+        let kv_store =
+            KipStorage::open_with_config(config.wal_recovery_mode(WalRecoveryMode::PointInTime))
+                .await?;
+        let dropped = kv_store.recovery_report().dropped();
+        assert_eq!(
+            dropped
+                .iter()
+                .map(|dropped| (dropped.gen, dropped.reason))
+                .collect_vec(),
+            vec![
+                (gens[0], CorruptionReason::Truncated),
+                (gens[1], CorruptionReason::AfterCorruption)
+            ]
+        );
+        assert_eq!(kv_store.get(&keys[1]).await?, None);
+
+        // 未恢复的WAL被清理，但其副本保留于隔离目录中
+        let quarantine_dir = wal_dir.join(QUARANTINE_PATH);
+        assert!(!FileExtension::Log.path_with_gen(&wal_dir, gens[1]).exists());
+        assert_eq!(
+            sorted_gen_list(&quarantine_dir, FileExtension::Log)?,
+            gens.to_vec()
+        );
+        assert_eq!(
+            fs::read(FileExtension::Log.path_with_gen(&quarantine_dir, gens[1]))?,
+            new_wal
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_comparator() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path()).comparator(ReverseComparator);
+        let keys = [b"1", b"2", b"3", b"4"].map(|key| Bytes::from_static(key));
+
+        let kv_store = KipStorage::open_with_config(config.clone()).await?;
+        for key in &keys[..2] {
+            kv_store.set(key.clone(), key.clone()).await?;
+        }
+        kv_store.flush().await?;
+        for key in &keys[2..] {
+            kv_store.set(key.clone(), key.clone()).await?;
+        }
+
+        let expected = keys
+            .iter()
+            .rev()
+            .map(|key| (key.clone(), key.clone()))
+            .collect_vec();
+        assert_eq!(
+            kv_store
+                .scan(Bound::Unbounded, Bound::Unbounded, None)
+                .await?,
+            expected
+        );
+        // 范围同样遵循Comparator的顺序
+        assert_eq!(
+            kv_store
+                .scan(Bound::Included(&keys[2]), Bound::Included(&keys[1]), None)
+                .await?,
+            expected[1..3].to_vec()
+        );
+        kv_store.flush().await?;
+        drop(kv_store);
+
+        // 无法使用不同的Comparator重新打开
+        assert!(matches!(
+            KipStorage::open_with_config(Config::new(temp_dir.path())).await,
+            Err(KernelError::ComparatorMismatch(name)) if name == "reverse"
+        ));
+
+        let kv_store = KipStorage::open_with_config(config).await?;
+        assert_eq!(
+            kv_store
+                .scan(Bound::Unbounded, Bound::Unbounded, None)
+                .await?,
+            expected
+        );
+        assert_eq!(kv_store.get(&keys[3]).await?, Some(keys[3].clone()));
+
+        Ok(())
+    }
+
     #[test]
     #[ignore]
     fn test_gen_create_1000() {
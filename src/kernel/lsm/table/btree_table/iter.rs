@@ -1,54 +1,87 @@
-use crate::kernel::lsm::iterator::{Iter, Seek, SeekIter};
-use crate::kernel::lsm::mem_table::KeyValue;
+use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
+use crate::kernel::lsm::mem_table::SeqKeyValue;
 use crate::kernel::lsm::table::btree_table::BTreeTable;
+use crate::kernel::KernelResult;
 use bytes::Bytes;
-use std::collections::btree_map::Range;
 use std::collections::Bound;
 
+/// BTreeTable迭代器
+///
+/// 通过记录前后两个方向的边界来表示游标位置，边界为None时表示该方向已无元素
 pub(crate) struct BTreeTableIter<'a> {
-    inner: Option<Range<'a, Bytes, KeyValue>>,
     table: &'a BTreeTable,
+    next_bound: Option<Bound<Bytes>>,
+    prev_bound: Option<Bound<Bytes>>,
 }
 
 impl<'a> BTreeTableIter<'a> {
     pub(crate) fn new(table: &'a BTreeTable) -> BTreeTableIter<'a> {
-        let mut iter = BTreeTableIter { inner: None, table };
+        let mut iter = BTreeTableIter {
+            table,
+            next_bound: None,
+            prev_bound: None,
+        };
         iter._seek(Seek::First);
         iter
     }
 
     fn _seek(&mut self, seek: Seek) {
-        self.inner = match seek {
-            Seek::First => Some(
-                self.table
-                    .inner
-                    .range::<Bytes, (Bound<Bytes>, Bound<Bytes>)>((
-                        Bound::Unbounded,
-                        Bound::Unbounded,
-                    )),
-            ),
-            Seek::Last => None,
-            Seek::Backward(key) => Some(
-                self.table
-                    .inner
-                    .range::<Bytes, (Bound<Bytes>, Bound<Bytes>)>((
-                        Bound::Included(Bytes::copy_from_slice(key)),
-                        Bound::Unbounded,
-                    )),
-            ),
+        let (next_bound, prev_bound) = match seek {
+            Seek::First => (Some(Bound::Unbounded), None),
+            Seek::Last => (None, Some(Bound::Unbounded)),
+            Seek::Backward(key) => {
+                let key = Bytes::copy_from_slice(key);
+                (
+                    Some(Bound::Included(key.clone())),
+                    Some(Bound::Excluded(key)),
+                )
+            }
+            Seek::Forward(key) => {
+                let key = Bytes::copy_from_slice(key);
+                (
+                    Some(Bound::Excluded(key.clone())),
+                    Some(Bound::Included(key)),
+                )
+            }
         };
+        self.next_bound = next_bound;
+        self.prev_bound = prev_bound;
+    }
+
+    fn item_move(&mut self, item: Option<&SeqKeyValue>, is_next: bool) -> Option<SeqKeyValue> {
+        match item {
+            Some(((key, _), ..)) => {
+                self.next_bound = Some(Bound::Excluded(key.clone()));
+                self.prev_bound = Some(Bound::Excluded(key.clone()));
+            }
+            None if is_next => {
+                self.next_bound = None;
+                self.prev_bound = Some(Bound::Unbounded);
+            }
+            None => {
+                self.next_bound = Some(Bound::Unbounded);
+                self.prev_bound = None;
+            }
+        }
+        item.cloned()
     }
 }
 
 impl<'a> Iter<'a> for BTreeTableIter<'a> {
-    type Item = KeyValue;
+    type Item = SeqKeyValue;
 
-    fn try_next(&mut self) -> crate::kernel::KernelResult<Option<Self::Item>> {
-        Ok(self
+    fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
+        let Some(bound) = self.next_bound.clone() else {
+            return Ok(None);
+        };
+        let table = self.table;
+        let item = table
             .inner
-            .as_mut()
-            .and_then(|iter| iter.next())
-            .map(item_clone))
+            .range((bound.map(|key| table.comparable_key(key)), Bound::Unbounded))
+            .next()
+            .map(|(_, item)| item);
+
+        Ok(self.item_move(item, true))
     }
 
     fn is_valid(&self) -> bool {
@@ -56,37 +89,61 @@ impl<'a> Iter<'a> for BTreeTableIter<'a> {
     }
 }
 
+impl<'a> ForwardIter<'a> for BTreeTableIter<'a> {
+    fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
+        let Some(bound) = self.prev_bound.clone() else {
+            return Ok(None);
+        };
+        let table = self.table;
+        let item = table
+            .inner
+            .range((Bound::Unbounded, bound.map(|key| table.comparable_key(key))))
+            .next_back()
+            .map(|(_, item)| item);
+
+        Ok(self.item_move(item, false))
+    }
+}
+
 impl<'a> SeekIter<'a> for BTreeTableIter<'a> {
-    fn seek(&mut self, seek: Seek<'_>) -> crate::kernel::KernelResult<()> {
+    fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()> {
         self._seek(seek);
 
         Ok(())
     }
 }
 
-fn item_clone((_, value): (&Bytes, &KeyValue)) -> KeyValue {
-    value.clone()
-}
-
 #[cfg(test)]
 mod tests {
+    use crate::kernel::lsm::comparator::{BytewiseComparator, Comparator};
     use crate::kernel::lsm::iterator::Seek;
+    use crate::kernel::lsm::mem_table::ValueMeta;
     use crate::kernel::lsm::table::btree_table::BTreeTable;
     use crate::kernel::lsm::table::Table;
     use crate::kernel::KernelResult;
     use bytes::Bytes;
+    use std::sync::Arc;
 
     #[test]
     fn test_iterator() -> KernelResult<()> {
+        let comparator: Arc<dyn Comparator> = Arc::new(BytewiseComparator);
         let vec = vec![
-            (Bytes::from(vec![b'1']), None),
-            (Bytes::from(vec![b'2']), Some(Bytes::from(vec![b'1']))),
-            (Bytes::from(vec![b'3']), None),
-            (Bytes::from(vec![b'4']), None),
-            (Bytes::from(vec![b'5']), Some(Bytes::from(vec![b'2']))),
-            (Bytes::from(vec![b'6']), None),
+            ((Bytes::from(vec![b'1']), None), 1, ValueMeta::default()),
+            (
+                (Bytes::from(vec![b'2']), Some(Bytes::from(vec![b'1']))),
+                2,
+                ValueMeta::default(),
+            ),
+            ((Bytes::from(vec![b'3']), None), 3, ValueMeta::default()),
+            ((Bytes::from(vec![b'4']), None), 4, ValueMeta::default()),
+            (
+                (Bytes::from(vec![b'5']), Some(Bytes::from(vec![b'2']))),
+                5,
+                ValueMeta::default(),
+            ),
+            ((Bytes::from(vec![b'6']), None), 6, ValueMeta::default()),
         ];
-        let table = BTreeTable::new(0, 0, vec.clone());
+        let table = BTreeTable::new(0, 0, vec.clone(), &comparator);
         let mut iter = table.iter()?;
 
         for test_data in vec.clone() {
@@ -104,6 +161,22 @@ mod tests {
         iter.seek(Seek::Last)?;
         assert_eq!(iter.try_next()?, None);
 
+        iter.seek(Seek::Last)?;
+        for test_data in vec.iter().rev() {
+            assert_eq!(iter.try_prev()?, Some(test_data.clone()))
+        }
+        assert_eq!(iter.try_prev()?, None);
+        assert_eq!(iter.try_next()?, Some(vec[0].clone()));
+        assert_eq!(iter.try_next()?, Some(vec[1].clone()));
+        assert_eq!(iter.try_prev()?, Some(vec[0].clone()));
+
+        iter.seek(Seek::Forward(&[b'3']))?;
+        assert_eq!(iter.try_prev()?, Some(vec[2].clone()));
+
+        iter.seek(Seek::Forward(&[b'3', b'5']))?;
+        assert_eq!(iter.try_prev()?, Some(vec[2].clone()));
+        assert_eq!(iter.try_next()?, Some(vec[3].clone()));
+
         Ok(())
     }
 }
@@ -1,25 +1,39 @@
 pub(crate) mod iter;
 
+use crate::kernel::lsm::comparator::{ComparableKey, Comparator};
 use crate::kernel::lsm::iterator::SeekIter;
-use crate::kernel::lsm::mem_table::KeyValue;
+use crate::kernel::lsm::mem_table::SeqKeyValue;
+use crate::kernel::lsm::range_tombstone::RangeTombstone;
 use crate::kernel::lsm::table::btree_table::iter::BTreeTableIter;
 use crate::kernel::lsm::table::Table;
+use crate::kernel::lsm::value_log::collect_blob_gens;
 use bytes::Bytes;
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 pub(crate) struct BTreeTable {
     level: usize,
     gen: i64,
     len: usize,
-    inner: BTreeMap<Bytes, KeyValue>,
+    inner: BTreeMap<ComparableKey, SeqKeyValue>,
+    range_tombstones: Vec<RangeTombstone>,
+    blob_gens: Vec<i64>,
+    comparator: Arc<dyn Comparator>,
 }
 
 impl BTreeTable {
-    pub(crate) fn new(level: usize, gen: i64, data: Vec<KeyValue>) -> Self {
+    #[allow(clippy::mutable_key_type)]
+    pub(crate) fn new(
+        level: usize,
+        gen: i64,
+        data: Vec<SeqKeyValue>,
+        comparator: &Arc<dyn Comparator>,
+    ) -> Self {
         let len = data.len();
+        let blob_gens = collect_blob_gens(&data);
         let inner = BTreeMap::from_iter(
             data.into_iter()
-                .map(|(key, value)| (key.clone(), (key, value))),
+                .map(|item| (ComparableKey::new(item.0 .0.clone(), comparator), item)),
         );
 
         BTreeTable {
@@ -27,13 +41,28 @@ impl BTreeTable {
             gen,
             len,
             inner,
+            range_tombstones: Vec::new(),
+            blob_gens,
+            comparator: Arc::clone(comparator),
         }
     }
+
+    pub(crate) fn with_range_tombstones(mut self, range_tombstones: Vec<RangeTombstone>) -> Self {
+        self.range_tombstones = range_tombstones;
+        self
+    }
+
+    pub(crate) fn comparable_key(&self, key: Bytes) -> ComparableKey {
+        ComparableKey::new(key, &self.comparator)
+    }
 }
 
 impl Table for BTreeTable {
-    fn query(&self, key: &[u8]) -> crate::kernel::KernelResult<Option<KeyValue>> {
-        Ok(self.inner.get(key).cloned())
+    fn query(&self, key: &[u8]) -> crate::kernel::KernelResult<Option<SeqKeyValue>> {
+        Ok(self
+            .inner
+            .get(&self.comparable_key(Bytes::copy_from_slice(key)))
+            .cloned())
     }
 
     fn len(&self) -> usize {
@@ -52,10 +81,31 @@ impl Table for BTreeTable {
         self.level
     }
 
+    fn range_tombstones(&self) -> &[RangeTombstone] {
+        &self.range_tombstones
+    }
+
+    fn max_seq(&self) -> i64 {
+        self.inner
+            .values()
+            .map(|(_, seq_id, _)| *seq_id)
+            .chain(
+                self.range_tombstones
+                    .iter()
+                    .map(|tombstone| tombstone.seq_id),
+            )
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn blob_gens(&self) -> &[i64] {
+        &self.blob_gens
+    }
+
     #[allow(clippy::todo)]
     fn iter<'a>(
         &'a self,
-    ) -> crate::kernel::KernelResult<Box<dyn SeekIter<'a, Item = KeyValue> + 'a + Send + Sync>>
+    ) -> crate::kernel::KernelResult<Box<dyn SeekIter<'a, Item = SeqKeyValue> + 'a + Send + Sync>>
     {
         Ok(Box::new(BTreeTableIter::new(self)))
     }
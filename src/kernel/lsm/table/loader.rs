@@ -1,19 +1,21 @@
 use crate::kernel::io::{IoFactory, IoType};
 use crate::kernel::lsm::compactor::LEVEL_0;
 use crate::kernel::lsm::log::LogLoader;
-use crate::kernel::lsm::mem_table::KeyValue;
-use crate::kernel::lsm::storage::Config;
+use crate::kernel::lsm::mem_table::{now_millis, record_from_bytes, SeqKeyValue};
+use crate::kernel::lsm::merge_operator::merge_versions;
+use crate::kernel::lsm::range_tombstone::{cover_seq, truncate_covered, RangeTombstone};
+use crate::kernel::lsm::storage::{Config, RecoveryReport};
 use crate::kernel::lsm::table::btree_table::BTreeTable;
 use crate::kernel::lsm::table::meta::TableMeta;
 use crate::kernel::lsm::table::scope::Scope;
-use crate::kernel::lsm::table::ss_table::block::{BlockCache, Entry, Value};
+use crate::kernel::lsm::table::ss_table::block::BlockCache;
 use crate::kernel::lsm::table::ss_table::SSTable;
 use crate::kernel::lsm::table::{BoxTable, Table, TableType};
+use crate::kernel::lsm::value_log::ValueLog;
 use crate::kernel::utils::lru_cache::ShardingLruCache;
 use crate::kernel::KernelResult;
-use bytes::Bytes;
+use itertools::Itertools;
 use std::collections::hash_map::RandomState;
-use std::io::Cursor;
 use std::mem;
 use std::sync::Arc;
 use tracing::warn;
@@ -25,6 +27,7 @@ pub(crate) struct TableLoader {
     config: Config,
     wal: LogLoader,
     cache: Arc<BlockCache>,
+    value_log: Arc<ValueLog>,
 }
 
 impl TableLoader {
@@ -32,39 +35,67 @@ impl TableLoader {
         config: Config,
         factory: Arc<IoFactory>,
         wal: LogLoader,
+        cache: Arc<BlockCache>,
     ) -> KernelResult<Self> {
         let inner = Arc::new(ShardingLruCache::new(
             config.table_cache_size,
             16,
             RandomState::default(),
         )?);
-        let cache = Arc::new(ShardingLruCache::new(
-            config.block_cache_size,
-            16,
-            RandomState::default(),
-        )?);
+        let value_log = Arc::new(ValueLog::new(&config)?);
         Ok(TableLoader {
             inner,
             factory,
             config,
             wal,
             cache,
+            value_log,
         })
     }
 
-    #[allow(clippy::match_single_binding)]
+    #[allow(dead_code)]
     pub(crate) async fn create(
         &self,
         gen: i64,
-        vec_data: Vec<KeyValue>,
+        vec_data: Vec<SeqKeyValue>,
+        level: usize,
+        table_type: TableType,
+    ) -> KernelResult<(Scope, TableMeta)> {
+        self.create_with_range_tombstones(gen, vec_data, Vec::new(), level, table_type)
+            .await
+    }
+
+    /// 创建附带范围删除标记的Table
+    ///
+    /// vec_data为空时使用范围删除标记的范围作为Table的Scope
+    #[allow(clippy::match_single_binding)]
+    pub(crate) async fn create_with_range_tombstones(
+        &self,
+        gen: i64,
+        vec_data: Vec<SeqKeyValue>,
+        range_tombstones: Vec<RangeTombstone>,
         level: usize,
         table_type: TableType,
     ) -> KernelResult<(Scope, TableMeta)> {
         // 获取数据的Key涵盖范围
-        let scope = Scope::from_sorted_vec_data(gen, &vec_data)?;
+        let scope = if vec_data.is_empty() {
+            Scope::from_range_tombstones(gen, &range_tombstones, self.config.comparator.as_ref())?
+        } else {
+            Scope::from_sorted_vec_data(gen, &vec_data)?
+        };
+        // Level 0的Table可能需要通过同gen的WAL进行恢复，因此持有其引用
+        if level == LEVEL_0 {
+            self.wal.retain(gen);
+        }
         let table: Box<dyn Table> = match table_type {
-            TableType::SortedString => Box::new(self.create_ss_table(gen, vec_data, level).await?),
-            TableType::BTree => Box::new(BTreeTable::new(level, gen, vec_data)),
+            TableType::SortedString => Box::new(
+                self.create_ss_table(gen, vec_data, range_tombstones, level)
+                    .await?,
+            ),
+            TableType::BTree => Box::new(
+                BTreeTable::new(level, gen, vec_data, &self.config.comparator)
+                    .with_range_tombstones(range_tombstones),
+            ),
         };
         let table_meta = TableMeta::from(table.as_ref());
         let _ = self.inner.put(gen, table);
@@ -72,6 +103,10 @@ impl TableLoader {
         Ok((scope, table_meta))
     }
 
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+
     pub(crate) fn get(&self, gen: i64) -> Option<&dyn Table> {
         self.inner
             .get_or_insert(gen, |gen| {
@@ -79,8 +114,9 @@ impl TableLoader {
 
                 let table: Box<dyn Table> = match table_factory
                     .reader(*gen, IoType::Direct)
-                    .and_then(|reader| SSTable::load_from_file(reader, Arc::clone(&self.cache)))
-                {
+                    .and_then(|reader| {
+                        SSTable::load_from_file(reader, Arc::clone(&self.cache), &self.config)
+                    }) {
                     Ok(ss_table) => Box::new(ss_table),
                     Err(err) => {
                         // 尝试恢复仅对Level 0的Table有效
@@ -89,17 +125,49 @@ impl TableLoader {
                             gen, err
                         );
                         let mut reload_data = Vec::new();
-                        self.wal.load(*gen, &mut reload_data, |bytes, records| {
-                            for (_, Entry { key, item, .. }) in
-                                Entry::<Value>::batch_decode(&mut Cursor::new(mem::take(bytes)))?
-                            {
-                                records.push((key, item.bytes));
+                        let mut report = RecoveryReport::default();
+                        let _ = self.wal.load_with_mode(
+                            *gen,
+                            self.config.wal_recovery_mode,
+                            &mut report,
+                            &mut reload_data,
+                            |bytes, records| {
+                            // WAL由所有ColumnFamily共享，仅恢复属于该ColumnFamily的数据
+                            for (family, vec_data) in record_from_bytes(&mem::take(bytes))? {
+                                if family == self.config.family_name {
+                                    records.extend(vec_data.into_iter().map(|(key, value)| {
+                                        ((key, value.bytes), value.seq_id, value.meta)
+                                    }));
+                                }
                             }
 
                             Ok(())
                         })?;
+                        for dropped in report.dropped() {
+                            warn!(
+                                "[LSMStore][Load Table: {}][dropped from wal]: offset: {}, reason: {:?}",
+                                gen, dropped.offset, dropped.reason
+                            );
+                        }
+                        let (range_data, reload_data): (Vec<_>, Vec<_>) = reload_data
+                            .into_iter()
+                            .partition(|(.., meta)| meta.is_range_del);
+                        let range_tombstones = range_data
+                            .into_iter()
+                            .map(|((start, end), seq_id, _)| {
+                                RangeTombstone::new(start, end.unwrap_or_default(), seq_id)
+                            })
+                            .collect_vec();
 
-                        Box::new(BTreeTable::new(LEVEL_0, *gen, reload_data))
+                        Box::new(
+                            BTreeTable::new(
+                                LEVEL_0,
+                                *gen,
+                                self.fold_reload_data(reload_data, &range_tombstones)?,
+                                &self.config.comparator,
+                            )
+                            .with_range_tombstones(range_tombstones),
+                        )
                     }
                 };
 
@@ -109,18 +177,55 @@ impl TableLoader {
             .ok()
     }
 
+    /// WAL中同一Key可能存在多条Merge操作数，需合并后才能置于同一Table中
+    ///
+    /// 与`MemTable`交换时相同，被范围删除标记覆盖的数据会被丢弃
+    fn fold_reload_data(
+        &self,
+        reload_data: Vec<SeqKeyValue>,
+        range_tombstones: &[RangeTombstone],
+    ) -> KernelResult<Vec<SeqKeyValue>> {
+        let now = now_millis();
+        let comparator = self.config.comparator.as_ref();
+        let mut vec_data = Vec::with_capacity(reload_data.len());
+
+        for (key, versions) in &reload_data
+            .into_iter()
+            .rev()
+            .sorted_by(|((key_a, _), ..), ((key_b, _), ..)| {
+                self.config.comparator.compare(key_a, key_b)
+            })
+            .group_by(|((key, _), ..)| key.clone())
+        {
+            let cover = cover_seq(range_tombstones, &key, None, comparator);
+            let Some(versions) = truncate_covered(versions.collect_vec(), cover) else {
+                continue;
+            };
+
+            if let Some(item) =
+                merge_versions(versions, self.config.merge_operator.as_deref(), now, false)?
+            {
+                vec_data.push(item);
+            }
+        }
+
+        Ok(vec_data)
+    }
+
     async fn create_ss_table(
         &self,
         gen: i64,
-        reload_data: Vec<(Bytes, Option<Bytes>)>,
+        reload_data: Vec<SeqKeyValue>,
+        range_tombstones: Vec<RangeTombstone>,
         level: usize,
     ) -> KernelResult<SSTable> {
-        SSTable::new(
+        SSTable::new_with_range_tombstones(
             &self.factory,
             &self.config,
             Arc::clone(&self.cache),
             gen,
             reload_data,
+            range_tombstones,
             level,
             IoType::Direct,
         )
@@ -136,10 +241,16 @@ impl TableLoader {
         self.inner.is_empty()
     }
 
+    /// 删除gen对应的Table或Blob文件
+    ///
+    /// Table与Blob文件的gen均由`Gen`生成，因此不会重复
     pub(crate) fn clean(&self, gen: i64) -> KernelResult<()> {
+        if self.value_log.is_blob_exist(gen)? {
+            return self.value_log.clean(gen);
+        }
         let _ = self.remove(&gen);
         self.factory.clean(gen)?;
-        self.wal.clean(gen)?;
+        self.wal.release(gen)?;
 
         Ok(())
     }
@@ -149,19 +260,30 @@ impl TableLoader {
     pub(crate) fn is_table_file_exist(&self, gen: i64) -> KernelResult<bool> {
         self.factory.exists(gen)
     }
+
+    pub(crate) fn wal(&self) -> &LogLoader {
+        &self.wal
+    }
+
+    pub(crate) fn value_log(&self) -> &ValueLog {
+        &self.value_log
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::kernel::io::{FileExtension, IoFactory, IoType};
+    use crate::kernel::lsm::column_family::DEFAULT_COLUMN_FAMILY;
     use crate::kernel::lsm::log::LogLoader;
-    use crate::kernel::lsm::mem_table::{data_to_bytes, DEFAULT_WAL_PATH};
+    use crate::kernel::lsm::mem_table::{record_to_bytes, ValueMeta, DEFAULT_WAL_PATH};
     use crate::kernel::lsm::storage::Config;
     use crate::kernel::lsm::table::loader::{TableLoader, TableType};
     use crate::kernel::lsm::version::DEFAULT_SS_TABLE_PATH;
+    use crate::kernel::utils::lru_cache::ShardingLruCache;
     use crate::kernel::KernelResult;
     use bincode::Options;
     use bytes::Bytes;
+    use std::collections::hash_map::RandomState;
     use std::sync::Arc;
     use tempfile::TempDir;
 
@@ -195,17 +317,43 @@ mod tests {
                 Some(value.clone()),
             );
 
-            let _ = log_writer.add_record(&data_to_bytes(key_value.clone())?)?;
-            vec_data.push(key_value);
+            // 过期时间需要随WAL一同恢复
+            let meta = ValueMeta::with_expire((i % 2 == 0).then_some(i64::MAX));
+
+            let _ = log_writer.add_record(&record_to_bytes(
+                &[(DEFAULT_COLUMN_FAMILY, &[key_value.clone()])],
+                i as i64,
+                meta,
+            )?)?;
+            vec_data.push((key_value, i as i64, meta));
         }
         // 测试重复数据是否被正常覆盖
-        let repeat_data = (vec_data[0].0.clone(), None);
-        let _ = log_writer.add_record(&data_to_bytes(repeat_data.clone())?)?;
+        let repeat_data = (
+            (vec_data[0].0 .0.clone(), None),
+            times as i64,
+            ValueMeta::default(),
+        );
+        let _ = log_writer.add_record(&record_to_bytes(
+            &[(DEFAULT_COLUMN_FAMILY, &[repeat_data.0.clone()])],
+            repeat_data.1,
+            ValueMeta::default(),
+        )?)?;
+        // 其他ColumnFamily的数据不会被恢复
+        let _ = log_writer.add_record(&record_to_bytes(
+            &[("other", &[(repeat_data.0 .0.clone(), Some(value.clone()))])],
+            times as i64 + 1,
+            ValueMeta::default(),
+        )?)?;
         vec_data[0] = repeat_data.clone();
 
         log_writer.flush()?;
 
-        let sst_loader = TableLoader::new(config, sst_factory.clone(), log_loader.clone())?;
+        let cache = Arc::new(ShardingLruCache::new(
+            config.block_cache_size,
+            16,
+            RandomState::default(),
+        )?);
+        let sst_loader = TableLoader::new(config, sst_factory.clone(), log_loader.clone(), cache)?;
 
         let _ = sst_loader
             .create(1, vec_data.clone(), 0, TableType::SortedString)
@@ -217,14 +365,12 @@ mod tests {
         let ss_table_loaded = sst_loader.get(1).unwrap();
 
         assert_eq!(
-            ss_table_loaded.query(&repeat_data.0)?,
+            ss_table_loaded.query(&repeat_data.0 .0)?,
             Some(repeat_data.clone())
         );
+        // WAL恢复时需要保留写入时的seq_id
         for kv in vec_data.iter().take(times).skip(1) {
-            assert_eq!(
-                ss_table_loaded.query(&kv.0)?.unwrap().1,
-                Some(value.clone())
-            )
+            assert_eq!(ss_table_loaded.query(&kv.0 .0)?, Some(kv.clone()))
         }
 
         // 模拟SSTable异常而使用Wal进行恢复的情况
@@ -236,14 +382,12 @@ mod tests {
         let ss_table_backup = sst_loader.get(1).unwrap();
 
         assert_eq!(
-            ss_table_backup.query(&repeat_data.0)?,
+            ss_table_backup.query(&repeat_data.0 .0)?,
             Some(repeat_data.clone())
         );
+        // WAL恢复时需要保留写入时的seq_id
         for kv in vec_data.iter().take(times).skip(1) {
-            assert_eq!(
-                ss_table_backup.query(&kv.0)?.unwrap().1,
-                Some(value.clone())
-            )
+            assert_eq!(ss_table_backup.query(&kv.0 .0)?, Some(kv.clone()))
         }
         Ok(())
     }
@@ -1,5 +1,6 @@
 use crate::kernel::lsm::iterator::SeekIter;
-use crate::kernel::lsm::mem_table::KeyValue;
+use crate::kernel::lsm::mem_table::SeqKeyValue;
+use crate::kernel::lsm::range_tombstone::RangeTombstone;
 use crate::kernel::lsm::table::meta::TableMeta;
 use crate::kernel::KernelResult;
 use itertools::Itertools;
@@ -19,7 +20,13 @@ pub enum TableType {
 pub(crate) type BoxTable = Box<dyn Table>;
 
 pub(crate) trait Table: Sync + Send {
-    fn query(&self, key: &[u8]) -> KernelResult<Option<KeyValue>>;
+    /// 查询Key对应的数据以及其写入时的seq_id
+    fn query(&self, key: &[u8]) -> KernelResult<Option<SeqKeyValue>>;
+
+    /// 批量查询一组有序的Key，结果与keys一一对应
+    fn multi_query(&self, keys: &[&[u8]]) -> KernelResult<Vec<Option<SeqKeyValue>>> {
+        keys.iter().map(|key| self.query(key)).collect()
+    }
 
     fn len(&self) -> usize;
 
@@ -29,9 +36,18 @@ pub(crate) trait Table: Sync + Send {
 
     fn level(&self) -> usize;
 
+    /// 随该Table持久化的范围删除标记
+    fn range_tombstones(&self) -> &[RangeTombstone];
+
+    /// Table中数据与范围删除标记的最大seq_id
+    fn max_seq(&self) -> i64;
+
+    /// Table中数据所引用的Blob文件
+    fn blob_gens(&self) -> &[i64];
+
     fn iter<'a>(
         &'a self,
-    ) -> KernelResult<Box<dyn SeekIter<'a, Item = KeyValue> + 'a + Sync + Send>>;
+    ) -> KernelResult<Box<dyn SeekIter<'a, Item = SeqKeyValue> + 'a + Sync + Send>>;
 }
 
 /// 通过一组SSTable收集对应的Gen
@@ -1,4 +1,6 @@
-use crate::kernel::lsm::mem_table::KeyValue;
+use crate::kernel::lsm::comparator::Comparator;
+use crate::kernel::lsm::mem_table::SeqKeyValue;
+use crate::kernel::lsm::range_tombstone::RangeTombstone;
 use crate::kernel::KernelResult;
 use crate::KernelError;
 use bytes::Bytes;
@@ -77,9 +79,17 @@ impl Scope {
     }
 
     /// 将多个scope重组融合成一个scope
-    pub(crate) fn fusion(scopes: &[Scope]) -> Option<Self> {
-        let start = scopes.iter().map(|scope| &scope.start).min()?.clone();
-        let end = scopes.iter().map(|scope| &scope.end).max()?.clone();
+    pub(crate) fn fusion(scopes: &[Scope], comparator: &dyn Comparator) -> Option<Self> {
+        let start = scopes
+            .iter()
+            .map(|scope| &scope.start)
+            .min_by(|a, b| comparator.compare(a, b))?
+            .clone();
+        let end = scopes
+            .iter()
+            .map(|scope| &scope.end)
+            .max_by(|a, b| comparator.compare(a, b))?
+            .clone();
 
         Some(Scope {
             start,
@@ -90,28 +100,35 @@ impl Scope {
     }
 
     /// 判断scope之间是否相交或包含
-    pub(crate) fn meet(&self, target: &Scope) -> bool {
-        (self.start.le(&target.start) && self.end.ge(&target.start))
-            || (self.start.le(&target.end) && self.end.ge(&target.end))
-            || (self.start.le(&target.start)) && self.end.ge(&target.end)
-            || (self.start.ge(&target.start)) && self.end.le(&target.end)
+    pub(crate) fn meet(&self, target: &Scope, comparator: &dyn Comparator) -> bool {
+        let le = |a: &Bytes, b: &Bytes| comparator.compare(a, b).is_le();
+
+        (le(&self.start, &target.start) && le(&target.start, &self.end))
+            || (le(&self.start, &target.end) && le(&target.end, &self.end))
+            || (le(&self.start, &target.start)) && le(&target.end, &self.end)
+            || (le(&target.start, &self.start)) && le(&self.end, &target.end)
     }
 
     /// 判断key与Scope是否相交或包含
-    pub(crate) fn meet_by_key(&self, key: &[u8]) -> bool {
-        self.start.as_ref().le(key) && self.end.as_ref().ge(key)
+    pub(crate) fn meet_by_key(&self, key: &[u8], comparator: &dyn Comparator) -> bool {
+        comparator.compare(&self.start, key).is_le() && comparator.compare(&self.end, key).is_ge()
     }
 
     #[allow(dead_code)]
-    pub(crate) fn meet_bound(&self, min: Bound<&[u8]>, max: Bound<&[u8]>) -> bool {
+    pub(crate) fn meet_bound(
+        &self,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+        comparator: &dyn Comparator,
+    ) -> bool {
         let is_min_inside = match min {
-            Bound::Included(key) => self.start.as_ref().le(key),
-            Bound::Excluded(key) => self.start.as_ref().lt(key),
+            Bound::Included(key) => comparator.compare(&self.start, key).is_le(),
+            Bound::Excluded(key) => comparator.compare(&self.start, key).is_lt(),
             Bound::Unbounded => true,
         };
         let is_max_inside = match max {
-            Bound::Included(key) => self.end.as_ref().ge(key),
-            Bound::Excluded(key) => self.end.as_ref().gt(key),
+            Bound::Included(key) => comparator.compare(&self.end, key).is_ge(),
+            Bound::Excluded(key) => comparator.compare(&self.end, key).is_gt(),
             Bound::Unbounded => true,
         };
 
@@ -122,12 +139,34 @@ impl Scope {
     #[allow(clippy::pattern_type_mismatch)]
     pub(crate) fn from_sorted_vec_data(
         gen: i64,
-        vec_mem_data: &Vec<KeyValue>,
+        vec_mem_data: &[SeqKeyValue],
     ) -> KernelResult<Self> {
-        match vec_mem_data.as_slice() {
-            [first, .., last] => Ok(Self::from_range(gen, first.0.clone(), last.0.clone())),
-            [one] => Ok(Self::from_range(gen, one.0.clone(), one.0.clone())),
+        match vec_mem_data {
+            [((first, _), ..), .., ((last, _), ..)] => {
+                Ok(Self::from_range(gen, first.clone(), last.clone()))
+            }
+            [((one, _), ..)] => Ok(Self::from_range(gen, one.clone(), one.clone())),
             _ => Err(KernelError::DataEmpty),
         }
     }
+
+    /// 由一组范围删除标记的范围构成scope，用于不存在数据的Table
+    pub(crate) fn from_range_tombstones(
+        gen: i64,
+        range_tombstones: &[RangeTombstone],
+        comparator: &dyn Comparator,
+    ) -> KernelResult<Self> {
+        let start = range_tombstones
+            .iter()
+            .map(|tombstone| &tombstone.start)
+            .min_by(|a, b| comparator.compare(a, b))
+            .ok_or(KernelError::DataEmpty)?;
+        let end = range_tombstones
+            .iter()
+            .map(|tombstone| &tombstone.end)
+            .max_by(|a, b| comparator.compare(a, b))
+            .ok_or(KernelError::DataEmpty)?;
+
+        Ok(Self::from_range(gen, start.clone(), end.clone()))
+    }
 }
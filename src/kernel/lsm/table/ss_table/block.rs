@@ -1,23 +1,30 @@
+use crate::kernel::lsm::comparator::{BytewiseComparator, Comparator};
+use crate::kernel::lsm::mem_table::ValueMeta;
+use crate::kernel::lsm::range_tombstone::RangeTombstone;
 use crate::kernel::lsm::storage::Config;
 use crate::kernel::utils::bloom_filter::BloomFilter;
 use crate::kernel::utils::lru_cache::ShardingLruCache;
 use crate::kernel::KernelResult;
 use crate::KernelError;
-use bytes::{Buf, BufMut, Bytes};
+use bytes::{Buf, Bytes};
 use integer_encoding::{FixedInt, FixedIntWriter, VarIntReader, VarIntWriter};
 use itertools::Itertools;
 use lz4::Decoder;
 use std::cmp::min;
 use std::io::{Cursor, Read, Write};
 use std::mem;
+use std::sync::Arc;
 
 /// BlockCache类型 可同时缓存两种类型
 ///
 /// Key为SSTable的gen且Index为None时返回Index类型
 ///
 /// Key为SSTable的gen且Index为Some时返回Data类型
+///
+/// BlockCache由所有ColumnFamily共享，而各ColumnFamily的Level 0 Table会因共享WAL而使用相同的gen，
+/// 因此Key的首位为ColumnFamily的id
 #[allow(dead_code)]
-pub(crate) type BlockCache = ShardingLruCache<(i64, Option<Index>), BlockType>;
+pub(crate) type BlockCache = ShardingLruCache<(usize, i64, Option<Index>), BlockType>;
 
 pub(crate) const DEFAULT_BLOCK_SIZE: usize = 4 * 1024;
 
@@ -26,6 +33,8 @@ pub(crate) const DEFAULT_DATA_RESTART_INTERVAL: usize = 16;
 
 pub(crate) const DEFAULT_INDEX_RESTART_INTERVAL: usize = 2;
 
+pub(crate) const DEFAULT_MAX_COMPRESSION_RATIO: f64 = 0.875;
+
 const CRC_SIZE: usize = 4;
 
 pub(crate) type KeyValue<T> = (Bytes, T);
@@ -66,18 +75,26 @@ where
     }
 
     pub(crate) fn batch_decode(cursor: &mut Cursor<Vec<u8>>) -> KernelResult<Vec<(usize, Self)>> {
+        Self::batch_decode_with(cursor, false)
+    }
+
+    /// is_legacy为true时以旧格式解码Item，详见`BlockItem::decode_legacy`
+    fn batch_decode_with(
+        cursor: &mut Cursor<Vec<u8>>,
+        is_legacy: bool,
+    ) -> KernelResult<Vec<(usize, Self)>> {
         let mut vec_entry = Vec::new();
         let mut index = 0;
 
         while !cursor.is_empty() {
-            vec_entry.push((index, Self::decode(cursor)?));
+            vec_entry.push((index, Self::decode_with(cursor, is_legacy)?));
             index += 1;
         }
 
         Ok(vec_entry)
     }
 
-    pub(crate) fn decode<R: Read>(reader: &mut R) -> KernelResult<Entry<T>> {
+    fn decode_with<R: Read>(reader: &mut R, is_legacy: bool) -> KernelResult<Entry<T>> {
         let unshared_len = reader.read_varint::<u32>()? as usize;
         let shared_len = reader.read_varint::<u32>()? as usize;
 
@@ -88,22 +105,66 @@ where
             unshared_len,
             shared_len,
             key: Bytes::from(bytes),
-            item: T::decode(reader)?,
+            item: if is_legacy {
+                T::decode_legacy(reader)?
+            } else {
+                T::decode(reader)?
+            },
         })
     }
 }
 
+/// Value的类型标记
+///
+/// 用于区分删除标记与空值，避免长度为0的Value被误判为删除
+const VALUE_TYPE_DELETE: u8 = 0;
+const VALUE_TYPE_PUT: u8 = 1;
+/// 附带过期时间的Value，过期时间紧随seq_id之后编码
+const VALUE_TYPE_PUT_WITH_TTL: u8 = 2;
+/// Merge操作数序列
+const VALUE_TYPE_MERGE: u8 = 3;
+/// 范围删除标记，Value为范围的end，仅出现于WAL中
+const VALUE_TYPE_RANGE_DELETE: u8 = 4;
+/// 被分离至Blob文件中的Value，此时Value为BlobPointer
+const VALUE_TYPE_BLOB: u8 = 5;
+/// 附带过期时间且被分离至Blob文件中的Value
+const VALUE_TYPE_BLOB_WITH_TTL: u8 = 6;
+
 /// 键值对对应的Value
+///
+/// 与Entry中的Key共同组成InternalKey(UserKey + seq_id + ValueType)
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub(crate) struct Value {
     value_len: usize,
+    pub(crate) seq_id: i64,
+    pub(crate) meta: ValueMeta,
     pub(crate) bytes: Option<Bytes>,
 }
 
 impl From<Option<Bytes>> for Value {
     fn from(bytes: Option<Bytes>) -> Self {
+        Value::new(bytes, 0)
+    }
+}
+
+impl Value {
+    pub(crate) fn new(bytes: Option<Bytes>, seq_id: i64) -> Self {
         let value_len = bytes.as_ref().map_or(0, Bytes::len);
-        Value { value_len, bytes }
+        Value {
+            value_len,
+            seq_id,
+            meta: ValueMeta::default(),
+            bytes,
+        }
+    }
+
+    pub(crate) fn meta(mut self, meta: ValueMeta) -> Self {
+        // 删除标记不存在过期与Merge的概念，Merge操作数则不存在过期的概念
+        if self.bytes.is_some() {
+            self.meta = meta;
+            self.meta.expire_at = meta.expire_at.filter(|_| !meta.is_merge);
+        }
+        self
     }
 }
 
@@ -134,6 +195,14 @@ pub(crate) trait BlockItem: Sized + Clone {
     where
         T: Read + ?Sized;
 
+    /// 解码未记录格式版本的旧SSTable中的Item，默认与decode一致
+    fn decode_legacy<T>(reader: &mut T) -> KernelResult<Self>
+    where
+        T: Read + ?Sized,
+    {
+        Self::decode(reader)
+    }
+
     fn encode(&self, bytes: &mut Vec<u8>) -> KernelResult<()>;
 }
 
@@ -142,19 +211,103 @@ impl BlockItem for Value {
     where
         T: Read + ?Sized,
     {
+        let mut value_type = [0u8];
+        reader.read_exact(&mut value_type)?;
+        let seq_id = reader.read_varint::<i64>()?;
+        let meta = match value_type[0] {
+            VALUE_TYPE_PUT_WITH_TTL => ValueMeta::with_expire(Some(reader.read_varint::<i64>()?)),
+            VALUE_TYPE_MERGE => ValueMeta::merge(),
+            VALUE_TYPE_RANGE_DELETE => ValueMeta::range_delete(),
+            VALUE_TYPE_BLOB => ValueMeta::blob(None),
+            VALUE_TYPE_BLOB_WITH_TTL => ValueMeta::blob(Some(reader.read_varint::<i64>()?)),
+            _ => ValueMeta::default(),
+        };
         let value_len = reader.read_varint::<u32>()? as usize;
 
-        let bytes = (value_len > 0)
-            .then(|| {
+        let bytes = match value_type[0] {
+            VALUE_TYPE_DELETE => None,
+            VALUE_TYPE_PUT
+            | VALUE_TYPE_PUT_WITH_TTL
+            | VALUE_TYPE_MERGE
+            | VALUE_TYPE_RANGE_DELETE
+            | VALUE_TYPE_BLOB
+            | VALUE_TYPE_BLOB_WITH_TTL => {
                 let mut value = vec![0u8; value_len];
-                reader.read(&mut value).ok().map(|_| Bytes::from(value))
-            })
-            .flatten();
+                reader.read_exact(&mut value)?;
+                Some(Bytes::from(value))
+            }
+            _ => return Err(KernelError::UnexpectedCommandType),
+        };
 
-        Ok(Value { value_len, bytes })
+        Ok(Value {
+            value_len,
+            seq_id,
+            meta,
+            bytes,
+        })
+    }
+
+    /// 旧格式中仅记录Value的长度与内容，长度为0时视为删除标记，seq_id视为0
+    fn decode_legacy<T>(mut reader: &mut T) -> KernelResult<Self>
+    where
+        T: Read + ?Sized,
+    {
+        let value_len = reader.read_varint::<u32>()? as usize;
+        let bytes = if value_len > 0 {
+            let mut value = vec![0u8; value_len];
+            reader.read_exact(&mut value)?;
+            Some(Bytes::from(value))
+        } else {
+            None
+        };
+
+        Ok(Value::new(bytes, 0))
     }
 
     fn encode(&self, bytes: &mut Vec<u8>) -> KernelResult<()> {
+        let value_type = match (&self.bytes, self.meta) {
+            (None, _) => VALUE_TYPE_DELETE,
+            (
+                Some(_),
+                ValueMeta {
+                    is_range_del: true, ..
+                },
+            ) => VALUE_TYPE_RANGE_DELETE,
+            (Some(_), ValueMeta { is_merge: true, .. }) => VALUE_TYPE_MERGE,
+            (
+                Some(_),
+                ValueMeta {
+                    is_blob: true,
+                    expire_at: None,
+                    ..
+                },
+            ) => VALUE_TYPE_BLOB,
+            (
+                Some(_),
+                ValueMeta {
+                    is_blob: true,
+                    expire_at: Some(_),
+                    ..
+                },
+            ) => VALUE_TYPE_BLOB_WITH_TTL,
+            (
+                Some(_),
+                ValueMeta {
+                    expire_at: None, ..
+                },
+            ) => VALUE_TYPE_PUT,
+            (
+                Some(_),
+                ValueMeta {
+                    expire_at: Some(_), ..
+                },
+            ) => VALUE_TYPE_PUT_WITH_TTL,
+        };
+        bytes.write_all(&[value_type])?;
+        bytes.write_varint(self.seq_id)?;
+        if let Some(expire_at) = self.meta.expire_at {
+            bytes.write_varint(expire_at)?;
+        }
         bytes.write_varint(self.value_len as u32)?;
 
         if let Some(value) = &self.bytes {
@@ -183,10 +336,138 @@ impl BlockItem for Index {
     }
 }
 
-#[derive(Clone, Copy)]
-pub(crate) enum CompressType {
+/// Block的压缩方式
+///
+/// 实际使用的压缩方式记录于每个Block末尾的一个字节中，因此各Level可使用不同的压缩方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompressType {
     None,
     LZ4,
+    Snappy,
+    /// level为压缩等级，dict_size大于0时以SSTable中的DataBlock为样本训练不超过该大小的字典
+    Zstd {
+        level: i32,
+        dict_size: usize,
+    },
+}
+
+impl CompressType {
+    fn tag(&self) -> u8 {
+        match self {
+            CompressType::None => 0,
+            CompressType::LZ4 => 1,
+            CompressType::Snappy => 2,
+            CompressType::Zstd { .. } => 3,
+        }
+    }
+}
+
+/// Block压缩器
+///
+/// 压缩后的数据末尾附带实际使用的压缩方式，压缩效果不佳时直接存储原数据
+pub(crate) struct Compressor<'a> {
+    compress_type: CompressType,
+    /// Zstd使用的字典，为空时不使用字典
+    dict: &'a [u8],
+    /// 压缩后的大小超过原大小的该比例时放弃压缩
+    max_ratio: f64,
+}
+
+impl<'a> Compressor<'a> {
+    pub(crate) fn new(compress_type: CompressType) -> Self {
+        Compressor {
+            compress_type,
+            dict: &[],
+            max_ratio: DEFAULT_MAX_COMPRESSION_RATIO,
+        }
+    }
+
+    pub(crate) fn dict(mut self, dict: &'a [u8]) -> Self {
+        self.dict = dict;
+        self
+    }
+
+    pub(crate) fn max_ratio(mut self, max_ratio: f64) -> Self {
+        self.max_ratio = max_ratio;
+        self
+    }
+
+    pub(crate) fn compress(&self, raw: &[u8], bytes: &mut Vec<u8>) -> KernelResult<()> {
+        let start = bytes.len();
+
+        match self.compress_type {
+            CompressType::None => (),
+            CompressType::LZ4 => {
+                let mut encoder = lz4::EncoderBuilder::new().level(4).build(&mut *bytes)?;
+                encoder.write_all(raw)?;
+                let (_, result) = encoder.finish();
+
+                result?;
+            }
+            CompressType::Snappy => {
+                bytes.resize(start + snap::raw::max_compress_len(raw.len()), 0);
+                let len = snap::raw::Encoder::new().compress(raw, &mut bytes[start..])?;
+                bytes.truncate(start + len);
+            }
+            CompressType::Zstd { level, .. } => {
+                let mut compressed = if self.dict.is_empty() {
+                    zstd::bulk::compress(raw, level)?
+                } else {
+                    zstd::bulk::Compressor::with_dictionary(level, self.dict)?.compress(raw)?
+                };
+                bytes.append(&mut compressed);
+            }
+        }
+        let compressed_len = bytes.len() - start;
+        let compress_type = if self.compress_type == CompressType::None
+            || compressed_len as f64 > raw.len() as f64 * self.max_ratio
+        {
+            // 压缩效果不佳时直接存储原数据，避免读取时无意义的解压
+            bytes.truncate(start);
+            bytes.extend_from_slice(raw);
+            CompressType::None
+        } else {
+            self.compress_type
+        };
+        bytes.push(compress_type.tag());
+
+        Ok(())
+    }
+}
+
+/// 依照Block末尾记录的压缩方式进行解压
+pub(crate) fn decompress(mut buf: Vec<u8>, dict: &[u8]) -> KernelResult<Vec<u8>> {
+    let Some(tag) = buf.pop() else {
+        return Err(KernelError::DataEmpty);
+    };
+
+    Ok(match tag {
+        0 => buf,
+        1 => lz4_decompress(buf)?,
+        2 => snap::raw::Decoder::new().decompress_vec(&buf)?,
+        3 => {
+            let mut decoder = zstd::stream::read::Decoder::with_dictionary(buf.as_slice(), dict)?;
+            let mut decoded = Vec::with_capacity(DEFAULT_BLOCK_SIZE);
+            let _ = decoder.read_to_end(&mut decoded)?;
+            decoded
+        }
+        _ => return Err(KernelError::UnknownCompressType(tag)),
+    })
+}
+
+fn lz4_decompress(buf: Vec<u8>) -> KernelResult<Vec<u8>> {
+    let mut decoder = Decoder::new(buf.reader())?;
+    let mut decoded = Vec::with_capacity(DEFAULT_BLOCK_SIZE);
+    let _ = decoder.read_to_end(&mut decoded)?;
+
+    Ok(decoded)
+}
+
+/// 以DataBlock为样本训练Zstd字典
+///
+/// 样本过少时训练会失败，此时不使用字典
+fn train_dict(samples: &[Vec<u8>], dict_size: usize) -> Vec<u8> {
+    zstd::dict::from_samples(samples, dict_size).unwrap_or_default()
 }
 
 #[derive(Debug)]
@@ -195,6 +476,13 @@ pub(crate) struct MetaBlock {
     pub(crate) len: usize,
     pub(crate) index_restart_interval: usize,
     pub(crate) data_restart_interval: usize,
+    /// 数据与范围删除标记的最大seq_id
+    pub(crate) max_seq: i64,
+    pub(crate) range_tombstones: Vec<RangeTombstone>,
+    /// 数据中的BlobPointer所引用的Blob文件
+    pub(crate) blob_gens: Vec<i64>,
+    /// DataBlock压缩时使用的Zstd字典，为空时未使用字典
+    pub(crate) compression_dict: Vec<u8>,
 }
 
 impl MetaBlock {
@@ -202,13 +490,25 @@ impl MetaBlock {
         bytes.write_fixedint(self.len as u32)?;
         bytes.write_fixedint(self.index_restart_interval as u32)?;
         bytes.write_fixedint(self.data_restart_interval as u32)?;
+        bytes.write_fixedint(self.max_seq)?;
 
+        bytes.write_varint(self.range_tombstones.len() as u32)?;
+        for tombstone in self.range_tombstones.iter() {
+            tombstone.encode(bytes)?;
+        }
+        bytes.write_varint(self.blob_gens.len() as u32)?;
+        for gen in self.blob_gens.iter() {
+            bytes.write_varint(*gen)?;
+        }
+        bytes.write_varint(self.compression_dict.len() as u32)?;
+        bytes.extend_from_slice(&self.compression_dict);
         self.filter.to_raw(bytes)?;
 
         Ok(())
     }
 
-    pub(crate) fn from_raw(bytes: &[u8]) -> Self {
+    /// 解析未记录格式版本的旧SSTable中的MetaBlock，其中仅记录了数据量、Restart间隔与布隆过滤器
+    pub(crate) fn from_raw_legacy(bytes: &[u8]) -> Self {
         let len = u32::decode_fixed(&bytes[0..4]) as usize;
         let index_restart_interval = u32::decode_fixed(&bytes[4..8]) as usize;
         let data_restart_interval = u32::decode_fixed(&bytes[8..12]) as usize;
@@ -219,8 +519,44 @@ impl MetaBlock {
             len,
             index_restart_interval,
             data_restart_interval,
+            max_seq: 0,
+            range_tombstones: Vec::new(),
+            blob_gens: Vec::new(),
+            compression_dict: Vec::new(),
         }
     }
+
+    pub(crate) fn from_raw(bytes: &[u8]) -> KernelResult<Self> {
+        let len = u32::decode_fixed(&bytes[0..4]) as usize;
+        let index_restart_interval = u32::decode_fixed(&bytes[4..8]) as usize;
+        let data_restart_interval = u32::decode_fixed(&bytes[8..12]) as usize;
+        let max_seq = i64::decode_fixed(&bytes[12..20]);
+
+        let mut cursor = Cursor::new(&bytes[20..]);
+        let tombstones_len = cursor.read_varint::<u32>()? as usize;
+        let range_tombstones = (0..tombstones_len)
+            .map(|_| RangeTombstone::decode(&mut cursor))
+            .try_collect()?;
+        let blob_gens_len = cursor.read_varint::<u32>()? as usize;
+        let blob_gens = (0..blob_gens_len)
+            .map(|_| cursor.read_varint::<i64>())
+            .try_collect()?;
+        let dict_len = cursor.read_varint::<u32>()? as usize;
+        let mut compression_dict = vec![0; dict_len];
+        cursor.read_exact(&mut compression_dict)?;
+        let filter = BloomFilter::from_raw(&bytes[20 + cursor.position() as usize..]);
+
+        Ok(Self {
+            filter,
+            len,
+            index_restart_interval,
+            data_restart_interval,
+            max_seq,
+            range_tombstones,
+            blob_gens,
+            compression_dict,
+        })
+    }
 }
 
 /// Block SSTable最小的存储单位
@@ -236,8 +572,10 @@ pub(crate) struct Block<T> {
 pub(crate) struct BlockOptions {
     block_size: usize,
     compress_type: CompressType,
+    max_compression_ratio: f64,
     data_restart_interval: usize,
     index_restart_interval: usize,
+    comparator: Arc<dyn Comparator>,
 }
 
 impl From<&Config> for BlockOptions {
@@ -245,8 +583,10 @@ impl From<&Config> for BlockOptions {
         BlockOptions {
             block_size: config.block_size,
             compress_type: CompressType::None,
+            max_compression_ratio: config.max_compression_ratio,
             data_restart_interval: config.data_restart_interval,
             index_restart_interval: config.index_restart_interval,
+            comparator: Arc::clone(&config.comparator),
         }
     }
 }
@@ -257,8 +597,10 @@ impl BlockOptions {
         BlockOptions {
             block_size: DEFAULT_BLOCK_SIZE,
             compress_type: CompressType::None,
+            max_compression_ratio: DEFAULT_MAX_COMPRESSION_RATIO,
             data_restart_interval: DEFAULT_DATA_RESTART_INTERVAL,
             index_restart_interval: DEFAULT_INDEX_RESTART_INTERVAL,
+            comparator: Arc::new(BytewiseComparator),
         }
     }
     #[allow(dead_code)]
@@ -272,6 +614,11 @@ impl BlockOptions {
         self
     }
     #[allow(dead_code)]
+    pub(crate) fn max_compression_ratio(mut self, max_compression_ratio: f64) -> Self {
+        self.max_compression_ratio = max_compression_ratio;
+        self
+    }
+    #[allow(dead_code)]
     pub(crate) fn data_restart_interval(mut self, data_restart_interval: usize) -> Self {
         self.data_restart_interval = data_restart_interval;
         self
@@ -296,10 +643,10 @@ impl BlockBuf {
         }
     }
 
-    fn add(&mut self, key_value: KeyValue<Value>) {
+    fn add(&mut self, key_value: KeyValue<Value>, comparator: &dyn Comparator) {
         // 断言新插入的键值对的Key大于buf中最后的key
         if let Some(last_key) = self.last_key() {
-            assert!(last_key.cmp(&key_value.0).is_lt());
+            assert!(comparator.compare(last_key, &key_value.0).is_lt());
         }
         self.bytes_size += key_value_bytes_len(&key_value);
         self.vec_key_value.push(key_value);
@@ -355,7 +702,7 @@ impl BlockBuilder {
     ///
     /// 请注意add的键值对需要自行保证key顺序插入,否则可能会出现问题
     pub(crate) fn add(&mut self, key_value: KeyValue<Value>) {
-        self.buf.add(key_value);
+        self.buf.add(key_value, self.options.comparator.as_ref());
         self.len += 1;
         // 超过指定的Block大小后进行Block构建(默认为4K大小)
         if self.is_out_of_byte() {
@@ -380,16 +727,35 @@ impl BlockBuilder {
     }
 
     /// 构建多个Block连续序列化组合成的两个Bytes 前者为多个DataBlock，后者为单个IndexBlock
-    pub(crate) async fn build(mut self) -> KernelResult<(Vec<u8>, usize, usize)> {
+    ///
+    /// 同时返回DataBlock压缩时使用的Zstd字典
+    pub(crate) async fn build(mut self) -> KernelResult<(Vec<u8>, usize, usize, Vec<u8>)> {
         self._build();
 
         let mut blocks_bytes = vec![];
         let mut offset = 0u32;
 
         let mut indexes = Vec::with_capacity(self.vec_block.len());
+        let vec_raw: Vec<Vec<u8>> = self
+            .vec_block
+            .iter()
+            .map(|(block, _)| {
+                let mut raw = Vec::new();
+                block.to_raw(&mut raw).map(|_| raw)
+            })
+            .try_collect()?;
+        let dict = match self.options.compress_type {
+            CompressType::Zstd { dict_size, .. } if dict_size > 0 => {
+                train_dict(&vec_raw, dict_size)
+            }
+            _ => Vec::new(),
+        };
+        let compressor = Compressor::new(self.options.compress_type)
+            .dict(&dict)
+            .max_ratio(self.options.max_compression_ratio);
 
-        for (block, last_key) in self.vec_block {
-            block.encode(self.options.compress_type, &mut blocks_bytes)?;
+        for (raw, (_, last_key)) in vec_raw.iter().zip(self.vec_block) {
+            compressor.compress(raw, &mut blocks_bytes)?;
 
             let len = blocks_bytes.len() - offset as usize;
 
@@ -399,26 +765,21 @@ impl BlockBuilder {
         let data_bytes_len = blocks_bytes.len();
 
         Block::new(indexes, self.options.index_restart_interval)
-            .encode(CompressType::None, &mut blocks_bytes)?;
+            .encode(&Compressor::new(CompressType::None), &mut blocks_bytes)?;
         let index_bytes_len = blocks_bytes.len() - data_bytes_len;
 
-        Ok((blocks_bytes, data_bytes_len, index_bytes_len))
+        Ok((blocks_bytes, data_bytes_len, index_bytes_len, dict))
     }
 }
 
 impl Block<Value> {
     /// 通过Key查询对应Value
     ///
-    /// 返回数据为Value的Option以及是否存在
-    pub(crate) fn find(&self, key: &[u8]) -> (Option<Bytes>, bool) {
-        self.binary_search(key)
+    /// 不存在时返回None
+    pub(crate) fn find(&self, key: &[u8], comparator: &dyn Comparator) -> Option<&Value> {
+        self.binary_search(key, comparator)
             .ok()
-            .and_then(|index| {
-                self.vec_entry
-                    .get(index)
-                    .map(|(_, entry)| (entry.item.bytes.clone(), true))
-            })
-            .unwrap_or((None, false))
+            .and_then(|index| self.vec_entry.get(index).map(|(_, entry)| &entry.item))
     }
 }
 
@@ -493,76 +854,77 @@ where
     }
 
     /// 查询相等或最近较大的Key
-    pub(crate) fn find_with_upper(&self, key: &[u8]) -> T {
+    pub(crate) fn find_with_upper(&self, key: &[u8], comparator: &dyn Comparator) -> T {
         let entries_len = self.vec_entry.len();
         let index = self
-            .binary_search(key)
+            .binary_search(key, comparator)
             .unwrap_or_else(|index| min(entries_len - 1, index));
         self.vec_entry[index].1.item.clone()
     }
 
-    pub(crate) fn binary_search(&self, key: &[u8]) -> Result<usize, usize> {
+    pub(crate) fn binary_search(
+        &self,
+        key: &[u8],
+        comparator: &dyn Comparator,
+    ) -> Result<usize, usize> {
+        let mut buf_key = Vec::new();
+
         self.vec_entry.binary_search_by(|(index, entry)| {
             if entry.shared_len > 0 {
                 // 对有前缀压缩的Key进行前缀拼接
-                let shared_len = min(entry.shared_len, key.len());
-                key[0..shared_len]
-                    .cmp(self.shared_key_prefix(*index, shared_len))
-                    .then_with(|| key[shared_len..].cmp(&entry.key))
+                buf_key.clear();
+                buf_key.extend_from_slice(self.shared_key_prefix(*index, entry.shared_len));
+                buf_key.extend_from_slice(&entry.key);
+
+                comparator.compare(&buf_key, key)
             } else {
-                key.cmp(&entry.key)
+                comparator.compare(&entry.key, key)
             }
-            .reverse()
         })
     }
 
     /// 序列化后进行压缩
     ///
-    /// 可选LZ4与不压缩
-    pub(crate) fn encode(
-        &self,
-        compress_type: CompressType,
-        bytes: &mut Vec<u8>,
-    ) -> KernelResult<()> {
-        match compress_type {
-            CompressType::None => self.to_raw(bytes)?,
-            CompressType::LZ4 => {
-                let mut buf = Vec::new();
-                self.to_raw(&mut buf)?;
-
-                let mut encoder = lz4::EncoderBuilder::new().level(4).build(bytes.writer())?;
-                let _ = encoder.write(&buf[..])?;
-                let (_, result) = encoder.finish();
-
-                result?;
-            }
-        }
+    /// 压缩方式由Compressor决定，并记录于Block末尾
+    pub(crate) fn encode(&self, compressor: &Compressor, bytes: &mut Vec<u8>) -> KernelResult<()> {
+        let mut buf = Vec::new();
+        self.to_raw(&mut buf)?;
 
-        Ok(())
+        compressor.compress(&buf, bytes)
     }
 
     /// 解压后反序列化
     ///
-    /// 与encode对应，进行数据解压操作并反序列化为Block
-    pub(crate) fn decode(
+    /// 与encode对应，依照Block末尾记录的压缩方式进行数据解压并反序列化为Block
+    pub(crate) fn decode(buf: Vec<u8>, dict: &[u8], restart_interval: usize) -> KernelResult<Self> {
+        Self::from_raw(decompress(buf, dict)?, restart_interval)
+    }
+
+    /// 解码未记录格式版本的旧SSTable中的Block
+    ///
+    /// 旧格式的Block末尾未记录压缩方式，DataBlock固定使用LZ4而IndexBlock不压缩
+    pub(crate) fn decode_legacy(
         buf: Vec<u8>,
         compress_type: CompressType,
         restart_interval: usize,
     ) -> KernelResult<Self> {
         let buf = match compress_type {
-            CompressType::None => buf,
-            CompressType::LZ4 => {
-                let mut decoder = Decoder::new(buf.reader())?;
-                let mut decoded = Vec::with_capacity(DEFAULT_BLOCK_SIZE);
-                let _ = decoder.read_to_end(&mut decoded)?;
-                decoded
-            }
+            CompressType::LZ4 => lz4_decompress(buf)?,
+            _ => buf,
         };
-        Self::from_raw(buf, restart_interval)
+        Self::from_raw_with(buf, restart_interval, true)
     }
 
     /// 读取Bytes进行Block的反序列化
-    pub(crate) fn from_raw(mut buf: Vec<u8>, restart_interval: usize) -> KernelResult<Self> {
+    pub(crate) fn from_raw(buf: Vec<u8>, restart_interval: usize) -> KernelResult<Self> {
+        Self::from_raw_with(buf, restart_interval, false)
+    }
+
+    fn from_raw_with(
+        mut buf: Vec<u8>,
+        restart_interval: usize,
+        is_legacy: bool,
+    ) -> KernelResult<Self> {
         assert!(!buf.is_empty());
         let date_bytes_len = buf.len() - CRC_SIZE;
         if crc32fast::hash(&buf) == u32::decode_fixed(&buf[date_bytes_len..]) {
@@ -571,7 +933,7 @@ where
         buf.truncate(date_bytes_len);
 
         let mut cursor = Cursor::new(buf);
-        let vec_entry = Entry::<T>::batch_decode(&mut cursor)?;
+        let vec_entry = Entry::<T>::batch_decode_with(&mut cursor, is_legacy)?;
         Ok(Self {
             restart_interval,
             vec_entry,
@@ -647,8 +1009,11 @@ fn longest_shared_len<T>(sharding: Vec<&KeyValue<T>>) -> usize {
 
 #[cfg(test)]
 mod tests {
+    use crate::kernel::lsm::comparator::BytewiseComparator;
+    use crate::kernel::lsm::mem_table::ValueMeta;
     use crate::kernel::lsm::table::ss_table::block::{
-        Block, BlockBuilder, BlockOptions, CompressType, Entry, Index, Value,
+        decompress, Block, BlockBuilder, BlockOptions, CompressType, Compressor, Entry, Index,
+        Value,
     };
     use crate::kernel::utils::lru_cache::LruCache;
     use crate::kernel::KernelResult;
@@ -682,6 +1047,86 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_value_type_with_seq() -> KernelResult<()> {
+        // 空值与删除标记需要能够被区分，且seq_id需要能被正确还原
+        let empty = Entry::new(
+            0,
+            1,
+            Bytes::from(vec![b'1']),
+            Value::new(Some(Bytes::new()), 7),
+        );
+        let delete = Entry::new(0, 1, Bytes::from(vec![b'2']), Value::new(None, i64::MAX));
+        let mut bytes = Vec::new();
+
+        empty.encode(&mut bytes)?;
+        delete.encode(&mut bytes)?;
+
+        let vec_entry = Entry::<Value>::batch_decode(&mut Cursor::new(bytes))?;
+
+        assert_eq!(vec_entry[0].1.item.bytes, Some(Bytes::new()));
+        assert_eq!(vec_entry[0].1.item.seq_id, 7);
+        assert_eq!(vec_entry[1].1.item.bytes, None);
+        assert_eq!(vec_entry[1].1.item.seq_id, i64::MAX);
+        assert_eq!(vec![(0, empty), (1, delete)], vec_entry);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_with_expire() -> KernelResult<()> {
+        let ttl = Entry::new(
+            0,
+            1,
+            Bytes::from(vec![b'1']),
+            Value::new(Some(Bytes::from(vec![b'1'])), 7).meta(ValueMeta::with_expire(Some(1_000))),
+        );
+        // 删除标记会忽略过期时间
+        let delete = Entry::new(
+            0,
+            1,
+            Bytes::from(vec![b'2']),
+            Value::new(None, 8).meta(ValueMeta::with_expire(Some(1_000))),
+        );
+        // Merge操作数会忽略过期时间
+        let merge = Entry::new(
+            0,
+            1,
+            Bytes::from(vec![b'3']),
+            Value::new(Some(Bytes::from(vec![b'3'])), 9).meta(ValueMeta {
+                expire_at: Some(1_000),
+                is_merge: true,
+                ..Default::default()
+            }),
+        );
+        let range_del = Entry::new(
+            0,
+            1,
+            Bytes::from(vec![b'4']),
+            Value::new(Some(Bytes::from(vec![b'6'])), 10).meta(ValueMeta::range_delete()),
+        );
+        let mut bytes = Vec::new();
+
+        ttl.encode(&mut bytes)?;
+        delete.encode(&mut bytes)?;
+        merge.encode(&mut bytes)?;
+        range_del.encode(&mut bytes)?;
+
+        let vec_entry = Entry::<Value>::batch_decode(&mut Cursor::new(bytes))?;
+
+        assert_eq!(vec_entry[0].1.item.meta.expire_at, Some(1_000));
+        assert_eq!(vec_entry[0].1.item.seq_id, 7);
+        assert_eq!(vec_entry[1].1.item.meta, ValueMeta::default());
+        assert_eq!(vec_entry[2].1.item.meta, ValueMeta::merge());
+        assert_eq!(vec_entry[3].1.item.meta, ValueMeta::range_delete());
+        assert_eq!(
+            vec![(0, ttl), (1, delete), (2, merge), (3, range_del)],
+            vec_entry
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_block() -> KernelResult<()> {
         let value = Bytes::from_static(b"Let life be beautiful like summer flowers");
@@ -704,11 +1149,12 @@ mod tests {
 
         let block = builder.vec_block[0].0.clone();
 
-        let (full_bytes, data_len, _) = builder.build().await?;
+        let (full_bytes, data_len, _, dict) = builder.build().await?;
+        assert!(dict.is_empty());
 
         let index_block = Block::<Index>::decode(
             full_bytes[data_len..].to_vec(),
-            CompressType::None,
+            &[],
             options.index_restart_interval,
         )?;
 
@@ -716,28 +1162,37 @@ mod tests {
 
         for kv in vec_data.iter().take(times) {
             let key = &kv.0;
-            let data_block = cache.get_or_insert(index_block.find_with_upper(key), |index| {
-                let &Index { offset, len } = index;
-                let target_block = Block::<Value>::decode(
-                    full_bytes[offset as usize..offset as usize + len].to_vec(),
-                    options.compress_type,
-                    options.data_restart_interval,
-                )?;
-                Ok(target_block)
-            })?;
-            assert_eq!(data_block.find(key), (Some(value.clone()), true))
-        }
-
-        test_block_serialization_(
-            block.clone(),
+            let data_block = cache.get_or_insert(
+                index_block.find_with_upper(key, &BytewiseComparator),
+                |index| {
+                    let &Index { offset, len } = index;
+                    let target_block = Block::<Value>::decode(
+                        full_bytes[offset as usize..offset as usize + len].to_vec(),
+                        &[],
+                        options.data_restart_interval,
+                    )?;
+                    Ok(target_block)
+                },
+            )?;
+            assert_eq!(
+                data_block
+                    .find(key, &BytewiseComparator)
+                    .map(|item| item.bytes.clone()),
+                Some(Some(value.clone()))
+            )
+        }
+
+        for compress_type in [
             CompressType::None,
-            options.data_restart_interval,
-        )?;
-        test_block_serialization_(
-            block.clone(),
             CompressType::LZ4,
-            options.data_restart_interval,
-        )?;
+            CompressType::Snappy,
+            CompressType::Zstd {
+                level: 3,
+                dict_size: 0,
+            },
+        ] {
+            test_block_serialization_(block.clone(), compress_type, options.data_restart_interval)?;
+        }
 
         Ok(())
     }
@@ -748,11 +1203,83 @@ mod tests {
         restart_interval: usize,
     ) -> KernelResult<()> {
         let mut bytes = Vec::new();
-        block.encode(compress_type, &mut bytes)?;
+        block.encode(&Compressor::new(compress_type), &mut bytes)?;
+
+        // 压缩方式记录于Block末尾
+        let tag = *bytes.last().unwrap();
+        assert_eq!(tag, compress_type.tag());
 
-        let de_block = Block::decode(bytes, compress_type, restart_interval)?;
+        let de_block = Block::decode(bytes, &[], restart_interval)?;
         assert_eq!(block, de_block);
 
         Ok(())
     }
+
+    #[test]
+    fn test_compress_skip() -> KernelResult<()> {
+        // 随机数据几乎无法压缩，此时直接存储原数据
+        let raw = (0..4096).map(|_| rand::random::<u8>()).collect::<Vec<u8>>();
+
+        for compress_type in [
+            CompressType::LZ4,
+            CompressType::Snappy,
+            CompressType::Zstd {
+                level: 3,
+                dict_size: 0,
+            },
+        ] {
+            let mut bytes = Vec::new();
+            Compressor::new(compress_type).compress(&raw, &mut bytes)?;
+
+            assert_eq!(bytes.len(), raw.len() + 1);
+            assert_eq!(bytes.last(), Some(&CompressType::None.tag()));
+            assert_eq!(decompress(bytes, &[])?, raw);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_block_with_zstd_dict() -> KernelResult<()> {
+        let compress_type = CompressType::Zstd {
+            level: 3,
+            dict_size: 4096,
+        };
+        let options = BlockOptions::new()
+            .block_size(512)
+            .compress_type(compress_type);
+        let mut builder = BlockBuilder::new(options.clone());
+        let mut vec_data = Vec::new();
+
+        for i in 0..4096u32 {
+            let key = Bytes::from(format!("KipDB-{i:08}"));
+            let value = Bytes::from(format!("user-{}-profile-{}", i % 97, i % 13));
+
+            vec_data.push((key.clone(), value.clone()));
+            builder.add((key, Value::from(Some(value))));
+        }
+        let (full_bytes, data_len, _, dict) = builder.build().await?;
+        assert!(!dict.is_empty());
+
+        let index_block = Block::<Index>::decode(
+            full_bytes[data_len..].to_vec(),
+            &[],
+            options.index_restart_interval,
+        )?;
+        for (key, value) in vec_data {
+            let Index { offset, len } = index_block.find_with_upper(&key, &BytewiseComparator);
+            let bytes = full_bytes[offset as usize..offset as usize + len].to_vec();
+            assert_eq!(bytes.last(), Some(&compress_type.tag()));
+
+            let data_block = Block::<Value>::decode(bytes, &dict, options.data_restart_interval)?;
+            assert_eq!(
+                data_block
+                    .find(&key, &BytewiseComparator)
+                    .map(|item| item.bytes.clone()),
+                Some(Some(value))
+            );
+        }
+
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+use crate::kernel::lsm::comparator::Comparator;
 use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
 use crate::kernel::lsm::table::ss_table::block::{Block, BlockItem, Entry};
 use crate::kernel::KernelResult;
@@ -8,6 +9,7 @@ use bytes::Bytes;
 /// Tips: offset偏移会额外向上偏移一位以使用0作为迭代的下界判断是否向前溢出了
 pub(crate) struct BlockIter<'a, T> {
     block: &'a Block<T>,
+    comparator: &'a dyn Comparator,
     entry_len: usize,
 
     offset: usize,
@@ -18,11 +20,12 @@ impl<'a, T> BlockIter<'a, T>
 where
     T: BlockItem,
 {
-    pub(crate) fn new(block: &'a Block<T>) -> BlockIter<'a, T> {
+    pub(crate) fn new(block: &'a Block<T>, comparator: &'a dyn Comparator) -> BlockIter<'a, T> {
         let buf_shared_key = block.shared_key_prefix(0, block.restart_shared_len(0));
 
         BlockIter {
             block,
+            comparator,
             entry_len: block.entry_len(),
             offset: 0,
             buf_shared_key,
@@ -43,18 +46,14 @@ where
 
     fn offset_move(&mut self, offset: usize, is_seek: bool) -> Option<(Bytes, T)> {
         let block = self.block;
-        let restart_interval = block.restart_interval();
-
-        let old_offset = self.offset;
         self.offset = offset;
 
+        // Tips: Seek至边界时不会更新buf_shared_key，因此每次移动都需要重新获取共享前缀
         (offset > 0 && offset < self.entry_len + 1)
             .then(|| {
                 let real_offset = offset - 1;
-                if old_offset - 1 / restart_interval != real_offset / restart_interval {
-                    self.buf_shared_key =
-                        block.shared_key_prefix(real_offset, block.restart_shared_len(real_offset));
-                }
+                self.buf_shared_key =
+                    block.shared_key_prefix(real_offset, block.restart_shared_len(real_offset));
                 (!is_seek).then(|| self.item())
             })
             .flatten()
@@ -94,15 +93,18 @@ where
     V: Sync + Send + BlockItem,
 {
     fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()> {
-        match seek {
-            Seek::First => Some(0),
-            Seek::Last => Some(self.entry_len + 1),
-            Seek::Backward(key) => match self.block.binary_search(key) {
-                Ok(index) => Some(index),
-                Err(index) => (index < self.entry_len).then_some(index),
+        let offset = match seek {
+            Seek::First => 0,
+            Seek::Last => self.entry_len + 1,
+            Seek::Backward(key) => match self.block.binary_search(key, self.comparator) {
+                Ok(index) | Err(index) => index,
             },
-        }
-        .and_then(|index| self.offset_move(index, true));
+            Seek::Forward(key) => match self.block.binary_search(key, self.comparator) {
+                Ok(index) => index + 2,
+                Err(index) => index + 1,
+            },
+        };
+        let _ = self.offset_move(offset, true);
 
         Ok(())
     }
@@ -110,6 +112,7 @@ where
 
 #[cfg(test)]
 mod tests {
+    use crate::kernel::lsm::comparator::BytewiseComparator;
     use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
     use crate::kernel::lsm::table::ss_table::block::{Block, Value, DEFAULT_DATA_RESTART_INTERVAL};
     use crate::kernel::lsm::table::ss_table::block_iter::BlockIter;
@@ -130,7 +133,7 @@ mod tests {
         ];
         let block = Block::new(data, DEFAULT_DATA_RESTART_INTERVAL);
 
-        let mut iterator = BlockIter::new(&block);
+        let mut iterator = BlockIter::new(&block, &BytewiseComparator);
 
         assert!(!iterator.is_valid());
 
@@ -198,6 +201,31 @@ mod tests {
             Some((Bytes::from(vec![b'4']), Value::from(None)))
         );
 
+        iterator.seek(Seek::Backward(&[b'5']))?;
+        assert_eq!(iterator.try_next()?, None);
+        assert_eq!(
+            iterator.try_prev()?,
+            Some((Bytes::from(vec![b'4']), Value::from(None)))
+        );
+
+        iterator.seek(Seek::Forward(&[b'3']))?;
+        assert_eq!(
+            iterator.try_prev()?,
+            Some((
+                Bytes::from(vec![b'2']),
+                Value::from(Some(Bytes::from(vec![b'0'])))
+            ))
+        );
+
+        iterator.seek(Seek::Forward(&[b'4']))?;
+        assert_eq!(
+            iterator.try_prev()?,
+            Some((Bytes::from(vec![b'4']), Value::from(None)))
+        );
+
+        iterator.seek(Seek::Forward(&[b'0']))?;
+        assert_eq!(iterator.try_prev()?, None);
+
         Ok(())
     }
 
@@ -217,7 +245,7 @@ mod tests {
         let block = Block::new(vec_data.clone(), DEFAULT_DATA_RESTART_INTERVAL);
 
         tokio_test::block_on(async move {
-            let mut iterator = BlockIter::new(&block);
+            let mut iterator = BlockIter::new(&block, &BytewiseComparator);
 
             for kv in vec_data.iter().take(times) {
                 assert_eq!(iterator.try_next()?.unwrap(), kv.clone());
@@ -227,6 +255,15 @@ mod tests {
                 assert_eq!(iterator.try_prev()?.unwrap(), vec_data[i]);
             }
 
+            iterator.seek(Seek::Last)?;
+            for i in (0..times).rev() {
+                assert_eq!(iterator.try_prev()?.unwrap(), vec_data[i]);
+            }
+
+            iterator.seek(Seek::Forward(&vec_data[514].0))?;
+            assert_eq!(iterator.try_prev()?.unwrap(), vec_data[514]);
+            assert_eq!(iterator.try_prev()?.unwrap(), vec_data[513]);
+
             Ok(())
         })
     }
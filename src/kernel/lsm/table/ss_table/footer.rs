@@ -5,7 +5,21 @@ use std::io::SeekFrom;
 
 /// Footer序列化长度定长
 /// 注意Footer序列化时，需要使用类似BinCode这样的定长序列化框架，否则若类似Rmp的话会导致Footer在不同数据时，长度不一致
-pub(crate) const TABLE_FOOTER_SIZE: usize = 21;
+pub(crate) const TABLE_FOOTER_SIZE: usize = 30;
+
+/// 未记录格式版本的旧Footer的长度
+const LEGACY_TABLE_FOOTER_SIZE: usize = 21;
+
+/// 记录于Footer末尾，用于区分未记录格式版本的旧SSTable
+const TABLE_MAGIC: u64 = 0x4b69_7044_4253_5354;
+
+/// 未记录格式版本的旧SSTable
+///
+/// 其Block末尾未记录压缩方式，且Value中未记录seq_id等信息
+pub(crate) const LEGACY_FORMAT_VERSION: u8 = 0;
+
+/// 当前的SSTable格式版本，Block末尾记录了压缩方式
+pub(crate) const TABLE_FORMAT_VERSION: u8 = 1;
 
 #[derive(Debug, PartialEq, Eq)]
 #[repr(C, align(32))]
@@ -16,12 +30,23 @@ pub(crate) struct Footer {
     pub(crate) meta_offset: u32,
     pub(crate) meta_len: u32,
     pub(crate) size_of_disk: u32,
+    /// SSTable的格式版本
+    pub(crate) version: u8,
 }
 
 impl Footer {
     /// 从对应文件的IOHandler中将Footer读取出来
+    ///
+    /// 末尾不存在TABLE_MAGIC时视为旧格式的Footer
     pub(crate) fn read_to_file(mut reader: &mut dyn IoReader) -> KernelResult<Self> {
-        let _ = reader.seek(SeekFrom::End(-(TABLE_FOOTER_SIZE as i64)))?;
+        let _ = reader.seek(SeekFrom::End(-8))?;
+        let is_legacy = reader.read_fixedint::<u64>()? != TABLE_MAGIC;
+        let footer_size = if is_legacy {
+            LEGACY_TABLE_FOOTER_SIZE
+        } else {
+            TABLE_FOOTER_SIZE
+        };
+        let _ = reader.seek(SeekFrom::End(-(footer_size as i64)))?;
 
         Ok(Footer {
             level: reader.read_fixedint()?,
@@ -30,6 +55,11 @@ impl Footer {
             meta_offset: reader.read_fixedint()?,
             meta_len: reader.read_fixedint()?,
             size_of_disk: reader.read_fixedint()?,
+            version: if is_legacy {
+                LEGACY_FORMAT_VERSION
+            } else {
+                reader.read_fixedint()?
+            },
         })
     }
 
@@ -40,6 +70,8 @@ impl Footer {
         bytes.write_fixedint(self.meta_offset)?;
         bytes.write_fixedint(self.meta_len)?;
         bytes.write_fixedint(self.size_of_disk)?;
+        bytes.write_fixedint(self.version)?;
+        bytes.write_fixedint(TABLE_MAGIC)?;
 
         Ok(())
     }
@@ -47,7 +79,9 @@ impl Footer {
 
 #[cfg(test)]
 mod test {
-    use crate::kernel::lsm::table::ss_table::footer::{Footer, TABLE_FOOTER_SIZE};
+    use crate::kernel::lsm::table::ss_table::footer::{
+        Footer, TABLE_FOOTER_SIZE, TABLE_FORMAT_VERSION,
+    };
     use crate::kernel::KernelResult;
 
     #[test]
@@ -60,6 +94,7 @@ mod test {
             meta_offset: 0,
             meta_len: 0,
             size_of_disk: 0,
+            version: TABLE_FORMAT_VERSION,
         };
         info.to_raw(&mut bytes)?;
 
@@ -1,5 +1,5 @@
 use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
-use crate::kernel::lsm::mem_table::KeyValue;
+use crate::kernel::lsm::mem_table::SeqKeyValue;
 use crate::kernel::lsm::table::ss_table::block::{BlockType, Index, Value};
 use crate::kernel::lsm::table::ss_table::block_iter::BlockIter;
 use crate::kernel::lsm::table::ss_table::SSTable;
@@ -15,7 +15,7 @@ pub(crate) struct SSTableIter<'a> {
 
 impl<'a> SSTableIter<'a> {
     pub(crate) fn new(ss_table: &'a SSTable) -> KernelResult<SSTableIter<'a>> {
-        let mut index_iter = BlockIter::new(ss_table.index_block()?);
+        let mut index_iter = BlockIter::new(ss_table.index_block()?, ss_table.comparator.as_ref());
         let index = index_iter.try_next()?.ok_or(KernelError::DataEmpty)?.1;
         let data_iter = Self::data_iter_init(ss_table, index)?;
 
@@ -30,10 +30,13 @@ impl<'a> SSTableIter<'a> {
         let block = {
             ss_table
                 .cache
-                .get_or_insert((ss_table.gen(), Some(index)), |(_, index)| {
-                    let index = (*index).ok_or_else(|| KernelError::DataEmpty)?;
-                    ss_table.data_block(index)
-                })
+                .get_or_insert(
+                    (ss_table.family_id, ss_table.gen(), Some(index)),
+                    |(_, _, index)| {
+                        let index = (*index).ok_or_else(|| KernelError::DataEmpty)?;
+                        ss_table.data_block(index)
+                    },
+                )
                 .map(|block_type| match block_type {
                     BlockType::Data(data_block) => Some(data_block),
                     _ => None,
@@ -41,7 +44,7 @@ impl<'a> SSTableIter<'a> {
         }
         .ok_or(KernelError::DataEmpty)?;
 
-        Ok(BlockIter::new(block))
+        Ok(BlockIter::new(block, ss_table.comparator.as_ref()))
     }
 
     fn data_iter_seek(&mut self, seek: Seek<'_>, index: Index) -> KernelResult<()> {
@@ -54,42 +57,36 @@ impl<'a> SSTableIter<'a> {
 
 impl<'a> ForwardIter<'a> for SSTableIter<'a> {
     fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
-        match self.data_iter.try_prev()? {
-            None => {
-                if let Some((_, index)) = self.index_iter.try_prev()? {
-                    self.data_iter_seek(Seek::Last, index)?;
-
-                    Ok(self
-                        .data_iter
-                        .try_prev()?
-                        .map(|(key, value)| (key, value.bytes)))
-                } else {
-                    Ok(None)
-                }
+        loop {
+            if let Some((key, value)) = self.data_iter.try_prev()? {
+                return Ok(Some(((key, value.bytes), value.seq_id, value.meta)));
+            }
+            if let Some((_, index)) = self.index_iter.try_prev()? {
+                self.data_iter_seek(Seek::Last, index)?;
+            } else {
+                // 使index_iter重新停留在第一个Block上，以保证后续try_next的正确
+                let _ = self.index_iter.try_next()?;
+                return Ok(None);
             }
-            Some((key, value)) => Ok(Some((key, value.bytes))),
         }
     }
 }
 
 impl<'a> Iter<'a> for SSTableIter<'a> {
-    type Item = KeyValue;
+    type Item = SeqKeyValue;
 
     fn try_next(&mut self) -> KernelResult<Option<Self::Item>> {
-        match self.data_iter.try_next()? {
-            None => {
-                if let Some((_, index)) = self.index_iter.try_next()? {
-                    self.data_iter_seek(Seek::First, index)?;
-
-                    Ok(self
-                        .data_iter
-                        .try_next()?
-                        .map(|(key, value)| (key, value.bytes)))
-                } else {
-                    Ok(None)
-                }
+        loop {
+            if let Some((key, value)) = self.data_iter.try_next()? {
+                return Ok(Some(((key, value.bytes), value.seq_id, value.meta)));
+            }
+            if let Some((_, index)) = self.index_iter.try_next()? {
+                self.data_iter_seek(Seek::First, index)?;
+            } else {
+                // 使index_iter重新停留在最后一个Block上，以保证后续try_prev的正确
+                let _ = self.index_iter.try_prev()?;
+                return Ok(None);
             }
-            Some((key, value)) => Ok(Some((key, value.bytes))),
         }
     }
 
@@ -100,13 +97,36 @@ impl<'a> Iter<'a> for SSTableIter<'a> {
 
 impl<'a> SeekIter<'a> for SSTableIter<'a> {
     fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()> {
-        self.index_iter.seek(seek)?;
+        match seek {
+            Seek::First => {
+                self.index_iter.seek(Seek::First)?;
+                if let Some((_, index)) = self.index_iter.try_next()? {
+                    self.data_iter_seek(Seek::First, index)?;
+                }
+            }
+            Seek::Last => self.seek_last()?,
+            // 索引的Key为Block中的最大Key，因此定位到第一个最大Key不小于key的Block
+            // Forward时若该Block中所有Key都大于key，则由try_prev回退至上一个Block
+            Seek::Backward(key) | Seek::Forward(key) => {
+                self.index_iter.seek(Seek::Backward(key))?;
 
-        if let Some((_, index)) = self.index_iter.try_next()? {
-            self.data_iter_seek(seek, index)?;
+                if let Some((_, index)) = self.index_iter.try_next()? {
+                    self.data_iter_seek(seek, index)?;
+                } else {
+                    self.seek_last()?;
+                }
+            }
         }
-        if matches!(seek, Seek::Last) {
-            self.data_iter.seek(Seek::Last)?;
+
+        Ok(())
+    }
+}
+
+impl SSTableIter<'_> {
+    fn seek_last(&mut self) -> KernelResult<()> {
+        self.index_iter.seek(Seek::Last)?;
+        if let Some((_, index)) = self.index_iter.try_prev()? {
+            self.data_iter_seek(Seek::Last, index)?;
         }
 
         Ok(())
@@ -117,6 +137,7 @@ impl<'a> SeekIter<'a> for SSTableIter<'a> {
 mod tests {
     use crate::kernel::io::{FileExtension, IoFactory, IoType};
     use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
+    use crate::kernel::lsm::mem_table::ValueMeta;
     use crate::kernel::lsm::storage::Config;
     use crate::kernel::lsm::table::ss_table::iter::SSTableIter;
     use crate::kernel::lsm::table::ss_table::SSTable;
@@ -150,7 +171,11 @@ mod tests {
         for i in 0..times {
             let mut key = b"KipDB-".to_vec();
             key.append(&mut bincode::options().with_big_endian().serialize(&i)?);
-            vec_data.push((Bytes::from(key), Some(value.clone())));
+            vec_data.push((
+                (Bytes::from(key), Some(value.clone())),
+                i as i64,
+                ValueMeta::default(),
+            ));
         }
         let cache = Arc::new(ShardingLruCache::new(
             config.table_cache_size,
@@ -179,7 +204,7 @@ mod tests {
             assert_eq!(iterator.try_prev()?.unwrap(), vec_data[i]);
         }
 
-        iterator.seek(Seek::Backward(&vec_data[114].0))?;
+        iterator.seek(Seek::Backward(&vec_data[114].0 .0))?;
         assert_eq!(iterator.try_next()?.unwrap(), vec_data[114]);
 
         iterator.seek(Seek::First)?;
@@ -188,6 +213,28 @@ mod tests {
         iterator.seek(Seek::Last)?;
         assert_eq!(iterator.try_next()?, None);
 
+        iterator.seek(Seek::Last)?;
+        for i in (0..times).rev() {
+            assert_eq!(iterator.try_prev()?.unwrap(), vec_data[i]);
+        }
+        assert_eq!(iterator.try_prev()?, None);
+        assert_eq!(iterator.try_next()?.unwrap(), vec_data[0]);
+
+        iterator.seek(Seek::Forward(&vec_data[1919].0 .0))?;
+        assert_eq!(iterator.try_prev()?.unwrap(), vec_data[1919]);
+        assert_eq!(iterator.try_next()?.unwrap(), vec_data[1920]);
+
+        // 超出范围时定位至末尾
+        iterator.seek(Seek::Backward(b"KipDB-\xff"))?;
+        assert_eq!(iterator.try_next()?, None);
+        assert_eq!(iterator.try_prev()?.unwrap(), vec_data[times - 1]);
+
+        iterator.seek(Seek::Forward(b"KipDB-\xff"))?;
+        assert_eq!(iterator.try_prev()?.unwrap(), vec_data[times - 1]);
+
+        iterator.seek(Seek::Forward(b"KipDB"))?;
+        assert_eq!(iterator.try_prev()?, None);
+
         Ok(())
     }
 }
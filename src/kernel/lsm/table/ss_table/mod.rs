@@ -1,14 +1,19 @@
 use crate::kernel::io::{IoFactory, IoReader, IoType};
-use crate::kernel::lsm::iterator::SeekIter;
-use crate::kernel::lsm::mem_table::KeyValue;
+use crate::kernel::lsm::comparator::Comparator;
+use crate::kernel::lsm::iterator::{EmptyIter, SeekIter};
+use crate::kernel::lsm::mem_table::SeqKeyValue;
+use crate::kernel::lsm::range_tombstone::RangeTombstone;
 use crate::kernel::lsm::storage::Config;
 use crate::kernel::lsm::table::ss_table::block::{
     Block, BlockBuilder, BlockCache, BlockItem, BlockOptions, BlockType, CompressType, Index,
     MetaBlock, Value,
 };
-use crate::kernel::lsm::table::ss_table::footer::{Footer, TABLE_FOOTER_SIZE};
+use crate::kernel::lsm::table::ss_table::footer::{
+    Footer, LEGACY_FORMAT_VERSION, TABLE_FOOTER_SIZE, TABLE_FORMAT_VERSION,
+};
 use crate::kernel::lsm::table::ss_table::iter::SSTableIter;
 use crate::kernel::lsm::table::Table;
+use crate::kernel::lsm::value_log::collect_blob_gens;
 use crate::kernel::utils::bloom_filter::BloomFilter;
 use crate::kernel::KernelResult;
 use crate::KernelError;
@@ -34,45 +39,88 @@ pub(crate) struct SSTable {
     reader: Mutex<Box<dyn IoReader>>,
     // 该SSTable的唯一编号(时间递增)
     gen: i64,
+    // 所属ColumnFamily的id
+    family_id: usize,
     // 统计信息存储Block
     meta: MetaBlock,
     // Block缓存(Index/Value)
     cache: Arc<BlockCache>,
+    // Key的排序规则
+    comparator: Arc<dyn Comparator>,
 }
 
 impl SSTable {
+    #[allow(dead_code)]
     pub(crate) async fn new(
         io_factory: &IoFactory,
         config: &Config,
         cache: Arc<BlockCache>,
         gen: i64,
-        vec_data: Vec<KeyValue>,
+        vec_data: Vec<SeqKeyValue>,
+        level: usize,
+        io_type: IoType,
+    ) -> KernelResult<SSTable> {
+        Self::new_with_range_tombstones(
+            io_factory,
+            config,
+            cache,
+            gen,
+            vec_data,
+            Vec::new(),
+            level,
+            io_type,
+        )
+        .await
+    }
+
+    /// 构建附带范围删除标记的SSTable，范围删除标记存储于MetaBlock中
+    ///
+    /// vec_data可以为空，此时该SSTable仅用于持有范围删除标记
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn new_with_range_tombstones(
+        io_factory: &IoFactory,
+        config: &Config,
+        cache: Arc<BlockCache>,
+        gen: i64,
+        vec_data: Vec<SeqKeyValue>,
+        range_tombstones: Vec<RangeTombstone>,
         level: usize,
         io_type: IoType,
     ) -> KernelResult<SSTable> {
         let len = vec_data.len();
+        let max_seq = vec_data
+            .iter()
+            .map(|(_, seq_id, _)| *seq_id)
+            .chain(range_tombstones.iter().map(|tombstone| tombstone.seq_id))
+            .max()
+            .unwrap_or(0);
         let data_restart_interval = config.data_restart_interval;
         let index_restart_interval = config.index_restart_interval;
         let mut filter = BloomFilter::new(len, config.desired_error_prob);
+        let blob_gens = collect_blob_gens(&vec_data);
 
         let mut builder = BlockBuilder::new(
             BlockOptions::from(config)
-                .compress_type(CompressType::LZ4)
+                .compress_type(config.level_compress_type[level])
                 .data_restart_interval(data_restart_interval)
                 .index_restart_interval(index_restart_interval),
         );
-        for data in vec_data {
-            let (key, value) = data;
+        for ((key, value), seq_id, meta) in vec_data {
             filter.insert(key.as_slice());
-            builder.add((key, Value::from(value)));
+            builder.add((key, Value::new(value, seq_id).meta(meta)));
         }
+        let (mut bytes, data_bytes_len, index_bytes_len, compression_dict) =
+            builder.build().await?;
         let meta = MetaBlock {
             filter,
             len,
             index_restart_interval,
             data_restart_interval,
+            max_seq,
+            range_tombstones,
+            blob_gens,
+            compression_dict,
         };
-        let (mut bytes, data_bytes_len, index_bytes_len) = builder.build().await?;
         meta.to_raw(&mut bytes)?;
 
         let footer = Footer {
@@ -82,6 +130,7 @@ impl SSTable {
             meta_offset: (data_bytes_len + index_bytes_len) as u32,
             meta_len: (bytes.len() - data_bytes_len + index_bytes_len) as u32,
             size_of_disk: (bytes.len() + TABLE_FOOTER_SIZE) as u32,
+            version: TABLE_FORMAT_VERSION,
         };
         footer.to_raw(&mut bytes)?;
 
@@ -95,8 +144,10 @@ impl SSTable {
             footer,
             reader,
             gen,
+            family_id: config.family_id,
             meta,
             cache,
+            comparator: Arc::clone(&config.comparator),
         })
     }
 
@@ -106,6 +157,7 @@ impl SSTable {
     pub(crate) fn load_from_file(
         mut reader: Box<dyn IoReader>,
         cache: Arc<BlockCache>,
+        config: &Config,
     ) -> KernelResult<Self> {
         let gen = reader.get_gen();
         let footer = Footer::read_to_file(reader.as_mut())?;
@@ -125,41 +177,45 @@ impl SSTable {
         let _ = reader.seek(SeekFrom::Start(*meta_offset as u64))?;
         let _ = reader.read(&mut buf)?;
 
-        let meta = MetaBlock::from_raw(&buf);
+        let meta = if footer.version == LEGACY_FORMAT_VERSION {
+            MetaBlock::from_raw_legacy(&buf)
+        } else {
+            MetaBlock::from_raw(&buf)?
+        };
         let reader = Mutex::new(reader);
         Ok(SSTable {
             footer,
             gen,
+            family_id: config.family_id,
             reader,
             meta,
             cache,
+            comparator: Arc::clone(&config.comparator),
         })
     }
 
     pub(crate) fn data_block(&self, index: Index) -> KernelResult<BlockType> {
-        Ok(BlockType::Data(Self::loading_block(
-            self.reader.lock().as_mut(),
+        Ok(BlockType::Data(self.loading_block(
             index.offset(),
             index.len(),
-            CompressType::LZ4,
             self.meta.data_restart_interval,
+            true,
         )?))
     }
 
     pub(crate) fn index_block(&self) -> KernelResult<&Block<Index>> {
         self.cache
-            .get_or_insert((self.gen(), None), |_| {
+            .get_or_insert((self.family_id, self.gen(), None), |_| {
                 let Footer {
                     index_offset,
                     index_len,
                     ..
                 } = self.footer;
-                Ok(BlockType::Index(Self::loading_block(
-                    self.reader.lock().as_mut(),
+                Ok(BlockType::Index(self.loading_block(
                     index_offset,
                     index_len as usize,
-                    CompressType::None,
                     self.meta.index_restart_interval,
+                    false,
                 )?))
             })
             .map(|block_type| match block_type {
@@ -169,43 +225,94 @@ impl SSTable {
             .ok_or(KernelError::DataEmpty)
     }
 
+    /// 读取并解码Block，is_data为true时为DataBlock，否则为IndexBlock
+    ///
+    /// 旧格式的SSTable中Block末尾未记录压缩方式，其DataBlock固定使用LZ4而IndexBlock不压缩
     fn loading_block<T>(
-        reader: &mut dyn IoReader,
+        &self,
         offset: u32,
         len: usize,
-        compress_type: CompressType,
         restart_interval: usize,
+        is_data: bool,
     ) -> KernelResult<Block<T>>
     where
         T: BlockItem,
     {
         let mut buf = vec![0; len];
-        let _ = reader.seek(SeekFrom::Start(offset as u64))?;
-        reader.read_exact(&mut buf)?;
+        {
+            let mut reader = self.reader.lock();
+            let _ = reader.seek(SeekFrom::Start(offset as u64))?;
+            reader.read_exact(&mut buf)?;
+        }
 
-        Block::decode(buf, compress_type, restart_interval)
+        if self.footer.version == LEGACY_FORMAT_VERSION {
+            let compress_type = if is_data {
+                CompressType::LZ4
+            } else {
+                CompressType::None
+            };
+            Block::decode_legacy(buf, compress_type, restart_interval)
+        } else {
+            let dict = if is_data {
+                self.meta.compression_dict.as_slice()
+            } else {
+                &[]
+            };
+            Block::decode(buf, dict, restart_interval)
+        }
     }
 }
 
 impl Table for SSTable {
-    fn query(&self, key: &[u8]) -> KernelResult<Option<KeyValue>> {
-        if self.meta.filter.contains(key) {
-            let index_block = self.index_block()?;
-
-            if let BlockType::Data(data_block) = self.cache.get_or_insert(
-                (self.gen(), Some(index_block.find_with_upper(key))),
-                |(_, index)| {
-                    let index = (*index).ok_or_else(|| KernelError::DataEmpty)?;
-                    Self::data_block(self, index)
-                },
-            )? {
-                if let (value, true) = data_block.find(key) {
-                    return Ok(Some((Bytes::copy_from_slice(key), value)));
-                }
+    fn query(&self, key: &[u8]) -> KernelResult<Option<SeqKeyValue>> {
+        Ok(self.multi_query(&[key])?.pop().flatten())
+    }
+
+    /// 索引Block仅获取一次，且相邻的Key处于同一数据Block时复用该Block
+    fn multi_query(&self, keys: &[&[u8]]) -> KernelResult<Vec<Option<SeqKeyValue>>> {
+        let mut results = Vec::with_capacity(keys.len());
+        if self.meta.len == 0 {
+            results.resize(keys.len(), None);
+            return Ok(results);
+        }
+        let index_block = self.index_block()?;
+        let mut last_block = None;
+
+        for key in keys {
+            if !self.meta.filter.contains(key) {
+                results.push(None);
+                continue;
             }
+            let index = index_block.find_with_upper(key, self.comparator.as_ref());
+            let block_type = match last_block {
+                Some((last_index, block_type)) if last_index == index => block_type,
+                _ => self.cache.get_or_insert(
+                    (self.family_id, self.gen(), Some(index)),
+                    |(_, _, index)| {
+                        let index = (*index).ok_or_else(|| KernelError::DataEmpty)?;
+                        Self::data_block(self, index)
+                    },
+                )?,
+            };
+            last_block = Some((index, block_type));
+
+            let item = match block_type {
+                BlockType::Data(data_block) => data_block.find(key, self.comparator.as_ref()).map(
+                    |Value {
+                         bytes,
+                         seq_id,
+                         meta,
+                         ..
+                     }| {
+                        ((Bytes::copy_from_slice(key), bytes.clone()), *seq_id, *meta)
+                    },
+                ),
+                _ => None,
+            };
+            results.push(item);
         }
 
-        Ok(None)
+        Ok(results)
     }
 
     fn len(&self) -> usize {
@@ -224,9 +331,24 @@ impl Table for SSTable {
         self.footer.level as usize
     }
 
+    fn range_tombstones(&self) -> &[RangeTombstone] {
+        &self.meta.range_tombstones
+    }
+
+    fn max_seq(&self) -> i64 {
+        self.meta.max_seq
+    }
+
+    fn blob_gens(&self) -> &[i64] {
+        &self.meta.blob_gens
+    }
+
     fn iter<'a>(
         &'a self,
-    ) -> KernelResult<Box<dyn SeekIter<'a, Item = KeyValue> + 'a + Send + Sync>> {
+    ) -> KernelResult<Box<dyn SeekIter<'a, Item = SeqKeyValue> + 'a + Send + Sync>> {
+        if self.meta.len == 0 {
+            return Ok(Box::new(EmptyIter::default()));
+        }
         Ok(SSTableIter::new(self).map(Box::new)?)
     }
 }
@@ -235,17 +357,23 @@ impl Table for SSTable {
 mod tests {
     use crate::kernel::io::{FileExtension, IoFactory, IoType};
     use crate::kernel::lsm::log::LogLoader;
-    use crate::kernel::lsm::mem_table::DEFAULT_WAL_PATH;
+    use crate::kernel::lsm::mem_table::{ValueMeta, DEFAULT_WAL_PATH};
     use crate::kernel::lsm::storage::Config;
     use crate::kernel::lsm::table::loader::TableLoader;
+    use crate::kernel::lsm::table::ss_table::block::{CompressType, Compressor};
+    use crate::kernel::lsm::table::ss_table::footer::LEGACY_FORMAT_VERSION;
     use crate::kernel::lsm::table::ss_table::SSTable;
     use crate::kernel::lsm::table::{Table, TableType};
     use crate::kernel::lsm::version::DEFAULT_SS_TABLE_PATH;
+    use crate::kernel::utils::bloom_filter::BloomFilter;
     use crate::kernel::utils::lru_cache::ShardingLruCache;
     use crate::kernel::KernelResult;
     use bincode::Options;
     use bytes::Bytes;
+    use integer_encoding::{FixedIntWriter, VarIntWriter};
+    use itertools::Itertools;
     use std::collections::hash_map::RandomState;
+    use std::io::Write;
     use std::sync::Arc;
     use tempfile::TempDir;
 
@@ -268,15 +396,24 @@ mod tests {
             &mut vec![0],
             |_, _| Ok(()),
         )?;
-        let sst_loader = TableLoader::new(config.clone(), sst_factory.clone(), log_loader)?;
+        let cache = Arc::new(ShardingLruCache::new(
+            config.block_cache_size,
+            16,
+            RandomState::default(),
+        )?);
+        let sst_loader = TableLoader::new(config.clone(), sst_factory.clone(), log_loader, cache)?;
 
         let mut vec_data = Vec::new();
         let times = 2333;
 
         for i in 0..times {
             vec_data.push((
-                Bytes::from(bincode::options().with_big_endian().serialize(&i)?),
-                Some(value.clone()),
+                (
+                    Bytes::from(bincode::options().with_big_endian().serialize(&i)?),
+                    Some(value.clone()),
+                ),
+                i as i64,
+                ValueMeta::with_expire((i % 2 == 0).then_some(i64::MAX)),
             ));
         }
         // Tips: 此处Level需要为0以上，因为Level 0默认为Mem类型，容易丢失
@@ -288,13 +425,192 @@ mod tests {
         let ss_table = sst_loader.get(1).unwrap();
 
         for kv in vec_data.iter().take(times) {
-            assert_eq!(ss_table.query(&kv.0)?.unwrap().1, Some(value.clone()))
+            assert_eq!(ss_table.query(&kv.0 .0)?, Some(kv.clone()))
         }
         let cache = ShardingLruCache::new(config.table_cache_size, 16, RandomState::default())?;
-        let ss_table =
-            SSTable::load_from_file(sst_factory.reader(1, IoType::Direct)?, Arc::new(cache))?;
+        let ss_table = SSTable::load_from_file(
+            sst_factory.reader(1, IoType::Direct)?,
+            Arc::new(cache),
+            &config,
+        )?;
         for kv in vec_data.iter().take(times) {
-            assert_eq!(ss_table.query(&kv.0)?.unwrap().1, Some(value.clone()))
+            assert_eq!(ss_table.query(&kv.0 .0)?, Some(kv.clone()))
+        }
+        let missing_key = Bytes::from_static(b"missing");
+        let keys = vec_data
+            .iter()
+            .map(|((key, _), ..)| &key[..])
+            .chain([&missing_key[..]])
+            .collect_vec();
+        let results = ss_table.multi_query(&keys)?;
+        assert_eq!(results.len(), times + 1);
+        for (kv, result) in vec_data.iter().zip(results.iter()) {
+            assert_eq!(result, &Some(kv.clone()))
+        }
+        assert_eq!(results[times], None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ss_table_level_compress_type() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.into_path())
+            .level_compress_type(0, CompressType::None)
+            .level_compress_type(1, CompressType::Snappy)
+            .level_compress_type(
+                2,
+                CompressType::Zstd {
+                    level: 3,
+                    dict_size: 4096,
+                },
+            );
+        let sst_factory = IoFactory::new(
+            config.dir_path.join(DEFAULT_SS_TABLE_PATH),
+            FileExtension::SSTable,
+        )?;
+        let cache = Arc::new(ShardingLruCache::new(
+            config.block_cache_size,
+            16,
+            RandomState::default(),
+        )?);
+        let vec_data = (0..2333)
+            .map(|i| {
+                (
+                    (
+                        Bytes::from(format!("KipDB-{i:08}")),
+                        Some(Bytes::from(format!("user-{}-profile-{}", i % 97, i % 13))),
+                    ),
+                    i as i64,
+                    ValueMeta::default(),
+                )
+            })
+            .collect_vec();
+
+        let mut sizes = Vec::new();
+        for level in 0..3 {
+            let gen = level as i64 + 1;
+            let _ = SSTable::new(
+                &sst_factory,
+                &config,
+                Arc::clone(&cache),
+                gen,
+                vec_data.clone(),
+                level,
+                IoType::Buf,
+            )
+            .await?;
+            // 压缩方式记录于文件中，重新载入时无需依赖Config
+            let ss_table = SSTable::load_from_file(
+                sst_factory.reader(gen, IoType::Buf)?,
+                Arc::clone(&cache),
+                &Config::new(config.dir_path.clone()),
+            )?;
+            for kv in vec_data.iter() {
+                assert_eq!(ss_table.query(&kv.0 .0)?, Some(kv.clone()))
+            }
+            assert_eq!(ss_table.meta.compression_dict.is_empty(), level != 2);
+            sizes.push(ss_table.footer.size_of_disk);
+        }
+        assert!(sizes[0] > sizes[1] && sizes[0] > sizes[2]);
+        // 未设置的Level沿用默认的LZ4
+        assert_eq!(config.level_compress_type[3], CompressType::LZ4);
+
+        Ok(())
+    }
+
+    /// 以未记录格式版本的旧格式写入SSTable
+    ///
+    /// 旧格式中Block末尾未记录压缩方式，DataBlock使用LZ4而IndexBlock不压缩，Value中仅记录长度与内容
+    fn write_legacy_table(
+        sst_factory: &IoFactory,
+        config: &Config,
+        gen: i64,
+        vec_data: &[(Bytes, Option<Bytes>)],
+    ) -> KernelResult<()> {
+        let fn_crc = |bytes: &mut Vec<u8>, start: usize| {
+            let crc = crc32fast::hash(&bytes[start..]);
+            bytes.write_fixedint(crc)
+        };
+        let mut raw = Vec::new();
+        let mut filter = BloomFilter::new(vec_data.len(), config.desired_error_prob);
+
+        for (key, value) in vec_data {
+            filter.insert(key.as_ref());
+            raw.write_varint(key.len() as u32)?;
+            raw.write_varint(0u32)?;
+            raw.write_all(key)?;
+            let value = value.clone().unwrap_or_default();
+            raw.write_varint(value.len() as u32)?;
+            raw.write_all(&value)?;
+        }
+        fn_crc(&mut raw, 0)?;
+        let mut bytes = Vec::new();
+        Compressor::new(CompressType::LZ4).compress(&raw, &mut bytes)?;
+        assert_eq!(bytes.pop(), Some(1));
+        let data_len = bytes.len();
+
+        let last_key = &vec_data.last().unwrap().0;
+        bytes.write_varint(last_key.len() as u32)?;
+        bytes.write_varint(0u32)?;
+        bytes.write_all(last_key)?;
+        bytes.write_varint(0u32)?;
+        bytes.write_varint(data_len as u32)?;
+        fn_crc(&mut bytes, data_len)?;
+        let index_len = bytes.len() - data_len;
+
+        bytes.write_fixedint(vec_data.len() as u32)?;
+        bytes.write_fixedint(config.index_restart_interval as u32)?;
+        bytes.write_fixedint(config.data_restart_interval as u32)?;
+        filter.to_raw(&mut bytes)?;
+        let meta_len = bytes.len() - data_len - index_len;
+
+        bytes.write_fixedint(1u8)?;
+        bytes.write_fixedint(data_len as u32)?;
+        bytes.write_fixedint(index_len as u32)?;
+        bytes.write_fixedint((data_len + index_len) as u32)?;
+        bytes.write_fixedint(meta_len as u32)?;
+        bytes.write_fixedint((bytes.len() + 21) as u32)?;
+
+        let mut writer = sst_factory.writer(gen, IoType::Buf)?;
+        writer.write_all(&bytes)?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ss_table_legacy_format() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.into_path());
+        let sst_factory = IoFactory::new(
+            config.dir_path.join(DEFAULT_SS_TABLE_PATH),
+            FileExtension::SSTable,
+        )?;
+        let vec_data = (0..100)
+            .map(|i| {
+                let key = Bytes::from(format!("KipDB-{i:08}"));
+                // 旧格式中长度为0的Value即为删除标记
+                let value = (i % 10 != 0).then(|| Bytes::from(format!("value-{i}")));
+                (key, value)
+            })
+            .collect_vec();
+        write_legacy_table(&sst_factory, &config, 1, &vec_data)?;
+
+        let cache = ShardingLruCache::new(config.table_cache_size, 16, RandomState::default())?;
+        let ss_table = SSTable::load_from_file(
+            sst_factory.reader(1, IoType::Buf)?,
+            Arc::new(cache),
+            &config,
+        )?;
+        assert_eq!(ss_table.footer.version, LEGACY_FORMAT_VERSION);
+        assert_eq!(ss_table.meta.max_seq, 0);
+
+        for (key, value) in vec_data {
+            assert_eq!(
+                ss_table.query(&key)?,
+                Some(((key, value), 0, ValueMeta::default()))
+            )
         }
 
         Ok(())
@@ -1,50 +1,44 @@
 use crate::kernel::lsm::mem_table::{key_value_bytes_len, KeyValue};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+/// MemTable的溢出触发器
+///
+/// 由并发写入的写入者共同计量，因此通过原子变量实现
 pub(crate) trait Trigger {
-    fn item_process(&mut self, item: &KeyValue);
+    fn item_process(&self, item: &KeyValue);
 
     fn is_exceeded(&self) -> bool;
-
-    fn reset(&mut self);
 }
 
-#[derive(Copy, Clone)]
 pub(crate) struct CountTrigger {
-    item_count: usize,
+    item_count: AtomicUsize,
     threshold: usize,
 }
 
 impl Trigger for CountTrigger {
-    fn item_process(&mut self, _item: &KeyValue) {
-        self.item_count += 1;
+    fn item_process(&self, _item: &KeyValue) {
+        let _ = self.item_count.fetch_add(1, Ordering::Relaxed);
     }
 
     fn is_exceeded(&self) -> bool {
-        self.item_count >= self.threshold
-    }
-
-    fn reset(&mut self) {
-        self.item_count = 0;
+        self.item_count.load(Ordering::Relaxed) >= self.threshold
     }
 }
 
-#[derive(Copy, Clone)]
 pub(crate) struct SizeOfMemTrigger {
-    size_of_mem: usize,
+    size_of_mem: AtomicUsize,
     threshold: usize,
 }
 
 impl Trigger for SizeOfMemTrigger {
-    fn item_process(&mut self, item: &KeyValue) {
-        self.size_of_mem += key_value_bytes_len(item);
+    fn item_process(&self, item: &KeyValue) {
+        let _ = self
+            .size_of_mem
+            .fetch_add(key_value_bytes_len(item), Ordering::Relaxed);
     }
 
     fn is_exceeded(&self) -> bool {
-        self.size_of_mem >= self.threshold
-    }
-
-    fn reset(&mut self) {
-        self.size_of_mem = 0;
+        self.size_of_mem.load(Ordering::Relaxed) >= self.threshold
     }
 }
 
@@ -57,14 +51,17 @@ pub enum TriggerType {
 pub(crate) struct TriggerFactory {}
 
 impl TriggerFactory {
-    pub(crate) fn create(trigger_type: TriggerType, threshold: usize) -> Box<dyn Trigger + Send> {
+    pub(crate) fn create(
+        trigger_type: TriggerType,
+        threshold: usize,
+    ) -> Box<dyn Trigger + Send + Sync> {
         match trigger_type {
             TriggerType::Count => Box::new(CountTrigger {
-                item_count: 0,
+                item_count: AtomicUsize::new(0),
                 threshold,
             }),
             TriggerType::SizeOfMem => Box::new(SizeOfMemTrigger {
-                size_of_mem: 0,
+                size_of_mem: AtomicUsize::new(0),
                 threshold,
             }),
         }
@@ -78,7 +75,7 @@ mod tests {
 
     #[test]
     fn test_count_trigger() {
-        let mut trigger = TriggerFactory::create(TriggerType::Count, 2);
+        let trigger = TriggerFactory::create(TriggerType::Count, 2);
 
         trigger.item_process(&(Bytes::new(), None));
         assert!(!trigger.is_exceeded());
@@ -89,7 +86,7 @@ mod tests {
 
     #[test]
     fn test_size_of_mem_trigger() {
-        let mut trigger = TriggerFactory::create(TriggerType::SizeOfMem, 2);
+        let trigger = TriggerFactory::create(TriggerType::SizeOfMem, 2);
 
         trigger.item_process(&(Bytes::from(vec![b'0']), None));
         assert!(!trigger.is_exceeded());
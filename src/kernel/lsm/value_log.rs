@@ -0,0 +1,316 @@
+use crate::kernel::io::{FileExtension, IoFactory, IoReader, IoType, IoWriter};
+use crate::kernel::lsm::mem_table::SeqKeyValue;
+use crate::kernel::lsm::storage::{Config, Gen};
+use crate::kernel::utils::lru_cache::ShardingLruCache;
+use crate::kernel::KernelResult;
+use crate::KernelError;
+use bytes::Bytes;
+use integer_encoding::{FixedInt, VarIntReader, VarIntWriter};
+use itertools::Itertools;
+use parking_lot::Mutex;
+use std::collections::hash_map::RandomState;
+use std::io::{Cursor, Read, SeekFrom, Write};
+
+pub(crate) const DEFAULT_VALUE_LOG_PATH: &str = "value_log";
+
+pub(crate) const DEFAULT_VALUE_LOG_FILE_SIZE: usize = 64 * 1024 * 1024;
+
+pub(crate) const DEFAULT_VALUE_LOG_GC_RATIO: f64 = 0.5;
+
+const CRC_SIZE: usize = 4;
+
+/// 分离后的数据以及新创建的Blob文件的gen与大小
+pub(crate) type SeparatedData = (Vec<SeqKeyValue>, Vec<(i64, u64)>);
+
+/// Value在Blob文件中的位置
+///
+/// 以此作为Table中被分离的Value，指向包含Key与Value的整条记录
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub(crate) struct BlobPointer {
+    pub(crate) gen: i64,
+    offset: u64,
+    pub(crate) len: u32,
+}
+
+impl BlobPointer {
+    pub(crate) fn encode(&self) -> KernelResult<Bytes> {
+        let mut bytes = Vec::new();
+        bytes.write_varint(self.gen)?;
+        bytes.write_varint(self.offset)?;
+        bytes.write_varint(self.len)?;
+
+        Ok(Bytes::from(bytes))
+    }
+
+    pub(crate) fn decode(bytes: &[u8]) -> KernelResult<Self> {
+        let mut cursor = Cursor::new(bytes);
+
+        Ok(BlobPointer {
+            gen: cursor.read_varint()?,
+            offset: cursor.read_varint()?,
+            len: cursor.read_varint()?,
+        })
+    }
+
+    /// 获取Table中的数据所持有的BlobPointer，Value未被分离时返回None
+    pub(crate) fn from_item(item: &SeqKeyValue) -> KernelResult<Option<Self>> {
+        match item {
+            ((_, Some(value)), _, meta) if meta.is_blob => Self::decode(value).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// 收集一组数据所引用的Blob文件的gen
+///
+/// 无法解析的BlobPointer会在读取其Value时返回错误，此处忽略
+pub(crate) fn collect_blob_gens<'a>(
+    vec_data: impl IntoIterator<Item = &'a SeqKeyValue>,
+) -> Vec<i64> {
+    vec_data
+        .into_iter()
+        .filter_map(|item| BlobPointer::from_item(item).ok().flatten())
+        .map(|pointer| pointer.gen)
+        .sorted()
+        .dedup()
+        .collect_vec()
+}
+
+/// Blob文件的统计信息
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BlobMeta {
+    pub(crate) size: u64,
+    /// 已不再被任何Table引用的记录的字节数
+    pub(crate) discard: u64,
+}
+
+impl BlobMeta {
+    pub(crate) fn discard_ratio(&self) -> f64 {
+        if self.size == 0 {
+            return 0.0;
+        }
+        self.discard as f64 / self.size as f64
+    }
+}
+
+/// 键值分离存储
+///
+/// Major压缩时超出阈值的Value会被追加写入至新的Blob文件中，Table中仅存储其BlobPointer，
+/// 使后续的压缩仅需重写BlobPointer；Blob文件写入后不再改变，
+/// 由`Compactor::blob_gc`依据Version中的discard统计重写其中仍存活的Value后整体删除
+pub(crate) struct ValueLog {
+    factory: IoFactory,
+    readers: ShardingLruCache<i64, Mutex<Box<dyn IoReader>>>,
+    threshold: Option<usize>,
+    file_size: usize,
+}
+
+impl ValueLog {
+    pub(crate) fn new(config: &Config) -> KernelResult<Self> {
+        Ok(ValueLog {
+            factory: IoFactory::new(
+                config.path().join(DEFAULT_VALUE_LOG_PATH),
+                FileExtension::Blob,
+            )?,
+            readers: ShardingLruCache::new(config.table_cache_size, 16, RandomState::default())?,
+            threshold: config.value_log_threshold,
+            file_size: config.value_log_file_size,
+        })
+    }
+
+    /// 将不小于阈值的Value写入新的Blob文件中，并将其替换为BlobPointer
+    ///
+    /// Merge操作数、删除标记以及已被分离的Value保持不变，返回新创建的Blob文件的gen与大小
+    ///
+    /// Blob文件在返回前持久化，使此后记录的VersionEdit所引用的SSTable不会指向停机后丢失的Blob数据
+    pub(crate) fn separate(&self, mut vec_data: Vec<SeqKeyValue>) -> KernelResult<SeparatedData> {
+        let Some(threshold) = self.threshold else {
+            return Ok((vec_data, Vec::new()));
+        };
+        let mut blob_files = Vec::new();
+        let mut option_writer: Option<(i64, Box<dyn IoWriter>, u64)> = None;
+        let mut buf = Vec::new();
+
+        for ((key, value), _, meta) in vec_data.iter_mut() {
+            let Some(bytes) = value.as_ref().filter(|bytes| bytes.len() >= threshold) else {
+                continue;
+            };
+            if meta.is_merge || meta.is_blob {
+                continue;
+            }
+            let (gen, writer, offset) = match &mut option_writer {
+                Some(writer) => writer,
+                None => {
+                    let gen = Gen::create();
+                    option_writer.insert((gen, self.factory.writer(gen, IoType::Buf)?, 0))
+                }
+            };
+            buf.clear();
+            Self::encode_record(key, bytes, &mut buf)?;
+            writer.write_all(&buf)?;
+
+            *value = Some(
+                BlobPointer {
+                    gen: *gen,
+                    offset: *offset,
+                    len: buf.len() as u32,
+                }
+                .encode()?,
+            );
+            meta.is_blob = true;
+            *offset += buf.len() as u64;
+
+            if *offset >= self.file_size as u64 {
+                if let Some((gen, mut writer, size)) = option_writer.take() {
+                    writer.sync_data()?;
+                    blob_files.push((gen, size));
+                }
+            }
+        }
+        if let Some((gen, mut writer, size)) = option_writer {
+            writer.sync_data()?;
+            blob_files.push((gen, size));
+        }
+
+        Ok((vec_data, blob_files))
+    }
+
+    /// 获取BlobPointer所指向的Value
+    pub(crate) fn resolve(&self, pointer: &[u8]) -> KernelResult<Bytes> {
+        let BlobPointer { gen, offset, len } = BlobPointer::decode(pointer)?;
+        let mut buf = vec![0; len as usize];
+        {
+            let mut reader = self
+                .readers
+                .get_or_insert(gen, |gen| {
+                    Ok(Mutex::new(self.factory.reader(*gen, IoType::Direct)?))
+                })?
+                .lock();
+            let _ = reader.seek(SeekFrom::Start(offset))?;
+            reader.read_exact(&mut buf)?;
+        }
+
+        Self::decode_record(&buf).map(|(_, value)| value)
+    }
+
+    /// Value被分离时将其替换为BlobPointer所指向的Value
+    pub(crate) fn resolve_item(&self, item: SeqKeyValue) -> KernelResult<SeqKeyValue> {
+        match item {
+            ((key, Some(pointer)), seq_id, mut meta) if meta.is_blob => {
+                meta.is_blob = false;
+                Ok(((key, Some(self.resolve(&pointer)?)), seq_id, meta))
+            }
+            item => Ok(item),
+        }
+    }
+
+    pub(crate) fn is_blob_exist(&self, gen: i64) -> KernelResult<bool> {
+        self.factory.exists(gen)
+    }
+
+    pub(crate) fn clean(&self, gen: i64) -> KernelResult<()> {
+        let _ = self.readers.remove(&gen);
+        self.factory.clean(gen)
+    }
+
+    /// 记录格式: key_len(varint) | key | value_len(varint) | value | crc32(fixed)
+    fn encode_record(key: &[u8], value: &[u8], buf: &mut Vec<u8>) -> KernelResult<()> {
+        buf.write_varint(key.len() as u32)?;
+        buf.write_all(key)?;
+        buf.write_varint(value.len() as u32)?;
+        buf.write_all(value)?;
+        buf.write_all(&crc32fast::hash(buf).encode_fixed_vec())?;
+
+        Ok(())
+    }
+
+    fn decode_record(buf: &[u8]) -> KernelResult<(Bytes, Bytes)> {
+        if buf.len() < CRC_SIZE {
+            return Err(KernelError::CrcMisMatch);
+        }
+        let (record, crc) = buf.split_at(buf.len() - CRC_SIZE);
+        if crc32fast::hash(record) != u32::decode_fixed(crc) {
+            return Err(KernelError::CrcMisMatch);
+        }
+        let mut cursor = Cursor::new(record);
+        let mut key = vec![0; cursor.read_varint::<u32>()? as usize];
+        cursor.read_exact(&mut key)?;
+        let mut value = vec![0; cursor.read_varint::<u32>()? as usize];
+        cursor.read_exact(&mut value)?;
+
+        Ok((Bytes::from(key), Bytes::from(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::lsm::mem_table::ValueMeta;
+    use crate::kernel::lsm::storage::Config;
+    use crate::kernel::lsm::value_log::{collect_blob_gens, BlobPointer, ValueLog};
+    use crate::kernel::KernelResult;
+    use bytes::Bytes;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_value_log() -> KernelResult<()> {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let config = Config::new(temp_dir.path())
+            .value_log_threshold(4)
+            .value_log_file_size(16);
+        let value_log = ValueLog::new(&config)?;
+        let (small, large) = (Bytes::from_static(b"v"), Bytes::from_static(b"large value"));
+        let vec_data = vec![
+            (
+                (Bytes::from_static(b"1"), Some(small.clone())),
+                1,
+                ValueMeta::default(),
+            ),
+            (
+                (Bytes::from_static(b"2"), Some(large.clone())),
+                2,
+                ValueMeta::default(),
+            ),
+            (
+                (Bytes::from_static(b"3"), Some(large.clone())),
+                3,
+                ValueMeta::merge(),
+            ),
+            ((Bytes::from_static(b"4"), None), 4, ValueMeta::default()),
+            (
+                (Bytes::from_static(b"5"), Some(large.clone())),
+                5,
+                ValueMeta::with_expire(Some(i64::MAX)),
+            ),
+        ];
+
+        let (separated, blob_files) = value_log.separate(vec_data.clone())?;
+        // 超出文件大小后切换至新的Blob文件
+        assert_eq!(blob_files.len(), 2);
+        assert_eq!(separated[0], vec_data[0]);
+        assert_eq!(separated[2], vec_data[2]);
+        assert_eq!(separated[3], vec_data[3]);
+        assert!(separated[1].2.is_blob);
+        assert_eq!(separated[4].2.expire_at, Some(i64::MAX));
+        assert_eq!(
+            collect_blob_gens(&separated),
+            blob_files.iter().map(|(gen, _)| *gen).collect::<Vec<_>>()
+        );
+
+        for (item, expected) in separated.into_iter().zip(vec_data) {
+            assert_eq!(value_log.resolve_item(item)?, expected);
+        }
+
+        let pointer = BlobPointer {
+            gen: blob_files[0].0,
+            offset: 0,
+            len: blob_files[0].1 as u32,
+        };
+        assert_eq!(BlobPointer::decode(&pointer.encode()?)?, pointer);
+        assert_eq!(value_log.resolve(&pointer.encode()?)?, large);
+
+        value_log.clean(blob_files[0].0)?;
+        assert!(!value_log.is_blob_exist(blob_files[0].0)?);
+
+        Ok(())
+    }
+}
@@ -11,6 +11,16 @@ pub(crate) enum VersionEdit {
     // Level 0则请忽略第二位的index参数，默认会放至最尾
     /// ((Vec(scope), Level), Index, TableMeta)
     NewFile((Vec<Scope>, usize), usize, TableMeta),
+    /// 已持久化至Table中的最大seq_id，用于重启后恢复Sequence
+    LastSequence(i64),
+    /// 创建数据库时所使用的Comparator名称，重新打开时用于校验
+    Comparator(String),
+    /// 新的Blob文件: (gen, 文件大小)
+    NewBlob(i64, u64),
+    /// Blob文件中新增的失效数据: (gen, 失效数据的字节数)
+    BlobDiscard(i64, u64),
+    /// 删除一组Blob文件
+    DeleteBlob(Vec<i64>),
     // // Level and SSTable Gen List
     // CompactPoint(usize, Vec<i64>),
 }
@@ -1,37 +1,70 @@
+use crate::kernel::lsm::compactor::LEVEL_0;
 use crate::kernel::lsm::iterator::level_iter::LevelIter;
 use crate::kernel::lsm::iterator::merging_iter::SeekMergingIter;
-use crate::kernel::lsm::iterator::{Iter, Seek, SeekIter};
+use crate::kernel::lsm::iterator::seq_iter::SeqFilterIter;
+use crate::kernel::lsm::iterator::{ForwardIter, Iter, Seek, SeekIter};
 use crate::kernel::lsm::mem_table::KeyValue;
+use crate::kernel::lsm::range_tombstone::RangeTombstone;
 use crate::kernel::lsm::version::Version;
 use crate::kernel::lsm::MAX_LEVEL;
 use crate::kernel::KernelResult;
+use itertools::Itertools;
+use std::sync::Arc;
 
 /// Version键值对迭代器
+///
+/// read_seq为Some时仅能迭代出seq_id不大于read_seq的数据
 pub struct VersionIter<'a> {
     merge_iter: SeekMergingIter<'a>,
 }
 
 impl<'a> VersionIter<'a> {
-    pub(crate) fn new(version: &'a Version) -> KernelResult<VersionIter<'a>> {
+    pub(crate) fn new(
+        version: &'a Version,
+        read_seq: Option<i64>,
+    ) -> KernelResult<VersionIter<'a>> {
         let mut vec_iter = Vec::new();
-        Self::merging_with_version(version, &mut vec_iter)?;
+        Self::merging_with_version(version, read_seq, Vec::new(), &mut vec_iter)?;
 
         Ok(Self {
-            merge_iter: SeekMergingIter::new(vec_iter)?,
+            merge_iter: SeekMergingIter::new(vec_iter, version.comparator())?,
         })
     }
 
+    /// mem_tombstones为MemTable中的范围删除标记，与Version中的范围删除标记共同作用于各Table的数据
     pub(crate) fn merging_with_version(
         version: &'a Version,
+        read_seq: Option<i64>,
+        mem_tombstones: Vec<RangeTombstone>,
         iter_vec: &mut Vec<Box<dyn SeekIter<'a, Item = KeyValue> + 'a + Send + Sync>>,
     ) -> KernelResult<()> {
-        for table in version.tables_by_level_0() {
-            iter_vec.push(table.iter()?);
+        let comparator = version.comparator().as_ref();
+        let range_tombstones: Arc<[RangeTombstone]> = Arc::from(
+            version
+                .range_tombstones()
+                .cloned()
+                .chain(mem_tombstones)
+                .collect_vec(),
+        );
+
+        // Merge操作数会与其所在位置之后(更旧)的数据合并
+        for (offset, table) in version.tables_by_level_0().into_iter().enumerate() {
+            iter_vec.push(Box::new(
+                SeqFilterIter::new(table.iter()?, read_seq)
+                    .merge_with(version, (LEVEL_0, offset + 1))
+                    .resolve_with(version.value_log())
+                    .covered_by(Arc::clone(&range_tombstones), comparator),
+            ));
         }
 
-        for level in 1..MAX_LEVEL - 1 {
+        for level in 1..MAX_LEVEL {
             if let Ok(level_iter) = LevelIter::new(version, level) {
-                iter_vec.push(Box::new(level_iter));
+                iter_vec.push(Box::new(
+                    SeqFilterIter::new(Box::new(level_iter), read_seq)
+                        .merge_with(version, (level + 1, 0))
+                        .resolve_with(version.value_log())
+                        .covered_by(Arc::clone(&range_tombstones), comparator),
+                ));
             }
         }
 
@@ -51,6 +84,12 @@ impl<'a> Iter<'a> for VersionIter<'a> {
     }
 }
 
+impl<'a> ForwardIter<'a> for VersionIter<'a> {
+    fn try_prev(&mut self) -> KernelResult<Option<Self::Item>> {
+        self.merge_iter.try_prev()
+    }
+}
+
 impl<'a> SeekIter<'a> for VersionIter<'a> {
     fn seek(&mut self, seek: Seek<'_>) -> KernelResult<()> {
         self.merge_iter.seek(seek)
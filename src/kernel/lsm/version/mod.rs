@@ -1,17 +1,23 @@
 use crate::kernel::io::{FileExtension, IoFactory};
 use crate::kernel::lsm::compactor::{SeekScope, LEVEL_0};
-use crate::kernel::lsm::mem_table::KeyValue;
+use crate::kernel::lsm::comparator::Comparator;
+use crate::kernel::lsm::mem_table::{expire_filter, now_millis, KeyValue, SeqKeyValue};
+use crate::kernel::lsm::merge_operator::{MergeOperands, MergeOperator};
+use crate::kernel::lsm::range_tombstone::{cover_seq, RangeTombstone};
 use crate::kernel::lsm::storage::{Config, Gen};
 use crate::kernel::lsm::table::loader::TableLoader;
 use crate::kernel::lsm::table::meta::TableMeta;
 use crate::kernel::lsm::table::scope::Scope;
 use crate::kernel::lsm::table::Table;
+use crate::kernel::lsm::value_log::{BlobMeta, ValueLog};
 use crate::kernel::lsm::version::cleaner::CleanTag;
 use crate::kernel::lsm::version::edit::{EditType, VersionEdit};
 use crate::kernel::lsm::version::meta::VersionMeta;
 use crate::kernel::lsm::MAX_LEVEL;
 use crate::kernel::{sorted_gen_list, KernelResult};
+use bytes::Bytes;
 use itertools::Itertools;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::sync::Arc;
 use tokio::sync::mpsc::UnboundedSender;
@@ -63,6 +69,12 @@ pub(crate) struct Version {
     pub(crate) level_slice: LevelSlice,
     /// 统计数据
     pub(crate) meta_data: VersionMeta,
+    /// 已持久化的最大seq_id
+    pub(crate) last_sequence: i64,
+    /// 各Table中的范围删除标记及其所在Table的gen
+    range_tombstones: Vec<(i64, RangeTombstone)>,
+    /// 各Blob文件的统计信息
+    blob_files: BTreeMap<i64, BlobMeta>,
     /// 清除信号发送器
     /// Drop时通知Cleaner进行删除
     clean_tx: UnboundedSender<CleanTag>,
@@ -99,6 +111,9 @@ impl Version {
                 size_of_disk: 0,
                 len: 0,
             },
+            last_sequence: 0,
+            range_tombstones: Vec::new(),
+            blob_files: BTreeMap::new(),
             clean_tx,
         };
 
@@ -117,6 +132,7 @@ impl Version {
     /// 也可以算作是一种Major Compaction异常时的备份？
     pub(crate) fn apply(&mut self, vec_version_edit: Vec<VersionEdit>) -> KernelResult<()> {
         let mut del_gens = Vec::new();
+        let mut new_gens = Vec::new();
         let mut vec_statistics_sst_meta = Vec::new();
 
         for version_edit in vec_version_edit {
@@ -125,6 +141,8 @@ impl Version {
                     vec_statistics_sst_meta.push(EditType::Del(sst_meta));
 
                     self.level_slice[level].retain(|scope| !vec_gen.contains(&scope.gen()));
+                    self.range_tombstones
+                        .retain(|(gen, _)| !vec_gen.contains(gen));
                     del_gens.append(&mut vec_gen);
                 }
                 VersionEdit::NewFile((vec_scope, level), index, sst_meta) => {
@@ -133,6 +151,7 @@ impl Version {
                     // Level 0中的Table绝对是以gen为优先级
                     // Level N中则不以gen为顺序，此处对gen排序是因为单次NewFile中的gen肯定是有序的
                     let scope_iter = vec_scope.into_iter().sorted_by_key(Scope::gen);
+                    new_gens.extend(scope_iter.clone().map(|scope| (scope.gen(), level)));
                     if level == LEVEL_0 {
                         for scope in scope_iter {
                             self.level_slice[level].push(scope);
@@ -143,6 +162,40 @@ impl Version {
                         }
                     }
                 }
+                VersionEdit::LastSequence(seq_id) => {
+                    self.last_sequence = self.last_sequence.max(seq_id);
+                }
+                // 已在`VersionStatus::load_with_path`中校验
+                VersionEdit::Comparator(_) => (),
+                VersionEdit::NewBlob(gen, size) => {
+                    let _ = self.blob_files.insert(gen, BlobMeta { size, discard: 0 });
+                }
+                VersionEdit::BlobDiscard(gen, discard) => {
+                    if let Some(blob_meta) = self.blob_files.get_mut(&gen) {
+                        blob_meta.discard = (blob_meta.discard + discard).min(blob_meta.size);
+                    }
+                }
+                VersionEdit::DeleteBlob(mut vec_gen) => {
+                    self.blob_files.retain(|gen, _| !vec_gen.contains(gen));
+                    del_gens.append(&mut vec_gen);
+                }
+            }
+        }
+        // 仅载入仍存在于Version中的Table的范围删除标记，避免载入已被删除的Table
+        for (gen, level) in new_gens {
+            if !self.level_slice[level]
+                .iter()
+                .any(|scope| scope.gen() == gen)
+            {
+                continue;
+            }
+            if let Some(table) = self.table_loader.get(gen) {
+                self.range_tombstones.extend(
+                    table
+                        .range_tombstones()
+                        .iter()
+                        .map(|tombstone| (gen, tombstone.clone())),
+                );
             }
         }
 
@@ -184,9 +237,63 @@ impl Version {
                     )
                 })
             })
+            .chain(
+                (self.last_sequence > 0).then_some(VersionEdit::LastSequence(self.last_sequence)),
+            )
+            .chain(Some(VersionEdit::Comparator(
+                self.comparator().name().to_string(),
+            )))
+            .chain(
+                self.blob_files
+                    .iter()
+                    .flat_map(|(gen, BlobMeta { size, discard })| {
+                        [
+                            Some(VersionEdit::NewBlob(*gen, *size)),
+                            (*discard > 0).then_some(VersionEdit::BlobDiscard(*gen, *discard)),
+                        ]
+                    })
+                    .flatten(),
+            )
             .collect_vec()
     }
 
+    /// 获取失效数据占比不小于ratio的Blob文件中占比最大的一个
+    pub(crate) fn blob_gc_candidate(&self, ratio: f64) -> Option<i64> {
+        self.blob_files
+            .iter()
+            .filter(|(_, blob_meta)| blob_meta.discard_ratio() >= ratio)
+            .max_by(|(_, a), (_, b)| a.discard_ratio().total_cmp(&b.discard_ratio()))
+            .map(|(gen, _)| *gen)
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn blob_meta(&self, gen: i64) -> Option<&BlobMeta> {
+        self.blob_files.get(&gen)
+    }
+
+    pub(crate) fn value_log(&self) -> &ValueLog {
+        self.table_loader.value_log()
+    }
+
+    /// 所有Table中的范围删除标记
+    pub(crate) fn range_tombstones(&self) -> impl Iterator<Item = &RangeTombstone> {
+        self.range_tombstones.iter().map(|(_, tombstone)| tombstone)
+    }
+
+    /// 判断gens以外的Table的Scope是否与范围删除标记的范围相交
+    pub(crate) fn is_range_overlapped(&self, tombstone: &RangeTombstone, gens: &[i64]) -> bool {
+        let comparator = self.comparator().as_ref();
+
+        self.level_slice
+            .iter()
+            .flatten()
+            .filter(|scope| !gens.contains(&scope.gen()))
+            .any(|scope| {
+                comparator.compare(&scope.start, &tombstone.end).is_lt()
+                    && comparator.compare(&tombstone.start, &scope.end).is_le()
+            })
+    }
+
     pub(crate) fn table(&self, level: usize, offset: usize) -> Option<&dyn Table> {
         self.level_slice[level]
             .get(offset)
@@ -215,7 +322,7 @@ impl Version {
         let (tables, scopes): (Vec<&dyn Table>, Vec<Scope>) = self.level_slice[level]
             .iter()
             .enumerate()
-            .filter(|(_, scope)| scope.meet(target_scope))
+            .filter(|(_, scope)| scope.meet(target_scope, self.comparator().as_ref()))
             .filter_map(|(index, scope)| {
                 self.table_loader.get(scope.gen()).map(|ss_table| {
                     if first_index.is_none() {
@@ -230,24 +337,90 @@ impl Version {
     }
 
     /// 使用Key从现有Tables中获取对应的数据
-    pub(crate) fn query(&self, key: &[u8]) -> KernelResult<(Option<KeyValue>, Option<SeekScope>)> {
+    ///
+    /// read_seq为Some时会跳过seq_id大于read_seq的数据，并继续向更旧的Table中查询
+    ///
+    /// 途经的Merge操作数会由新至旧收集至operands中，并继续向更旧的Table查询直至首个非Merge的数据
+    pub(crate) fn query(
+        &self,
+        key: &[u8],
+        read_seq: Option<i64>,
+        operands: &mut MergeOperands,
+    ) -> KernelResult<(Option<KeyValue>, Option<SeekScope>)> {
+        self.query_from(key, read_seq, (LEVEL_0, 0), operands)
+    }
+
+    /// 将位于start的Table中的Merge操作数与更旧的数据合并
+    ///
+    /// start为(level, offset)，Level 0时offset为需要跳过的最新的Table数量
+    pub(crate) fn merge_with_older(
+        &self,
+        key: &[u8],
+        operands: Bytes,
+        read_seq: Option<i64>,
+        start: (usize, usize),
+    ) -> KernelResult<Option<Bytes>> {
+        let mut merge_operands = MergeOperands::default();
+        merge_operands.push(operands);
+
+        let (key_value, _) = self.query_from(key, read_seq, start, &mut merge_operands)?;
+        merge_operands.fold(
+            self.merge_operator(),
+            key,
+            key_value.and_then(|(_, value)| value),
+        )
+    }
+
+    pub(crate) fn merge_operator(&self) -> Option<&dyn MergeOperator> {
+        self.table_loader.config().merge_operator.as_deref()
+    }
+
+    pub(crate) fn comparator(&self) -> &Arc<dyn Comparator> {
+        &self.table_loader.config().comparator
+    }
+
+    fn query_from(
+        &self,
+        key: &[u8],
+        read_seq: Option<i64>,
+        (start_level, offset): (usize, usize),
+        operands: &mut MergeOperands,
+    ) -> KernelResult<(Option<KeyValue>, Option<SeekScope>)> {
         let table_loader = &self.table_loader;
+        let cover = cover_seq(
+            self.range_tombstones(),
+            key,
+            read_seq,
+            self.comparator().as_ref(),
+        );
         // Level 0的Table是无序且Table间的数据是可能重复的,因此需要遍历
-        for scope in self.level_slice[LEVEL_0].iter().rev() {
-            if let SeekOption::Hit(key_value) =
-                Self::query_by_scope(key, table_loader, scope, LEVEL_0)?
-            {
-                return Ok((Some(key_value), None));
+        if start_level == LEVEL_0 {
+            for scope in self.level_slice[LEVEL_0].iter().rev().skip(offset) {
+                if let SeekOption::Hit(key_value) = Self::query_by_scope(
+                    key,
+                    table_loader,
+                    scope,
+                    (LEVEL_0, read_seq, cover),
+                    operands,
+                )? {
+                    return Ok((Some(key_value), None));
+                }
             }
         }
         // 仅仅记录第一个key与SSTable的scope meet且seek miss的level
         let mut miss_seek = None;
         // Level 1-MAX_LEVEL的数据排布有序且唯一，因此在每一个等级可以直接找到唯一一个Key可能在范围内的Table
-        for level in 1..MAX_LEVEL {
+        for level in start_level.max(1)..MAX_LEVEL {
             let offset = self.query_meet_index(key, level);
 
             if let Some(scope) = self.level_slice[level].get(offset) {
-                match Self::query_by_scope(key, table_loader, scope, level)? {
+                match Self::query_by_scope(
+                    key,
+                    table_loader,
+                    scope,
+                    (level, read_seq, cover),
+                    operands,
+                )? {
                     SeekOption::Hit(value) => return Ok((Some(value), miss_seek)),
                     SeekOption::Miss(Some(seek_scope)) => {
                         let _ = miss_seek.get_or_insert(seek_scope);
@@ -260,20 +433,157 @@ impl Version {
         Ok((None, miss_seek))
     }
 
+    /// 批量查询一组有序且不重复的Key，结果与keys一一对应
+    ///
+    /// 各Key在每个Level中按其所处的Table进行分组，同一Table仅加载一次并通过`Table::multi_query`
+    /// 共享索引以及数据Block，operands为各Key在MemTable中已收集的Merge操作数
+    pub(crate) fn multi_query(
+        &self,
+        keys: &[&[u8]],
+        read_seq: Option<i64>,
+        operands: &mut [MergeOperands],
+    ) -> KernelResult<Vec<(Option<KeyValue>, Option<SeekScope>)>> {
+        let comparator = self.comparator().as_ref();
+        let covers = keys
+            .iter()
+            .map(|key| cover_seq(self.range_tombstones(), key, read_seq, comparator))
+            .collect_vec();
+        let mut results = (0..keys.len()).map(|_| (None, None)).collect_vec();
+        let mut pending = (0..keys.len()).collect_vec();
+
+        for scope in self.level_slice[LEVEL_0].iter().rev() {
+            let group = pending
+                .iter()
+                .copied()
+                .filter(|i| scope.meet_by_key(keys[*i], comparator))
+                .collect_vec();
+            self.multi_query_by_scope(
+                keys,
+                &group,
+                scope,
+                (LEVEL_0, read_seq, &covers),
+                operands,
+                &mut results,
+            )?;
+            pending.retain(|i| results[*i].0.is_none());
+        }
+        for level in 1..MAX_LEVEL {
+            // pending有序，因此处于同一Table的Key是连续的
+            for (offset, group) in &pending
+                .iter()
+                .copied()
+                .group_by(|i| self.query_meet_index(keys[*i], level))
+            {
+                if let Some(scope) = self.level_slice[level].get(offset) {
+                    let group = group
+                        .filter(|i| scope.meet_by_key(keys[*i], comparator))
+                        .collect_vec();
+                    self.multi_query_by_scope(
+                        keys,
+                        &group,
+                        scope,
+                        (level, read_seq, &covers),
+                        operands,
+                        &mut results,
+                    )?;
+                }
+            }
+            pending.retain(|i| results[*i].0.is_none());
+        }
+
+        Ok(results)
+    }
+
+    /// group为keys中处于该scope内的Key的下标
+    fn multi_query_by_scope(
+        &self,
+        keys: &[&[u8]],
+        group: &[usize],
+        scope: &Scope,
+        (level, read_seq, covers): (usize, Option<i64>, &[Option<i64>]),
+        operands: &mut [MergeOperands],
+        results: &mut [(Option<KeyValue>, Option<SeekScope>)],
+    ) -> KernelResult<()> {
+        if group.is_empty() {
+            return Ok(());
+        }
+        if let Some(ss_table) = self.table_loader.get(scope.gen()) {
+            let group_keys = group.iter().map(|i| keys[*i]).collect_vec();
+
+            for (i, item) in group.iter().zip(ss_table.multi_query(&group_keys)?) {
+                match Self::seek_option(
+                    item,
+                    ss_table.level(),
+                    scope,
+                    (level, read_seq, covers[*i]),
+                    &mut operands[*i],
+                    self.value_log(),
+                )? {
+                    SeekOption::Hit(key_value) => results[*i].0 = Some(key_value),
+                    SeekOption::Miss(Some(seek_scope)) => {
+                        let _ = results[*i].1.get_or_insert(seek_scope);
+                    }
+                    SeekOption::Miss(None) => (),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// cover为覆盖该Key的范围删除标记的seq_id，seq_id小于cover的数据视为已删除
     fn query_by_scope(
         key: &[u8],
         table_loader: &Arc<TableLoader>,
         scope: &Scope,
-        level: usize,
+        (level, read_seq, cover): (usize, Option<i64>, Option<i64>),
+        operands: &mut MergeOperands,
     ) -> KernelResult<SeekOption<KeyValue>> {
-        if scope.meet_by_key(key) {
+        if scope.meet_by_key(key, table_loader.config().comparator.as_ref()) {
             if let Some(ss_table) = table_loader.get(scope.gen()) {
-                if let Some(value) = ss_table.query(key)? {
-                    return Ok(SeekOption::Hit(value));
-                } else if level > LEVEL_0 && scope.seeks_increase() {
-                    return Ok(SeekOption::Miss(Some((scope.clone(), ss_table.level()))));
+                return Self::seek_option(
+                    ss_table.query(key)?,
+                    ss_table.level(),
+                    scope,
+                    (level, read_seq, cover),
+                    operands,
+                    table_loader.value_log(),
+                );
+            }
+        }
+
+        Ok(SeekOption::Miss(None))
+    }
+
+    /// 将Table中查询到的数据转换为SeekOption，Merge操作数会被收集至operands中
+    ///
+    /// 命中的Value被分离时通过value_log获取其实际的Value
+    fn seek_option(
+        item: Option<SeqKeyValue>,
+        table_level: usize,
+        scope: &Scope,
+        (level, read_seq, cover): (usize, Option<i64>, Option<i64>),
+        operands: &mut MergeOperands,
+        value_log: &ValueLog,
+    ) -> KernelResult<SeekOption<KeyValue>> {
+        if let Some((key_value, seq_id, meta)) = item {
+            if read_seq.map_or(true, |read_seq| seq_id <= read_seq) {
+                if cover.map_or(false, |cover| seq_id < cover) {
+                    return Ok(SeekOption::Hit((key_value.0, None)));
+                }
+                if meta.is_merge {
+                    operands.push(key_value.1.unwrap_or_default());
+                    return Ok(SeekOption::Miss(None));
                 }
+                let (key, value) = expire_filter(key_value, meta.expire_at, now_millis());
+                let value = match value {
+                    Some(pointer) if meta.is_blob => Some(value_log.resolve(&pointer)?),
+                    value => value,
+                };
+                return Ok(SeekOption::Hit((key, value)));
             }
+        } else if level > LEVEL_0 && scope.seeks_increase() {
+            return Ok(SeekOption::Miss(Some((scope.clone(), table_level))));
         }
 
         Ok(SeekOption::Miss(None))
@@ -281,7 +591,7 @@ impl Version {
 
     pub(crate) fn query_meet_index(&self, key: &[u8], level: usize) -> usize {
         self.level_slice[level]
-            .binary_search_by(|scope| scope.start.as_ref().cmp(key))
+            .binary_search_by(|scope| self.comparator().compare(&scope.start, key))
             .unwrap_or_else(|index| index.saturating_sub(1))
     }
 
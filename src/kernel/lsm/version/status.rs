@@ -1,13 +1,16 @@
 use crate::kernel::io::{FileExtension, IoFactory, IoType, IoWriter};
+use crate::kernel::lsm::compactor::LEVEL_0;
 use crate::kernel::lsm::log::{LogLoader, LogWriter};
 use crate::kernel::lsm::storage::{Config, Gen};
 use crate::kernel::lsm::table::loader::TableLoader;
+use crate::kernel::lsm::table::ss_table::block::BlockCache;
 use crate::kernel::lsm::version::cleaner::Cleaner;
 use crate::kernel::lsm::version::edit::VersionEdit;
 use crate::kernel::lsm::version::{
     snapshot_gen, Version, DEFAULT_SS_TABLE_PATH, DEFAULT_VERSION_PATH,
 };
 use crate::kernel::KernelResult;
+use crate::KernelError;
 use std::mem;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -29,13 +32,18 @@ pub(crate) struct VersionStatus {
 }
 
 impl VersionStatus {
-    pub(crate) fn load_with_path(config: Config, wal: LogLoader) -> KernelResult<Self> {
+    pub(crate) fn load_with_path(
+        config: Config,
+        wal: LogLoader,
+        block_cache: Arc<BlockCache>,
+    ) -> KernelResult<Self> {
         let sst_path = config.path().join(DEFAULT_SS_TABLE_PATH);
         let sst_factory = Arc::new(IoFactory::new(sst_path, FileExtension::SSTable)?);
         let ss_table_loader = Arc::new(TableLoader::new(
             config.clone(),
             Arc::clone(&sst_factory),
             wal,
+            block_cache,
         )?);
         let log_factory = Arc::new(IoFactory::new(
             config.path().join(DEFAULT_VERSION_PATH),
@@ -53,6 +61,16 @@ impl VersionStatus {
                 Ok(())
             },
         )?;
+        let comparator_name = config.comparator.name();
+        let persisted_name = version_logs.iter().find_map(|edit| match edit {
+            VersionEdit::Comparator(name) => Some(name.clone()),
+            _ => None,
+        });
+        if let Some(name) = &persisted_name {
+            if name != comparator_name {
+                return Err(KernelError::ComparatorMismatch(name.clone()));
+            }
+        }
         let edit_approximate_count = AtomicUsize::new(version_logs.len());
         let (clean_tx, clean_rx) = unbounded_channel();
         let version = Arc::new(Version::load_from_log(
@@ -60,6 +78,10 @@ impl VersionStatus {
             &ss_table_loader,
             clean_tx,
         )?);
+        // Level 0的Table可能需要通过同gen的WAL进行恢复，因此持有其引用
+        for scope in &version.level_slice[LEVEL_0] {
+            ss_table_loader.wal().retain(scope.gen());
+        }
         let mut cleaner = Cleaner::new(&ss_table_loader, clean_rx);
 
         let _ignore = tokio::spawn(async move {
@@ -68,6 +90,13 @@ impl VersionStatus {
 
         let mut ver_log_writer = ver_log_loader.writer(log_gen)?;
         let _ = ver_log_writer.seek_end()?;
+        // 首次打开时持久化Comparator的名称
+        if persisted_name.is_none() {
+            let _ =
+                ver_log_writer.add_record(&bincode::serialize(&vec![VersionEdit::Comparator(
+                    comparator_name.to_string(),
+                )])?)?;
+        }
 
         Ok(Self {
             inner: RwLock::new(VersionInner {
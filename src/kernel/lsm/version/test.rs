@@ -1,13 +1,16 @@
 use crate::kernel::io::IoType;
 use crate::kernel::lsm::log::LogLoader;
+use crate::kernel::lsm::mem_table::ValueMeta;
 use crate::kernel::lsm::storage::Config;
 use crate::kernel::lsm::table::TableType;
 use crate::kernel::lsm::version::edit::VersionEdit;
 use crate::kernel::lsm::version::status::VersionStatus;
 use crate::kernel::lsm::version::Version;
 use crate::kernel::lsm::version::DEFAULT_VERSION_PATH;
+use crate::kernel::utils::lru_cache::ShardingLruCache;
 use crate::kernel::KernelResult;
 use bytes::Bytes;
+use std::collections::hash_map::RandomState;
 use std::sync::Arc;
 use std::time::Duration;
 use tempfile::TempDir;
@@ -30,14 +33,19 @@ fn test_version_clean() -> KernelResult<()> {
 
         // 注意：将ss_table的创建防止VersionStatus的创建前
         // 因为VersionStatus检测无Log时会扫描当前文件夹下的SSTable进行重组以进行容灾
-        let ver_status = VersionStatus::load_with_path(config.clone(), wal.clone())?;
+        let cache = Arc::new(ShardingLruCache::new(
+            config.block_cache_size,
+            16,
+            RandomState::default(),
+        )?);
+        let ver_status = VersionStatus::load_with_path(config.clone(), wal.clone(), cache)?;
 
         let sst_loader = ver_status.loader().clone();
 
         let (scope_1, meta_1) = sst_loader
             .create(
                 1,
-                vec![(Bytes::from_static(b"test"), None)],
+                vec![((Bytes::from_static(b"test"), None), 0, ValueMeta::default())],
                 0,
                 TableType::SortedString,
             )
@@ -46,7 +54,7 @@ fn test_version_clean() -> KernelResult<()> {
         let (scope_2, meta_2) = sst_loader
             .create(
                 2,
-                vec![(Bytes::from_static(b"test"), None)],
+                vec![((Bytes::from_static(b"test"), None), 0, ValueMeta::default())],
                 0,
                 TableType::SortedString,
             )
@@ -90,6 +98,7 @@ fn test_version_clean() -> KernelResult<()> {
             snapshot,
             vec![
                 VersionEdit::NewFile((vec![scope_2], 0), 0, meta_2),
+                VersionEdit::Comparator(config.comparator.name().to_string()),
                 VersionEdit::DeleteFile((vec![2], 0), meta_2),
             ]
         );
@@ -135,13 +144,18 @@ fn test_version_apply_and_log() -> KernelResult<()> {
 
         // 注意：将ss_table的创建防止VersionStatus的创建前
         // 因为VersionStatus检测无Log时会扫描当前文件夹下的SSTable进行重组以进行容灾
-        let ver_status_1 = VersionStatus::load_with_path(config.clone(), wal.clone())?;
+        let cache = Arc::new(ShardingLruCache::new(
+            config.block_cache_size,
+            16,
+            RandomState::default(),
+        )?);
+        let ver_status_1 = VersionStatus::load_with_path(config.clone(), wal.clone(), cache)?;
 
         let (scope_1, meta_1) = ver_status_1
             .loader()
             .create(
                 1,
-                vec![(Bytes::from_static(b"test"), None)],
+                vec![((Bytes::from_static(b"test"), None), 0, ValueMeta::default())],
                 0,
                 TableType::SortedString,
             )
@@ -151,7 +165,7 @@ fn test_version_apply_and_log() -> KernelResult<()> {
             .loader()
             .create(
                 2,
-                vec![(Bytes::from_static(b"test"), None)],
+                vec![((Bytes::from_static(b"test"), None), 0, ValueMeta::default())],
                 0,
                 TableType::SortedString,
             )
@@ -169,7 +183,11 @@ fn test_version_apply_and_log() -> KernelResult<()> {
             .loader()
             .create(
                 3,
-                vec![(Bytes::from_static(b"test3"), None)],
+                vec![(
+                    (Bytes::from_static(b"test3"), None),
+                    0,
+                    ValueMeta::default(),
+                )],
                 0,
                 TableType::SortedString,
             )
@@ -179,7 +197,11 @@ fn test_version_apply_and_log() -> KernelResult<()> {
             .loader()
             .create(
                 4,
-                vec![(Bytes::from_static(b"test4"), None)],
+                vec![(
+                    (Bytes::from_static(b"test4"), None),
+                    0,
+                    ValueMeta::default(),
+                )],
                 0,
                 TableType::SortedString,
             )
@@ -196,7 +218,12 @@ fn test_version_apply_and_log() -> KernelResult<()> {
 
         drop(ver_status_1);
 
-        let ver_status_2 = VersionStatus::load_with_path(config, wal.clone())?;
+        let cache = Arc::new(ShardingLruCache::new(
+            config.block_cache_size,
+            16,
+            RandomState::default(),
+        )?);
+        let ver_status_2 = VersionStatus::load_with_path(config, wal.clone(), cache)?;
         let version_2 = ver_status_2.current().await;
 
         assert_eq!(version_1.level_slice, version_2.level_slice);
@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use fslock::LockFile;
 use serde::{Deserialize, Serialize};
+use std::collections::Bound;
 use std::ffi::OsStr;
 use std::path::Path;
 use std::time::Duration;
@@ -9,6 +10,7 @@ use std::{fs, path::PathBuf};
 use tokio::time;
 
 use crate::kernel::io::FileExtension;
+use crate::kernel::write_batch::WriteBatch;
 use crate::KernelError;
 
 pub mod io;
@@ -18,6 +20,7 @@ pub mod rocksdb_storage;
 #[cfg(feature = "sled")]
 pub mod sled_storage;
 pub mod utils;
+pub mod write_batch;
 
 pub type KernelResult<T> = std::result::Result<T, KernelError>;
 
@@ -46,6 +49,19 @@ pub trait Storage: Send + Sync + 'static + Sized {
     /// 通过键删除键值对
     async fn remove(&self, key: &[u8]) -> KernelResult<()>;
 
+    /// 原子地应用一组批量写入
+    async fn write(&self, batch: WriteBatch) -> KernelResult<()>;
+
+    /// 范围查询
+    ///
+    /// 以Key的顺序返回[min, max]范围内最多limit个键值对，不包含已删除的数据
+    async fn scan(
+        &self,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+        limit: Option<usize>,
+    ) -> KernelResult<Vec<(Bytes, Bytes)>>;
+
     async fn size_of_disk(&self) -> KernelResult<u64>;
 
     async fn len(&self) -> KernelResult<usize>;
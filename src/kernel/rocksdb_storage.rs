@@ -1,8 +1,11 @@
+use crate::kernel::write_batch::WriteBatch;
 use crate::kernel::Storage;
 use crate::KernelError;
 use async_trait::async_trait;
 use bytes::Bytes;
 use core::slice::SlicePattern;
+use rocksdb::{Direction, IteratorMode};
+use std::collections::Bound;
 use std::path::PathBuf;
 
 #[derive(Debug)]
@@ -55,6 +58,59 @@ impl Storage for RocksdbStorage {
         }
     }
 
+    #[inline]
+    async fn write(&self, batch: WriteBatch) -> crate::kernel::KernelResult<()> {
+        let mut rocksdb_batch = rocksdb::WriteBatch::default();
+
+        for (key, value) in batch.into_default_iter()? {
+            match value {
+                Some(value) => rocksdb_batch.put(key.as_slice(), &value),
+                None => rocksdb_batch.delete(key.as_slice()),
+            }
+        }
+        self.data_base.write(rocksdb_batch)?;
+
+        Ok(())
+    }
+
+    #[inline]
+    async fn scan(
+        &self,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+        limit: Option<usize>,
+    ) -> crate::kernel::KernelResult<Vec<(Bytes, Bytes)>> {
+        let mode = match min {
+            Bound::Included(key) | Bound::Excluded(key) => {
+                IteratorMode::From(key, Direction::Forward)
+            }
+            Bound::Unbounded => IteratorMode::Start,
+        };
+        let mut items = Vec::new();
+
+        for item in self.data_base.iterator(mode) {
+            if limit.map_or(false, |limit| items.len() >= limit) {
+                break;
+            }
+            let (key, value) = item?;
+
+            if matches!(min, Bound::Excluded(min_key) if min_key == key.as_ref()) {
+                continue;
+            }
+            let is_overed = match max {
+                Bound::Included(max_key) => key.as_ref() > max_key,
+                Bound::Excluded(max_key) => key.as_ref() >= max_key,
+                Bound::Unbounded => false,
+            };
+            if is_overed {
+                break;
+            }
+            items.push((Bytes::from(key.to_vec()), Bytes::from(value.to_vec())));
+        }
+
+        Ok(items)
+    }
+
     #[inline]
     async fn size_of_disk(&self) -> crate::kernel::KernelResult<u64> {
         Err(KernelError::NotSupport(
@@ -1,9 +1,11 @@
+use crate::kernel::write_batch::WriteBatch;
 use crate::kernel::Storage;
 use crate::KernelError;
 use async_trait::async_trait;
 use bytes::Bytes;
 use core::slice::SlicePattern;
 use sled::Db;
+use std::collections::Bound;
 use std::path::PathBuf;
 
 #[derive(Debug)]
@@ -57,6 +59,41 @@ impl Storage for SledStorage {
         }
     }
 
+    #[inline]
+    async fn write(&self, batch: WriteBatch) -> crate::kernel::KernelResult<()> {
+        let mut sled_batch = sled::Batch::default();
+
+        for (key, value) in batch.into_default_iter()? {
+            match value {
+                Some(value) => sled_batch.insert(key.as_slice(), value.to_vec()),
+                None => sled_batch.remove(key.as_slice()),
+            }
+        }
+        self.data_base.apply_batch(sled_batch)?;
+
+        Ok(())
+    }
+
+    #[inline]
+    async fn scan(
+        &self,
+        min: Bound<&[u8]>,
+        max: Bound<&[u8]>,
+        limit: Option<usize>,
+    ) -> crate::kernel::KernelResult<Vec<(Bytes, Bytes)>> {
+        let mut items = Vec::new();
+
+        for item in self.data_base.range::<&[u8], _>((min, max)) {
+            if limit.map_or(false, |limit| items.len() >= limit) {
+                break;
+            }
+            let (key, value) = item?;
+            items.push((Bytes::from(key.to_vec()), Bytes::from(value.to_vec())));
+        }
+
+        Ok(items)
+    }
+
     #[inline]
     async fn size_of_disk(&self) -> crate::kernel::KernelResult<u64> {
         Ok(self.data_base.size_on_disk()?)
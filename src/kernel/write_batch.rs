@@ -0,0 +1,167 @@
+use crate::kernel::lsm::column_family::DEFAULT_COLUMN_FAMILY;
+use crate::kernel::KernelResult;
+use crate::KernelError;
+use bytes::Bytes;
+use std::collections::BTreeMap;
+use std::iter;
+
+type BatchData = Vec<(Bytes, Option<Bytes>)>;
+
+/// 批量写入
+///
+/// 累积一组put/remove操作，由`Storage::write`以原子的方式一次性应用
+/// 与`Transaction`不同，WriteBatch不进行冲突检测也不持有Version，因此更加轻量
+#[derive(Debug, Default, Clone)]
+pub struct WriteBatch {
+    data: BatchData,
+    /// 指定ColumnFamily的操作，以ColumnFamily的名称作为Key
+    cf_data: BTreeMap<String, BatchData>,
+}
+
+impl WriteBatch {
+    #[inline]
+    pub fn new() -> Self {
+        WriteBatch::default()
+    }
+
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        WriteBatch {
+            data: Vec::with_capacity(capacity),
+            cf_data: BTreeMap::new(),
+        }
+    }
+
+    /// 设置键值对
+    #[inline]
+    pub fn put(&mut self, key: Bytes, value: Bytes) {
+        self.data.push((key, Some(value)));
+    }
+
+    /// 删除键值对
+    ///
+    /// Tips: 与`Storage::remove`不同，此处不会检测Key是否存在
+    #[inline]
+    pub fn remove(&mut self, key: &[u8]) {
+        self.data.push((Bytes::copy_from_slice(key), None));
+    }
+
+    /// 在指定的ColumnFamily中设置键值对
+    ///
+    /// Tips: 仅KipStorage支持ColumnFamily
+    #[inline]
+    pub fn put_cf(&mut self, cf: &str, key: Bytes, value: Bytes) {
+        self.cf_data_mut(cf).push((key, Some(value)));
+    }
+
+    /// 删除指定的ColumnFamily中的键值对
+    #[inline]
+    pub fn remove_cf(&mut self, cf: &str, key: &[u8]) {
+        self.cf_data_mut(cf)
+            .push((Bytes::copy_from_slice(key), None));
+    }
+
+    fn cf_data_mut(&mut self, cf: &str) -> &mut BatchData {
+        if cf == DEFAULT_COLUMN_FAMILY {
+            return &mut self.data;
+        }
+        self.cf_data.entry(cf.to_string()).or_default()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len() + self.cf_data.values().map(Vec::len).sum::<usize>()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.cf_data.clear();
+    }
+
+    /// 遍历所有ColumnFamily的操作: (ColumnFamily的名称, Key, Value)，Value为None时表示删除
+    ///
+    /// 默认ColumnFamily的操作在前，同一ColumnFamily内依照写入顺序排列
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Bytes, Option<&Bytes>)> {
+        iter::once((DEFAULT_COLUMN_FAMILY, &self.data))
+            .chain(self.cf_data.iter().map(|(cf, data)| (cf.as_str(), data)))
+            .flat_map(|(cf, data)| {
+                data.iter()
+                    .map(move |(key, value)| (cf, key, value.as_ref()))
+            })
+    }
+
+    /// 按写入顺序获取默认ColumnFamily的操作，Value为None时表示删除
+    ///
+    /// 包含其他ColumnFamily的操作时返回`KernelError::NotSupport`，避免其被静默丢弃
+    #[inline]
+    pub fn into_default_iter(self) -> KernelResult<impl Iterator<Item = (Bytes, Option<Bytes>)>> {
+        if self.has_column_family() {
+            return Err(KernelError::NotSupport(
+                "WriteBatch contains column family operations",
+            ));
+        }
+
+        Ok(self.data.into_iter())
+    }
+
+    /// 是否包含默认ColumnFamily以外的操作
+    #[inline]
+    pub fn has_column_family(&self) -> bool {
+        !self.cf_data.is_empty()
+    }
+
+    /// 拆分为默认ColumnFamily与其他ColumnFamily的操作
+    pub(crate) fn into_parts(self) -> (BatchData, BTreeMap<String, BatchData>) {
+        (self.data, self.cf_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::kernel::lsm::column_family::DEFAULT_COLUMN_FAMILY;
+    use crate::kernel::write_batch::WriteBatch;
+    use crate::KernelError;
+    use bytes::Bytes;
+    use itertools::Itertools;
+
+    #[test]
+    fn test_write_batch_iter() {
+        let (key_1, key_2) = (Bytes::from_static(b"k1"), Bytes::from_static(b"k2"));
+        let mut batch = WriteBatch::new();
+
+        batch.put(key_1.clone(), key_1.clone());
+        batch.remove_cf("meta", &key_2);
+        batch.put_cf(DEFAULT_COLUMN_FAMILY, key_2.clone(), key_2.clone());
+
+        // 所有ColumnFamily的操作均被遍历
+        assert_eq!(
+            batch.iter().collect_vec(),
+            vec![
+                (DEFAULT_COLUMN_FAMILY, &key_1, Some(&key_1)),
+                (DEFAULT_COLUMN_FAMILY, &key_2, Some(&key_2)),
+                ("meta", &key_2, None),
+            ]
+        );
+        assert!(matches!(
+            batch.clone().into_default_iter(),
+            Err(KernelError::NotSupport(_))
+        ));
+
+        batch.clear();
+        batch.put(key_1.clone(), key_1.clone());
+        assert_eq!(
+            batch
+                .into_default_iter()
+                .map(Iterator::collect::<Vec<_>>)
+                .ok(),
+            Some(vec![(key_1.clone(), Some(key_1))])
+        );
+    }
+}
@@ -1,7 +1,8 @@
 use crate::error::ConnectionError;
 use crate::proto::kipdb_rpc_client::KipdbRpcClient;
 use crate::proto::{
-    BatchGetReq, BatchRemoveReq, BatchSetReq, Empty, GetReq, Kv, RemoveReq, SetReq,
+    BatchGetReq, BatchRemoveReq, BatchSetReq, CompareAndSwapReq, CompareAndSwapResp, Empty, GetReq,
+    Kv, PutIfAbsentReq, RemoveReq, SetReq,
 };
 use tonic::transport::Channel;
 
@@ -49,6 +50,43 @@ impl KipdbClient {
         Ok(resp.into_inner().value)
     }
 
+    /// 当Key的当前值与expected一致时将其替换为new_value，不一致时通过Err返回当前值
+    #[inline]
+    pub async fn compare_and_swap(
+        &mut self,
+        key: Key,
+        expected: Option<Value>,
+        new_value: Option<Value>,
+    ) -> ConnectionResult<Result<(), Option<Value>>> {
+        let req = tonic::Request::new(CompareAndSwapReq {
+            key,
+            expected,
+            new_value,
+        });
+        let resp = self.conn.compare_and_swap(req).await?;
+        Ok(Self::cas_result(resp.into_inner()))
+    }
+
+    /// 仅当Key不存在时设置键值对，已存在时通过Err返回当前值
+    #[inline]
+    pub async fn put_if_absent(
+        &mut self,
+        key: Key,
+        value: Value,
+    ) -> ConnectionResult<Result<(), Option<Value>>> {
+        let req = tonic::Request::new(PutIfAbsentReq { key, value });
+        let resp = self.conn.put_if_absent(req).await?;
+        Ok(Self::cas_result(resp.into_inner()))
+    }
+
+    fn cas_result(resp: CompareAndSwapResp) -> Result<(), Option<Value>> {
+        if resp.success {
+            Ok(())
+        } else {
+            Err(resp.current)
+        }
+    }
+
     #[inline]
     pub async fn batch_set(&mut self, kvs: Vec<KV>) -> ConnectionResult<Vec<KV>> {
         let req = tonic::Request::new(BatchSetReq {
@@ -73,11 +111,17 @@ impl KipdbClient {
         Ok(resp.into_inner().failure)
     }
 
+    /// 批量获取Value，结果与keys一一对应
     #[inline]
-    pub async fn batch_get(&mut self, keys: Vec<Key>) -> ConnectionResult<Vec<Value>> {
+    pub async fn batch_get(&mut self, keys: Vec<Key>) -> ConnectionResult<Vec<Option<Value>>> {
         let req = tonic::Request::new(BatchGetReq { keys });
         let resp = self.conn.batch_get(req).await?;
-        Ok(resp.into_inner().values)
+        Ok(resp
+            .into_inner()
+            .values
+            .into_iter()
+            .map(|value| value.value)
+            .collect())
     }
 
     #[inline]
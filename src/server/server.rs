@@ -1,11 +1,14 @@
 use crate::error::ConnectionError;
 use crate::kernel::lsm::storage::KipStorage;
+use crate::kernel::write_batch::WriteBatch;
 use crate::kernel::Storage;
 use crate::proto::kipdb_rpc_server::{KipdbRpc, KipdbRpcServer};
 use crate::proto::{
-    BatchGetReq, BatchGetResp, BatchRemoveReq, BatchRemoveResp, BatchSetReq, BatchSetResp, Empty,
-    FlushResp, GetReq, GetResp, LenResp, RemoveReq, RemoveResp, SetReq, SetResp, SizeOfDiskResp,
+    BatchGetReq, BatchGetResp, BatchRemoveReq, BatchRemoveResp, BatchSetReq, BatchSetResp,
+    CompareAndSwapReq, CompareAndSwapResp, Empty, FlushResp, GetReq, GetResp, LenResp,
+    OptionalValue, PutIfAbsentReq, RemoveReq, RemoveResp, SetReq, SetResp, SizeOfDiskResp,
 };
+use crate::KernelError;
 use bytes::Bytes;
 use std::sync::Arc;
 use tonic::transport::Server;
@@ -22,6 +25,23 @@ pub async fn serve(ip: &String, port: u16) -> Result<(), ConnectionError> {
     Ok(())
 }
 
+/// 将条件写入的结果转换为响应，不一致时附带Key的当前值
+fn cas_resp(
+    result: Result<Result<(), Option<Bytes>>, KernelError>,
+) -> Result<Response<CompareAndSwapResp>, Status> {
+    match result {
+        Ok(Ok(())) => Ok(Response::new(CompareAndSwapResp {
+            success: true,
+            current: None,
+        })),
+        Ok(Err(current)) => Ok(Response::new(CompareAndSwapResp {
+            success: false,
+            current: current.map(|v| v.to_vec()),
+        })),
+        Err(_) => Err(Status::internal("Failed to compare and swap")),
+    }
+}
+
 struct KipdbServer {
     kv_store: Arc<KipStorage>,
 }
@@ -65,23 +85,49 @@ impl KipdbRpc for KipdbServer {
         }))
     }
 
+    async fn compare_and_swap(
+        &self,
+        request: Request<CompareAndSwapReq>,
+    ) -> Result<Response<CompareAndSwapResp>, Status> {
+        let req = request.into_inner();
+        let result = self
+            .kv_store
+            .compare_and_swap(
+                Bytes::from(req.key),
+                req.expected.map(Bytes::from),
+                req.new_value.map(Bytes::from),
+            )
+            .await;
+        cas_resp(result)
+    }
+
+    async fn put_if_absent(
+        &self,
+        request: Request<PutIfAbsentReq>,
+    ) -> Result<Response<CompareAndSwapResp>, Status> {
+        let req = request.into_inner();
+        let result = self
+            .kv_store
+            .put_if_absent(Bytes::from(req.key), Bytes::from(req.value))
+            .await;
+        cas_resp(result)
+    }
+
     async fn batch_set(
         &self,
         request: Request<BatchSetReq>,
     ) -> Result<Response<BatchSetResp>, Status> {
         let req = request.into_inner();
-        let mut failure = Vec::new();
-        // TODO change kv_store.set return type for parallel processing
-        for kv in req.kvs {
-            let success = self
-                .kv_store
-                .set(Bytes::from(kv.key.clone()), Bytes::from(kv.value.clone()))
-                .await
-                .map_or(false, |_| true);
-            if !success {
-                failure.push(kv);
-            }
+        let mut batch = WriteBatch::with_capacity(req.kvs.len());
+
+        for kv in req.kvs.iter() {
+            batch.put(Bytes::from(kv.key.clone()), Bytes::from(kv.value.clone()));
         }
+        // WriteBatch原子写入，失败时所有键值对均未写入
+        let failure = match self.kv_store.write(batch).await {
+            Ok(_) => Vec::new(),
+            Err(_) => req.kvs,
+        };
         Ok(Response::new(BatchSetResp { failure }))
     }
 
@@ -90,18 +136,27 @@ impl KipdbRpc for KipdbServer {
         request: Request<BatchRemoveReq>,
     ) -> Result<Response<BatchRemoveResp>, Status> {
         let req = request.into_inner();
-        let mut failure = Vec::new();
-        // TODO change kv_store.remove return type for parallel processing
-        for key in req.keys {
-            let success = self
-                .kv_store
-                .remove(key.as_slice())
-                .await
-                .map_or(false, |_| true);
-            if !success {
+        let keys = req.keys.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        // 预先查询以报告不存在的键，其余键通过WriteBatch原子删除
+        let result = self.kv_store.multi_get(&keys).await;
+        let Ok(values) = result else {
+            return Ok(Response::new(BatchRemoveResp { failure: req.keys }));
+        };
+        let mut batch = WriteBatch::with_capacity(req.keys.len());
+        let (mut existing, mut failure) = (Vec::new(), Vec::new());
+
+        for (key, value) in req.keys.into_iter().zip(values) {
+            if value.is_some() {
+                batch.remove(&key);
+                existing.push(key);
+            } else {
                 failure.push(key);
             }
         }
+        // WriteBatch原子写入，失败时所有存在的键均未删除
+        if !batch.is_empty() && self.kv_store.write(batch).await.is_err() {
+            failure.extend(existing);
+        }
         Ok(Response::new(BatchRemoveResp { failure }))
     }
 
@@ -110,17 +165,17 @@ impl KipdbRpc for KipdbServer {
         request: Request<BatchGetReq>,
     ) -> Result<Response<BatchGetResp>, Status> {
         let req = request.into_inner();
-        let mut values = Vec::new();
-        // TODO change kv_store.get return type for parallel processing
-        for key in req.keys {
-            values.push(
-                self.kv_store
-                    .get(key.as_slice())
-                    .await
-                    .map_or(None, |v| v)
-                    .map_or(vec![], |v| v.to_vec()),
-            );
-        }
+        let keys = req.keys.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let values = self
+            .kv_store
+            .multi_get(&keys)
+            .await
+            .map_err(|_| Status::internal("Failed to batch get"))?
+            .into_iter()
+            .map(|value| OptionalValue {
+                value: value.map(|v| v.to_vec()),
+            })
+            .collect();
         Ok(Response::new(BatchGetResp { values }))
     }
 
@@ -1,8 +1,10 @@
 use bytes::Bytes;
 use kip_db::kernel::io::{FileExtension, IoFactory, IoType};
 use kip_db::kernel::lsm::storage::KipStorage;
+use kip_db::kernel::write_batch::WriteBatch;
 use kip_db::kernel::KernelResult;
 use kip_db::kernel::Storage;
+use std::collections::Bound;
 use std::io::{Read, Seek, SeekFrom, Write};
 use tempfile::TempDir;
 use walkdir::WalkDir;
@@ -197,6 +199,124 @@ fn remove_key_with_kv_store<T: Storage>() -> KernelResult<()> {
     })
 }
 
+#[test]
+fn write_batch() -> KernelResult<()> {
+    #[cfg(feature = "sled")]
+    {
+        use kip_db::kernel::sled_storage::SledStorage;
+        write_batch_with_kv_store::<SledStorage>()?;
+    }
+    write_batch_with_kv_store::<KipStorage>()?;
+
+    Ok(())
+}
+
+fn write_batch_with_kv_store<T: Storage>() -> KernelResult<()> {
+    tokio_test::block_on(async move {
+        let key1: Vec<u8> = encode_key("key1")?;
+        let key2: Vec<u8> = encode_key("key2")?;
+        let key3: Vec<u8> = encode_key("key3")?;
+        let value1: Vec<u8> = encode_key("value1")?;
+        let value2: Vec<u8> = encode_key("value2")?;
+
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let kv_store = T::open(temp_dir.path()).await?;
+        kv_store
+            .set(Bytes::from(key3.clone()), Bytes::from(value1.clone()))
+            .await?;
+
+        let mut batch = WriteBatch::new();
+        batch.put(Bytes::from(key1.clone()), Bytes::from(value1.clone()));
+        batch.put(Bytes::from(key2.clone()), Bytes::from(value1.clone()));
+        // 同一Batch中后写入的操作覆盖先写入的操作
+        batch.put(Bytes::from(key2.clone()), Bytes::from(value2.clone()));
+        batch.remove(&key3);
+        assert_eq!(batch.len(), 4);
+
+        kv_store.write(batch).await?;
+        assert_eq!(
+            kv_store.get(&key1).await?,
+            Some(Bytes::from(value1.clone()))
+        );
+        assert_eq!(
+            kv_store.get(&key2).await?,
+            Some(Bytes::from(value2.clone()))
+        );
+        assert_eq!(kv_store.get(&key3).await?, None);
+
+        // Open from disk again and check persistent data.
+        kv_store.flush().await?;
+        drop(kv_store);
+        let kv_store = T::open(temp_dir.path()).await?;
+        assert_eq!(kv_store.get(&key1).await?, Some(Bytes::from(value1)));
+        assert_eq!(kv_store.get(&key2).await?, Some(Bytes::from(value2)));
+        assert_eq!(kv_store.get(&key3).await?, None);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn scan() -> KernelResult<()> {
+    #[cfg(feature = "sled")]
+    {
+        use kip_db::kernel::sled_storage::SledStorage;
+        scan_with_kv_store::<SledStorage>()?;
+    }
+    scan_with_kv_store::<KipStorage>()?;
+
+    Ok(())
+}
+
+fn scan_with_kv_store<T: Storage>() -> KernelResult<()> {
+    tokio_test::block_on(async move {
+        let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+        let kv_store = T::open(temp_dir.path()).await?;
+        let key = |i: u32| Bytes::from(i.to_be_bytes().to_vec());
+
+        // 模拟数据分布在MemTable以及SSTable中
+        for i in 0..100 {
+            kv_store.set(key(i), key(i)).await?;
+        }
+        kv_store.flush().await?;
+        for i in 100..200 {
+            kv_store.set(key(i), key(i)).await?;
+        }
+        for i in 50..60 {
+            kv_store.remove(&key(i)).await?;
+        }
+        kv_store.set(key(10), key(1000)).await?;
+
+        let items = kv_store
+            .scan(Bound::Unbounded, Bound::Unbounded, None)
+            .await?;
+        assert_eq!(items.len(), 190);
+        assert_eq!(items[10], (key(10), key(1000)));
+        assert_eq!(items[50], (key(60), key(60)));
+
+        let items = kv_store
+            .scan(Bound::Excluded(&key(45)), Bound::Included(&key(150)), None)
+            .await?;
+        assert_eq!(items.first(), Some(&(key(46), key(46))));
+        assert_eq!(items.last(), Some(&(key(150), key(150))));
+        assert_eq!(items.len(), 95);
+
+        let items = kv_store
+            .scan(
+                Bound::Included(&key(95)),
+                Bound::Excluded(&key(150)),
+                Some(10),
+            )
+            .await?;
+        assert_eq!(
+            items,
+            (95..105).map(|i| (key(i), key(i))).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    })
+}
+
 // Insert data until total size of the directory decreases.
 // Test data correctness after compaction.
 #[test]